@@ -0,0 +1,381 @@
+use std::sync::OnceLock;
+use crate::game::{Color, PieceType};
+use crate::square::Square;
+
+pub const FILE_A: u64 = 0x0101010101010101;
+pub const FILE_H: u64 = FILE_A << 7;
+pub const RANK_1: u64 = 0xff;
+pub const RANK_3: u64 = RANK_1 << 16;
+pub const RANK_6: u64 = RANK_1 << 40;
+pub const RANK_8: u64 = RANK_1 << 56;
+
+// leaper attacks are fixed per square so they are built at compile time
+const KNIGHT_DELTAS: [(i32, i32); 8] = [
+    (1, 2), (2, 1), (2, -1), (1, -2),
+    (-1, -2), (-2, -1), (-2, 1), (-1, 2)
+];
+
+const KING_DELTAS: [(i32, i32); 8] = [
+    (0, 1), (1, 1), (1, 0), (1, -1),
+    (0, -1), (-1, -1), (-1, 0), (-1, 1)
+];
+
+const ROOK_DIRECTIONS: [(i32, i32); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+const BISHOP_DIRECTIONS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+
+const fn leaper_table(deltas: &[(i32, i32)]) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let file = (square % 8) as i32;
+        let rank = (square / 8) as i32;
+        let mut i = 0;
+        while i < deltas.len() {
+            let (df, dr) = deltas[i];
+            let (f, r) = (file + df, rank + dr);
+            if f >= 0 && f < 8 && r >= 0 && r < 8 {
+                table[square] |= 1u64 << (r * 8 + f);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    table
+}
+
+const fn pawn_table(forward: i32) -> [u64; 64] {
+    leaper_table(&[(-1, forward), (1, forward)])
+}
+
+static KNIGHT_ATTACKS: [u64; 64] = leaper_table(&KNIGHT_DELTAS);
+static KING_ATTACKS: [u64; 64] = leaper_table(&KING_DELTAS);
+static WHITE_PAWN_ATTACKS: [u64; 64] = pawn_table(1);
+static BLACK_PAWN_ATTACKS: [u64; 64] = pawn_table(-1);
+
+pub(crate) fn knight_attacks(square: usize) -> u64 {
+    KNIGHT_ATTACKS[square]
+}
+
+pub(crate) fn king_attacks(square: usize) -> u64 {
+    KING_ATTACKS[square]
+}
+
+// squares a pawn of the given color standing on `square` attacks
+pub(crate) fn pawn_attacks(color: Color, square: usize) -> u64 {
+    match color {
+        Color::White => WHITE_PAWN_ATTACKS[square],
+        Color::Black => BLACK_PAWN_ATTACKS[square],
+    }
+}
+
+// magic multipliers found offline, one per square, for hashing the relevant
+// blockers of a slider into its slice of the attack table
+static ROOK_MAGICS: [u64; 64] = [
+    0x2080002080400010, 0x00c0002001401000, 0x2100110008402002, 0x0880080081041000,
+    0x0200020020041008, 0x2300040008010012, 0x0c00283004008201, 0x0180010000407a80,
+    0x0168800080400020, 0x0010400040201000, 0x1001002001001048, 0x1001002408100100,
+    0x0801000408010012, 0x4001000209000400, 0x08a20004c8020001, 0x2002801145002280,
+    0x0080860021004200, 0x001000c009402002, 0x00b0002004002800, 0x100a808010020800,
+    0x8101010008000410, 0x0244008002000480, 0x0000040010810208, 0x2000020000448534,
+    0x4104400480008033, 0x0000810100204000, 0x0440430900200010, 0x4600240900100100,
+    0x0060080080040080, 0x0001000300080400, 0x0004084400011002, 0x0023040200008041,
+    0x0580050043002080, 0x0400804002802008, 0x0001002001004010, 0x1000200901001000,
+    0x4410800801800c00, 0xa012003806001004, 0x0020100104008802, 0x0004808402000041,
+    0x0010400170898000, 0x0080500020004004, 0x1040408012020020, 0x8010040008004040,
+    0x2001080100110004, 0x0000020004008080, 0x0021010810040002, 0x0800008c43020024,
+    0x0000800021005100, 0x0070201040008080, 0x0000d04282006a00, 0x0010014400080240,
+    0x0001080110050100, 0x0012000810240600, 0x0402000801040200, 0x028100108a004100,
+    0x0050800300102045, 0x8208210040120882, 0x8010600101183441, 0x020b000910006045,
+    0x0241001002480005, 0x0081000400880241, 0x0000009008024124, 0x0048122980410402
+];
+
+static BISHOP_MAGICS: [u64; 64] = [
+    0x0848020822040013, 0x8010a40085821200, 0x0008008430840822, 0x0808048108040000,
+    0x1304042100008104, 0x5001012010204023, 0x81048801b8200420, 0x200a008084012000,
+    0x0040102001042084, 0x840a505042428020, 0x0000700102202920, 0x44101c0c10800002,
+    0x0040040422000000, 0x0180020802090202, 0x4020020811041202, 0x000104308c042000,
+    0x4140661002424400, 0x0028012008010460, 0x0188062102002a00, 0x0014004840102008,
+    0x0105000290400002, 0x8001022200410400, 0x104a041918013446, 0x008a000082008238,
+    0x04a0060008100430, 0x0008220008820801, 0x2508041208005010, 0x4008080200202020,
+    0x2441001013004000, 0x0030008060407000, 0x4008108000420800, 0x0012021050290100,
+    0x0210080482200500, 0xcc01112048100480, 0x0020402806500440, 0x00048e0080580080,
+    0x0040102020020080, 0x0028010440080807, 0x4601041108008800, 0x8040810e04104200,
+    0x901210110400088a, 0xa003080212081050, 0x00c1004048401004, 0x900000a014400800,
+    0x0008021040405401, 0x4020008206002090, 0x0004190424030100, 0x0424008a02026250,
+    0x8004088250900040, 0x1c00430088a04200, 0x0001020094040001, 0x8040210020880061,
+    0x2010040450442032, 0x0800840850044001, 0x0004040802140004, 0x0004080a04222020,
+    0x8088802110022000, 0x1081a10416114400, 0x0205010a24060820, 0x0000000720411080,
+    0x1008000208430400, 0x580c026028810840, 0x802020441020a110, 0x12c0022401020018
+];
+
+// walks each ray until it leaves the board or hits a blocker (blocker included),
+// only used to fill the magic tables
+fn sliding_attacks(square: usize, occupancy: u64, directions: &[(i32, i32)]) -> u64 {
+    let mut attacks = 0;
+    let file = (square % 8) as i32;
+    let rank = (square / 8) as i32;
+    for (df, dr) in directions {
+        let (mut f, mut r) = (file + df, rank + dr);
+        while (0..8).contains(&f) && (0..8).contains(&r) {
+            let bit = 1u64 << (r * 8 + f);
+            attacks |= bit;
+            if occupancy & bit != 0 {
+                break;
+            }
+            f += df;
+            r += dr;
+        }
+    }
+    attacks
+}
+
+// squares whose occupancy can change the attack set, the board edge never matters
+fn relevant_mask(square: usize, directions: &[(i32, i32)]) -> u64 {
+    let mut mask = 0;
+    let file = (square % 8) as i32;
+    let rank = (square / 8) as i32;
+    for (df, dr) in directions {
+        let (mut f, mut r) = (file + df, rank + dr);
+        while (0..8).contains(&(f + df)) && (0..8).contains(&(r + dr)) {
+            mask |= 1u64 << (r * 8 + f);
+            f += df;
+            r += dr;
+        }
+    }
+    mask
+}
+
+struct Magic {
+    mask: u64,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occupancy: u64) -> usize {
+        self.offset + ((occupancy & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+struct SlidingTables {
+    rook: Vec<Magic>,
+    bishop: Vec<Magic>,
+    attacks: Vec<u64>,
+}
+
+static SLIDING_TABLES: OnceLock<SlidingTables> = OnceLock::new();
+
+fn build_magics(magics: &[u64; 64], directions: &[(i32, i32)], attacks: &mut Vec<u64>) -> Vec<Magic> {
+    let mut result = Vec::with_capacity(64);
+    for (square, &magic) in magics.iter().enumerate() {
+        let mask = relevant_mask(square, directions);
+        let bits = mask.count_ones();
+        let entry = Magic { mask, magic, shift: 64 - bits, offset: attacks.len() };
+        attacks.resize(attacks.len() + (1 << bits), 0);
+
+        // carry-rippler over every subset of the mask
+        let mut subset = 0u64;
+        loop {
+            attacks[entry.index(subset)] = sliding_attacks(square, subset, directions);
+            subset = subset.wrapping_sub(mask) & mask;
+            if subset == 0 {
+                break;
+            }
+        }
+        result.push(entry);
+    }
+    result
+}
+
+fn sliding_tables() -> &'static SlidingTables {
+    SLIDING_TABLES.get_or_init(|| {
+        let mut attacks = Vec::new();
+        let rook = build_magics(&ROOK_MAGICS, &ROOK_DIRECTIONS, &mut attacks);
+        let bishop = build_magics(&BISHOP_MAGICS, &BISHOP_DIRECTIONS, &mut attacks);
+        SlidingTables { rook, bishop, attacks }
+    })
+}
+
+pub(crate) fn rook_attacks(square: usize, occupancy: u64) -> u64 {
+    let tables = sliding_tables();
+    tables.attacks[tables.rook[square].index(occupancy)]
+}
+
+pub(crate) fn bishop_attacks(square: usize, occupancy: u64) -> u64 {
+    let tables = sliding_tables();
+    tables.attacks[tables.bishop[square].index(occupancy)]
+}
+
+pub(crate) fn queen_attacks(square: usize, occupancy: u64) -> u64 {
+    rook_attacks(square, occupancy) | bishop_attacks(square, occupancy)
+}
+
+// squares a slider additionally sees once the first of `blockers` on each ray is lifted,
+// e.g. with own pieces as blockers the enemy pieces behind them are pinned or skewered
+pub(crate) fn rook_xray_attacks(square: usize, occupancy: u64, blockers: u64) -> u64 {
+    let attacks = rook_attacks(square, occupancy);
+    let blockers = blockers & attacks & occupancy;
+    attacks ^ rook_attacks(square, occupancy ^ blockers)
+}
+
+pub(crate) fn bishop_xray_attacks(square: usize, occupancy: u64, blockers: u64) -> u64 {
+    let attacks = bishop_attacks(square, occupancy);
+    let blockers = blockers & attacks & occupancy;
+    attacks ^ bishop_attacks(square, occupancy ^ blockers)
+}
+
+// attack set of any piece, pawns only attack diagonally forward
+pub(crate) fn piece_attacks(piece_type: PieceType, color: Color, square: usize, occupancy: u64) -> u64 {
+    match piece_type {
+        PieceType::Pawn => pawn_attacks(color, square),
+        PieceType::Knight => knight_attacks(square),
+        PieceType::Bishop => bishop_attacks(square, occupancy),
+        PieceType::Rook => rook_attacks(square, occupancy),
+        PieceType::Queen => queen_attacks(square, occupancy),
+        PieceType::King => king_attacks(square),
+    }
+}
+
+// every square from `square` to the edge of the board in one direction, not including `square`
+const fn ray(square: usize, df: i32, dr: i32) -> u64 {
+    let mut ray = 0;
+    let (mut f, mut r) = ((square % 8) as i32 + df, (square / 8) as i32 + dr);
+    while f >= 0 && f < 8 && r >= 0 && r < 8 {
+        ray |= 1u64 << (r * 8 + f);
+        f += df;
+        r += dr;
+    }
+    ray
+}
+
+// for every pair of aligned squares either the squares strictly between them
+// or the whole line through both, empty when they share no rank, file or diagonal
+const fn ray_table(full_line: bool) -> [[u64; 64]; 64] {
+    let mut table = [[0; 64]; 64];
+    let mut a = 0;
+    while a < 64 {
+        let mut i = 0;
+        while i < 8 {
+            let (df, dr) = KING_DELTAS[i];
+            let line = ray(a, df, dr) | ray(a, -df, -dr) | (1u64 << a);
+            let (mut f, mut r) = ((a % 8) as i32 + df, (a / 8) as i32 + dr);
+            let mut squares = 0;
+            while f >= 0 && f < 8 && r >= 0 && r < 8 {
+                let b = (r * 8 + f) as usize;
+                table[a][b] = if full_line { line } else { squares };
+                squares |= 1u64 << b;
+                f += df;
+                r += dr;
+            }
+            i += 1;
+        }
+        a += 1;
+    }
+    table
+}
+
+static BETWEEN: [[u64; 64]; 64] = ray_table(false);
+static LINE: [[u64; 64]; 64] = ray_table(true);
+
+// squares strictly between two squares sharing a rank, file or diagonal, empty otherwise
+pub fn between(a: Square, b: Square) -> u64 {
+    BETWEEN[a.index()][b.index()]
+}
+
+// the full rank, file or diagonal through both squares, empty if they are not aligned
+pub fn line(a: Square, b: Square) -> u64 {
+    LINE[a.index()][b.index()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn knight_attacks_from_corner() {
+        // a1 attacks b3 and c2
+        assert_eq!(knight_attacks(0), (1u64 << 17) | (1u64 << 10));
+    }
+
+    #[test]
+    fn king_attacks_in_center() {
+        assert_eq!(king_attacks(27).count_ones(), 8);
+        assert_eq!(king_attacks(7).count_ones(), 3);
+    }
+
+    #[test]
+    fn pawn_attacks_do_not_wrap() {
+        // a2 only attacks b3, h7 only attacks g6
+        assert_eq!(pawn_attacks(Color::White, 8), 1u64 << 17);
+        assert_eq!(pawn_attacks(Color::Black, 55), 1u64 << 46);
+    }
+
+    #[test]
+    fn rook_attacks_stop_at_blockers() {
+        // rook on a1 with a blocker on a3 and d1
+        let occupancy = (1u64 << 16) | (1u64 << 3);
+        let expected = (1u64 << 8) | (1u64 << 16) | (1u64 << 1) | (1u64 << 2) | (1u64 << 3);
+        assert_eq!(rook_attacks(0, occupancy), expected);
+    }
+
+    #[test]
+    fn magic_lookup_matches_ray_walk() {
+        // a handful of pseudo random occupancies per square
+        let mut seed = 0x9E3779B97F4A7C15u64;
+        for square in 0..64 {
+            for _ in 0..32 {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                let occupancy = seed & (seed >> 3);
+                assert_eq!(rook_attacks(square, occupancy),
+                           sliding_attacks(square, occupancy, &ROOK_DIRECTIONS));
+                assert_eq!(bishop_attacks(square, occupancy),
+                           sliding_attacks(square, occupancy, &BISHOP_DIRECTIONS));
+            }
+        }
+    }
+
+    #[test]
+    fn between_covers_lines_only() {
+        // a1-d4 diagonal, e1-e4 file, knight distance has nothing between
+        assert_eq!(between(Square::A1, Square::D4), Square::B2.bit() | Square::C3.bit());
+        assert_eq!(between(Square::E4, Square::E1), Square::E2.bit() | Square::E3.bit());
+        assert_eq!(between(Square::A1, Square::B3), 0);
+        assert_eq!(between(Square::A1, Square::B1), 0);
+        assert_eq!(between(Square::A1, Square::A1), 0);
+    }
+
+    #[test]
+    fn xray_attacks_see_through_the_first_blocker() {
+        // rook a1, blockers a3 and a5: the x-ray reaches a4 and a5 but stops there
+        let occupancy = (1u64 << 16) | (1u64 << 32);
+        assert_eq!(rook_xray_attacks(0, occupancy, 1u64 << 16) & FILE_A, (1u64 << 24) | (1u64 << 32));
+        // blockers not on any ray change nothing
+        assert_eq!(rook_xray_attacks(0, occupancy, 1u64 << 63), 0);
+        // bishop a1 through c3 up to h8
+        assert_eq!(bishop_xray_attacks(0, 1u64 << 18, !0), 0x8040201000000000 | (1u64 << 27));
+    }
+
+    #[test]
+    fn line_through_aligned_squares() {
+        // b2 and d4 lie on the long diagonal
+        assert_eq!(line(Square::B2, Square::D4), 0x8040201008040201);
+        assert_eq!(line(Square::D4, Square::B2), 0x8040201008040201);
+        assert_eq!(line(Square::E2, Square::E8), FILE_A << 4);
+        assert_eq!(line(Square::A1, Square::B3), 0);
+        assert_eq!(line(Square::F1, Square::F1), 0);
+        for a in Square::iter() {
+            for b in Square::iter() {
+                assert_eq!(between(a, b) & !line(a, b), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn bishop_attacks_on_empty_board() {
+        assert_eq!(bishop_attacks(0, 0).count_ones(), 7);
+        assert_eq!(bishop_attacks(27, 0).count_ones(), 13);
+    }
+}
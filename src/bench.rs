@@ -0,0 +1,68 @@
+use std::time::Duration;
+use crate::game::Game;
+use crate::search::{SearchOptions, Searcher};
+use crate::timeman::Instant;
+
+// depth each position is searched to unless asked for otherwise
+pub const BENCH_DEPTH: usize = 5;
+
+// openings, middlegames and endgames with castling, en passant and promotions among them,
+// changing this list changes the signature
+pub const BENCH_POSITIONS: [&str; 12] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 10",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 11",
+    "4rrk1/pp1n3p/3q2pQ/2p1pb2/2PP4/2P3N1/P2B2PP/4RRK1 b - - 7 19",
+    "rq3rk1/ppp2ppp/1bnpb3/3N2B1/3NP3/7P/PPPQ1PP1/2KR3R w - - 7 14",
+    "r1bq1r1k/1pp1n1pp/1p1p4/4p2Q/4Pp2/1BNP4/PPP2PPP/3R1RK1 w - - 2 14",
+    "r3r1k1/2p2ppp/p1p1bn2/8/1q2P3/2NPQN2/PPP3PP/R4RK1 b - - 2 15",
+    "r2qk2r/pp1bbppp/2n1pn2/3p4/3P4/2NBPN2/PP3PPP/R1BQ1RK1 w kq - 4 9",
+    "6k1/6p1/6Pp/ppp5/3pn2P/1P3K2/1PP2P2/3N4 b - - 0 1",
+    "8/8/1p2k1p1/3p3p/1p1P1P1P/1P2PK2/8/8 w - - 3 54",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "8/1P6/8/8/8/8/6k1/4K3 w - - 0 1",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchResult {
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+impl BenchResult {
+    pub fn nps(&self) -> u64 {
+        (self.nodes as u128 * 1000 / self.time.as_millis().max(1)) as u64
+    }
+}
+
+// searches every bench position to `depth` from an empty hash, `report` is called with each
+// position's fen and node count as it finishes. the node total only changes with the search
+pub fn bench(depth: usize, mut report: impl FnMut(&str, u64)) -> BenchResult {
+    let mut searcher = Searcher::new();
+    let options = SearchOptions { depth, ..SearchOptions::default() };
+    let started = Instant::now();
+    let mut nodes = 0;
+    for fen in BENCH_POSITIONS {
+        let mut game = Game::read_FEN(fen);
+        searcher.clear_hash();
+        let result = searcher.search_with(&mut game, &options);
+        report(fen, result.nodes);
+        nodes += result.nodes;
+    }
+    BenchResult { nodes, time: started.elapsed() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_count_is_stable() {
+        let mut counts = vec![];
+        let first = bench(2, |_, nodes| counts.push(nodes));
+        assert_eq!(counts.len(), BENCH_POSITIONS.len());
+        assert_eq!(counts.iter().sum::<u64>(), first.nodes);
+        assert!(counts.iter().all(|nodes| *nodes > 0));
+        assert_eq!(bench(2, |_, _| {}).nodes, first.nodes);
+    }
+}
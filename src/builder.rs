@@ -0,0 +1,149 @@
+use crate::game::*;
+use crate::square::Square;
+use crate::validate::PositionError;
+
+// assembles a position piece by piece, e.g.
+// PositionBuilder::new().piece(Square::E1, Color::White, PieceType::King)...build()
+#[derive(Debug, Clone)]
+pub struct PositionBuilder {
+    board: [Option<(Color, PieceType)>; 64],
+    side_to_move: Color,
+    castling: CastlingRights,
+    en_passant: Option<Square>,
+    halfmove_clock: usize,
+    fullmove_number: usize,
+}
+
+impl Default for PositionBuilder {
+    fn default() -> PositionBuilder {
+        PositionBuilder::new()
+    }
+}
+
+impl PositionBuilder {
+    // an empty board with white to move
+    pub fn new() -> PositionBuilder {
+        PositionBuilder {
+            board: [None; 64],
+            side_to_move: Color::White,
+            castling: CastlingRights::NONE,
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+
+    // starts from an existing position, to change a few things about it
+    pub fn from_game(game: &Game) -> PositionBuilder {
+        let mut builder = PositionBuilder::new();
+        for square in Square::iter() {
+            builder.board[square.index()] = game.piece_at(square).map(|p| (p.color, p.piece_type));
+        }
+        builder.side_to_move = game.active_color;
+        builder.castling = game.castling_rights;
+        builder.en_passant = game.en_passant.map(|bit| Square::ALL[bit.trailing_zeros() as usize]);
+        builder.halfmove_clock = game.halfmove_clock;
+        builder.fullmove_number = game.fullmove_number;
+        builder
+    }
+
+    pub fn piece(mut self, square: Square, color: Color, piece_type: PieceType) -> PositionBuilder {
+        self.board[square.index()] = Some((color, piece_type));
+        self
+    }
+
+    pub fn remove(mut self, square: Square) -> PositionBuilder {
+        self.board[square.index()] = None;
+        self
+    }
+
+    pub fn side_to_move(mut self, color: Color) -> PositionBuilder {
+        self.side_to_move = color;
+        self
+    }
+
+    pub fn castling(mut self, rights: CastlingRights) -> PositionBuilder {
+        self.castling = rights;
+        self
+    }
+
+    pub fn en_passant(mut self, square: Option<Square>) -> PositionBuilder {
+        self.en_passant = square;
+        self
+    }
+
+    pub fn halfmove_clock(mut self, clock: usize) -> PositionBuilder {
+        self.halfmove_clock = clock;
+        self
+    }
+
+    pub fn fullmove_number(mut self, number: usize) -> PositionBuilder {
+        self.fullmove_number = number.max(1);
+        self
+    }
+
+    // the position if it passes Game::validate
+    pub fn build(&self) -> Result<Game, PositionError> {
+        let mut rows = Vec::new();
+        for rank in (0..8).rev() {
+            let row: String = (0..8).map(|file| match self.board[rank * 8 + file] {
+                None => '1',
+                Some((color, piece_type)) => {
+                    let piece = Piece { position: 0, color, piece_type };
+                    piece.to_string().chars().next().unwrap()
+                }
+            }).collect();
+            rows.push(row);
+        }
+        let color = match self.side_to_move {
+            Color::White => "w",
+            Color::Black => "b",
+        };
+        // every row names exactly eight squares so the FEN is always well formed
+        let mut game = Game::read_FEN(&format!("{} {} - - 0 1", rows.join("/"), color));
+        game.castling_rights = self.castling;
+        game.en_passant = self.en_passant.map(|square| square.bit());
+        game.halfmove_clock = self.halfmove_clock;
+        game.fullmove_number = self.fullmove_number;
+        game.refresh_incremental_state();
+        game.validate()?;
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_valid_positions() {
+        let game = PositionBuilder::new()
+            .piece(Square::E1, Color::White, PieceType::King)
+            .piece(Square::H1, Color::White, PieceType::Rook)
+            .piece(Square::E8, Color::Black, PieceType::King)
+            .piece(Square::E4, Color::White, PieceType::Knight)
+            .side_to_move(Color::Black)
+            .castling(CastlingRights::WHITEKINGSIDE)
+            .fullmove_number(12)
+            .build()
+            .unwrap();
+        assert_eq!(game.to_fen(), "4k3/8/8/8/4N3/8/8/4K2R b K - 0 12");
+
+        let start = Game::initialize();
+        let rebuilt = PositionBuilder::from_game(&start).build().unwrap();
+        assert!(rebuilt == start);
+    }
+
+    #[test]
+    fn rejects_impossible_positions() {
+        let kings = PositionBuilder::new()
+            .piece(Square::E1, Color::White, PieceType::King)
+            .piece(Square::E8, Color::Black, PieceType::King);
+        assert!(kings.build().is_ok());
+        assert_eq!(kings.clone().remove(Square::E8).build().err(), Some(PositionError::MissingKing(Color::Black)));
+        assert_eq!(kings.clone().piece(Square::A8, Color::White, PieceType::Pawn).build().err(),
+                   Some(PositionError::PawnOnBackRank(Square::A8)));
+        assert_eq!(kings.castling(CastlingRights::BLACKQUEENSIDE).build().err(),
+                   Some(PositionError::InvalidCastlingRights(CastlingRights::BLACKQUEENSIDE)));
+    }
+}
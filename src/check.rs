@@ -0,0 +1,117 @@
+use crate::attacks::*;
+use crate::game::*;
+use crate::movegen::Move;
+use crate::square::Square;
+use crate::utils::*;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum CheckKind {
+    None,
+    // the moved piece itself attacks the king
+    Direct,
+    // moving uncovered a slider behind it
+    Discovered,
+    Double,
+}
+
+impl Game {
+    pub fn gives_check(&self, mv: Move) -> bool {
+        self.check_kind(mv) != CheckKind::None
+    }
+
+    // works out the check a move delivers from the bitboards, without playing it
+    pub fn check_kind(&self, mv: Move) -> CheckKind {
+        let us = self.active_color;
+        let king = match self.bitboard(us.opponent(), PieceType::King) {
+            0 => return CheckKind::None,
+            bit => bit,
+        };
+        let king_square = bit_scan(king);
+        let (from, to) = (mv.from(), mv.to());
+        let piece_type = self.piece_at(Square::from_index(from)).unwrap().piece_type;
+        let to_bit = 1u64 << to;
+
+        let mut vacated = 1u64 << from;
+        let mut occupied = (self.occupied() & !vacated) | to_bit;
+        if piece_type == PieceType::Pawn && self.en_passant == Some(to_bit) {
+            occupied &= match us {
+                Color::White => !(to_bit >> 8),
+                Color::Black => !(to_bit << 8),
+            };
+        }
+
+        // where the moving pieces end up, castling moves the rook as well
+        let mut landed = vec![(to, mv.promotion().unwrap_or(piece_type))];
+        if let Some(castling) = self.castling(mv) {
+            vacated |= 1u64 << castling.rook_from;
+            occupied = (self.occupied() & !vacated) | (1u64 << castling.king_to) | (1u64 << castling.rook_to);
+            landed = vec![(castling.king_to, PieceType::King), (castling.rook_to, PieceType::Rook)];
+        }
+
+        let direct = landed.iter()
+            .filter(|(square, piece_type)| piece_attacks(*piece_type, us, *square, occupied) & king != 0)
+            .count();
+
+        // sliders that stayed put and now see the king through the vacated squares
+        let queens = self.bitboard(us, PieceType::Queen) & !vacated;
+        let rooks = (self.bitboard(us, PieceType::Rook) & !vacated) | queens;
+        let bishops = (self.bitboard(us, PieceType::Bishop) & !vacated) | queens;
+        let discovered = ((rook_attacks(king_square, occupied) & rooks)
+            | (bishop_attacks(king_square, occupied) & bishops)).count_ones() as usize;
+
+        match (direct, discovered) {
+            (0, 0) => CheckKind::None,
+            (1, 0) => CheckKind::Direct,
+            (0, 1) => CheckKind::Discovered,
+            _ => CheckKind::Double,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(fen: &str, mv: &str) -> CheckKind {
+        Game::read_FEN(fen).check_kind(mv.parse().unwrap())
+    }
+
+    #[test]
+    fn direct_and_quiet_moves() {
+        assert_eq!(kind("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8"), CheckKind::Direct);
+        assert_eq!(kind("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a7"), CheckKind::None);
+        assert_eq!(kind("4k3/8/8/8/8/8/8/4K1N1 w - - 0 1", "g1f3"), CheckKind::None);
+        assert_eq!(kind("4k3/8/8/8/8/5N2/8/4K3 w - - 0 1", "f3d6"), CheckKind::Direct);
+    }
+
+    #[test]
+    fn discovered_and_double_checks() {
+        // the e4 knight shields the king from the e1 rook
+        let fen = "4k3/8/8/8/4N3/8/8/K3R3 w - - 0 1";
+        assert_eq!(kind(fen, "e4c3"), CheckKind::Discovered);
+        assert_eq!(kind(fen, "e4d6"), CheckKind::Double);
+        assert_eq!(kind(fen, "e4f6"), CheckKind::Double);
+    }
+
+    #[test]
+    fn promotion_castling_and_en_passant_checks() {
+        // promoting to a knight checks from f8, promoting to a queen does not reach e6
+        assert_eq!(kind("8/5P2/4k3/8/8/8/8/K7 w - - 0 1", "f7f8n"), CheckKind::Direct);
+        assert_eq!(kind("8/5P2/4k3/8/8/8/8/K7 w - - 0 1", "f7f8q"), CheckKind::None);
+        // the castled rook lands on f1 facing the king on f8
+        assert_eq!(kind("5k2/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1"), CheckKind::Direct);
+        // capturing en passant removes the d5 pawn from the bishop's diagonal
+        assert_eq!(kind("6k1/8/8/3pP3/8/8/B7/4K3 w - d6 0 1", "e5d6"), CheckKind::Discovered);
+    }
+
+    #[test]
+    fn agrees_with_playing_the_move() {
+        let mut game = Game::read_FEN("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        for mv in game.legal_moves() {
+            let predicted = game.gives_check(mv);
+            game.make_move(mv);
+            assert_eq!(predicted, game.checkers() != 0, "{}", mv);
+            game.unmake_move();
+        }
+    }
+}
@@ -0,0 +1,464 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use crate::game::{Color, Game};
+use crate::movegen::Move;
+use crate::outcome::{GameResult, Outcome};
+use crate::pgn::{parse_pgn, GameRecord};
+use crate::render::{move_list, render, Blindfold, RenderOptions};
+use crate::search::{SearchOptions, Searcher, MAX_DEPTH};
+use crate::skill::Skill;
+use crate::timeman::TimeLimits;
+
+const HELP: &str = "enter a move in SAN (Nf3, exd5, O-O) or uci (g1f3), or one of:
+  moves   list the legal moves
+  hint    ask the engine for a move, hint <depth> to search deeper
+  undo    take back your last move and the engine's reply
+  redo    play a taken back move again
+  goto    goto <ply> jumps to that many plies into the game
+  save    save <file> writes the game as pgn, with the rest of the session beside it
+  load    load <file> resumes a saved game
+  flip    turn the board around
+  peek    show the whole board once when playing blindfold
+  draw    claim a draw by repetition or the fifty move rule
+  resign  give the game up
+  quit    leave without finishing";
+
+pub const DEFAULT_HINT_DEPTH: usize = 4;
+
+// how the game against the engine is set up
+#[derive(Debug, Clone, Copy)]
+pub struct CliConfig {
+    pub human: Color,
+    pub skill: Skill,
+    // the engine's time per move, weaker levels stop before it runs out
+    pub think_time: Duration,
+    // how far a hint looks ahead, shallow enough to leave some of the thinking to the human
+    pub hint_depth: usize,
+    pub render: RenderOptions,
+}
+
+impl Default for CliConfig {
+    fn default() -> CliConfig {
+        CliConfig { human: Color::White, skill: Skill::new(crate::skill::MAX_SKILL), think_time: Duration::from_secs(1),
+            hint_depth: DEFAULT_HINT_DEPTH, render: RenderOptions { coordinates: true, move_list: true, ..RenderOptions::default() } }
+    }
+}
+
+// "1-0 checkmate, you win" from the human's point of view
+pub fn result_message(outcome: &Outcome, human: Color) -> String {
+    let verdict = match outcome.result {
+        GameResult::Draw => "a draw",
+        result if result == GameResult::win_for(human) => "you win",
+        _ => "you lose",
+    };
+    format!("{} {}, {}", outcome.result, outcome.reason, verdict)
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+    }
+}
+
+fn side(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+// what a pgn has no place for, kept in "<file>.state" next to the saved game
+pub fn state_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".state");
+    PathBuf::from(name)
+}
+
+// a game between someone at the terminal and the engine, the board is shown before every move
+pub struct CliGame<R: BufRead, W: Write> {
+    game: Game,
+    config: CliConfig,
+    searcher: Searcher,
+    // moves taken back, the next one to replay last
+    redo: Vec<Move>,
+    // the time each side has spent on its moves, white first
+    clocks: [Duration; 2],
+    turn_start: Instant,
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> CliGame<R, W> {
+    pub fn new(game: Game, config: CliConfig, input: R, output: W) -> CliGame<R, W> {
+        CliGame { game, config, searcher: Searcher::new(), redo: vec![],
+            clocks: [Duration::ZERO; 2], turn_start: Instant::now(), input, output }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn clock(&self, color: Color) -> Duration {
+        self.clocks[side(color)]
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut record = GameRecord::from_game(&self.game);
+        let (white, black) = match self.config.human {
+            Color::White => ("Human".to_string(), crate::uci::ENGINE_NAME.to_string()),
+            Color::Black => (crate::uci::ENGINE_NAME.to_string(), "Human".to_string()),
+        };
+        record.headers.push(("Event".to_string(), "Casual game".to_string()));
+        record.headers.push(("White".to_string(), white));
+        record.headers.push(("Black".to_string(), black));
+        let mut state = format!("human {}\nlevel {}\nthink_time {}\nhint_depth {}\nflipped {}\nwhite_clock {}\nblack_clock {}\n",
+            color_name(self.config.human), self.config.skill.level, self.config.think_time.as_secs_f64(), self.config.hint_depth,
+            self.config.render.flipped, self.clocks[0].as_secs_f64(), self.clocks[1].as_secs_f64());
+        if !self.redo.is_empty() {
+            let redo: Vec<String> = self.redo.iter().map(|mv| mv.to_uci()).collect();
+            state += &format!("redo {}\n", redo.join(" "));
+        }
+        let write = |path: &Path, text: String| fs::write(path, text).map_err(|err| format!("{}: {}", path.display(), err));
+        write(path, record.to_pgn()?)?;
+        write(&state_path(path), state)
+    }
+
+    // the first game in a pgn file, and the session around it when the state file is there
+    pub fn load(&mut self, path: &Path) -> Result<(), String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let record = parse_pgn(&text)?.into_iter().next().ok_or_else(|| format!("{}: no game in the file", path.display()))?;
+        let game = record.final_position()?;
+        let (mut config, mut clocks, mut redo) = (self.config, [Duration::ZERO; 2], vec![]);
+        let state = state_path(path);
+        if state.exists() {
+            let text = fs::read_to_string(&state).map_err(|err| format!("{}: {}", state.display(), err))?;
+            for line in text.lines() {
+                let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+                let invalid = || format!("{}: invalid {} '{}'", state.display(), key, value);
+                let seconds = || value.parse().map(Duration::from_secs_f64).map_err(|_| invalid());
+                match key {
+                    "human" if value == "white" => config.human = Color::White,
+                    "human" if value == "black" => config.human = Color::Black,
+                    "level" => config.skill = Skill::new(value.parse().map_err(|_| invalid())?),
+                    "think_time" => config.think_time = seconds()?,
+                    "hint_depth" => config.hint_depth = value.parse().map_err(|_| invalid())?,
+                    "flipped" => config.render.flipped = value.parse().map_err(|_| invalid())?,
+                    "white_clock" => clocks[0] = seconds()?,
+                    "black_clock" => clocks[1] = seconds()?,
+                    // stored with the next move to replay last, so they are checked from the back
+                    "redo" => {
+                        let mut replay = game.clone();
+                        for text in value.split_whitespace().rev() {
+                            let mv = Move::from_uci(text, &replay).map_err(|_| invalid())?;
+                            replay.make_move(mv);
+                            redo.push(mv);
+                        }
+                        redo.reverse();
+                    }
+                    "" => {}
+                    _ => return Err(invalid()),
+                }
+            }
+        }
+        (self.game, self.config, self.clocks, self.redo) = (game, config, clocks, redo);
+        Ok(())
+    }
+
+    // plays until the game is over, None when the human quit or the input ran out first
+    pub fn run(&mut self) -> io::Result<Option<Outcome>> {
+        loop {
+            write!(self.output, "\n{}", self.board())?;
+            if let Some(outcome) = self.game.update_outcome() {
+                writeln!(self.output, "{}", result_message(&outcome, self.config.human))?;
+                return Ok(Some(outcome));
+            }
+            if self.game.active_color == self.config.human {
+                if !self.human_turn()? {
+                    return Ok(None);
+                }
+            } else {
+                self.engine_turn()?;
+            }
+        }
+    }
+
+    // fully blindfold there is only the game so far to go on
+    fn board(&self) -> String {
+        if self.config.render.blindfold != Blindfold::Hidden {
+            return render(&self.game, &self.config.render);
+        }
+        let moves = move_list(&self.game);
+        if moves.is_empty() { "no moves yet\n".to_string() } else { moves.join(" ") + "\n" }
+    }
+
+    // false when the human is leaving
+    fn human_turn(&mut self) -> io::Result<bool> {
+        self.turn_start = Instant::now();
+        loop {
+            write!(self.output, "{} to move: ", color_name(self.game.active_color))?;
+            self.output.flush()?;
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(false);
+            }
+            match line.trim() {
+                "" => {}
+                "quit" | "exit" => return Ok(false),
+                "help" => writeln!(self.output, "{}", HELP)?,
+                "resign" => {
+                    self.game.resign(self.config.human);
+                    return Ok(true);
+                }
+                "draw" => {
+                    if self.game.claim_draw() {
+                        return Ok(true);
+                    }
+                    writeln!(self.output, "There is no draw to claim")?;
+                }
+                "flip" => {
+                    self.config.render.flipped = !self.config.render.flipped;
+                    write!(self.output, "{}", self.board())?;
+                }
+                "peek" => {
+                    let options = RenderOptions { blindfold: Blindfold::Off, ..self.config.render };
+                    write!(self.output, "{}", render(&self.game, &options))?;
+                }
+                "undo" => {
+                    if self.undo() {
+                        return Ok(true);
+                    }
+                    writeln!(self.output, "No move to take back")?;
+                }
+                "redo" => {
+                    if self.redo() {
+                        return Ok(true);
+                    }
+                    writeln!(self.output, "No move to replay")?;
+                }
+                text if text.starts_with("save ") => match self.save(Path::new(text[5..].trim())) {
+                    Ok(()) => writeln!(self.output, "Saved to {}", text[5..].trim())?,
+                    Err(err) => writeln!(self.output, "{}", err)?,
+                },
+                text if text.starts_with("load ") => match self.load(Path::new(text[5..].trim())) {
+                    Ok(()) => return Ok(true),
+                    Err(err) => writeln!(self.output, "{}", err)?,
+                },
+                text if text.starts_with("goto ") => match text[5..].trim().parse() {
+                    Ok(ply) if self.goto(ply) => return Ok(true),
+                    Ok(ply) => writeln!(self.output, "No ply {}, the game has {}", ply, self.plies())?,
+                    Err(_) => writeln!(self.output, "Usage: goto <ply>")?,
+                },
+                "hint" => self.hint(self.config.hint_depth)?,
+                text if text.starts_with("hint ") => match text[5..].trim().parse() {
+                    Ok(depth) => self.hint(depth)?,
+                    Err(_) => writeln!(self.output, "Usage: hint [depth]")?,
+                },
+                "moves" => {
+                    let moves: Vec<String> = self.game.legal_moves().iter().map(|mv| self.game.to_san(*mv)).collect();
+                    writeln!(self.output, "{}", moves.join(" "))?;
+                }
+                text => match self.game.parse_move(text) {
+                    Ok(mv) => {
+                        self.play(mv);
+                        return Ok(true);
+                    }
+                    Err(err) => writeln!(self.output, "{}, try help", err)?,
+                },
+            }
+        }
+    }
+
+    // the moves taken back stay available as long as the game follows them, the time
+    // since the turn started goes on the mover's clock
+    fn play(&mut self, mv: Move) {
+        self.clocks[side(self.game.active_color)] += self.turn_start.elapsed();
+        match self.redo.last() {
+            Some(next) if next.to_uci() == mv.to_uci() => {
+                self.redo.pop();
+            }
+            _ => self.redo.clear(),
+        }
+        self.game.make_move(mv);
+    }
+
+    // the plies played and taken back, as far as goto can reach
+    fn plies(&self) -> usize {
+        self.game.history.len() + self.redo.len()
+    }
+
+    // back to the human's turn, which takes the engine's reply along with the human's move
+    fn undo(&mut self) -> bool {
+        let Some(mv) = self.game.unmake_move() else {
+            return false;
+        };
+        self.redo.push(mv);
+        if self.game.active_color != self.config.human {
+            if let Some(mv) = self.game.unmake_move() {
+                self.redo.push(mv);
+            }
+        }
+        true
+    }
+
+    fn redo(&mut self) -> bool {
+        let Some(mv) = self.redo.pop() else {
+            return false;
+        };
+        self.game.make_move(mv);
+        if self.game.active_color != self.config.human {
+            if let Some(mv) = self.redo.pop() {
+                self.game.make_move(mv);
+            }
+        }
+        true
+    }
+
+    fn goto(&mut self, ply: usize) -> bool {
+        if ply > self.plies() {
+            return false;
+        }
+        while self.game.history.len() > ply {
+            let mv = self.game.unmake_move().unwrap();
+            self.redo.push(mv);
+        }
+        while self.game.history.len() < ply {
+            let mv = self.redo.pop().unwrap();
+            self.game.make_move(mv);
+        }
+        true
+    }
+
+    // the engine's pick for the human, marked on the board when it is in color
+    fn hint(&mut self, depth: usize) -> io::Result<()> {
+        let options = SearchOptions { depth: depth.clamp(1, MAX_DEPTH), ..SearchOptions::default() };
+        let Some(mv) = self.searcher.search_with(&mut self.game, &options).best_move else {
+            return Ok(());
+        };
+        if self.config.render.color {
+            let render_options = RenderOptions { hint: Some((mv.from(), mv.to())), ..self.config.render };
+            write!(self.output, "{}", render(&self.game, &render_options))?;
+        }
+        writeln!(self.output, "hint: {}", self.game.to_san(mv))
+    }
+
+    fn engine_turn(&mut self) -> io::Result<()> {
+        self.turn_start = Instant::now();
+        let options = SearchOptions { depth: MAX_DEPTH, time: Some(TimeLimits::fixed(self.config.think_time)),
+            ..SearchOptions::default() };
+        let Some(mv) = self.config.skill.pick_move(&mut self.searcher, &mut self.game, &options) else {
+            return Ok(());
+        };
+        writeln!(self.output, "engine plays {}", self.game.to_san(mv))?;
+        self.play(mv);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outcome::TerminationReason;
+
+    fn play(fen: &str, human: Color, input: &str) -> (Option<Outcome>, String) {
+        let config = CliConfig { human, think_time: Duration::from_millis(200), ..CliConfig::default() };
+        let mut session = CliGame::new(Game::read_FEN(fen), config, input.as_bytes(), vec![]);
+        let outcome = session.run().unwrap();
+        (outcome, String::from_utf8(session.output).unwrap())
+    }
+
+    #[test]
+    fn plays_the_human_and_the_engine_in_turn() {
+        let (outcome, out) = play("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", Color::White, "Ra9\nRa8#\n");
+        assert_eq!(outcome, Some(Outcome::new(GameResult::WhiteWins, TerminationReason::Checkmate)));
+        assert!(out.contains("try help"), "{}", out);
+        assert!(out.ends_with("1-0 checkmate, you win\n"), "{}", out);
+
+        let (outcome, out) = play("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", Color::Black, "");
+        assert!(out.contains("engine plays Ra8#"), "{}", out);
+        assert!(out.ends_with("1-0 checkmate, you lose\n"), "{}", out);
+        assert_eq!(outcome.unwrap().result, GameResult::WhiteWins);
+    }
+
+    #[test]
+    fn stops_on_resignation_or_quit() {
+        let (outcome, out) = play("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", Color::White, "draw\nresign\n");
+        assert!(out.contains("There is no draw to claim"));
+        assert_eq!(outcome.unwrap().reason, TerminationReason::Resignation);
+        assert!(out.ends_with("0-1 resignation, you lose\n"), "{}", out);
+        let (outcome, _) = play("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", Color::White, "e4\nquit\n");
+        assert_eq!(outcome, None);
+    }
+
+    #[test]
+    fn takes_back_and_replays_moves() {
+        let config = CliConfig { think_time: Duration::from_millis(100), ..CliConfig::default() };
+        let input = "e4\nundo\nundo\nredo\ngoto 0\ngoto 5\ngoto x\nredo\nredo\nquit\n";
+        let mut session = CliGame::new(Game::initialize(), config, input.as_bytes(), vec![]);
+        session.run().unwrap();
+        let out = String::from_utf8(session.output.clone()).unwrap();
+        assert!(out.contains("No move to take back"), "{}", out);
+        assert!(out.contains("No ply 5, the game has 2\nwhite to move: Usage: goto <ply>"), "{}", out);
+        assert!(out.contains("No move to replay"), "{}", out);
+        // the one engine reply was replayed rather than searched again
+        assert_eq!(out.matches("engine plays").count(), 1, "{}", out);
+        assert_eq!(session.game().history.len(), 2);
+        assert_eq!(session.game().history[0].mv.to_uci(), "e2e4");
+    }
+
+    #[test]
+    fn saves_and_resumes_games() {
+        let dir = std::env::temp_dir().join(format!("rustic-save-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("game.pgn");
+        let config = CliConfig { think_time: Duration::from_millis(100), hint_depth: 2, ..CliConfig::default() };
+        let input = format!("d4\nundo\nsave {}\nquit\n", path.display());
+        let mut session = CliGame::new(Game::read_FEN("4k3/8/8/8/8/8/3P4/4K3 w - - 0 1"), config, input.as_bytes(), vec![]);
+        session.run().unwrap();
+        let pgn = fs::read_to_string(&path).unwrap();
+        assert!(pgn.contains("[White \"Human\"]") && pgn.contains("[FEN \"4k3/8/8/8/8/8/3P4/4K3 w - - 0 1\"]"), "{}", pgn);
+        let state = fs::read_to_string(state_path(&path)).unwrap();
+        assert!(state.starts_with("human white\nlevel 20\nthink_time 0.1\nhint_depth 2\nflipped false\n"), "{}", state);
+        assert!(state.ends_with(" d2d4\n"), "{}", state);
+
+        let mut resumed = CliGame::new(Game::initialize(), CliConfig::default(), &b"redo\nquit\n"[..], vec![]);
+        resumed.load(&path).unwrap();
+        assert_eq!(resumed.config.hint_depth, 2);
+        assert_eq!(resumed.clock(Color::White), session.clock(Color::White));
+        resumed.run().unwrap();
+        assert_eq!(resumed.game().history.len(), 2);
+        assert_eq!(resumed.game().history[0].mv.to_uci(), "d2d4");
+
+        fs::write(state_path(&path), "level x\n").unwrap();
+        assert!(resumed.load(&path).unwrap_err().ends_with("invalid level 'x'"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn plays_blindfold() {
+        let render = RenderOptions { blindfold: Blindfold::Hidden, ..RenderOptions::default() };
+        let config = CliConfig { think_time: Duration::from_millis(100), render, ..CliConfig::default() };
+        let mut session = CliGame::new(Game::initialize(), config, &b"e4\npeek\nquit\n"[..], vec![]);
+        session.run().unwrap();
+        let out = String::from_utf8(session.output).unwrap();
+        assert!(out.starts_with("\nno moves yet\nwhite to move: "), "{}", out);
+        assert!(out.contains("\n1. e4 "), "{}", out);
+        // the peek is the only board shown
+        assert_eq!(out.matches("R N B Q K B N R").count(), 1, "{}", out);
+    }
+
+    #[test]
+    fn hints_at_a_move() {
+        let (_, out) = play("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", Color::White, "hint\nhint 0\nhint x\nquit\n");
+        assert_eq!(out.matches("hint: Ra8#\n").count(), 2, "{}", out);
+        assert!(out.contains("Usage: hint [depth]"), "{}", out);
+
+        let config = CliConfig { render: RenderOptions { color: true, ..RenderOptions::default() }, ..CliConfig::default() };
+        let mut session = CliGame::new(Game::read_FEN("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"), config, &b"hint\nquit\n"[..], vec![]);
+        session.run().unwrap();
+        let out = String::from_utf8(session.output).unwrap();
+        assert!(out.contains(&crate::render::background(crate::render::Palette::default().hint)), "{}", out);
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use crate::game::Game;
+use crate::movegen::Move;
+use crate::outcome::GameResult;
+use crate::pgn::*;
+use crate::polyglot::polyglot_key;
+
+// where a position occurred: the game and the number of plies played before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PositionHit {
+    pub game: usize,
+    pub ply: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PositionStats {
+    pub games: usize,
+    pub white_wins: usize,
+    pub black_wins: usize,
+    pub draws: usize,
+    // moves played from the position with how many games continued with each, most popular first
+    pub next_moves: Vec<(Move, usize)>,
+}
+
+// games indexed by the Zobrist key of every main line position they went through
+#[derive(Default)]
+pub struct Database {
+    games: Vec<GameRecord>,
+    index: HashMap<u64, Vec<PositionHit>>,
+}
+
+impl Database {
+    pub fn new() -> Database {
+        Database::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    pub fn game(&self, index: usize) -> Option<&GameRecord> {
+        self.games.get(index)
+    }
+
+    // returns the index of the game, a position repeated within one game is indexed once
+    pub fn add_game(&mut self, record: GameRecord) -> Result<usize, String> {
+        let id = self.games.len();
+        let mut game = record.starting_position()?;
+        let mut seen = Vec::new();
+        let moves = record.mainline();
+        for ply in 0..=moves.len() {
+            let key = polyglot_key(&game);
+            if !seen.contains(&key) {
+                seen.push(key);
+                self.index.entry(key).or_default().push(PositionHit { game: id, ply });
+            }
+            if let Some(mv) = moves.get(ply) {
+                game.make_move(*mv);
+            }
+        }
+        self.games.push(record);
+        Ok(id)
+    }
+
+    pub fn add_pgn(&mut self, text: &str) -> Result<(), String> {
+        for record in parse_pgn(text)? {
+            self.add_game(record)?;
+        }
+        Ok(())
+    }
+
+    pub fn add_pgn_file(&mut self, path: &Path) -> Result<(), String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        self.add_pgn(&text).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    // every game that reached the position, in the order they were added
+    pub fn search(&self, game: &Game) -> &[PositionHit] {
+        self.index.get(&polyglot_key(game)).map_or(&[], |hits| hits.as_slice())
+    }
+
+    pub fn stats(&self, game: &Game) -> PositionStats {
+        let mut stats = PositionStats::default();
+        for hit in self.search(game) {
+            let record = &self.games[hit.game];
+            stats.games += 1;
+            match record.result {
+                Some(GameResult::WhiteWins) => stats.white_wins += 1,
+                Some(GameResult::BlackWins) => stats.black_wins += 1,
+                Some(GameResult::Draw) => stats.draws += 1,
+                None => (),
+            }
+            if let Some(node) = record.moves.get(hit.ply) {
+                match stats.next_moves.iter_mut().find(|(mv, _)| *mv == node.mv) {
+                    Some((_, count)) => *count += 1,
+                    None => stats.next_moves.push((node.mv, 1)),
+                }
+            }
+        }
+        stats.next_moves.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAMES: &str = "\
+[Result \"1-0\"]\n1. e4 e5 2. Nf3 Nc6 1-0\n\n\
+[Result \"0-1\"]\n1. Nf3 Nc6 2. e4 e5 0-1\n\n\
+[Result \"1/2-1/2\"]\n1. d4 d5 2. Nf3 Nf6 3. Ng1 Ng8 4. Nf3 1/2-1/2\n";
+
+    #[test]
+    fn finds_games_through_transpositions() {
+        let mut database = Database::new();
+        database.add_pgn(GAMES).unwrap();
+        assert_eq!(database.len(), 3);
+
+        let start = Game::initialize();
+        let stats = database.stats(&start);
+        assert_eq!((stats.games, stats.white_wins, stats.black_wins, stats.draws), (3, 1, 1, 1));
+        assert_eq!(stats.next_moves.len(), 3);
+
+        // both move orders reach the same position after two moves each
+        let position = Game::read_FEN("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+        let hits = database.search(&position);
+        assert_eq!(hits, [PositionHit { game: 0, ply: 4 }, PositionHit { game: 1, ply: 4 }]);
+
+        // the repeated position counts once for the game that repeated it
+        let knight_out = Game::read_FEN("rnbqkbnr/ppp1pppp/8/3p4/3P4/5N2/PPP1PPPP/RNBQKB1R b KQkq - 1 2");
+        assert_eq!(database.search(&knight_out), [PositionHit { game: 2, ply: 3 }]);
+        assert!(database.search(&Game::read_FEN("4k3/8/8/8/8/8/8/4K3 w - - 0 1")).is_empty());
+    }
+}
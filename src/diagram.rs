@@ -0,0 +1,96 @@
+use crate::game::*;
+
+// what one line of a diagram says about its eight squares, None for lines
+// like borders and file labels that carry no squares
+fn parse_line(line: &str) -> Result<Option<String>, String> {
+    let compact: String = line.chars().filter(|ch| !ch.is_whitespace() && *ch != '|' && *ch != '+').collect();
+    if compact.is_empty() || compact == "abcdefgh" || compact.chars().all(|ch| ch == '-' || ch == '=') {
+        return Ok(None);
+    }
+
+    let chars: Vec<char> = line.chars().collect();
+    let mut row = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        let next = chars.get(i + 1).copied().unwrap_or(' ');
+        match ch {
+            // older boards printed empty squares by name, e.g. e4
+            'a'..='h' if ('1'..='8').contains(&next) => {
+                row.push('1');
+                i += 1;
+            }
+            'p' | 'r' | 'n' | 'b' | 'q' | 'k' | 'P' | 'R' | 'N' | 'B' | 'Q' | 'K' => row.push(ch),
+            '.' | '-' | '_' => row.push('1'),
+            // rank numbers at the edge of the board
+            '1'..='8' => (),
+            ch if ch.is_whitespace() || ch == '|' || ch == '+' => (),
+            _ => return Err(format!("Unexpected character {} in diagram line {}", ch, line)),
+        }
+        i += 1;
+    }
+
+    let squares = row.chars().count();
+    if squares != 8 {
+        return Err(format!("Diagram line {} has {} squares", line.trim(), squares));
+    }
+    Ok(Some(row))
+}
+
+impl Game {
+    // reads a board printed by Display, or one drawn with . for empty squares,
+    // eighth rank first; the diagram carries no castling rights or en passant square
+    pub fn from_diagram(diagram: &str, side_to_move: Color) -> Result<Game, String> {
+        let mut rows = Vec::new();
+        for line in diagram.lines() {
+            if let Some(row) = parse_line(line)? {
+                rows.push(row);
+            }
+        }
+        if rows.len() != 8 {
+            return Err(format!("Diagram has {} ranks instead of 8", rows.len()));
+        }
+
+        let color = match side_to_move {
+            Color::White => "w",
+            Color::Black => "b",
+        };
+        let fen = format!("{} {} - - 0 1", rows.join("/"), color);
+        Game::try_from_fen(&fen).map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_printed_boards() {
+        let game = Game::read_FEN("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b - - 0 1");
+        let parsed = Game::from_diagram(&game.to_string(), Color::Black).unwrap();
+        assert!(parsed == game);
+    }
+
+    #[test]
+    fn reads_hand_drawn_boards() {
+        let diagram = "
+  +-----------------+
+8 | . . . . k . . . |
+7 | . . . . . . . . |
+6 | . . . . . . . . |
+5 | . . . b . . . . |
+4 | . . . . P . . . |
+3 | . . . . . . . . |
+2 | . . . . . . . . |
+1 | . . . . K . . R |
+  +-----------------+
+    a b c d e f g h
+";
+        let game = Game::from_diagram(diagram, Color::White).unwrap();
+        assert_eq!(game.to_fen(), "4k3/8/8/3b4/4P3/8/8/4K2R w - - 0 1");
+
+        assert!(Game::from_diagram("........\n", Color::White).is_err());
+        assert!(Game::from_diagram(&diagram.replace("K . . R", "K . R"), Color::White).is_err());
+        assert!(Game::from_diagram(&diagram.replace("k .", "x ."), Color::White).is_err());
+    }
+}
@@ -0,0 +1,201 @@
+use crate::game::*;
+use crate::movegen::Move;
+
+// a position with its EPD operations, e.g. bm, am, id and ce
+#[derive(Clone)]
+pub struct Epd {
+    pub game: Game,
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+// comments and ids are strings, everything else is written as bare tokens
+fn quoted(opcode: &str, operand: &str) -> bool {
+    opcode == "id"
+        || (opcode.len() == 2 && opcode.starts_with('c') && opcode.as_bytes()[1].is_ascii_digit())
+        || operand.contains([' ', ';', '"'])
+}
+
+// splits the operations part on semicolons outside of quoted strings
+fn parse_operations(text: &str) -> Result<Vec<(String, Vec<String>)>, String> {
+    let mut operations = Vec::new();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(ch) => string.push(ch),
+                        None => return Err(format!("Unterminated string in {}", text)),
+                    }
+                }
+                words.push(string);
+            }
+            ';' | ' ' | '\t' => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                if ch == ';' {
+                    if words.is_empty() {
+                        return Err(format!("Empty operation in {}", text));
+                    }
+                    let opcode = words.remove(0);
+                    operations.push((opcode, std::mem::take(&mut words)));
+                }
+            }
+            ch => word.push(ch),
+        }
+    }
+    if !word.is_empty() || !words.is_empty() {
+        return Err(format!("Operation without a semicolon in {}", text));
+    }
+    Ok(operations)
+}
+
+impl Epd {
+    pub fn new(game: Game) -> Epd {
+        Epd { game, operations: vec![] }
+    }
+
+    // four FEN fields followed by operations, hmvc and fmvn set the clocks
+    pub fn parse(line: &str) -> Result<Epd, String> {
+        let fields: Vec<&str> = line.trim().splitn(5, ' ').collect();
+        if fields.len() < 4 {
+            return Err(format!("EPD needs four position fields: {}", line));
+        }
+        let fen = format!("{} 0 1", fields[..4].join(" "));
+        let game = Game::try_from_fen(&fen).map_err(|err| err.to_string())?;
+        let operations = parse_operations(fields.get(4).copied().unwrap_or(""))?;
+
+        let mut epd = Epd { game, operations };
+        if let Some(clock) = epd.operand("hmvc") {
+            epd.game.halfmove_clock = clock.parse().map_err(|_| format!("Invalid hmvc {}", clock))?;
+        }
+        if let Some(number) = epd.operand("fmvn") {
+            epd.game.fullmove_number = number.parse().map_err(|_| format!("Invalid fmvn {}", number))?;
+        }
+        Ok(epd)
+    }
+
+    pub fn to_epd(&self) -> String {
+        let fen = self.game.to_fen();
+        let mut epd = fen.split(' ').take(4).collect::<Vec<_>>().join(" ");
+        for (opcode, operands) in &self.operations {
+            epd.push(' ');
+            epd.push_str(opcode);
+            for operand in operands {
+                if quoted(opcode, operand) {
+                    epd.push_str(&format!(" \"{}\"", operand));
+                } else {
+                    epd.push_str(&format!(" {}", operand));
+                }
+            }
+            epd.push(';');
+        }
+        epd
+    }
+
+    pub fn operands(&self, opcode: &str) -> Option<&[String]> {
+        self.operations.iter()
+            .find(|(op, _)| op == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    // first operand of the opcode
+    pub fn operand(&self, opcode: &str) -> Option<&str> {
+        self.operands(opcode)?.first().map(|operand| operand.as_str())
+    }
+
+    // replaces any operation with the same opcode
+    pub fn set(&mut self, opcode: &str, operands: Vec<String>) {
+        match self.operations.iter_mut().find(|(op, _)| op == opcode) {
+            Some((_, existing)) => *existing = operands,
+            None => self.operations.push((opcode.to_string(), operands)),
+        }
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.operand("id")
+    }
+
+    // bm, the moves the position is solved by
+    pub fn best_moves(&self) -> Result<Vec<Move>, String> {
+        self.san_moves("bm")
+    }
+
+    // am, moves that should not be played
+    pub fn avoid_moves(&self) -> Result<Vec<Move>, String> {
+        self.san_moves("am")
+    }
+
+    // ce, centipawn evaluation from the side to move
+    pub fn centipawn_eval(&self) -> Option<i32> {
+        self.operand("ce")?.parse().ok()
+    }
+
+    pub fn set_best_moves(&mut self, moves: &[Move]) {
+        let operands = moves.iter().map(|mv| self.game.to_san(*mv)).collect();
+        self.set("bm", operands);
+    }
+
+    fn san_moves(&self, opcode: &str) -> Result<Vec<Move>, String> {
+        self.operands(opcode)
+            .unwrap_or(&[])
+            .iter()
+            .map(|san| self.game.parse_san(san))
+            .collect()
+    }
+}
+
+// every non-empty line of an EPD file that is not a # comment
+pub fn parse_epd_file(text: &str) -> Result<Vec<Epd>, String> {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(Epd::parse)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WAC_1: &str = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";";
+
+    #[test]
+    fn reads_standard_opcodes() {
+        let epd = Epd::parse(WAC_1).unwrap();
+        assert_eq!(epd.id(), Some("WAC.001"));
+        assert_eq!(epd.best_moves().unwrap(), vec!["g3g6".parse().unwrap()]);
+        assert!(epd.avoid_moves().unwrap().is_empty());
+        assert_eq!(epd.centipawn_eval(), None);
+        assert_eq!(epd.to_epd(), WAC_1);
+
+        let epd = Epd::parse("4k3/8/8/8/8/8/8/4K2R w K - am Rh8+ Kd1; ce -15; hmvc 12; fmvn 40; c0 \"two words\";").unwrap();
+        assert_eq!(epd.avoid_moves().unwrap().len(), 2);
+        assert_eq!(epd.centipawn_eval(), Some(-15));
+        assert_eq!((epd.game.halfmove_clock, epd.game.fullmove_number), (12, 40));
+        assert_eq!(epd.operand("c0"), Some("two words"));
+    }
+
+    #[test]
+    fn writes_operations() {
+        let mut epd = Epd::new(Game::initialize());
+        epd.set_best_moves(&["e2e4".parse().unwrap(), "d2d4".parse().unwrap()]);
+        epd.set("id", vec!["start".to_string()]);
+        epd.set("ce", vec!["30".to_string()]);
+        assert_eq!(epd.to_epd(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - bm e4 d4; id \"start\"; ce 30;");
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert!(Epd::parse("8/8/8/8 w").is_err());
+        assert!(Epd::parse("4k3/8/8/8/8/8/8/4K3 w - - bm Ke2").is_err());
+        assert!(Epd::parse("4k3/8/8/8/8/8/8/4K3 w - - id \"open;").is_err());
+        assert!(Epd::parse("4k3/8/8/8/8/8/8/4K3 w - - bm Ke5;").unwrap().best_moves().is_err());
+        assert_eq!(parse_epd_file(&format!("# suite\n\n{}\n", WAC_1)).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,291 @@
+use crate::attacks::piece_attacks;
+use crate::game::*;
+use crate::pawns::{adjacent_files, file_mask, PawnTable};
+use crate::pst::square_bonus;
+use crate::utils::{bit_scan, pop_lsb};
+
+// phase weight of each piece, all of them on the board make MAX_PHASE
+const PHASE_WEIGHTS: [(PieceType, i32); 4] = [
+    (PieceType::Knight, 1), (PieceType::Bishop, 1), (PieceType::Rook, 2), (PieceType::Queen, 4),
+];
+pub const MAX_PHASE: i32 = 24;
+
+// middlegame bonus for each pawn sheltering the king, one and two ranks in front of it
+const SHIELD_NEAR: i32 = 10;
+const SHIELD_FAR: i32 = 5;
+
+// bonus for each square a piece attacks that is not held by its own side
+const MOBILITY_WEIGHTS: [(PieceType, i32, i32); 4] = [
+    (PieceType::Knight, 4, 4), (PieceType::Bishop, 5, 5), (PieceType::Rook, 2, 4), (PieceType::Queen, 1, 2),
+];
+
+// what one piece adds to white's score, negative for black pieces
+pub(crate) fn piece_score(color: Color, piece_type: PieceType, square: usize) -> (i32, i32) {
+    let material = material_value(piece_type);
+    let (mg, eg) = square_bonus(color, piece_type, square);
+    match color {
+        Color::White => (material + mg, material + eg),
+        Color::Black => (-material - mg, -material - eg),
+    }
+}
+
+fn material_value(piece_type: PieceType) -> i32 {
+    if piece_type == PieceType::King { 0 } else { piece_type.value() }
+}
+
+fn taper((mg, eg): (i32, i32), phase: i32) -> i32 {
+    (mg * phase + eg * (MAX_PHASE - phase)) / MAX_PHASE
+}
+
+fn add(a: (i32, i32), b: (i32, i32)) -> (i32, i32) {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a: (i32, i32), b: (i32, i32)) -> (i32, i32) {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+// one side's share of each evaluation term as (middlegame, endgame)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EvalTerms {
+    pub material: (i32, i32),
+    pub pst: (i32, i32),
+    pub pawns: (i32, i32),
+    pub king_safety: (i32, i32),
+    pub mobility: (i32, i32),
+}
+
+impl EvalTerms {
+    pub fn sum(&self) -> (i32, i32) {
+        [self.pst, self.pawns, self.king_safety, self.mobility].into_iter().fold(self.material, add)
+    }
+}
+
+// every term of the evaluation for both sides, `total` is white minus black after tapering,
+// so it is evaluate() seen from white's side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EvalBreakdown {
+    pub white: EvalTerms,
+    pub black: EvalTerms,
+    pub phase: i32,
+    pub total: i32,
+}
+
+impl EvalBreakdown {
+    // a term as white minus black, tapered by the phase
+    pub fn net(&self, term: fn(&EvalTerms) -> (i32, i32)) -> i32 {
+        taper(sub(term(&self.white), term(&self.black)), self.phase)
+    }
+}
+
+pub fn evaluate_detailed(game: &Game) -> EvalBreakdown {
+    let mut sides = [EvalTerms::default(); 2];
+    for piece in &game.pieces {
+        let material = material_value(piece.piece_type);
+        let terms = &mut sides[piece.color as usize];
+        terms.material = add(terms.material, (material, material));
+        terms.pst = add(terms.pst, square_bonus(piece.color, piece.piece_type, bit_scan(piece.position)));
+    }
+    for color in [Color::White, Color::Black] {
+        let terms = &mut sides[color as usize];
+        terms.pawns = game.pawn_structure(color);
+        terms.king_safety = game.king_safety(color);
+        terms.mobility = game.piece_mobility(color);
+    }
+    let [white, black] = sides;
+    let phase = game.phase();
+    EvalBreakdown { white, black, phase, total: taper(sub(white.sum(), black.sum()), phase) }
+}
+
+impl Game {
+    // static evaluation in centipawns from the side to move's point of view
+    pub fn evaluate(&self) -> i32 {
+        self.evaluate_with_pawns([self.pawn_structure(Color::White), self.pawn_structure(Color::Black)])
+    }
+
+    // the same, with the pawn structure looked up in `table` when it was seen before
+    pub fn evaluate_cached(&self, table: &mut PawnTable) -> i32 {
+        self.evaluate_with_pawns(table.probe(self))
+    }
+
+    fn evaluate_with_pawns(&self, pawns: [(i32, i32); 2]) -> i32 {
+        let mut score = add(self.incremental_eval(), sub(pawns[0], pawns[1]));
+        score = add(score, sub(self.king_safety(Color::White), self.king_safety(Color::Black)));
+        score = add(score, sub(self.piece_mobility(Color::White), self.piece_mobility(Color::Black)));
+        // blend the two scores by how much material is left
+        let score = taper(score, self.phase());
+        match self.active_color {
+            Color::White => score,
+            Color::Black => -score,
+        }
+    }
+
+    // material and piece-square score, white minus black, as make_move keeps it
+    pub fn incremental_eval(&self) -> (i32, i32) {
+        self.psqt
+    }
+
+    // the same summed over the board
+    pub fn material_and_pst(&self) -> (i32, i32) {
+        let (mut mg, mut eg) = (0, 0);
+        for piece in &self.pieces {
+            let (piece_mg, piece_eg) = piece_score(piece.color, piece.piece_type, bit_scan(piece.position));
+            mg += piece_mg;
+            eg += piece_eg;
+        }
+        (mg, eg)
+    }
+
+    // own pawns on the king's file and the files next to it, right in front of the king
+    pub fn king_safety(&self, color: Color) -> (i32, i32) {
+        let king = self.bitboard(color, PieceType::King);
+        if king == 0 {
+            return (0, 0);
+        }
+        let square = bit_scan(king);
+        let files = file_mask(square % 8) | adjacent_files(square % 8);
+        let rank = |offset: i32| {
+            let rank = (square / 8) as i32 + offset;
+            if (0..8).contains(&rank) { 0xff << (rank * 8) } else { 0 }
+        };
+        let forward = match color {
+            Color::White => 1,
+            Color::Black => -1,
+        };
+        let pawns = self.bitboard(color, PieceType::Pawn) & files;
+        let near = (pawns & rank(forward)).count_ones() as i32;
+        let far = (pawns & rank(2 * forward)).count_ones() as i32;
+        (near * SHIELD_NEAR + far * SHIELD_FAR, 0)
+    }
+
+    // squares the minor and major pieces attack that are not taken by their own side
+    pub fn piece_mobility(&self, color: Color) -> (i32, i32) {
+        let occupied = self.occupied();
+        let own = self.occupancy(color);
+        let (mut mg, mut eg) = (0, 0);
+        for (piece_type, weight_mg, weight_eg) in MOBILITY_WEIGHTS {
+            let mut pieces = self.bitboard(color, piece_type);
+            while pieces != 0 {
+                let square = pop_lsb(&mut pieces);
+                let count = (piece_attacks(piece_type, color, square, occupied) & !own).count_ones() as i32;
+                mg += count * weight_mg;
+                eg += count * weight_eg;
+            }
+        }
+        (mg, eg)
+    }
+
+    // MAX_PHASE with every piece still on the board down to 0 with only kings and pawns,
+    // promotions can not push it past MAX_PHASE
+    pub fn phase(&self) -> i32 {
+        let mut phase = 0;
+        for color in [Color::White, Color::Black] {
+            for (piece_type, weight) in PHASE_WEIGHTS {
+                phase += self.bitboard(color, piece_type).count_ones() as i32 * weight;
+            }
+        }
+        phase.min(MAX_PHASE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symmetric_positions_are_equal() {
+        assert_eq!(Game::initialize().evaluate(), 0);
+        let game = Game::read_FEN("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+        let mirrored = Game::read_FEN("rnbqkb1r/pppp1ppp/5n2/4p3/4P3/2N5/PPPP1PPP/R1BQKBNR b KQkq - 2 3");
+        assert_eq!(game.evaluate(), mirrored.evaluate());
+    }
+
+    #[test]
+    fn phase_follows_material() {
+        assert_eq!(Game::initialize().phase(), MAX_PHASE);
+        assert_eq!(Game::read_FEN("4k3/pppp4/8/8/8/8/PPPP4/4K3 w - - 0 1").phase(), 0);
+        assert_eq!(Game::read_FEN("3rk3/8/8/8/8/8/8/2N1K3 w - - 0 1").phase(), 3);
+        assert_eq!(Game::read_FEN("QQQQk3/8/8/8/8/8/8/QQQQK3 w - - 0 1").phase(), MAX_PHASE);
+    }
+
+    #[test]
+    fn kings_centralise_in_the_endgame() {
+        // with all the pieces on the board the king belongs on its back rank, with only pawns in the centre
+        let forward = Game::read_FEN("rnbqkbnr/pppppppp/8/8/8/4K3/PPPPPPPP/RNBQ1BNR w kq - 0 1");
+        assert!(forward.evaluate() < Game::initialize().evaluate());
+        let pawns_back = Game::read_FEN("4k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1");
+        let pawns_forward = Game::read_FEN("4k3/pp6/8/8/4K3/8/PP6/8 w - - 0 1");
+        assert!(pawns_forward.evaluate() > pawns_back.evaluate());
+    }
+
+    fn walk(game: &mut Game, depth: usize) {
+        assert_eq!(game.incremental_eval(), game.material_and_pst(), "in {}", game.to_fen());
+        if depth == 0 {
+            return;
+        }
+        for mv in game.legal_moves() {
+            game.make_move(mv);
+            walk(game, depth - 1);
+            game.unmake_move();
+        }
+    }
+
+    #[test]
+    fn incremental_score_matches_full_recomputation() {
+        walk(&mut Game::initialize(), 3);
+        // castling, en passant and promotions with and without captures
+        walk(&mut Game::read_FEN("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"), 2);
+        walk(&mut Game::read_FEN("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"), 2);
+        let mut game = Game::initialize();
+        game.make_null_move();
+        walk(&mut game, 1);
+    }
+
+    #[test]
+    fn breakdown_adds_up_to_the_evaluation() {
+        let start = evaluate_detailed(&Game::initialize());
+        assert_eq!(start.white, start.black);
+        assert_eq!(start.total, 0);
+        assert_eq!(start.white.material, (4000, 4000));
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let game = Game::read_FEN(fen);
+            let breakdown = evaluate_detailed(&game);
+            let expected = if game.active_color == Color::White { game.evaluate() } else { -game.evaluate() };
+            assert_eq!(breakdown.total, expected, "in {}", fen);
+            assert_eq!(breakdown.phase, game.phase());
+            assert_eq!(add(breakdown.white.material, breakdown.white.pst), add(
+                game.material_and_pst(), add(breakdown.black.material, breakdown.black.pst)));
+        }
+    }
+
+    #[test]
+    fn shelters_the_king_and_rewards_mobility() {
+        let sheltered = Game::read_FEN("6k1/8/8/8/8/8/5PPP/6K1 w - - 0 1");
+        let exposed = Game::read_FEN("6k1/8/8/8/8/5PPP/8/6K1 w - - 0 1");
+        assert_eq!(sheltered.king_safety(Color::White), (3 * SHIELD_NEAR, 0));
+        assert_eq!(exposed.king_safety(Color::White), (3 * SHIELD_FAR, 0));
+        assert_eq!(sheltered.king_safety(Color::Black), (0, 0));
+        let open = Game::read_FEN("4k3/8/8/8/3B4/8/8/4K3 w - - 0 1");
+        assert_eq!(open.piece_mobility(Color::White), (13 * 5, 13 * 5));
+        let breakdown = evaluate_detailed(&open);
+        assert_eq!(breakdown.net(|terms| terms.mobility), 13 * 5);
+    }
+
+    #[test]
+    fn rewards_material_and_placement() {
+        let up_a_knight = Game::read_FEN("rnbqkb1r/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert!(up_a_knight.evaluate() > 250);
+        let centralised = Game::read_FEN("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1");
+        let cornered = Game::read_FEN("4k3/8/8/8/8/8/8/N3K3 w - - 0 1");
+        assert!(centralised.evaluate() > cornered.evaluate());
+        // the side to move sees the score from its own side
+        let to_move = Game::read_FEN("4k3/8/8/8/3N4/8/8/4K3 b - - 0 1");
+        assert_eq!(to_move.evaluate(), -centralised.evaluate());
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use reqwest::Client;
+use serde_json::Value;
+use crate::game::{Color, Game};
+use crate::movegen::Move;
+use crate::polyglot::Book;
+
+const EXPLORER_URL: &str = "https://explorer.lichess.ovh";
+const TIMEOUT: Duration = Duration::from_secs(5);
+const USER_AGENT: &str = concat!("rustic_chess/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Database {
+    // over the board games between titled players
+    Masters,
+    // rated games played on lichess
+    Lichess,
+}
+
+impl FromStr for Database {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Database, String> {
+        match s {
+            "masters" => Ok(Database::Masters),
+            "lichess" => Ok(Database::Lichess),
+            _ => Err(format!("Unknown explorer database {}", s)),
+        }
+    }
+}
+
+pub fn explorer_url(database: Database, game: &Game) -> String {
+    let path = match database {
+        Database::Masters => "masters",
+        Database::Lichess => "lichess",
+    };
+    // a fen has nothing else that needs escaping
+    format!("{}/{}?fen={}", EXPLORER_URL, path, game.to_fen().replace(' ', "%20"))
+}
+
+// how often a move was played and how those games ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MoveStats {
+    pub white: u64,
+    pub draws: u64,
+    pub black: u64,
+}
+
+impl MoveStats {
+    pub fn games(&self) -> u64 {
+        self.white + self.draws + self.black
+    }
+
+    // points per game for `side`, None before any game
+    pub fn score(&self, side: Color) -> Option<f64> {
+        let wins = match side {
+            Color::White => self.white,
+            Color::Black => self.black,
+        };
+        let games = self.games();
+        (games > 0).then(|| (wins as f64 + self.draws as f64 / 2.0) / games as f64)
+    }
+}
+
+// the moves of an explorer response, most played first as lichess sends them
+pub fn parse_explorer(text: &str, game: &Game) -> Result<Vec<(Move, MoveStats)>, String> {
+    let value: Value = serde_json::from_str(text).map_err(|err| err.to_string())?;
+    let moves = value["moves"].as_array().ok_or("Response without a moves list")?;
+    moves.iter().map(|entry| {
+        let uci = entry["uci"].as_str().ok_or("Move without a uci field")?;
+        let count = |key: &str| entry[key].as_u64().unwrap_or(0);
+        Ok((Move::from_uci(uci, game)?, MoveStats { white: count("white"), draws: count("draws"), black: count("black") }))
+    }).collect()
+}
+
+// one row of the explorer, from the games, the book or both
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplorerMove {
+    pub mv: Move,
+    pub san: String,
+    pub stats: Option<MoveStats>,
+    // the move's share of the book weight in this position
+    pub book: Option<f64>,
+}
+
+// the explorer moves with the book's added, moves only the book knows go last by weight
+pub fn merge(game: &Game, stats: &[(Move, MoveStats)], book_moves: &[(Move, u16)]) -> Vec<ExplorerMove> {
+    let total: u64 = book_moves.iter().map(|(_, weight)| *weight as u64).sum();
+    let share = |mv: Move| {
+        book_moves.iter().find(|(book_move, _)| *book_move == mv)
+            .map(|(_, weight)| if total == 0 { 0.0 } else { *weight as f64 / total as f64 })
+    };
+    let mut rows: Vec<ExplorerMove> = stats.iter()
+        .map(|(mv, stats)| ExplorerMove { mv: *mv, san: game.to_san(*mv), stats: Some(*stats), book: share(*mv) })
+        .collect();
+    for (mv, _) in book_moves {
+        if !rows.iter().any(|row| row.mv == *mv) {
+            rows.push(ExplorerMove { mv: *mv, san: game.to_san(*mv), stats: None, book: share(*mv) });
+        }
+    }
+    rows
+}
+
+// the rows as a table, scores for the side to move
+pub struct ExplorerTable {
+    pub side: Color,
+    pub rows: Vec<ExplorerMove>,
+}
+
+impl fmt::Display for ExplorerTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<8} {:>9} {:>6} {:>6} {:>6} {:>6} {:>5}", "Move", "Games", "White", "Draw", "Black", "Score", "Book")?;
+        for row in &self.rows {
+            let percent = |count: u64, games: u64| format!("{:.0}%", 100.0 * count as f64 / games as f64);
+            match row.stats.filter(|stats| stats.games() > 0) {
+                Some(stats) => {
+                    let games = stats.games();
+                    write!(f, "{:<8} {:>9} {:>6} {:>6} {:>6} {:>6}", row.san, games, percent(stats.white, games),
+                        percent(stats.draws, games), percent(stats.black, games),
+                        format!("{:.0}%", 100.0 * stats.score(self.side).unwrap_or(0.0)))?;
+                }
+                None => write!(f, "{:<8} {:>9} {:>6} {:>6} {:>6} {:>6}", row.san, "-", "", "", "", "")?,
+            }
+            match row.book {
+                Some(share) => writeln!(f, " {:>5}", format!("{:.0}%", 100.0 * share))?,
+                None => writeln!(f, " {:>5}", "")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// fetches explorer statistics and remembers them for the session
+pub struct Explorer {
+    client: Client,
+    runtime: tokio::runtime::Runtime,
+    // by url, which has the database and the fen in it
+    cache: HashMap<String, Vec<(Move, MoveStats)>>,
+}
+
+impl Explorer {
+    pub fn new() -> Result<Explorer, String> {
+        let client = Client::builder().user_agent(USER_AGENT).timeout(TIMEOUT).build().map_err(|err| err.to_string())?;
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().map_err(|err| err.to_string())?;
+        Ok(Explorer { client, runtime, cache: HashMap::new() })
+    }
+
+    pub fn fetch(&mut self, database: Database, game: &Game) -> Result<Vec<(Move, MoveStats)>, String> {
+        let url = explorer_url(database, game);
+        if let Some(stats) = self.cache.get(&url) {
+            return Ok(stats.clone());
+        }
+        let text = self.runtime.block_on(async {
+            let response = self.client.get(&url).send().await?.error_for_status()?;
+            response.text().await
+        }).map_err(|err| format!("{}: {}", url, err))?;
+        let stats = parse_explorer(&text, game)?;
+        self.cache.insert(url, stats.clone());
+        Ok(stats)
+    }
+
+    // the explorer statistics merged with `book`, the book alone when the explorer cannot be reached
+    pub fn explore(&mut self, database: Database, game: &Game, book: Option<&Book>) -> (ExplorerTable, Option<String>) {
+        let book_moves = book.map(|book| book.probe(game)).unwrap_or_default();
+        let (stats, error) = match self.fetch(database, game) {
+            Ok(stats) => (stats, None),
+            Err(err) => (vec![], Some(err)),
+        };
+        (ExplorerTable { side: game.active_color, rows: merge(game, &stats, &book_moves) }, error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESPONSE: &str = r#"{"white":1000,"draws":1500,"black":700,"moves":[
+        {"uci":"e2e4","san":"e4","white":600,"draws":800,"black":400},
+        {"uci":"d2d4","san":"d4","white":400,"draws":700,"black":300}]}"#;
+
+    #[test]
+    fn reads_explorer_statistics() {
+        let game = Game::initialize();
+        assert_eq!(explorer_url(Database::Masters, &game),
+            "https://explorer.lichess.ovh/masters?fen=rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR%20w%20KQkq%20-%200%201");
+        assert_eq!("lichess".parse(), Ok(Database::Lichess));
+        let stats = parse_explorer(RESPONSE, &game).unwrap();
+        assert_eq!(stats[0].0.to_uci(), "e2e4");
+        assert_eq!(stats[1].1, MoveStats { white: 400, draws: 700, black: 300 });
+        assert_eq!(MoveStats { white: 1, draws: 2, black: 3 }.score(Color::Black), Some(4.0 / 6.0));
+        assert_eq!(MoveStats::default().score(Color::White), None);
+        assert!(parse_explorer(r#"{"moves":[{"uci":"e2e5"}]}"#, &game).is_err());
+    }
+
+    #[test]
+    fn merges_book_moves() {
+        let game = Game::initialize();
+        let stats = parse_explorer(RESPONSE, &game).unwrap();
+        let book = [(Move::from_uci("d2d4", &game).unwrap(), 3), (Move::from_uci("c2c4", &game).unwrap(), 1)];
+        let rows = merge(&game, &stats, &book);
+        let moves: Vec<(&str, Option<f64>)> = rows.iter().map(|row| (row.san.as_str(), row.book)).collect();
+        assert_eq!(moves, [("e4", None), ("d4", Some(0.75)), ("c4", Some(0.25))]);
+        let text = ExplorerTable { side: Color::White, rows }.to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[1], "e4            1800    33%    44%    22%    56%      ");
+        assert_eq!(lines[3], "c4               -                               25%");
+    }
+}
@@ -1,63 +1,34 @@
 use bitflags::bitflags;
 use std::collections::VecDeque;
+use std::fmt;
+use crate::makemove::Undo;
+use crate::outcome::Outcome;
+use crate::render::{render, RenderOptions};
+use crate::square::{Rank, Square};
 use crate::utils::*;
+use crate::zobrist;
 
 // bitboard is more useful when generating moves and stuff
 type PiecePosition = u64;
 
-pub fn bit_to_position(bit: PiecePosition) -> Result<String, String> {
-    if bit == 0 {
-        return Err("No piece present!".to_string());
-    }
-    else {
-        let onebit_index = bit_scan(bit);
-        return Ok(index_to_position(onebit_index));
-    }
-}
-
-pub fn position_to_bit(position: &str) -> Result<PiecePosition, String> {
-    if position.len() != 2 {
-        return Err(format!("Invalid length of position {}", position.len()));
-    }
-
-    let bytes = position.as_bytes();
-    let byte0 = bytes[0];
-    if byte0 < 97 || byte0 >= 97 + 8 {
-        return Err(format!("Invalid column character {}", byte0 as char));
-    }
-    let column = (byte0 - 97) as u32;
-    let byte1 = bytes[1];
-    let row;
-    match (byte1 as char).to_digit(10) {
-        Some(number) => if number < 1 || number > 8 {
-            return Err(format!("Invalid row character {}", byte1 as char));
-        } else {
-            row = number - 1;
-        }, 
-        None => return Err(format!("Invalid row character {}", byte1 as char))
-    }
-    let square_number = row * 8 + column;
-    let bit = (1 as u64) << square_number;
-    Ok(bit)
-
-}
-
-static COL_MAP: [char; 8] = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
- 
-pub fn index_to_position(index: usize) -> String {
-    let column = index % 8;
-    let row = index / 8 + 1;
-    // 2, 1
-    return format!("{}{}", COL_MAP[column], row);
-}
-
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum Color {
     White,
     Black
 }
 
-#[derive(Debug, PartialEq)]
+impl Color {
+    pub fn opponent(&self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum PieceType {
     Pawn,
     Rook, 
@@ -67,15 +38,34 @@ pub enum PieceType {
     King
 }
 
-#[derive(Debug, PartialEq)]
+impl PieceType {
+    pub const ALL: [PieceType; 6] = [
+        PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen, PieceType::King,
+    ];
+
+    // material value in centipawns
+    pub fn value(&self) -> i32 {
+        match self {
+            PieceType::Pawn => 100,
+            PieceType::Knight => 320,
+            PieceType::Bishop => 330,
+            PieceType::Rook => 500,
+            PieceType::Queen => 900,
+            PieceType::King => 20000,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Piece {
-    position: PiecePosition,
-    color: Color,
-    piece_type: PieceType
+    pub position: PiecePosition,
+    pub color: Color,
+    pub piece_type: PieceType
 }
 
-impl Piece {
-    fn to_string(&self) -> String {
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut result = match self.piece_type {
             PieceType::Pawn => "p ", 
             PieceType::Rook => "r ", 
@@ -88,19 +78,21 @@ impl Piece {
         if self.color == Color::White {
             result.make_ascii_uppercase();
         }
-        result
+        write!(f, "{}", result)
     }
 }
 
-// Square is either empty or occupied
+// a board square is either empty or occupied
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Copy, Clone)]
-pub enum Square {
+pub enum SquareContent {
     Empty,
     Occupied(usize),
 }
 
 bitflags! {
-    struct CastlingRights: u8 {
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct CastlingRights: u8 {
         const NONE = 0;
         const WHITEKINGSIDE = 1 << 0;
         const WHITEQUEENSIDE = 1 << 1;
@@ -114,77 +106,167 @@ bitflags! {
     }
 }
 
+// each right with the color it belongs to, in FEN order
+pub(crate) const CASTLING_SIDES: [(CastlingRights, Color); 4] = [
+    (CastlingRights::WHITEKINGSIDE, Color::White),
+    (CastlingRights::WHITEQUEENSIDE, Color::White),
+    (CastlingRights::BLACKKINGSIDE, Color::Black),
+    (CastlingRights::BLACKQUEENSIDE, Color::Black),
+];
+
+// rook squares of the standard setup, in the same order
+const STANDARD_CASTLING_ROOKS: [usize; 4] = [7, 0, 63, 56];
+
+// what was wrong with a FEN string that could not be read
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum FenError {
+    MissingField(&'static str),
+    WrongRankCount(usize),
+    WrongRankLength { rank: usize, squares: usize },
+    InvalidPiece(char),
+    InvalidActiveColor(String),
+    InvalidCastling(char),
+    InvalidEnPassant(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+    TrailingInput(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::MissingField(field) => write!(f, "FEN is missing the {} field", field),
+            FenError::WrongRankCount(count) => write!(f, "Expected 8 ranks, found {}", count),
+            FenError::WrongRankLength { rank, squares } => write!(f, "Rank {} describes {} squares instead of 8", rank, squares),
+            FenError::InvalidPiece(ch) => write!(f, "Invalid piece character {}", ch),
+            FenError::InvalidActiveColor(color) => write!(f, "Invalid active color {}", color),
+            FenError::InvalidCastling(ch) => write!(f, "Invalid castling character {}", ch),
+            FenError::InvalidEnPassant(square) => write!(f, "Invalid en passant square {}", square),
+            FenError::InvalidHalfmoveClock(clock) => write!(f, "Invalid halfmove {}", clock),
+            FenError::InvalidFullmoveNumber(number) => write!(f, "Invalid fullmove {}", number),
+            FenError::TrailingInput(rest) => write!(f, "Unexpected input after the FEN: {}", rest),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
 // Game type to own the data
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone)]
 pub struct Game {
     pub pieces: Vec<Piece>,
-    pub squares: Vec<Square>,
+    pub squares: Vec<SquareContent>,
     pub active_color: Color,
     pub castling_rights: CastlingRights, 
+    // starting square of the rook for each right in CASTLING_SIDES order, not only
+    // the corners in chess960
+    pub(crate) castling_rooks: [usize; 4],
+    pub chess960: bool,
     pub en_passant: Option<PiecePosition>,
     pub halfmove_clock: usize,
     pub fullmove_number: usize,
+    pub history: Vec<Undo>,
+    pub outcome: Option<Outcome>,
+    // Zobrist key of the position, kept up to date by make_move
+    pub(crate) hash: u64,
+    // the same over the pawns alone, for caching pawn structure
+    pub(crate) pawn_hash: u64,
+    // material and piece-square score, white minus black, as (middlegame, endgame)
+    pub(crate) psqt: (i32, i32),
 }
 
-
-impl Game {
-    fn push_piece_and_sqaure(&mut self, position: usize, color: Color,
-                             piece_type: PieceType, index: &mut usize) {
-        self.pieces.push(Piece {position: (1 as u64) << position, 
-                                color: color, piece_type: piece_type});
-
-        self.squares.push(Square::Occupied(*index));
-        *index += 1;
+// two games are equal when they describe the same position with the same clocks,
+// regardless of piece order or the moves that led there
+impl PartialEq for Game {
+    fn eq(&self, other: &Game) -> bool {
+        Square::iter().all(|square| self.piece_at(square) == other.piece_at(square))
+            && self.active_color == other.active_color
+            && self.castling_rights == other.castling_rights
+            && self.castling_rooks == other.castling_rooks
+            && self.en_passant == other.en_passant
+            && self.halfmove_clock == other.halfmove_clock
+            && self.fullmove_number == other.fullmove_number
     }
+}
 
-    fn push_empty_square(&mut self) {
-        self.squares.push(Square::Empty);
+// the board with coordinates and dots on the empty squares, see render for the other looks
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let options = RenderOptions { coordinates: true, ..RenderOptions::default() };
+        write!(f, "{}", render(self, &options))
     }
+}
 
+impl Game {
     pub fn initialize() -> Game {
         Game::read_FEN("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
     }
 
-    pub fn to_string(&self) -> String {
-        let mut board = "".to_owned();
-        let mut temp = "".to_owned();
-        
-        for (i, square) in self.squares.iter().enumerate() {
-            match square {
-                Square::Empty => temp.push_str(&index_to_position(i)),
-                Square::Occupied(idx) => temp.push_str(&self.pieces[*idx].to_string()),
-            }
+    pub fn piece_at(&self, square: Square) -> Option<&Piece> {
+        match self.squares[square.index()] {
+            SquareContent::Empty => None,
+            SquareContent::Occupied(idx) => Some(&self.pieces[idx]),
+        }
+    }
 
-            if (i+1) % 8 == 0 {
-                temp.push_str("\n");
-                board.insert_str(0, &temp);
-                temp.clear()
-            }
+    // bitboard of every piece of the given color and type
+    pub fn bitboard(&self, color: Color, piece_type: PieceType) -> PiecePosition {
+        self.pieces.iter()
+            .filter(|p| p.color == color && p.piece_type == piece_type)
+            .fold(0, |bb, p| bb | p.position)
+    }
 
-        }
-        board.insert_str(0, &temp);
-        board
+    pub fn occupancy(&self, color: Color) -> PiecePosition {
+        self.pieces.iter()
+            .filter(|p| p.color == color)
+            .fold(0, |bb, p| bb | p.position)
     }
 
+    // panics on malformed input, use try_from_fen for anything typed in by a user
     #[allow(non_snake_case)]
     pub fn read_FEN(fen: &str) -> Game {
+        match Game::try_from_fen(fen) {
+            Ok(game) => game,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    pub fn try_from_fen(fen: &str) -> Result<Game, FenError> {
         let mut game = Game {
             pieces: vec![],
             squares: vec![],
             active_color: Color:: White,
             castling_rights: CastlingRights::ALL,
+            castling_rooks: STANDARD_CASTLING_ROOKS,
+            chess960: false,
             en_passant: None,
             halfmove_clock: 0, 
             fullmove_number: 1,
+            history: vec![],
+            outcome: None,
+            hash: 0,
+            pawn_hash: 0,
+            psqt: (0, 0),
         };
+        let fen = fen.trim();
         let (position, rest) = split_on(fen, ' ');
+        if position.is_empty() {
+            return Err(FenError::MissingField("piece placement"));
+        }
+
+        let rows = position.split('/').count();
+        if rows != 8 {
+            return Err(FenError::WrongRankCount(rows));
+        }
 
         let mut deque_squares = VecDeque::new();
         let mut piece_index = 0;
         let mut piece_position = 64;
 
-        for row in position.splitn(8, |ch| ch == '/') {
+        for row in position.split('/') {
             piece_position -= 8;
-            let (pieces, squares) = parse_row(&row, piece_index, piece_position);
+            let (pieces, squares) = parse_row(row, piece_index, piece_position)?;
             for p in pieces {
                 game.pieces.push(p);
                 piece_index += 1;
@@ -199,62 +281,213 @@ impl Game {
         game.active_color = match color_to_move {
             "w" => Color::White, 
             "b" => Color::Black, 
-            _ => panic!("Unknown color assigner")
+            "" => return Err(FenError::MissingField("active color")),
+            other => return Err(FenError::InvalidActiveColor(other.to_string())),
         };
 
         let (castling_rights, rest) = split_on(rest, ' ');
+        if castling_rights.is_empty() {
+            return Err(FenError::MissingField("castling rights"));
+        }
+        // KQkq pick the outermost rook on that side of the king (X-FEN), file letters
+        // name the rook directly (Shredder-FEN)
         let mut castling = CastlingRights::NONE;
         for ch in castling_rights.chars(){
-            match ch {
-                'K' => castling |= CastlingRights::WHITEKINGSIDE,
-                'Q' => castling |= CastlingRights::WHITEQUEENSIDE,
-                'k' => castling |= CastlingRights::BLACKKINGSIDE,
-                'q' => castling |= CastlingRights::BLACKQUEENSIDE,
-                '-' => (),
-                other => panic!("not a valid character for castling")
+            if ch == '-' && castling_rights.len() == 1 {
+                continue;
+            }
+            let (right, rook) = game.castling_rook_for(ch).ok_or(FenError::InvalidCastling(ch))?;
+            if !"KQkq".contains(ch) {
+                game.chess960 = true;
             }
+            castling |= right;
+            game.castling_rooks[CASTLING_SIDES.iter().position(|(r, _)| *r == right).unwrap()] = rook;
         }
         game.castling_rights = castling;
+        if !game.has_standard_castling() {
+            game.chess960 = true;
+        }
 
         let (en_passant, rest) = split_on(rest, ' ');
 
         match en_passant {
+            "" => return Err(FenError::MissingField("en passant square")),
             "-" => game.en_passant = None,
-            s => match position_to_bit(s) {
-                Err(msg) => panic!("{}", msg),
-                Ok(bit) => game.en_passant = Some(bit),
+            s => match s.parse::<Square>() {
+                Ok(square) if square.rank() == Rank::Third || square.rank() == Rank::Sixth =>
+                    game.en_passant = Some(square.bit()),
+                _ => return Err(FenError::InvalidEnPassant(s.to_string())),
             }
         };
 
         let (halfmove_clock, rest) = split_on(rest, ' ');
         match halfmove_clock.parse() {
             Ok(number) => game.halfmove_clock = number,
-            Err(_) => panic!("Invalid halfmove {}", halfmove_clock),
+            Err(_) if halfmove_clock.is_empty() => return Err(FenError::MissingField("halfmove clock")),
+            Err(_) => return Err(FenError::InvalidHalfmoveClock(halfmove_clock.to_string())),
         }
 
         let (fullmove_number, rest) = split_on(rest, ' ');
         match fullmove_number.parse() {
-            Ok(number) => game.fullmove_number = number,
-            Err(_) => panic!("Invalid fullmove {}", fullmove_number),
+            Ok(number) if number > 0 => game.fullmove_number = number,
+            _ if fullmove_number.is_empty() => return Err(FenError::MissingField("fullmove number")),
+            _ => return Err(FenError::InvalidFullmoveNumber(fullmove_number.to_string())),
         }
 
+        if !rest.trim().is_empty() {
+            return Err(FenError::TrailingInput(rest.trim().to_string()));
+        }
 
-        game
+        game.refresh_incremental_state();
+        Ok(game)
     }
 }
 
-fn parse_row(row: &str, mut piece_index: usize, mut piece_position: usize) -> (Vec<Piece>, VecDeque<Square>) {
+impl Game {
+    // the keys and scores make_move keeps up to date, computed from scratch after the
+    // position was set up some other way
+    pub(crate) fn refresh_incremental_state(&mut self) {
+        self.hash = zobrist::compute(self);
+        self.pawn_hash = zobrist::compute_pawns(self);
+        self.psqt = self.material_and_pst();
+    }
+
+    // standard FEN, X-FEN for chess960 positions
+    pub fn to_fen(&self) -> String {
+        self.fen_with_castling(self.castling_field(false))
+    }
+
+    // castling rights always written as rook files, e.g. HAha
+    pub fn to_shredder_fen(&self) -> String {
+        self.fen_with_castling(self.castling_field(true))
+    }
+
+    fn castling_field(&self, shredder: bool) -> String {
+        let mut castling = String::new();
+        for (i, (right, color)) in CASTLING_SIDES.iter().enumerate() {
+            if !self.castling_rights.contains(*right) {
+                continue;
+            }
+            let rook = self.castling_rooks[i];
+            let outermost = self.outermost_rook(*right, *color) == Some(rook);
+            let ch = match (shredder || !outermost, i % 2) {
+                (true, _) => (b'A' + (rook % 8) as u8) as char,
+                (false, 0) => 'K',
+                (false, _) => 'Q',
+            };
+            castling.push(if *color == Color::White { ch } else { ch.to_ascii_lowercase() });
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+        castling
+    }
+
+    // the right and rook square a castling character stands for, None if there is
+    // no king on the back rank or no rook to castle with
+    fn castling_rook_for(&self, ch: char) -> Option<(CastlingRights, usize)> {
+        let color = if ch.is_ascii_uppercase() { Color::White } else { Color::Black };
+        let back_rank = if color == Color::White { 0 } else { 56 };
+        let sides = if color == Color::White { &CASTLING_SIDES[0..2] } else { &CASTLING_SIDES[2..4] };
+        let (kingside, queenside) = (sides[0].0, sides[1].0);
+        let king = (0..8).map(|file| back_rank + file)
+            .find(|square| self.piece_at(Square::from_index(*square)).is_some_and(|p| p.color == color && p.piece_type == PieceType::King));
+
+        match ch.to_ascii_lowercase() {
+            // without the pieces in place still record the standard rook, validate reports it
+            'k' => Some((kingside, self.outermost_rook(kingside, color).unwrap_or(back_rank + 7))),
+            'q' => Some((queenside, self.outermost_rook(queenside, color).unwrap_or(back_rank))),
+            file @ 'a'..='h' => {
+                let rook = back_rank + (file as usize - 'a' as usize);
+                let right = if rook > king? { kingside } else { queenside };
+                Some((right, rook))
+            }
+            _ => None,
+        }
+    }
+
+    // rook furthest from the king on the given side of the back rank
+    fn outermost_rook(&self, right: CastlingRights, color: Color) -> Option<usize> {
+        let back_rank = if color == Color::White { 0 } else { 56 };
+        let is_piece = |square: usize, piece_type| self.piece_at(Square::from_index(square))
+            .is_some_and(|p| p.color == color && p.piece_type == piece_type);
+        let king = (back_rank..back_rank + 8).find(|square| is_piece(*square, PieceType::King))?;
+        let kingside = right.intersects(CastlingRights::WHITEKINGSIDE | CastlingRights::BLACKKINGSIDE);
+        if kingside {
+            (king + 1..back_rank + 8).rev().find(|square| is_piece(*square, PieceType::Rook))
+        } else {
+            (back_rank..king).find(|square| is_piece(*square, PieceType::Rook))
+        }
+    }
+
+    // king on the e-file and every castling rook in its corner
+    fn has_standard_castling(&self) -> bool {
+        CASTLING_SIDES.iter().enumerate().all(|(i, (right, color))| {
+            let king = if *color == Color::White { 4 } else { 60 };
+            !self.castling_rights.contains(*right)
+                || (self.castling_rooks[i] == STANDARD_CASTLING_ROOKS[i]
+                    && self.bitboard(*color, PieceType::King) == 1u64 << king)
+        })
+    }
+
+    fn fen_with_castling(&self, castling: String) -> String {
+        let mut rows = Vec::new();
+        for rank in (0..8usize).rev() {
+            let mut row = String::new();
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.piece_at(Square::from_index(rank * 8 + file)) {
+                    None => empty += 1,
+                    Some(piece) => {
+                        if empty > 0 {
+                            row.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        row.push_str(piece.to_string().trim_end());
+                    }
+                }
+            }
+            if empty > 0 {
+                row.push_str(&empty.to_string());
+            }
+            rows.push(row);
+        }
+
+        let color = match self.active_color {
+            Color::White => "w",
+            Color::Black => "b",
+        };
+
+        let en_passant = match self.en_passant {
+            None => "-".to_string(),
+            Some(bit) => Square::from_index(bit_scan(bit)).to_string(),
+        };
+
+        format!("{} {} {} {} {} {}", rows.join("/"), color, castling, en_passant,
+                self.halfmove_clock, self.fullmove_number)
+    }
+}
+
+fn parse_row(row: &str, mut piece_index: usize, mut piece_position: usize) -> Result<(Vec<Piece>, VecDeque<SquareContent>), FenError> {
     let mut pieces = Vec::new();
     let mut squares = VecDeque::new();
 
+    if let Some(ch) = row.chars().find(|ch| !"prnbqkPRNBQK12345678".contains(*ch)) {
+        return Err(FenError::InvalidPiece(ch));
+    }
+    let length: u32 = row.chars().map(|ch| ch.to_digit(10).unwrap_or(1)).sum();
+    if length != 8 {
+        return Err(FenError::WrongRankLength { rank: piece_position / 8 + 1, squares: length as usize });
+    }
+
     let mut color; 
 
     macro_rules! add_piece {
         ($piece_type:ident) => {
             {
-                let piece = Piece {color:color, position: (1 as u64) << piece_position, 
+                let piece = Piece {color, position: 1u64 << piece_position, 
                                 piece_type: PieceType::$piece_type};
-                let square = Square::Occupied(piece_index);
+                let square = SquareContent::Occupied(piece_index);
                 pieces.push(piece);
                 squares.push_front(square);
                 piece_position += 1;
@@ -276,16 +509,111 @@ fn parse_row(row: &str, mut piece_index: usize, mut piece_position: usize) -> (V
 
             num => {
                 match num.to_digit(10) {
-                    None => panic!("Invalid input: {}", num),
-                    Some(number) => for i in 0..number {
-                        squares.push_front(Square::Empty);
+                    Some(number) if (1..=8).contains(&number) => for _ in 0..number {
+                        squares.push_front(SquareContent::Empty);
                         piece_position += 1;
                     }
+                    _ => return Err(FenError::InvalidPiece(ch)),
                 }
             }
         }
 
     }
-    (pieces, squares)
+    Ok((pieces, squares))
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fen_round_trips() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w Kq f6 0 3",
+            "4k3/8/8/8/8/8/8/4K3 b - - 57 120",
+        ] {
+            let game = Game::read_FEN(fen);
+            assert_eq!(game.to_fen(), fen);
+            assert!(Game::read_FEN(&game.to_fen()) == game);
+        }
+    }
+
+    #[test]
+    fn malformed_fens_are_rejected() {
+        let cases = [
+            ("", FenError::MissingField("piece placement")),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1", FenError::WrongRankCount(7)),
+            ("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::WrongRankLength { rank: 7, squares: 7 }),
+            ("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::InvalidPiece('9')),
+            ("rnbqkbnrp/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::WrongRankLength { rank: 8, squares: 9 }),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1", FenError::InvalidPiece('X')),
+            ("8/8/8/8/8/8/8/8", FenError::MissingField("active color")),
+            ("8/8/8/8/8/8/8/8 x KQkq - 0 1", FenError::InvalidActiveColor("x".to_string())),
+            ("8/8/8/8/8/8/8/8 w KQz - 0 1", FenError::InvalidCastling('z')),
+            ("8/8/8/8/8/8/8/8 w K- - 0 1", FenError::InvalidCastling('-')),
+            ("8/8/8/8/8/8/8/8 w - e5 0 1", FenError::InvalidEnPassant("e5".to_string())),
+            ("8/8/8/8/8/8/8/8 w - - x 1", FenError::InvalidHalfmoveClock("x".to_string())),
+            ("8/8/8/8/8/8/8/8 w - - 0", FenError::MissingField("fullmove number")),
+            ("8/8/8/8/8/8/8/8 w - - 0 0", FenError::InvalidFullmoveNumber("0".to_string())),
+            ("8/8/8/8/8/8/8/8 w - - 0 1 extra", FenError::TrailingInput("extra".to_string())),
+        ];
+        for (fen, err) in cases {
+            assert_eq!(Game::try_from_fen(fen).err(), Some(err), "{}", fen);
+        }
+        assert!(Game::try_from_fen(" 8/8/8/8/8/8/8/8 w - - 0 1 ").is_ok());
+    }
 
+    #[test]
+    fn chess960_castling_fields_round_trip() {
+        let shredder = "bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w GEge - 0 1";
+        let game = Game::read_FEN(shredder);
+        assert!(game.chess960);
+        assert_eq!(game.castling_rooks, [6, 4, 62, 60]);
+        assert_eq!(game.to_shredder_fen(), shredder);
+        assert_eq!(game.to_fen(), "bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w KQkq - 0 1");
+        assert!(Game::read_FEN(&game.to_fen()) == game);
+
+        // the castling rook is not the outermost one, so X-FEN has to name its file
+        let xfen = "1k2r2r/8/8/8/8/8/8/1K2R2R w Ee - 0 1";
+        let game = Game::read_FEN(xfen);
+        assert_eq!(game.castling_rights, CastlingRights::WHITEKINGSIDE | CastlingRights::BLACKKINGSIDE);
+        assert_eq!(game.to_fen(), xfen);
+        assert!(Game::read_FEN(&game.to_shredder_fen()) == game);
+
+        let standard = Game::read_FEN("r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1");
+        assert!(standard == Game::read_FEN("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"));
+        assert!(!Game::read_FEN("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").chess960);
+        assert_eq!(Game::try_from_fen("8/8/8/8/8/8/8/R6R w A - 0 1").err(), Some(FenError::InvalidCastling('A')));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn game_round_trips_through_json() {
+        let mut game = Game::read_FEN("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        game.make_move(crate::movegen::Move::from_uci("e1g1", &game).unwrap());
+        let json = serde_json::to_string(&game).unwrap();
+        let mut restored: Game = serde_json::from_str(&json).unwrap();
+        assert!(restored == game);
+        assert_eq!(restored.history.len(), 1);
+        assert_eq!(restored.unmake_move().unwrap().to_string(), "e1g1");
+
+        let mv: crate::movegen::Move = serde_json::from_str(&serde_json::to_string(&game.history[0].mv).unwrap()).unwrap();
+        assert!(mv.is_castle());
+    }
+
+    #[test]
+    fn equality_ignores_piece_order_and_history() {
+        let mut game = Game::initialize();
+        for mv in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+            game.make_move(mv.parse().unwrap());
+        }
+        let fresh = Game::read_FEN("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 4 3");
+        assert!(game == fresh);
+        assert!(Game::read_FEN(&game.to_fen()) == game);
+        assert!(game != Game::initialize());
+    }
 }
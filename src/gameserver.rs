@@ -0,0 +1,438 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use serde_json::{json, Value};
+use crate::game::{Color, Game};
+use crate::movegen::Move;
+use crate::pgn::GameRecord;
+use crate::timeman::Instant;
+use crate::websocket;
+
+// how often running clocks are checked for a flag fall
+const TICK: Duration = Duration::from_millis(100);
+
+fn index(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+// both players' time, the side to move's clock runs from `turn_started`
+#[derive(Debug, Clone, Copy)]
+struct Clock {
+    remaining: [Duration; 2],
+    increment: Duration,
+    turn_started: Option<Instant>,
+}
+
+impl Clock {
+    fn remaining(&self, color: Color, to_move: Color) -> Duration {
+        let running = match self.turn_started {
+            Some(started) if color == to_move => started.elapsed(),
+            _ => Duration::ZERO,
+        };
+        self.remaining[index(color)].saturating_sub(running)
+    }
+
+    // stops the mover's clock, false when their time ran out before the move
+    fn punch(&mut self, mover: Color) -> bool {
+        let left = self.remaining(mover, mover);
+        self.remaining[index(mover)] = left + self.increment;
+        self.turn_started = Some(Instant::now());
+        !left.is_zero()
+    }
+}
+
+struct Room {
+    game: Game,
+    players: [Option<(u64, String)>; 2],
+    spectators: Vec<u64>,
+    clock: Option<Clock>,
+}
+
+impl Room {
+    fn seat(&self, client: u64) -> Option<Color> {
+        [Color::White, Color::Black].into_iter()
+            .find(|color| matches!(&self.players[index(*color)], Some((id, _)) if *id == client))
+    }
+
+    fn status(&self) -> String {
+        match self.game.outcome {
+            Some(outcome) => format!("{} {}", outcome.result, outcome.reason),
+            None if self.players.iter().any(Option::is_none) => "waiting".to_string(),
+            None => "started".to_string(),
+        }
+    }
+
+    fn audience(&self) -> Vec<u64> {
+        self.players.iter().flatten().map(|(id, _)| *id).chain(self.spectators.iter().copied()).collect()
+    }
+
+    fn state(&self, id: u64) -> Value {
+        let name = |color: Color| self.players[index(color)].as_ref().map(|(_, name)| name.clone());
+        let millis = |color: Color| self.clock.map(|clock| clock.remaining(color, self.game.active_color).as_millis() as u64);
+        let moves: Vec<String> = self.game.history.iter().map(|undo| undo.mv.to_uci()).collect();
+        json!({
+            "type": "state", "game": id, "fen": self.game.to_fen(), "moves": moves.join(" "),
+            "white": name(Color::White), "black": name(Color::Black),
+            "wtime": millis(Color::White), "btime": millis(Color::Black), "status": self.status(),
+        })
+    }
+
+    // the finished game as PGN, with the players' names
+    fn pgn(&self, id: u64) -> Result<String, String> {
+        let mut record = GameRecord::from_game(&self.game);
+        record.headers.push(("Event".to_string(), "Rustic Chess server".to_string()));
+        record.headers.push(("Round".to_string(), id.to_string()));
+        for (tag, player) in ["White", "Black"].into_iter().zip(&self.players) {
+            if let Some((_, name)) = player {
+                record.headers.push((tag.to_string(), name.clone()));
+            }
+        }
+        record.to_pgn()
+    }
+}
+
+// every game on the server and the clients watching them, messages to a client go
+// through its channel so a slow connection never holds up the others
+pub struct Lobby {
+    games: BTreeMap<u64, Room>,
+    clients: HashMap<u64, mpsc::Sender<String>>,
+    next_id: u64,
+    // where finished games are written as game-<id>.pgn
+    pgn_dir: Option<PathBuf>,
+}
+
+impl Lobby {
+    pub fn new(pgn_dir: Option<PathBuf>) -> Lobby {
+        Lobby { games: BTreeMap::new(), clients: HashMap::new(), next_id: 1, pgn_dir }
+    }
+
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    pub fn connect(&mut self, sender: mpsc::Sender<String>) -> u64 {
+        let client = self.next_id();
+        self.clients.insert(client, sender);
+        client
+    }
+
+    // leaving a game in progress loses it, leaving one still waiting for an opponent cancels it
+    pub fn disconnect(&mut self, client: u64) {
+        self.clients.remove(&client);
+        let (mut left, mut cancelled) = (vec![], vec![]);
+        for (id, room) in &mut self.games {
+            room.spectators.retain(|spectator| *spectator != client);
+            if let Some(color) = room.seat(client) {
+                if room.players.iter().any(Option::is_none) {
+                    cancelled.push(*id);
+                } else if room.game.outcome.is_none() {
+                    room.game.resign(color);
+                    left.push(*id);
+                }
+            }
+        }
+        for id in cancelled {
+            if let Some(room) = self.games.remove(&id) {
+                let message = json!({ "type": "cancelled", "game": id });
+                for client in room.audience() {
+                    self.send(client, &message);
+                }
+            }
+        }
+        for id in left {
+            self.finish(id);
+        }
+    }
+
+    fn send(&self, client: u64, message: &Value) {
+        if let Some(sender) = self.clients.get(&client) {
+            let _ = sender.send(message.to_string());
+        }
+    }
+
+    fn broadcast(&self, id: u64) {
+        if let Some(room) = self.games.get(&id) {
+            let state = room.state(id);
+            for client in room.audience() {
+                self.send(client, &state);
+            }
+        }
+    }
+
+    // tells everyone about the end of a game, saves it and closes the room
+    fn finish(&mut self, id: u64) {
+        self.broadcast(id);
+        let (Some(room), Some(dir)) = (self.games.remove(&id), &self.pgn_dir) else { return };
+        let saved = room.pgn(id).and_then(|pgn| {
+            fs::create_dir_all(dir).and_then(|_| fs::write(dir.join(format!("game-{}.pgn", id)), pgn))
+                .map_err(|err| err.to_string())
+        });
+        if let Err(err) = saved {
+            eprintln!("could not save game {}: {}", id, err);
+        }
+    }
+
+    // flags the side to move in every game where their time ran out
+    pub fn tick(&mut self) {
+        let mut flagged = vec![];
+        for (id, room) in &mut self.games {
+            let to_move = room.game.active_color;
+            if let (None, Some(clock)) = (room.game.outcome, room.clock) {
+                if clock.turn_started.is_some() && clock.remaining(to_move, to_move).is_zero() {
+                    room.game.flag_fall(to_move);
+                    flagged.push(*id);
+                }
+            }
+        }
+        for id in flagged {
+            self.finish(id);
+        }
+    }
+
+    // handles one json message from a client, errors are sent back to it
+    pub fn handle(&mut self, client: u64, text: &str) {
+        if let Err(err) = self.dispatch(client, text) {
+            self.send(client, &json!({ "type": "error", "message": err }));
+        }
+    }
+
+    fn room(&mut self, message: &Value) -> Result<(u64, &mut Room), String> {
+        let id = message["game"].as_u64().ok_or("Missing game")?;
+        self.games.get_mut(&id).map(|room| (id, room)).ok_or_else(|| format!("No game {}", id))
+    }
+
+    fn dispatch(&mut self, client: u64, text: &str) -> Result<(), String> {
+        let message: Value = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        let name = message["name"].as_str().unwrap_or("Anonymous").to_string();
+        match message["type"].as_str().ok_or("Missing type")? {
+            // create {time, increment} in seconds, no time for an untimed game, color for the creator's seat
+            "create" => {
+                let game = match message["fen"].as_str() {
+                    Some(fen) => {
+                        let game = Game::try_from_fen(fen).map_err(|err| err.to_string())?;
+                        game.validate().map_err(|err| err.to_string())?;
+                        game
+                    }
+                    None => Game::initialize(),
+                };
+                let clock = message["time"].as_f64().map(|time| {
+                    let time = Duration::from_secs_f64(time.max(0.0));
+                    let increment = Duration::from_secs_f64(message["increment"].as_f64().unwrap_or(0.0).max(0.0));
+                    Clock { remaining: [time; 2], increment, turn_started: None }
+                });
+                let color = if message["color"].as_str() == Some("black") { Color::Black } else { Color::White };
+                let mut room = Room { game, players: [None, None], spectators: vec![], clock };
+                room.players[index(color)] = Some((client, name));
+                let id = self.next_id();
+                self.games.insert(id, room);
+                self.broadcast(id);
+            }
+            "join" => {
+                let (id, room) = self.room(&message)?;
+                if room.seat(client).is_some() {
+                    return Err("Already playing in this game".to_string());
+                }
+                let seat = room.players.iter_mut().find(|seat| seat.is_none()).ok_or("The game is full")?;
+                *seat = Some((client, name));
+                // the clock starts once both players are there
+                if let Some(clock) = &mut room.clock {
+                    clock.turn_started = Some(Instant::now());
+                }
+                self.broadcast(id);
+            }
+            "watch" => {
+                let (id, room) = self.room(&message)?;
+                if !room.spectators.contains(&client) {
+                    room.spectators.push(client);
+                }
+                let state = room.state(id);
+                self.send(client, &state);
+            }
+            "move" => {
+                let (id, room) = self.room(&message)?;
+                let color = room.seat(client).ok_or("Not playing in this game")?;
+                if room.status() != "started" {
+                    return Err(format!("The game is {}", room.status()));
+                }
+                if room.game.active_color != color {
+                    return Err("Not your move".to_string());
+                }
+                let mv = Move::from_uci(message["move"].as_str().ok_or("Missing move")?, &room.game)?;
+                let in_time = room.clock.as_mut().is_none_or(|clock| clock.punch(color));
+                if in_time {
+                    room.game.make_move(mv);
+                    room.game.update_outcome();
+                } else {
+                    room.game.flag_fall(color);
+                }
+                if room.game.outcome.is_some() { self.finish(id) } else { self.broadcast(id) }
+            }
+            "resign" => {
+                let (id, room) = self.room(&message)?;
+                let color = room.seat(client).ok_or("Not playing in this game")?;
+                if room.game.outcome.is_some() {
+                    return Err("The game is over".to_string());
+                }
+                room.game.resign(color);
+                self.finish(id);
+            }
+            "list" => {
+                let games: Vec<Value> = self.games.iter()
+                    .map(|(id, room)| {
+                        let name = |color: Color| room.players[index(color)].as_ref().map(|(_, name)| name.clone());
+                        json!({ "game": id, "white": name(Color::White), "black": name(Color::Black), "status": room.status() })
+                    })
+                    .collect();
+                self.send(client, &json!({ "type": "games", "games": games }));
+            }
+            other => return Err(format!("Unknown message type {}", other)),
+        }
+        Ok(())
+    }
+}
+
+fn handle_connection(stream: TcpStream, lobby: Arc<Mutex<Lobby>>) -> io::Result<()> {
+    let mut connection = websocket::accept(stream)?;
+    let client = lobby.lock().unwrap().connect(connection.sender());
+    let result = (|| {
+        while let Some(text) = connection.recv()? {
+            lobby.lock().unwrap().handle(client, &text);
+        }
+        Ok(())
+    })();
+    lobby.lock().unwrap().disconnect(client);
+    result
+}
+
+// accepts websocket clients, each on its own thread
+pub fn serve(listener: TcpListener, pgn_dir: Option<PathBuf>) -> io::Result<()> {
+    let lobby = Arc::new(Mutex::new(Lobby::new(pgn_dir)));
+    let ticking = lobby.clone();
+    thread::spawn(move || loop {
+        thread::sleep(TICK);
+        ticking.lock().unwrap().tick();
+    });
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("could not accept a connection: {}", err);
+                continue;
+            }
+        };
+        let lobby = lobby.clone();
+        thread::spawn(move || {
+            if let Err(err) = handle_connection(stream, lobby) {
+                eprintln!("connection closed: {}", err);
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(lobby: &mut Lobby) -> (u64, mpsc::Receiver<String>) {
+        let (sender, receiver) = mpsc::channel();
+        (lobby.connect(sender), receiver)
+    }
+
+    fn last(receiver: &mpsc::Receiver<String>) -> Value {
+        let text = receiver.try_iter().last().expect("no message");
+        serde_json::from_str(&text).unwrap()
+    }
+
+    #[test]
+    fn plays_a_game_with_spectators() {
+        let dir = std::env::temp_dir().join(format!("rustic-server-{}", std::process::id()));
+        let mut lobby = Lobby::new(Some(dir.clone()));
+        let (white, white_in) = client(&mut lobby);
+        let (black, black_in) = client(&mut lobby);
+        let (spectator, spectator_in) = client(&mut lobby);
+        lobby.handle(white, r#"{"type":"create","name":"alice","time":60,"increment":1}"#);
+        let created = last(&white_in);
+        let id = created["game"].as_u64().unwrap();
+        assert_eq!(created["status"], "waiting");
+
+        lobby.handle(black, &format!(r#"{{"type":"join","game":{},"name":"bob"}}"#, id));
+        lobby.handle(spectator, &format!(r#"{{"type":"watch","game":{}}}"#, id));
+        assert_eq!(last(&spectator_in)["status"], "started");
+        lobby.handle(black, &format!(r#"{{"type":"move","game":{},"move":"e7e5"}}"#, id));
+        assert_eq!(last(&black_in)["message"], "Not your move");
+        lobby.handle(white, &format!(r#"{{"type":"move","game":{},"move":"e2e5"}}"#, id));
+        assert!(last(&white_in)["message"].as_str().unwrap().contains("e2e5"));
+
+        for (player, mv) in [(white, "f2f3"), (black, "e7e5"), (white, "g2g4"), (black, "d8h4")] {
+            lobby.handle(player, &format!(r#"{{"type":"move","game":{},"move":"{}"}}"#, id, mv));
+        }
+        let state = last(&spectator_in);
+        assert_eq!(state["status"], "0-1 checkmate");
+        assert_eq!(state["moves"], "f2f3 e7e5 g2g4 d8h4");
+        assert!(state["wtime"].as_u64().unwrap() > 60_000);
+
+        let pgn = fs::read_to_string(dir.join(format!("game-{}.pgn", id))).unwrap();
+        assert!(pgn.contains("[White \"alice\"]") && pgn.contains("[Black \"bob\"]"), "{}", pgn);
+        assert!(pgn.contains("2. g4 Qh4# 0-1"), "{}", pgn);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn leaving_or_running_out_of_time_loses() {
+        let mut lobby = Lobby::new(None);
+        let (white, white_in) = client(&mut lobby);
+        let (black, _) = client(&mut lobby);
+        lobby.handle(white, r#"{"type":"create","time":0.05}"#);
+        let id = last(&white_in)["game"].as_u64().unwrap();
+        lobby.handle(black, &format!(r#"{{"type":"join","game":{}}}"#, id));
+        thread::sleep(Duration::from_millis(80));
+        lobby.tick();
+        assert_eq!(last(&white_in)["status"], "0-1 flag fall");
+
+        lobby.handle(white, r#"{"type":"create"}"#);
+        let id = last(&white_in)["game"].as_u64().unwrap();
+        lobby.handle(black, &format!(r#"{{"type":"join","game":{}}}"#, id));
+        lobby.disconnect(black);
+        assert_eq!(last(&white_in)["status"], "1-0 resignation");
+        // finished games leave the lobby
+        lobby.handle(white, r#"{"type":"list"}"#);
+        assert_eq!(last(&white_in)["games"], json!([]));
+        lobby.handle(white, &format!(r#"{{"type":"watch","game":{}}}"#, id));
+        assert_eq!(last(&white_in)["message"], format!("No game {}", id));
+    }
+
+    #[test]
+    fn leaving_a_waiting_game_cancels_it() {
+        let mut lobby = Lobby::new(None);
+        let (white, white_in) = client(&mut lobby);
+        let (spectator, spectator_in) = client(&mut lobby);
+        lobby.handle(white, r#"{"type":"create"}"#);
+        let id = last(&white_in)["game"].as_u64().unwrap();
+        lobby.handle(spectator, &format!(r#"{{"type":"watch","game":{}}}"#, id));
+        lobby.disconnect(white);
+        assert_eq!(last(&spectator_in), json!({ "type": "cancelled", "game": id }));
+        lobby.handle(spectator, r#"{"type":"list"}"#);
+        assert_eq!(last(&spectator_in)["games"], json!([]));
+    }
+
+    #[test]
+    fn refuses_impossible_positions() {
+        let mut lobby = Lobby::new(None);
+        let (white, white_in) = client(&mut lobby);
+        lobby.handle(white, r#"{"type":"create","fen":"8/8/8/8/8/8/8/4K3 w - - 0 1"}"#);
+        assert_eq!(last(&white_in), json!({ "type": "error", "message": "Black has no king" }));
+        lobby.handle(white, r#"{"type":"create","fen":"not a fen"}"#);
+        assert_eq!(last(&white_in)["type"], "error");
+    }
+}
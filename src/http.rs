@@ -0,0 +1,113 @@
+use std::io::{self, BufRead, Write};
+
+// request bodies are small json documents, anything bigger is refused
+const MAX_BODY: usize = 1 << 20;
+// the request line and headers together, and how many headers, before a request is refused
+const MAX_HEAD: usize = 8 << 10;
+const MAX_HEADERS: usize = 32;
+
+// the request line and headers of an http request, header names in lowercase
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestHead {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl RequestHead {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub fn read_request_head(reader: &mut impl BufRead) -> io::Result<RequestHead> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut bytes = vec![];
+    loop {
+        if bytes.len() == MAX_HEAD {
+            return Err(invalid("request head too large"));
+        }
+        let chunk = reader.fill_buf()?;
+        if chunk.is_empty() {
+            return Err(invalid("request ended inside the headers"));
+        }
+        let read = bytes.len();
+        let taken = chunk.len().min(MAX_HEAD - read);
+        bytes.extend_from_slice(&chunk[..taken]);
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut request = httparse::Request::new(&mut headers);
+        match request.parse(&bytes).map_err(|err| invalid(&err.to_string()))? {
+            // the body starts right after the head and stays in the reader
+            httparse::Status::Complete(len) => {
+                reader.consume(len - read);
+                let headers = request.headers.iter()
+                    .map(|header| (header.name.to_ascii_lowercase(), String::from_utf8_lossy(header.value).trim().to_string()))
+                    .collect();
+                let (Some(method), Some(path)) = (request.method, request.path) else {
+                    return Err(invalid("bad request line"));
+                };
+                return Ok(RequestHead { method: method.to_string(), path: path.to_string(), headers });
+            }
+            httparse::Status::Partial => reader.consume(taken),
+        }
+    }
+}
+
+// a whole request, the body read to its Content-Length
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub head: RequestHead,
+    pub body: String,
+}
+
+pub fn read_request(reader: &mut impl BufRead) -> io::Result<Request> {
+    let head = read_request_head(reader)?;
+    let len = match head.header("content-length") {
+        Some(len) => len.parse().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "bad Content-Length"))?,
+        None => 0,
+    };
+    if len > MAX_BODY {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "request body too large"));
+    }
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body)?;
+    let body = String::from_utf8(body).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Ok(Request { head, body })
+}
+
+// writes a response and asks the client to close the connection after it
+pub fn write_response(out: &mut impl Write, status: &str, content_type: &str, body: &str) -> io::Result<()> {
+    write!(out, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, content_type, body.len(), body)?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_a_request_with_a_body() {
+        let text = "POST /rpc HTTP/1.1\r\nHost: x\r\nContent-Type: application/json\r\nContent-Length: 4\r\n\r\n{}{}extra";
+        let request = read_request(&mut text.as_bytes()).unwrap();
+        assert_eq!((request.head.method.as_str(), request.head.path.as_str()), ("POST", "/rpc"));
+        assert_eq!(request.head.header("Content-Type"), Some("application/json"));
+        assert_eq!(request.body, "{}{}");
+        assert!(read_request(&mut "GET / HTTP/1.1\r\nHost".as_bytes()).is_err());
+
+        // a request read a few bytes at a time leaves its body in the reader
+        let mut reader = io::BufReader::with_capacity(3, text.as_bytes());
+        assert_eq!(read_request(&mut reader).unwrap().body, "{}{}");
+
+        let long_line = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_HEAD));
+        assert!(read_request(&mut long_line.as_bytes()).is_err());
+        let many_headers = format!("GET / HTTP/1.1\r\n{}\r\n", "X-A: b\r\n".repeat(MAX_HEADERS + 1));
+        assert!(read_request(&mut many_headers.as_bytes()).is_err());
+
+        let mut out = vec![];
+        write_response(&mut out, "200 OK", "text/plain", "hi").unwrap();
+        assert!(String::from_utf8(out).unwrap().ends_with("Content-Length: 2\r\nConnection: close\r\n\r\nhi"));
+    }
+}
@@ -0,0 +1,94 @@
+use reqwest::header::ACCEPT;
+use reqwest::Client;
+use crate::pgn::*;
+
+const LICHESS_API: &str = "https://lichess.org/api";
+const CHESSCOM_API: &str = "https://api.chess.com/pub";
+
+// both sites reject requests without a user agent
+const USER_AGENT: &str = concat!("rustic_chess/", env!("CARGO_PKG_VERSION"));
+
+pub fn client() -> Result<Client, String> {
+    Client::builder().user_agent(USER_AGENT).build().map_err(|err| err.to_string())
+}
+
+pub fn lichess_games_url(user: &str, max: Option<usize>) -> String {
+    let mut url = format!("{}/games/user/{}?pgnInJson=true&clocks=false&evals=false", LICHESS_API, user);
+    if let Some(max) = max {
+        url.push_str(&format!("&max={}", max));
+    }
+    url
+}
+
+pub fn chesscom_archives_url(user: &str) -> String {
+    format!("{}/player/{}/games/archives", CHESSCOM_API, user.to_lowercase())
+}
+
+// lichess streams one json object per game, the pgn is embedded when asked for with pgnInJson
+pub fn parse_lichess_ndjson(text: &str) -> Result<Vec<GameRecord>, String> {
+    let mut records = Vec::new();
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let value: serde_json::Value = serde_json::from_str(line).map_err(|err| err.to_string())?;
+        let pgn = value["pgn"].as_str().ok_or("Game without a pgn field")?;
+        records.extend(parse_pgn(pgn)?);
+    }
+    Ok(records)
+}
+
+// the monthly archive urls, oldest first
+pub fn parse_chesscom_archives(text: &str) -> Result<Vec<String>, String> {
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|err| err.to_string())?;
+    let archives = value["archives"].as_array().ok_or("Response without an archives list")?;
+    archives.iter()
+        .map(|url| url.as_str().map(String::from).ok_or_else(|| "Archive url is not a string".to_string()))
+        .collect()
+}
+
+async fn fetch(client: &Client, url: &str, accept: &str) -> Result<String, String> {
+    let response = client.get(url).header(ACCEPT, accept).send().await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| format!("{}: {}", url, err))?;
+    response.text().await.map_err(|err| format!("{}: {}", url, err))
+}
+
+pub async fn import_lichess(client: &Client, user: &str, max: Option<usize>) -> Result<Vec<GameRecord>, String> {
+    let text = fetch(client, &lichess_games_url(user, max), "application/x-ndjson").await?;
+    parse_lichess_ndjson(&text)
+}
+
+// chess.com serves games by month, every archive is downloaded as one pgn file
+pub async fn import_chesscom(client: &Client, user: &str) -> Result<Vec<GameRecord>, String> {
+    let archives = fetch(client, &chesscom_archives_url(user), "application/json").await?;
+    let mut records = Vec::new();
+    for archive in parse_chesscom_archives(&archives)? {
+        let text = fetch(client, &format!("{}/pgn", archive), "application/x-chess-pgn").await?;
+        records.extend(parse_pgn(&text)?);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_api_urls() {
+        assert_eq!(lichess_games_url("someone", Some(10)),
+            "https://lichess.org/api/games/user/someone?pgnInJson=true&clocks=false&evals=false&max=10");
+        assert_eq!(chesscom_archives_url("SomeOne"), "https://api.chess.com/pub/player/someone/games/archives");
+    }
+
+    #[test]
+    fn parses_responses() {
+        let ndjson = "{\"id\":\"a\",\"pgn\":\"[White \\\"x\\\"]\\n[Result \\\"1-0\\\"]\\n\\n1. e4 e5 1-0\\n\"}\n\
+            {\"id\":\"b\",\"pgn\":\"[Result \\\"*\\\"]\\n\\n1. d4 *\\n\"}\n";
+        let records = parse_lichess_ndjson(ndjson).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].header("White"), Some("x"));
+        assert_eq!(records[0].mainline().len(), 2);
+        assert!(parse_lichess_ndjson("{\"id\":\"a\"}").is_err());
+
+        let archives = "{\"archives\":[\"https://api.chess.com/pub/player/someone/games/2024/01\"]}";
+        assert_eq!(parse_chesscom_archives(archives).unwrap(), ["https://api.chess.com/pub/player/someone/games/2024/01"]);
+    }
+}
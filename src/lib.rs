@@ -0,0 +1,65 @@
+pub mod attacks;
+pub mod bench;
+pub mod builder;
+pub mod check;
+pub mod cligame;
+pub mod database;
+pub mod diagram;
+pub mod epd;
+pub mod eval;
+#[cfg(feature = "explorer")]
+pub mod explorer;
+pub mod game;
+#[cfg(feature = "server")]
+pub mod gameserver;
+#[cfg(feature = "server")]
+pub mod http;
+#[cfg(feature = "import")]
+pub mod import;
+#[cfg(feature = "lichess-bot")]
+pub mod lichess;
+pub mod makemove;
+pub mod mate;
+pub mod matchrunner;
+pub mod movegen;
+pub mod netplay;
+pub mod nnue;
+pub mod outcome;
+pub mod pawns;
+pub mod pgn;
+#[cfg(feature = "png")]
+pub mod png;
+pub mod polyglot;
+pub mod protolog;
+pub mod puzzle;
+pub mod pst;
+pub mod render;
+pub mod repl;
+#[cfg(feature = "server")]
+pub mod rpc;
+pub mod san;
+pub mod search;
+pub mod see;
+pub mod skill;
+pub mod sprt;
+pub mod square;
+pub mod status;
+#[cfg(feature = "online-tablebase")]
+pub mod tablebase;
+pub mod testsuite;
+pub mod theme;
+pub mod timeman;
+pub mod tournament;
+pub mod tt;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod uci;
+pub mod ucioption;
+pub mod utils;
+pub mod validate;
+#[cfg(feature = "wasm-bindgen")]
+pub mod wasm;
+#[cfg(feature = "server")]
+pub mod websocket;
+pub mod xboard;
+pub mod zobrist;
@@ -0,0 +1,414 @@
+use std::time::Duration;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use reqwest::{Client, Response};
+use serde_json::Value;
+use crate::game::{Color, Game};
+use crate::movegen::Move;
+use crate::search::{SearchOptions, Searcher, MAX_DEPTH};
+use crate::timeman::TimeControl;
+
+const LICHESS: &str = "https://lichess.org";
+const USER_AGENT: &str = concat!("rustic_chess/", env!("CARGO_PKG_VERSION"));
+
+// which challenges the bot takes, the others are declined with the reason lichess shows the challenger
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChallengeFilter {
+    pub rated: bool,
+    pub casual: bool,
+    pub variants: Vec<String>,
+    // initial clock in seconds, correspondence games have no clock and are always declined
+    pub min_time: u64,
+    pub max_time: u64,
+}
+
+impl Default for ChallengeFilter {
+    fn default() -> ChallengeFilter {
+        ChallengeFilter {
+            rated: true, casual: true, variants: vec!["standard".to_string(), "chess960".to_string()],
+            min_time: 60, max_time: 3 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Challenge {
+    pub id: String,
+    pub challenger: String,
+    pub rated: bool,
+    pub variant: String,
+    // None for correspondence and unlimited games
+    pub time: Option<u64>,
+    pub increment: u64,
+}
+
+impl ChallengeFilter {
+    // the decline reason lichess knows for a challenge the bot does not want
+    pub fn decline_reason(&self, challenge: &Challenge) -> Option<&'static str> {
+        if !self.variants.contains(&challenge.variant) {
+            return Some("variant");
+        }
+        if challenge.rated && !self.rated {
+            return Some("casual");
+        }
+        if !challenge.rated && !self.casual {
+            return Some("rated");
+        }
+        match challenge.time {
+            None => Some("timeControl"),
+            Some(time) if time < self.min_time => Some("tooFast"),
+            Some(time) if time > self.max_time => Some("tooSlow"),
+            Some(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BotConfig {
+    pub token: String,
+    pub filter: ChallengeFilter,
+}
+
+impl BotConfig {
+    // --token T, or the LICHESS_BOT_TOKEN environment variable, then the filter as
+    // --variants standard,chess960 --no-rated --no-casual --min-time SECS --max-time SECS
+    pub fn from_args(args: &[String]) -> Result<BotConfig, String> {
+        let mut token = std::env::var("LICHESS_BOT_TOKEN").ok();
+        let mut filter = ChallengeFilter::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("Missing value after {}", arg));
+            match arg.as_str() {
+                "--token" => token = Some(value()?.clone()),
+                "--variants" => filter.variants = value()?.split(',').map(String::from).collect(),
+                "--no-rated" => filter.rated = false,
+                "--no-casual" => filter.casual = false,
+                "--min-time" => filter.min_time = value()?.parse().map_err(|_| "Bad --min-time")?,
+                "--max-time" => filter.max_time = value()?.parse().map_err(|_| "Bad --max-time")?,
+                _ => return Err(format!("Unknown option {}", arg)),
+            }
+        }
+        let token = token.ok_or("No token, pass --token or set LICHESS_BOT_TOKEN")?;
+        Ok(BotConfig { token, filter })
+    }
+}
+
+// what the incoming event stream tells the bot about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Challenge(Challenge),
+    GameStart(String),
+    Other,
+}
+
+pub fn parse_event(line: &str) -> Result<Event, String> {
+    let value: Value = serde_json::from_str(line).map_err(|err| err.to_string())?;
+    match value["type"].as_str() {
+        Some("challenge") => {
+            let challenge = &value["challenge"];
+            Ok(Event::Challenge(Challenge {
+                id: challenge["id"].as_str().ok_or("Challenge without an id")?.to_string(),
+                challenger: challenge["challenger"]["name"].as_str().unwrap_or("?").to_string(),
+                rated: challenge["rated"].as_bool().unwrap_or(false),
+                variant: challenge["variant"]["key"].as_str().unwrap_or("standard").to_string(),
+                time: challenge["timeControl"]["limit"].as_u64(),
+                increment: challenge["timeControl"]["increment"].as_u64().unwrap_or(0),
+            }))
+        }
+        Some("gameStart") => Ok(Event::GameStart(value["game"]["id"].as_str().ok_or("Game without an id")?.to_string())),
+        _ => Ok(Event::Other),
+    }
+}
+
+// the game as it stands after an update from the game stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameState {
+    pub moves: Vec<String>,
+    pub wtime: Duration,
+    pub btime: Duration,
+    pub winc: Duration,
+    pub binc: Duration,
+    pub status: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameEvent {
+    // the first line, with the players and the starting position, "startpos" for the usual one
+    Full { white: String, black: String, initial_fen: String, chess960: bool, state: GameState },
+    State(GameState),
+    Other,
+}
+
+fn parse_state(value: &Value) -> GameState {
+    let millis = |key: &str| Duration::from_millis(value[key].as_u64().unwrap_or(0));
+    GameState {
+        moves: value["moves"].as_str().unwrap_or("").split_whitespace().map(String::from).collect(),
+        wtime: millis("wtime"),
+        btime: millis("btime"),
+        winc: millis("winc"),
+        binc: millis("binc"),
+        status: value["status"].as_str().unwrap_or("started").to_string(),
+    }
+}
+
+pub fn parse_game_event(line: &str) -> Result<GameEvent, String> {
+    let value: Value = serde_json::from_str(line).map_err(|err| err.to_string())?;
+    match value["type"].as_str() {
+        Some("gameFull") => Ok(GameEvent::Full {
+            white: value["white"]["id"].as_str().unwrap_or("").to_string(),
+            black: value["black"]["id"].as_str().unwrap_or("").to_string(),
+            initial_fen: value["initialFen"].as_str().unwrap_or("startpos").to_string(),
+            chess960: value["variant"]["key"].as_str() == Some("chess960"),
+            state: parse_state(&value["state"]),
+        }),
+        Some("gameState") => Ok(GameEvent::State(parse_state(&value))),
+        _ => Ok(GameEvent::Other),
+    }
+}
+
+// replays the moves so far on the starting position, chess960 castles come as king takes rook
+pub fn position(initial_fen: &str, chess960: bool, moves: &[String]) -> Result<Game, String> {
+    let mut game = if initial_fen == "startpos" {
+        Game::initialize()
+    } else {
+        Game::try_from_fen(initial_fen).map_err(|err| err.to_string())?
+    };
+    game.chess960 |= chess960;
+    for uci in moves {
+        let mv = Move::from_uci(uci, &game)?;
+        game.make_move(mv);
+    }
+    Ok(game)
+}
+
+// the move to play on our clock, None when the game is already over
+pub fn choose_move(searcher: &mut Searcher, game: &mut Game, state: &GameState) -> Option<Move> {
+    let (time, increment) = match game.active_color {
+        Color::White => (state.wtime, state.winc),
+        Color::Black => (state.btime, state.binc),
+    };
+    let clock = TimeControl { time, increment, moves_to_go: None };
+    let options = SearchOptions { depth: MAX_DEPTH, time: Some(clock.limits()), ..SearchOptions::default() };
+    searcher.search_with(game, &options).best_move
+}
+
+pub fn client() -> Result<Client, String> {
+    Client::builder().user_agent(USER_AGENT).build().map_err(|err| err.to_string())
+}
+
+// one lichess account playing through the bot api
+pub struct Bot {
+    client: Client,
+    config: BotConfig,
+}
+
+impl Bot {
+    pub fn new(config: BotConfig) -> Result<Bot, String> {
+        Ok(Bot { client: client()?, config })
+    }
+
+    async fn get(&self, path: &str) -> Result<Response, String> {
+        self.client.get(format!("{}{}", LICHESS, path))
+            .header(AUTHORIZATION, format!("Bearer {}", self.config.token))
+            .send().await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| format!("{}: {}", path, err))
+    }
+
+    async fn post(&self, path: &str, form: &str) -> Result<(), String> {
+        self.client.post(format!("{}{}", LICHESS, path))
+            .header(AUTHORIZATION, format!("Bearer {}", self.config.token))
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(form.to_string())
+            .send().await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|err| format!("{}: {}", path, err))
+    }
+
+    // the account's id, to tell which side the bot plays in each game
+    pub async fn account_id(&self) -> Result<String, String> {
+        let text = self.get("/api/account").await?.text().await.map_err(|err| err.to_string())?;
+        let value: Value = serde_json::from_str(&text).map_err(|err| err.to_string())?;
+        value["id"].as_str().map(String::from).ok_or_else(|| "Account without an id".to_string())
+    }
+
+    // accepts challenges and plays every game that starts, until the event stream closes
+    pub async fn run(self) -> Result<(), String> {
+        let bot = std::sync::Arc::new(self);
+        let me = bot.account_id().await?;
+        let mut events = bot.get("/api/stream/event").await?;
+        let mut lines = LineBuffer::default();
+        while let Some(chunk) = events.chunk().await.map_err(|err| err.to_string())? {
+            // one bad line or a challenge that could not be answered must not stop the bot
+            for line in lines.push(&chunk) {
+                let event = match parse_event(&line) {
+                    Ok(event) => event,
+                    Err(err) => {
+                        eprintln!("skipping event {}: {}", line, err);
+                        continue;
+                    }
+                };
+                match event {
+                    Event::Challenge(challenge) => {
+                        if let Err(err) = bot.answer(&challenge).await {
+                            eprintln!("challenge {}: {}", challenge.id, err);
+                        }
+                    }
+                    Event::GameStart(id) => {
+                        let (bot, me) = (bot.clone(), me.clone());
+                        tokio::spawn(async move {
+                            if let Err(err) = bot.play(&id, &me).await {
+                                eprintln!("game {}: {}", id, err);
+                            }
+                        });
+                    }
+                    // events this bot has no use for, like challengeCanceled or gameFinish
+                    Event::Other => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn answer(&self, challenge: &Challenge) -> Result<(), String> {
+        match self.config.filter.decline_reason(challenge) {
+            None => self.post(&format!("/api/challenge/{}/accept", challenge.id), "").await,
+            Some(reason) => self.post(&format!("/api/challenge/{}/decline", challenge.id), &format!("reason={}", reason)).await,
+        }
+    }
+
+    // follows one game and answers every position where it is our move
+    async fn play(&self, id: &str, me: &str) -> Result<(), String> {
+        let mut stream = self.get(&format!("/api/bot/game/stream/{}", id)).await?;
+        let mut lines = LineBuffer::default();
+        let mut setup = None;
+        let mut searcher = Some(Searcher::new());
+        while let Some(chunk) = stream.chunk().await.map_err(|err| err.to_string())? {
+            for line in lines.push(&chunk) {
+                let state = match parse_game_event(&line)? {
+                    GameEvent::Full { white, black, initial_fen, chess960, state } => {
+                        let color = if white == me { Color::White } else if black == me { Color::Black } else {
+                            return Err("We are not playing in this game".to_string());
+                        };
+                        setup = Some((color, initial_fen, chess960));
+                        state
+                    }
+                    GameEvent::State(state) => state,
+                    GameEvent::Other => continue,
+                };
+                let Some((color, initial_fen, chess960)) = &setup else { continue };
+                if state.status != "started" {
+                    return Ok(());
+                }
+                let mut game = position(initial_fen, *chess960, &state.moves)?;
+                if game.active_color != *color {
+                    continue;
+                }
+                // the search blocks, so it runs off the async threads and hands the searcher back
+                let mut taken = searcher.take().unwrap();
+                let (taken, mv) = tokio::task::spawn_blocking(move || {
+                    let mv = choose_move(&mut taken, &mut game, &state);
+                    (taken, mv)
+                }).await.map_err(|err| err.to_string())?;
+                searcher = Some(taken);
+                if let Some(mv) = mv {
+                    self.post(&format!("/api/bot/game/{}/move/{}", id, mv.to_uci()), "").await?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// splits a streamed ndjson body into lines, lichess sends empty lines to keep the connection open
+#[derive(Debug, Default)]
+struct LineBuffer {
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(chunk);
+        let mut lines = vec![];
+        while let Some(end) = self.pending.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line).trim().to_string();
+            if !line.is_empty() {
+                lines.push(line);
+            }
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn challenge(rated: bool, variant: &str, time: Option<u64>) -> Challenge {
+        Challenge { id: "c".to_string(), challenger: "someone".to_string(), rated, variant: variant.to_string(), time, increment: 0 }
+    }
+
+    #[test]
+    fn filters_challenges() {
+        let filter = ChallengeFilter { casual: false, ..ChallengeFilter::default() };
+        assert_eq!(filter.decline_reason(&challenge(true, "standard", Some(300))), None);
+        assert_eq!(filter.decline_reason(&challenge(false, "standard", Some(300))), Some("rated"));
+        assert_eq!(filter.decline_reason(&challenge(true, "atomic", Some(300))), Some("variant"));
+        assert_eq!(filter.decline_reason(&challenge(true, "standard", Some(15))), Some("tooFast"));
+        assert_eq!(filter.decline_reason(&challenge(true, "standard", Some(100_000))), Some("tooSlow"));
+        assert_eq!(filter.decline_reason(&challenge(true, "chess960", None)), Some("timeControl"));
+    }
+
+    #[test]
+    fn reads_configuration() {
+        let args: Vec<String> = "--token abc --variants standard --no-casual --min-time 180"
+            .split_whitespace().map(String::from).collect();
+        let config = BotConfig::from_args(&args).unwrap();
+        assert_eq!(config.token, "abc");
+        assert_eq!(config.filter.variants, ["standard"]);
+        assert_eq!((config.filter.casual, config.filter.min_time), (false, 180));
+        assert!(BotConfig::from_args(&["--min-time".to_string()]).is_err());
+    }
+
+    #[test]
+    fn parses_stream_events() {
+        let line = r#"{"type":"challenge","challenge":{"id":"abc","challenger":{"name":"Someone"},"rated":true,
+            "variant":{"key":"standard"},"timeControl":{"type":"clock","limit":300,"increment":3}}}"#;
+        assert_eq!(parse_event(&line.replace('\n', "")), Ok(Event::Challenge(Challenge {
+            id: "abc".to_string(), challenger: "Someone".to_string(), rated: true, variant: "standard".to_string(),
+            time: Some(300), increment: 3,
+        })));
+        assert_eq!(parse_event(r#"{"type":"gameStart","game":{"id":"g1"}}"#), Ok(Event::GameStart("g1".to_string())));
+        assert_eq!(parse_event(r#"{"type":"challengeCanceled"}"#), Ok(Event::Other));
+        assert_eq!(parse_event(r#"{"type":"gameFinish","game":{"id":"g1"}}"#), Ok(Event::Other));
+        assert!(parse_event(r#"{"type":"challenge","challenge":{}}"#).is_err());
+
+        let full = r#"{"type":"gameFull","white":{"id":"bot"},"black":{"id":"human"},"initialFen":"startpos",
+            "variant":{"key":"standard"},"state":{"type":"gameState","moves":"e2e4 e7e5","wtime":60000,"btime":59000,
+            "winc":1000,"binc":1000,"status":"started"}}"#.replace('\n', "");
+        let GameEvent::Full { white, state, .. } = parse_game_event(&full).unwrap() else { panic!() };
+        assert_eq!((white.as_str(), state.moves.len(), state.btime), ("bot", 2, Duration::from_millis(59000)));
+        let game = position("startpos", false, &state.moves).unwrap();
+        assert_eq!(game.active_color, Color::White);
+        assert!(position("startpos", false, &["e2e5".to_string()]).is_err());
+    }
+
+    #[test]
+    fn splits_chunks_into_lines() {
+        let mut buffer = LineBuffer::default();
+        assert!(buffer.push(b"{\"a\":").is_empty());
+        assert_eq!(buffer.push(b"1}\n\n{\"b\":2}\n{"), ["{\"a\":1}", "{\"b\":2}"]);
+    }
+
+    #[test]
+    fn moves_on_its_clock() {
+        let mut game = Game::read_FEN("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let state = GameState {
+            moves: vec![], wtime: Duration::from_secs(5), btime: Duration::from_secs(5), winc: Duration::ZERO,
+            binc: Duration::ZERO, status: "started".to_string(),
+        };
+        let mv = choose_move(&mut Searcher::new(), &mut game, &state);
+        assert_eq!(mv.map(|mv| mv.to_uci()), Some("a1a8".to_string()));
+    }
+}
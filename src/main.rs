@@ -1,9 +1,531 @@
-mod game;
-mod utils;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::process;
+use std::time::Duration;
+use rustic_chess::bench::{bench, BENCH_DEPTH};
+use rustic_chess::game::*;
+use rustic_chess::epd::parse_epd_file;
+use rustic_chess::mate::solve_mate;
+use rustic_chess::matchrunner::{book_openings, run_match, BuiltinEngine, MatchConfig, MatchEngine, UciEngine};
+use rustic_chess::polyglot::Book;
+use rustic_chess::sprt::Sprt;
+use rustic_chess::tournament::{self, Format};
+use rustic_chess::netplay::{self, EnginePlayer, HumanPlayer, Player};
+use rustic_chess::search::SearchOptions;
+use rustic_chess::theme::Theme;
 
-use game::*;
+// mate <max plies> <fen>: prints the shortest forced mate in SAN
+fn mate(args: &[String]) -> Result<(), String> {
+    let plies = args.first().and_then(|plies| plies.parse().ok()).ok_or("Usage: mate <max plies> <fen>")?;
+    let game = Game::try_from_fen(&args[1..].join(" ")).map_err(|err| err.to_string())?;
+    match solve_mate(&game, plies) {
+        Some(line) => {
+            let mut position = game.clone();
+            let moves: Vec<String> = line.iter().map(|mv| {
+                let san = position.to_san(*mv);
+                position.make_move(*mv);
+                san
+            }).collect();
+            println!("mate in {}: {}", line.len().div_ceil(2), moves.join(" "));
+        }
+        None => println!("no mate within {} plies", plies),
+    }
+    Ok(())
+}
+
+// bench [depth]: searches the built-in positions, the node total is the signature of the search
+fn run_bench(args: &[String]) -> Result<(), String> {
+    let depth = match args.first() {
+        Some(depth) => depth.parse().map_err(|_| "Usage: bench [depth]")?,
+        None => BENCH_DEPTH,
+    };
+    let result = bench(depth, |fen, nodes| println!("{:>10} {}", nodes, fen));
+    println!("nodes {} time {} ms nps {}", result.nodes, result.time.as_millis(), result.nps());
+    Ok(())
+}
+
+// testsuite <file> [--depth N | --time MS]: searches every position of an EPD file, e.g. WAC or STS,
+// and counts the ones where the engine plays a bm move and no am move
+fn testsuite(args: &[String]) -> Result<(), String> {
+    use rustic_chess::testsuite::{run_engine_suite_file, SuiteLimit};
+    let usage = "Usage: testsuite <file> [--depth N | --time MS]";
+    let path = args.first().ok_or(usage)?;
+    let limit = match &args[1..] {
+        [] => SuiteLimit { depth: None, time: None },
+        [option, value] if option == "--depth" => SuiteLimit { depth: Some(value.parse().map_err(|_| usage)?), time: None },
+        [option, value] if option == "--time" => {
+            SuiteLimit { depth: None, time: Some(Duration::from_millis(value.parse().map_err(|_| usage)?)) }
+        }
+        _ => return Err(usage.to_string()),
+    };
+    let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    let report = run_engine_suite_file(&text, limit)?;
+    for result in report.failed() {
+        println!("failed {} played {}", result.id, result.found.map_or("nothing".to_string(), |mv| mv.to_uci()));
+    }
+    println!("solved {}/{}", report.solved(), report.total());
+    Ok(())
+}
+
+// "self" for this engine in the process, otherwise the command line of a uci engine
+fn match_engine(spec: &str, depth: Option<usize>) -> Result<Box<dyn MatchEngine>, String> {
+    if spec == "self" {
+        return Ok(Box::new(BuiltinEngine::new("Rustic Chess", depth)));
+    }
+    let words: Vec<String> = spec.split_whitespace().map(String::from).collect();
+    let (command, args) = words.split_first().ok_or("Empty engine command")?;
+    Ok(Box::new(UciEngine::start(command, args, &[])?))
+}
+
+// the options match and tournament share, with the ones left for the command itself
+struct MatchArgs {
+    config: MatchConfig,
+    depth: Option<usize>,
+    rest: Vec<(String, String)>,
+}
+
+fn match_options(options: &[String], usage: &str) -> Result<MatchArgs, String> {
+    let mut config = MatchConfig::default();
+    let (mut depth, mut book, mut book_plies, mut rest) = (None, None, 8, vec![]);
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        let value = options.next().ok_or(usage)?;
+        let number = || value.parse::<f64>().map_err(|_| format!("Bad number {}", value));
+        match option.as_str() {
+            "--games" => config.games = number()? as usize,
+            "--tc" => {
+                let (time, increment) = value.split_once('+').unwrap_or((value, "0"));
+                config.time = Duration::from_secs_f64(time.parse().map_err(|_| usage)?);
+                config.increment = Duration::from_secs_f64(increment.parse().map_err(|_| usage)?);
+            }
+            "--depth" => depth = Some(number()? as usize),
+            "--epd" => {
+                let text = fs::read_to_string(value).map_err(|err| format!("{}: {}", value, err))?;
+                config.openings = parse_epd_file(&text)?.into_iter().map(|epd| epd.game).collect();
+            }
+            "--book" => book = Some(Book::from_bytes(&fs::read(value).map_err(|err| format!("{}: {}", value, err))?)?),
+            "--book-plies" => book_plies = number()? as usize,
+            _ => rest.push((option.clone(), value.clone())),
+        }
+    }
+    if let Some(book) = book {
+        config.openings = book_openings(&book, book_plies, config.games.div_ceil(2));
+    }
+    Ok(MatchArgs { config, depth, rest })
+}
+
+// match <engine> <engine> [--games N] [--tc SECS+INC] [--depth N] [--epd FILE | --book FILE [--book-plies N]]
+// [--pgn FILE] [--sprt ELO0,ELO1 [--alpha A] [--beta B]], with an sprt --games is the most games played
+fn run_engine_match(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: match <engine> <engine> [--games N] [--tc SECS+INC] [--depth N] [--epd FILE] \
+        [--book FILE] [--book-plies N] [--pgn FILE] [--sprt ELO0,ELO1] [--alpha A] [--beta B]";
+    let [first, second, options @ ..] = args else {
+        return Err(usage.to_string());
+    };
+    let MatchArgs { mut config, depth, rest } = match_options(options, usage)?;
+    let mut pgn = None;
+    for (option, value) in rest {
+        let number = || value.parse::<f64>().map_err(|_| format!("Bad number {}", value));
+        match option.as_str() {
+            "--pgn" => pgn = Some(value.clone()),
+            "--sprt" => {
+                let (elo0, elo1) = value.split_once(',').ok_or(usage)?;
+                let sprt = config.sprt.get_or_insert_with(Sprt::default);
+                sprt.elo0 = elo0.parse().map_err(|_| usage)?;
+                sprt.elo1 = elo1.parse().map_err(|_| usage)?;
+            }
+            "--alpha" => config.sprt.get_or_insert_with(Sprt::default).alpha = number()?,
+            "--beta" => config.sprt.get_or_insert_with(Sprt::default).beta = number()?,
+            _ => return Err(usage.to_string()),
+        }
+    }
+    let (mut first, mut second) = (match_engine(first, depth)?, match_engine(second, depth)?);
+    let mut games = String::new();
+    let score = run_match(first.as_mut(), second.as_mut(), &config, |number, record, score| {
+        let result = record.result.map_or("*".to_string(), |result| result.to_string());
+        println!("game {} {} - {} {}, score {}-{}-{}", number, record.header("White").unwrap_or("?"),
+            record.header("Black").unwrap_or("?"), result, score.wins, score.losses, score.draws);
+        if let Some(sprt) = config.sprt {
+            let (lower, upper) = sprt.bounds();
+            println!("llr {:.2} ({:.2}, {:.2})", sprt.llr(score), lower, upper);
+        }
+        games.push_str(&record.to_pgn().unwrap_or_default());
+        games.push('\n');
+    });
+    println!("{} vs {}: +{} -{} ={} score {:.1}%", first.name(), second.name(), score.wins, score.losses, score.draws,
+        score.score() * 100.0);
+    match (score.elo(), score.elo_margin()) {
+        (Some(elo), Some(margin)) => println!("elo difference {:.1} +/- {:.1}", elo, margin),
+        _ => println!("elo difference cannot be estimated from these results"),
+    }
+    if let Some(sprt) = config.sprt {
+        match sprt.decide(&score) {
+            Some(result) => println!("sprt [{}, {}]: {}", sprt.elo0, sprt.elo1, result),
+            None => println!("sprt [{}, {}]: undecided after {} games", sprt.elo0, sprt.elo1, score.games()),
+        }
+    }
+    if let Some(path) = pgn {
+        fs::write(&path, games).map_err(|err| format!("{}: {}", path, err))?;
+    }
+    Ok(())
+}
+
+// tournament <engine> <engine>... [--format round-robin|gauntlet] [--pgn-dir DIR] and the match options,
+// --games is then the number of games per pairing
+fn run_tournament(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: tournament <engine> <engine>... [--format round-robin|gauntlet] [--games N] [--tc SECS+INC] \
+        [--depth N] [--epd FILE] [--book FILE] [--book-plies N] [--pgn-dir DIR]";
+    let split = args.iter().position(|arg| arg.starts_with("--")).unwrap_or(args.len());
+    let (specs, options) = args.split_at(split);
+    if specs.len() < 2 {
+        return Err(usage.to_string());
+    }
+    let MatchArgs { config, depth, rest } = match_options(options, usage)?;
+    let (mut format, mut pgn_dir) = (Format::RoundRobin, None);
+    for (option, value) in rest {
+        match option.as_str() {
+            "--format" => format = value.parse()?,
+            "--pgn-dir" => pgn_dir = Some(std::path::PathBuf::from(value)),
+            _ => return Err(usage.to_string()),
+        }
+    }
+    let mut engines = specs.iter().map(|spec| match_engine(spec, depth)).collect::<Result<Vec<_>, _>>()?;
+    let names: Vec<String> = engines.iter().enumerate().map(|(i, engine)| format!("{}-{}", i + 1, engine.name())).collect();
+    let mut pgns: Vec<((usize, usize), String)> = vec![];
+    let table = tournament::run_tournament(&mut engines, format, &config, |i, j, record| {
+        let result = record.result.map_or("*".to_string(), |result| result.to_string());
+        println!("{} - {} {}", record.header("White").unwrap_or("?"), record.header("Black").unwrap_or("?"), result);
+        let pgn = record.to_pgn().unwrap_or_default() + "\n";
+        match pgns.last_mut() {
+            Some((pairing, games)) if *pairing == (i, j) => games.push_str(&pgn),
+            _ => pgns.push(((i, j), pgn)),
+        }
+    });
+    print!("{}", table);
+    if let Some(dir) = pgn_dir {
+        fs::create_dir_all(&dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+        for ((i, j), games) in pgns {
+            // one file per pairing, named after the engines' places on the command line
+            let name: String = format!("{}-vs-{}.pgn", names[i], names[j]).chars()
+                .map(|ch| if ch.is_ascii_alphanumeric() || "-._".contains(ch) { ch } else { '_' })
+                .collect();
+            fs::write(dir.join(&name), games).map_err(|err| format!("{}: {}", name, err))?;
+        }
+    }
+    Ok(())
+}
+
+// a human at this terminal, or the engine with --engine <depth>
+fn local_player(args: &[String]) -> Result<Box<dyn Player>, String> {
+    match args.iter().position(|arg| arg == "--engine") {
+        Some(i) => {
+            let depth = args.get(i + 1).and_then(|depth| depth.parse().ok()).ok_or("Usage: --engine <depth>")?;
+            Ok(Box::new(EnginePlayer::new(SearchOptions { depth, ..SearchOptions::default() })))
+        }
+        None => Ok(Box::new(HumanPlayer::new(io::stdin().lock(), io::stdout()))),
+    }
+}
+
+// serve <address> [white|black] [--engine <depth>]: waits for one opponent to connect
+fn serve(args: &[String]) -> Result<(), String> {
+    let address = args.first().ok_or("Usage: serve <address> [white|black] [--engine <depth>]")?;
+    let color = if args.iter().any(|arg| arg == "black") { Color::Black } else { Color::White };
+    let listener = std::net::TcpListener::bind(address).map_err(|err| err.to_string())?;
+    println!("waiting for an opponent on {}", address);
+    netplay::serve(&listener, local_player(args)?.as_mut(), color, Game::initialize())
+        .map(|outcome| println!("{} {}", outcome.result, outcome.reason))
+}
+
+// connect <address> [--engine <depth>]: plays the color the server leaves over
+fn connect(args: &[String]) -> Result<(), String> {
+    let address = args.first().ok_or("Usage: connect <address> [--engine <depth>]")?;
+    netplay::connect(address.as_str(), local_player(args)?.as_mut())
+        .map(|outcome| println!("{} {}", outcome.result, outcome.reason))
+}
+
+// server <address> [--pgn-dir <dir>]: hosts games for websocket clients
+#[cfg(feature = "server")]
+fn server(args: &[String]) -> Result<(), String> {
+    let address = args.first().ok_or("Usage: server <address> [--pgn-dir <dir>]")?;
+    let pgn_dir = args.iter().position(|arg| arg == "--pgn-dir")
+        .map(|i| args.get(i + 1).map(std::path::PathBuf::from).ok_or("Usage: --pgn-dir <dir>"))
+        .transpose()?;
+    let listener = std::net::TcpListener::bind(address).map_err(|err| err.to_string())?;
+    println!("serving games on ws://{}", address);
+    rustic_chess::gameserver::serve(listener, pgn_dir).map_err(|err| err.to_string())
+}
+
+// rpc <address>: answers analysis requests as JSON-RPC on /rpc and REST on /analyze and /legal_moves
+#[cfg(feature = "server")]
+fn rpc(args: &[String]) -> Result<(), String> {
+    let address = args.first().ok_or("Usage: rpc <address>")?;
+    let listener = std::net::TcpListener::bind(address).map_err(|err| err.to_string())?;
+    println!("answering requests on http://{}", address);
+    rustic_chess::rpc::serve(listener).map_err(|err| err.to_string())
+}
+
+// lichess-bot [--token T] [filter options]: plays on lichess.org as the token's bot account
+#[cfg(feature = "lichess-bot")]
+fn lichess_bot(args: &[String]) -> Result<(), String> {
+    use rustic_chess::lichess::{Bot, BotConfig};
+    let bot = Bot::new(BotConfig::from_args(args)?)?;
+    let runtime = tokio::runtime::Runtime::new().map_err(|err| err.to_string())?;
+    runtime.block_on(bot.run())
+}
+
+// explorer [fen] [--db masters|lichess] [--book file]: the moves played from the position
+// with their results, next to the book's
+#[cfg(feature = "explorer")]
+fn explorer(args: &[String]) -> Result<(), String> {
+    use rustic_chess::explorer::{Database, Explorer};
+    let usage = "Usage: explorer [fen] [--db masters|lichess] [--book file]";
+    let (mut fen, mut database, mut book) = (vec![], Database::Masters, None);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--db" => database = args.next().ok_or(usage)?.parse()?,
+            "--book" => {
+                let path = args.next().ok_or(usage)?;
+                book = Some(Book::from_bytes(&fs::read(path).map_err(|err| format!("{}: {}", path, err))?)?);
+            }
+            _ => fen.push(arg.as_str()),
+        }
+    }
+    let game = if fen.is_empty() { Game::initialize() } else { Game::try_from_fen(&fen.join(" ")).map_err(|err| err.to_string())? };
+    let (table, error) = Explorer::new()?.explore(database, &game, book.as_ref());
+    if let Some(err) = error {
+        eprintln!("explorer unavailable, {}", err);
+    }
+    print!("{}", table);
+    Ok(())
+}
+
+// play [white|black] [--level 0-20] [--time secs] [--hint-depth n] [--fen fen | --load file] [--board ascii|unicode|figurine]
+// [--color] [--blindfold pawns|hidden]: a game against the engine at the terminal, what the program does when run
+// without a command
+fn play(args: &[String]) -> Result<(), String> {
+    use rustic_chess::cligame::{CliConfig, CliGame};
+    use rustic_chess::skill::Skill;
+    let usage = "Usage: play [white|black] [--level 0-20] [--time secs] [--hint-depth n] [--fen fen | --load file] [--board ascii|unicode|figurine] [--color] [--blindfold pawns|hidden]";
+    let (mut config, mut game, mut load) = (CliConfig::default(), Game::initialize(), None);
+    Theme::user()?.apply_render(&mut config.render);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "white" => config.human = Color::White,
+            "black" => config.human = Color::Black,
+            "--level" => config.skill = Skill::new(args.next().and_then(|level| level.parse().ok()).ok_or(usage)?),
+            "--time" => {
+                let secs: f64 = args.next().and_then(|secs| secs.parse().ok()).ok_or(usage)?;
+                config.think_time = Duration::from_secs_f64(secs.max(0.01));
+            }
+            "--hint-depth" => config.hint_depth = args.next().and_then(|depth| depth.parse().ok()).ok_or(usage)?,
+            "--board" => config.render.style = args.next().ok_or(usage)?.parse()?,
+            "--color" => config.render.color = true,
+            "--blindfold" => config.render.blindfold = args.next().ok_or(usage)?.parse()?,
+            // the fen runs to the next option
+            "--fen" => {
+                let fen: Vec<&str> = args.clone().take_while(|word| !word.starts_with("--")).map(String::as_str).collect();
+                if fen.is_empty() {
+                    return Err(usage.to_string());
+                }
+                args.nth(fen.len() - 1);
+                game = Game::try_from_fen(&fen.join(" ")).map_err(|err| err.to_string())?;
+            }
+            "--load" => load = Some(args.next().ok_or(usage)?),
+            _ => return Err(usage.to_string()),
+        }
+    }
+    // your own pieces at the bottom
+    config.render.flipped = config.human == Color::Black;
+    let mut session = CliGame::new(game, config, io::stdin().lock(), io::stdout());
+    // a saved session brings its own side, level and board orientation
+    if let Some(path) = load {
+        session.load(std::path::Path::new(path))?;
+    }
+    session.run().map(|_| ()).map_err(|err| err.to_string())
+}
+
+// diagram [fen] [--flip] [--no-coordinates] [--size px] [--arrow e2e4] [--highlight e4] [--out file]:
+// the position as an svg, printed unless --out names a file, which is a png when the name ends in .png
+// and the png feature is on
+fn diagram(args: &[String]) -> Result<(), String> {
+    use rustic_chess::render::{to_svg, SvgOptions};
+    let usage = "Usage: diagram [fen] [--flip] [--no-coordinates] [--size px] [--arrow e2e4] [--highlight e4] [--out file]";
+    let fen: Vec<&str> = args.iter().take_while(|word| !word.starts_with("--")).map(String::as_str).collect();
+    let game = if fen.is_empty() {
+        Game::initialize()
+    } else {
+        Game::try_from_fen(&fen.join(" ")).map_err(|err| err.to_string())?
+    };
+    let (theme, mut options, mut out) = (Theme::user()?, SvgOptions::default(), None);
+    theme.apply_svg(&mut options);
+    let mut args = args[fen.len()..].iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--flip" => options.flipped = true,
+            "--no-coordinates" => options.coordinates = false,
+            "--size" => options.size = args.next().and_then(|size| size.parse().ok()).filter(|size| *size > 0).ok_or(usage)?,
+            "--arrow" => {
+                let arrow = args.next().filter(|arrow| arrow.len() == 4 && arrow.is_ascii()).ok_or(usage)?;
+                options.arrows.push((arrow[..2].parse()?, arrow[2..].parse()?, theme.svg_arrow.clone()));
+            }
+            "--highlight" => options.highlights.push((args.next().ok_or(usage)?.parse()?, theme.svg_highlight.clone())),
+            "--out" => out = Some(args.next().ok_or(usage)?),
+            _ => return Err(usage.to_string()),
+        }
+    }
+    match out {
+        #[cfg(feature = "png")]
+        Some(path) if path.to_lowercase().ends_with(".png") => {
+            fs::write(path, rustic_chess::png::to_png(&game, &options)?).map_err(|err| format!("{}: {}", path, err))
+        }
+        #[cfg(not(feature = "png"))]
+        Some(path) if path.to_lowercase().ends_with(".png") => Err(format!("{}: png diagrams need the png feature", path)),
+        Some(path) => fs::write(path, to_svg(&game, &options)).map_err(|err| format!("{}: {}", path, err)),
+        None => {
+            print!("{}", to_svg(&game, &options));
+            Ok(())
+        }
+    }
+}
+
+// puzzles <file> [--stats file] [--board ascii|unicode|figurine] [--color]: solves the puzzles in an EPD
+// file or a lichess puzzle csv, the stats carry over between sessions in <file>.stats
+fn puzzles(args: &[String]) -> Result<(), String> {
+    use rustic_chess::puzzle::{parse_puzzles, stats_path, PuzzleStats, Trainer};
+    use rustic_chess::render::RenderOptions;
+    let usage = "Usage: puzzles <file> [--stats file] [--board ascii|unicode|figurine] [--color]";
+    let path = std::path::Path::new(args.first().ok_or(usage)?);
+    let (mut stats_file, mut render) = (stats_path(path), RenderOptions { coordinates: true, ..RenderOptions::default() });
+    Theme::user()?.apply_render(&mut render);
+    let mut args = args[1..].iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--stats" => stats_file = args.next().ok_or(usage)?.into(),
+            "--board" => render.style = args.next().ok_or(usage)?.parse()?,
+            "--color" => render.color = true,
+            _ => return Err(usage.to_string()),
+        }
+    }
+    let puzzles = parse_puzzles(&fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?)?;
+    let mut trainer = Trainer::new(PuzzleStats::load(&stats_file)?, render, io::stdin().lock(), io::stdout());
+    // the stats are shown after every puzzle, and once more when leaving part way
+    if !trainer.run(&puzzles).map_err(|err| err.to_string())? {
+        println!("\n{}", trainer.stats);
+    }
+    trainer.stats.save(&stats_file)
+}
+
+// tui [white|black] [--level 0-20] [--tc secs+inc]: a game against the engine full screen,
+// moves go in with the arrow keys, the mouse or typed out
+#[cfg(feature = "tui")]
+fn tui(args: &[String]) -> Result<(), String> {
+    use rustic_chess::cligame::result_message;
+    use rustic_chess::skill::Skill;
+    use rustic_chess::tui::{self, TuiConfig};
+    let usage = "Usage: tui [white|black] [--level 0-20] [--tc secs+inc]";
+    let mut config = TuiConfig::default();
+    Theme::user()?.apply_tui(&mut config);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "white" => config.human = Color::White,
+            "black" => config.human = Color::Black,
+            "--level" => config.skill = Skill::new(args.next().and_then(|level| level.parse().ok()).ok_or(usage)?),
+            "--tc" => {
+                let value = args.next().ok_or(usage)?;
+                let (time, increment) = value.split_once('+').unwrap_or((value, "0"));
+                let seconds = |text: &str| text.parse::<f64>().map(Duration::from_secs_f64).map_err(|_| usage);
+                (config.time, config.increment) = (seconds(time)?, seconds(increment)?);
+            }
+            _ => return Err(usage.to_string()),
+        }
+    }
+    if let Some(outcome) = tui::run(Game::initialize(), config).map_err(|err| err.to_string())? {
+        println!("{}", result_message(&outcome, config.human));
+    }
+    Ok(())
+}
+
+// analyze [fen]: the engine analyses the position full screen while moves are played for
+// either side and stepped through
+#[cfg(feature = "tui")]
+fn analyze(args: &[String]) -> Result<(), String> {
+    use rustic_chess::tui::{self, TuiConfig};
+    let game = if args.is_empty() {
+        Game::initialize()
+    } else {
+        Game::try_from_fen(&args.join(" ")).map_err(|err| err.to_string())?
+    };
+    let mut config = TuiConfig { analyze: true, ..TuiConfig::default() };
+    Theme::user()?.apply_tui(&mut config);
+    tui::run(game, config).map(|_| ()).map_err(|err| err.to_string())
+}
+
+// uci|xboard [--log file] [--log-size mb] [--log-keep n]: talks to a gui on stdin and stdout,
+// with --log every line both ways goes to a log file that is rotated when it gets too big
+fn protocol(args: &[String], xboard: bool) -> Result<(), String> {
+    use rustic_chess::protolog::{self, ProtocolLog, DEFAULT_KEEP, DEFAULT_MAX_BYTES};
+    let usage = "Usage: uci|xboard [--log file] [--log-size mb] [--log-keep n]";
+    let (mut path, mut max_bytes, mut keep) = (None, DEFAULT_MAX_BYTES, DEFAULT_KEEP);
+    let mut options = args.iter();
+    while let Some(option) = options.next() {
+        let value = options.next().ok_or(usage)?;
+        match option.as_str() {
+            "--log" => path = Some(value),
+            "--log-size" => max_bytes = value.parse::<u64>().map_err(|_| usage)? * 1024 * 1024,
+            "--log-keep" => keep = value.parse().map_err(|_| usage)?,
+            _ => return Err(usage.to_string()),
+        }
+    }
+    let (input, output) = (io::stdin().lock(), io::stdout());
+    let result = match path {
+        Some(path) => {
+            let log = ProtocolLog::open(path, max_bytes, keep).map_err(|err| format!("{}: {}", path, err))?;
+            let (input, output) = protolog::logged(input, output, log);
+            if xboard { rustic_chess::xboard::run(input, output) } else { rustic_chess::uci::run(input, output) }
+        }
+        None if xboard => rustic_chess::xboard::run(input, output),
+        None => rustic_chess::uci::run(input, output),
+    };
+    result.map_err(|err| err.to_string())
+}
 
 fn main() {
-    let game = Game::initialize();
-    println!("{}", game.to_string());
-}
\ No newline at end of file
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("mate") => mate(&args[1..]),
+        Some("bench") => run_bench(&args[1..]),
+        Some("testsuite") => testsuite(&args[1..]),
+        Some("match") => run_engine_match(&args[1..]),
+        Some("tournament") => run_tournament(&args[1..]),
+        Some("serve") => serve(&args[1..]),
+        Some("connect") => connect(&args[1..]),
+        Some("diagram") => diagram(&args[1..]),
+        Some("puzzles") => puzzles(&args[1..]),
+        Some("repl") => rustic_chess::repl::run(io::stdin().lock(), io::stdout(), io::stdin().is_terminal())
+            .map_err(|err| err.to_string()),
+        Some("uci") => protocol(&args[1..], false),
+        Some("xboard") => protocol(&args[1..], true),
+        #[cfg(feature = "explorer")]
+        Some("explorer") => explorer(&args[1..]),
+        #[cfg(feature = "tui")]
+        Some("tui") => tui(&args[1..]),
+        #[cfg(feature = "tui")]
+        Some("analyze") => analyze(&args[1..]),
+        #[cfg(feature = "server")]
+        Some("server") => server(&args[1..]),
+        #[cfg(feature = "server")]
+        Some("rpc") => rpc(&args[1..]),
+        #[cfg(feature = "lichess-bot")]
+        Some("lichess-bot") => lichess_bot(&args[1..]),
+        None | Some("play") => play(args.get(1..).unwrap_or_default()),
+        Some(command) => Err(format!("Unknown command {}", command)),
+    };
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
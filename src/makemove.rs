@@ -0,0 +1,489 @@
+use crate::game::*;
+use crate::eval::piece_score;
+use crate::movegen::{Move, MoveFlags};
+use crate::square::Square;
+use crate::utils::bit_scan;
+use crate::zobrist;
+
+// everything make_move cannot recompute when taking the move back
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct Undo {
+    pub mv: Move,
+    captured: Option<(Piece, usize)>,
+    castling_rights: CastlingRights,
+    en_passant: Option<u64>,
+    halfmove_clock: usize,
+    fullmove_number: usize,
+    // key of the position before the move, for repetition checks
+    pub(crate) hash: u64,
+    pawn_hash: u64,
+    psqt: (i32, i32),
+}
+
+// where the king and rook start and end up on a castle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Castling {
+    pub king_from: usize,
+    pub king_to: usize,
+    pub rook_from: usize,
+    pub rook_to: usize,
+}
+
+impl Game {
+    // the squares of a castling move, the king always lands on the g or c file and the rook next
+    // to it. the move itself is the king's two square step, or in chess960 the king taking its rook.
+    // works before the move is made and after, for unmake_move
+    pub(crate) fn castling(&self, mv: Move) -> Option<Castling> {
+        // moves parsed from plain coordinates have no flags, a king stepping two files castles
+        let is_king = |square: usize| self.piece_at(Square::from_index(square)).is_some_and(|piece| piece.piece_type == PieceType::King);
+        let two_step = !self.chess960 && mv.from().abs_diff(mv.to()) == 2 && (is_king(mv.from()) || is_king(mv.to()));
+        if !mv.is_castle() && !two_step {
+            return None;
+        }
+        let kingside = mv.flags().contains(MoveFlags::KINGSIDE_CASTLE) || (two_step && mv.to() > mv.from());
+        let back_rank = mv.from() / 8 * 8;
+        let color = if back_rank == 0 { Color::White } else { Color::Black };
+        let side = CASTLING_SIDES.iter()
+            .position(|(right, side_color)| *side_color == color && right.intersects(CastlingRights::WHITEKINGSIDE | CastlingRights::BLACKKINGSIDE) == kingside)
+            .unwrap();
+        let (king_file, rook_file) = if kingside { (6, 5) } else { (2, 3) };
+        Some(Castling {
+            king_from: mv.from(),
+            king_to: back_rank + king_file,
+            rook_from: self.castling_rooks[side],
+            rook_to: back_rank + rook_file,
+        })
+    }
+
+    // plays a move generated by `legal_moves`, the move itself is not validated
+    pub fn make_move(&mut self, mv: Move) {
+        let (from, to) = (mv.from(), mv.to());
+        let piece_type = self.piece_at(Square::from_index(from)).unwrap().piece_type;
+        let to_bit = 1u64 << to;
+        let mut undo = Undo {
+            mv,
+            captured: None,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            hash: self.hash,
+            pawn_hash: self.pawn_hash,
+            psqt: self.psqt,
+        };
+        // the old rights and en passant square come out here, the new ones go in at the end
+        self.hash ^= zobrist::castling_key(self.castling_rights) ^ zobrist::en_passant_key(self) ^ zobrist::side_key();
+
+        let castling = self.castling(mv);
+        // a chess960 castle lands on the king's own rook, that is not a capture
+        let mut captured = if castling.is_some() { None } else { self.remove_piece(to) };
+        // en passant takes the pawn that just passed the target square
+        if piece_type == PieceType::Pawn && self.en_passant == Some(to_bit) {
+            let victim = match self.active_color {
+                Color::White => to - 8,
+                Color::Black => to + 8,
+            };
+            captured = self.remove_piece(victim);
+        }
+        match castling {
+            Some(castling) => self.move_king_and_rook(castling.king_from, castling.king_to, castling.rook_from, castling.rook_to),
+            None => self.move_piece(from, to),
+        }
+
+        if let Some(piece) = mv.promotion() {
+            if let SquareContent::Occupied(idx) = self.squares[to] {
+                self.pieces[idx].piece_type = piece;
+                let color = self.pieces[idx].color;
+                self.update_keys(color, PieceType::Pawn, to, -1);
+                self.update_keys(color, piece, to, 1);
+            }
+        }
+
+        self.castling_rights.remove(self.castling_rights_lost(piece_type, from, to));
+
+        self.en_passant = if piece_type == PieceType::Pawn && from.abs_diff(to) == 16 {
+            Some(1u64 << ((from + to) / 2))
+        } else {
+            None
+        };
+
+        if piece_type == PieceType::Pawn || captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if self.active_color == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.active_color = self.active_color.opponent();
+        self.hash ^= zobrist::castling_key(self.castling_rights) ^ zobrist::en_passant_key(self);
+
+        undo.captured = captured;
+        self.history.push(undo);
+    }
+
+    // rights lost when the king moves, or anything moves from or to a castling rook's square
+    fn castling_rights_lost(&self, piece_type: PieceType, from: usize, to: usize) -> CastlingRights {
+        let mut lost = CastlingRights::NONE;
+        for (i, (right, color)) in CASTLING_SIDES.iter().enumerate() {
+            let rook = self.castling_rooks[i];
+            if (piece_type == PieceType::King && *color == self.active_color) || rook == from || rook == to {
+                lost |= *right;
+            }
+        }
+        lost
+    }
+
+    // takes back the last move played with make_move, returning it
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        if self.history.last()?.mv.is_null() {
+            self.unmake_null_move();
+            return Some(Move::null());
+        }
+        let undo = self.history.pop()?;
+        let mv = undo.mv;
+        self.outcome = None;
+        let (from, to) = (mv.from(), mv.to());
+        self.active_color = self.active_color.opponent();
+
+        if let Some(castling) = self.castling(mv) {
+            self.move_king_and_rook(castling.king_to, castling.king_from, castling.rook_to, castling.rook_from);
+        } else {
+            self.move_piece(to, from);
+            let idx = match self.squares[from] {
+                SquareContent::Occupied(idx) => idx,
+                SquareContent::Empty => unreachable!("unmade move has no piece"),
+            };
+            if mv.promotion().is_some() {
+                self.pieces[idx].piece_type = PieceType::Pawn;
+            }
+        }
+        if let Some((piece, idx)) = undo.captured {
+            self.restore_piece(piece, idx);
+        }
+
+        self.castling_rights = undo.castling_rights;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+        self.hash = undo.hash;
+        self.pawn_hash = undo.pawn_hash;
+        self.psqt = undo.psqt;
+        Some(mv)
+    }
+
+    // hands the turn to the opponent without moving, for null move pruning
+    pub fn make_null_move(&mut self) {
+        self.history.push(Undo {
+            mv: Move::null(),
+            captured: None,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            hash: self.hash,
+            pawn_hash: self.pawn_hash,
+            psqt: self.psqt,
+        });
+        self.hash ^= zobrist::en_passant_key(self) ^ zobrist::side_key();
+        self.en_passant = None;
+        self.halfmove_clock += 1;
+        self.active_color = self.active_color.opponent();
+    }
+
+    pub fn unmake_null_move(&mut self) {
+        if let Some(undo) = self.history.pop() {
+            debug_assert!(undo.mv.is_null(), "unmake_null_move after a real move");
+            self.en_passant = undo.en_passant;
+            self.halfmove_clock = undo.halfmove_clock;
+            self.active_color = self.active_color.opponent();
+            self.hash = undo.hash;
+            self.pawn_hash = undo.pawn_hash;
+            self.psqt = undo.psqt;
+        }
+    }
+
+    // a piece appears on (sign 1) or leaves (sign -1) `square`, in both Zobrist keys
+    // and in the material and piece-square score
+    fn update_keys(&mut self, color: Color, piece_type: PieceType, square: usize, sign: i32) {
+        let key = zobrist::piece_key(color, piece_type, square);
+        self.hash ^= key;
+        if piece_type == PieceType::Pawn {
+            self.pawn_hash ^= key;
+        }
+        let (mg, eg) = piece_score(color, piece_type, square);
+        self.psqt.0 += sign * mg;
+        self.psqt.1 += sign * eg;
+    }
+
+    fn move_piece(&mut self, from: usize, to: usize) {
+        if let SquareContent::Occupied(idx) = self.squares[from] {
+            let (color, piece_type) = (self.pieces[idx].color, self.pieces[idx].piece_type);
+            self.update_keys(color, piece_type, from, -1);
+            self.update_keys(color, piece_type, to, 1);
+            self.pieces[idx].position = 1u64 << to;
+            self.squares[to] = SquareContent::Occupied(idx);
+            self.squares[from] = SquareContent::Empty;
+        }
+    }
+
+    // both castling pieces at once, in chess960 either may land where the other one stood
+    fn move_king_and_rook(&mut self, king_from: usize, king_to: usize, rook_from: usize, rook_to: usize) {
+        let (SquareContent::Occupied(king), SquareContent::Occupied(rook)) = (self.squares[king_from], self.squares[rook_from]) else {
+            unreachable!("castling without a king and rook");
+        };
+        let color = self.pieces[king].color;
+        self.update_keys(color, PieceType::King, king_from, -1);
+        self.update_keys(color, PieceType::Rook, rook_from, -1);
+        self.squares[king_from] = SquareContent::Empty;
+        self.squares[rook_from] = SquareContent::Empty;
+        self.pieces[king].position = 1u64 << king_to;
+        self.pieces[rook].position = 1u64 << rook_to;
+        self.squares[king_to] = SquareContent::Occupied(king);
+        self.squares[rook_to] = SquareContent::Occupied(rook);
+        self.update_keys(color, PieceType::King, king_to, 1);
+        self.update_keys(color, PieceType::Rook, rook_to, 1);
+    }
+
+    // takes the piece off the board, the last piece in the list fills its slot
+    fn remove_piece(&mut self, square: usize) -> Option<(Piece, usize)> {
+        let idx = match self.squares[square] {
+            SquareContent::Empty => return None,
+            SquareContent::Occupied(idx) => idx,
+        };
+        let piece = self.pieces.swap_remove(idx);
+        self.update_keys(piece.color, piece.piece_type, square, -1);
+        self.squares[square] = SquareContent::Empty;
+        if let Some(moved) = self.pieces.get(idx) {
+            self.squares[bit_scan(moved.position)] = SquareContent::Occupied(idx);
+        }
+        Some((piece, idx))
+    }
+
+    // inverse of remove_piece, puts the piece back into its old slot
+    fn restore_piece(&mut self, piece: Piece, idx: usize) {
+        let square = bit_scan(piece.position);
+        self.update_keys(piece.color, piece.piece_type, square, 1);
+        self.pieces.push(piece);
+        let last = self.pieces.len() - 1;
+        self.pieces.swap(idx, last);
+        self.squares[bit_scan(self.pieces[last].position)] = SquareContent::Occupied(last);
+        self.squares[square] = SquareContent::Occupied(idx);
+    }
+}
+
+// leaf nodes `depth` plies below the position
+pub fn perft(game: &mut Game, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let mut nodes = 0;
+    for mv in game.legal_moves() {
+        game.make_move(mv);
+        nodes += perft(game, depth - 1);
+        game.unmake_move();
+    }
+    nodes
+}
+
+// the perft count split by the first move, as other engines print it to compare against
+pub fn divide(game: &mut Game, depth: usize) -> Vec<(Move, u64)> {
+    game.legal_moves().into_iter().map(|mv| {
+        game.make_move(mv);
+        let nodes = perft(game, depth.saturating_sub(1));
+        game.unmake_move();
+        (mv, nodes)
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perft_matches_reference_counts() {
+        assert_eq!(perft(&mut Game::initialize(), 3), 8902);
+        let mut kiwipete = Game::read_FEN("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        assert_eq!(perft(&mut kiwipete, 2), 2039);
+        let mut endgame = Game::read_FEN("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
+        assert_eq!(perft(&mut endgame, 3), 2812);
+        let mut promotions = Game::read_FEN("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1");
+        assert_eq!(perft(&mut promotions, 3), 9467);
+        let mut tricky = Game::read_FEN("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8");
+        assert_eq!(perft(&mut tricky, 2), 1486);
+    }
+
+    // board, rights and clocks must come back exactly after every kind of move
+    fn snapshot(game: &Game) -> String {
+        let mut pieces: Vec<_> = game.pieces.iter().map(|p| (p.position, p.color, p.piece_type)).collect();
+        pieces.sort_by_key(|p| p.0);
+        format!("{:?}\n{}{:?} {:?} {} {}", pieces, game, game.castling_rights, game.en_passant,
+                game.halfmove_clock, game.fullmove_number)
+    }
+
+    #[test]
+    fn unmake_restores_every_move() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 3 20",
+        ];
+        for fen in fens {
+            let mut game = Game::read_FEN(fen);
+            let before = snapshot(&game);
+            for mv in game.legal_moves() {
+                game.make_move(mv);
+                assert_eq!(game.unmake_move(), Some(mv));
+                assert_eq!(snapshot(&game), before, "{:?} in {}", mv, fen);
+                assert_eq!(game.legal_moves().len(), Game::read_FEN(fen).legal_moves().len());
+            }
+        }
+    }
+
+    #[test]
+    fn null_move_passes_the_turn() {
+        let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 3 20";
+        let mut game = Game::read_FEN(fen);
+        let before = snapshot(&game);
+        game.make_null_move();
+        assert_eq!(game.active_color, Color::Black);
+        assert_eq!(game.en_passant, None);
+        assert_eq!(game.history.len(), 1);
+        game.unmake_null_move();
+        assert_eq!(snapshot(&game), before);
+        assert_eq!(game.active_color, Color::White);
+        assert!(game.history.is_empty());
+
+        // unmake_move walks back over null moves too
+        game.make_move("e1e2".parse().unwrap());
+        game.make_null_move();
+        assert_eq!(game.unmake_move(), Some(Move::null()));
+        assert_eq!(game.unmake_move(), Some(Move::new(Square::E1, Square::E2)));
+        assert_eq!(snapshot(&game), before);
+    }
+
+    #[test]
+    fn unmake_with_empty_history_does_nothing() {
+        let mut game = Game::initialize();
+        assert_eq!(game.unmake_move(), None);
+        assert_eq!(game.active_color, Color::White);
+    }
+
+    #[test]
+    fn double_push_sets_en_passant_and_clocks() {
+        let mut game = Game::initialize();
+        game.make_move(Move::new(Square::E2, Square::E4));
+        assert_eq!(game.en_passant, Some(1u64 << 20));
+        assert_eq!(game.active_color, Color::Black);
+        assert_eq!(game.fullmove_number, 1);
+
+        game.make_move(Move::new(Square::G8, Square::F6));
+        assert_eq!(game.en_passant, None);
+        assert_eq!(game.halfmove_clock, 1);
+        assert_eq!(game.fullmove_number, 2);
+    }
+
+    #[test]
+    fn en_passant_removes_the_passed_pawn() {
+        let mut game = Game::read_FEN("4k3/8/8/3pP3/8/8/8/4K3 w - d6 3 20");
+        game.make_move(Move::new(Square::E5, Square::D6));
+        assert!(game.piece_at(Square::D5).is_none());
+        assert_eq!(game.bitboard(Color::Black, PieceType::Pawn), 0);
+        assert_eq!(game.bitboard(Color::White, PieceType::Pawn), 1u64 << 43);
+        assert_eq!(game.halfmove_clock, 0);
+    }
+
+    #[test]
+    fn castling_moves_the_rook_and_clears_rights() {
+        let mut game = Game::read_FEN("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        game.make_move(Move::new(Square::E1, Square::G1));
+        assert_eq!(game.piece_at(Square::G1).unwrap().piece_type, PieceType::King);
+        assert_eq!(game.piece_at(Square::F1).unwrap().piece_type, PieceType::Rook);
+        assert!(game.piece_at(Square::H1).is_none());
+        assert_eq!(game.castling_rights, CastlingRights::BLACKKINGSIDE | CastlingRights::BLACKQUEENSIDE);
+
+        game.make_move(Move::new(Square::E8, Square::C8));
+        assert_eq!(game.piece_at(Square::C8).unwrap().piece_type, PieceType::King);
+        assert_eq!(game.piece_at(Square::D8).unwrap().piece_type, PieceType::Rook);
+        assert!(game.piece_at(Square::A8).is_none());
+        assert_eq!(game.castling_rights, CastlingRights::NONE);
+    }
+
+    #[test]
+    fn capturing_a_rook_removes_its_castling_right() {
+        let mut game = Game::read_FEN("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        game.make_move(Move::new(Square::H1, Square::H8));
+        assert_eq!(game.castling_rights, CastlingRights::WHITEQUEENSIDE | CastlingRights::BLACKQUEENSIDE);
+        assert_eq!(game.pieces.len(), 5);
+        assert_eq!(game.piece_at(Square::H8).unwrap().color, Color::White);
+    }
+
+    #[test]
+    fn promotion_replaces_the_pawn() {
+        let mut game = Game::read_FEN("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+        let promotions: Vec<Move> = game.legal_moves().into_iter()
+            .filter(|mv| mv.promotion().is_some())
+            .collect();
+        // a8 and b8 each with queen, rook, bishop and knight
+        assert_eq!(promotions.len(), 8);
+
+        game.make_move(Move::new_promotion(Square::A7, Square::B8, PieceType::Knight));
+        let promoted = game.piece_at(Square::B8).unwrap();
+        assert_eq!(promoted.piece_type, PieceType::Knight);
+        assert_eq!(promoted.color, Color::White);
+        assert_eq!(game.bitboard(Color::White, PieceType::Pawn), 0);
+        assert_eq!(game.bitboard(Color::Black, PieceType::Rook), 0);
+    }
+
+    #[test]
+    fn castling_is_not_offered_after_the_king_moved() {
+        let mut game = Game::read_FEN("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        game.make_move(Move::new(Square::E1, Square::E2));
+        game.make_move(Move::new(Square::E8, Square::E7));
+        game.make_move(Move::new(Square::E2, Square::E1));
+        game.make_move(Move::new(Square::E7, Square::E8));
+        assert!(!game.legal_moves().contains(&Move::new(Square::E1, Square::G1)));
+        assert!(!game.legal_moves().contains(&Move::new(Square::E1, Square::C1)));
+    }
+
+    #[test]
+    fn chess960_perft_matches_reference_counts() {
+        let mut first = Game::read_FEN("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9");
+        assert_eq!(perft(&mut first, 3), 12189);
+        let mut second = Game::read_FEN("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9");
+        assert_eq!(perft(&mut second, 3), 18002);
+        let mut third = Game::read_FEN("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9");
+        assert_eq!(perft(&mut third, 3), 10471);
+    }
+
+    #[test]
+    fn chess960_castling_is_the_king_taking_its_rook() {
+        let mut game = Game::read_FEN("1r3kr1/pppppppp/8/8/8/8/PPPPPPPP/1R3KR1 w GBgb - 0 1");
+        let before = game.to_fen();
+        let kingside = Move::from_uci("f1g1", &game).unwrap();
+        assert!(kingside.is_castle());
+        assert_eq!(game.to_san(kingside), "O-O");
+        // the king and rook swap squares
+        game.make_move(kingside);
+        assert_eq!(game.to_shredder_fen(), "1r3kr1/pppppppp/8/8/8/8/PPPPPPPP/1R3RK1 b gb - 1 1");
+        assert_eq!(game.hash(), crate::zobrist::compute(&game));
+        game.unmake_move();
+        assert_eq!(game.to_fen(), before);
+        let queenside = Move::from_uci("f1b1", &game).unwrap();
+        assert_eq!(game.to_san(queenside), "O-O-O");
+        game.make_move(queenside);
+        assert_eq!(game.to_shredder_fen(), "1r3kr1/pppppppp/8/8/8/8/PPPPPPPP/2KR2R1 b gb - 1 1");
+        // the plain king step is not castling here
+        game.unmake_move();
+        assert!(Move::from_uci("f1d1", &game).is_err());
+    }
+
+    #[test]
+    fn chess960_rook_moves_clear_their_own_right() {
+        let mut game = Game::read_FEN("bqnbrkrn/pppppppp/8/8/8/8/PPPPPP1P/BQNBRKRN w GEge - 0 1");
+        game.make_move("g1g2".parse().unwrap());
+        assert_eq!(game.castling_rights, CastlingRights::WHITEQUEENSIDE | CastlingRights::BLACKKINGSIDE | CastlingRights::BLACKQUEENSIDE);
+        assert_eq!(game.to_shredder_fen(), "bqnbrkrn/pppppppp/8/8/8/8/PPPPPPRP/BQNBRK1N b Ege - 1 1");
+    }
+}
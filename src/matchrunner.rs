@@ -0,0 +1,500 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use crate::game::{Color, Game};
+use crate::movegen::Move;
+use crate::outcome::GameResult;
+use crate::pgn::GameRecord;
+use crate::polyglot::Book;
+use crate::search::{SearchOptions, Searcher, MATE, MAX_DEPTH};
+use crate::skill::random;
+use crate::sprt::Sprt;
+use crate::timeman::{Instant, TimeControl};
+
+// how long an engine gets to answer uci and isready
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// a move that arrives this late after the flag fell still counts, for pipe and scheduling delays
+const MOVE_GRACE: Duration = Duration::from_millis(50);
+
+// both players' clocks before a move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clocks {
+    pub wtime: Duration,
+    pub btime: Duration,
+    pub increment: Duration,
+}
+
+impl Clocks {
+    fn time(&self, color: Color) -> Duration {
+        match color {
+            Color::White => self.wtime,
+            Color::Black => self.btime,
+        }
+    }
+
+    fn time_mut(&mut self, color: Color) -> &mut Duration {
+        match color {
+            Color::White => &mut self.wtime,
+            Color::Black => &mut self.btime,
+        }
+    }
+}
+
+// one side of a match, the score is the engine's own from the mover's point of view
+pub trait MatchEngine {
+    fn name(&self) -> String;
+    fn new_game(&mut self) -> Result<(), String>;
+    fn play(&mut self, game: &Game, clocks: &Clocks) -> Result<(Move, Option<i32>), String>;
+}
+
+// this engine in the same process, searching to `depth` or on the clock when None
+pub struct BuiltinEngine {
+    name: String,
+    searcher: Searcher,
+    depth: Option<usize>,
+}
+
+impl BuiltinEngine {
+    pub fn new(name: &str, depth: Option<usize>) -> BuiltinEngine {
+        BuiltinEngine { name: name.to_string(), searcher: Searcher::new(), depth }
+    }
+}
+
+impl MatchEngine for BuiltinEngine {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn new_game(&mut self) -> Result<(), String> {
+        self.searcher.clear_hash();
+        Ok(())
+    }
+
+    fn play(&mut self, game: &Game, clocks: &Clocks) -> Result<(Move, Option<i32>), String> {
+        let clock = TimeControl { time: clocks.time(game.active_color), increment: clocks.increment, moves_to_go: None };
+        let options = match self.depth {
+            Some(depth) => SearchOptions { depth, ..SearchOptions::default() },
+            None => SearchOptions { depth: MAX_DEPTH, time: Some(clock.limits()), ..SearchOptions::default() },
+        };
+        let result = self.searcher.search_with(&mut game.clone(), &options);
+        let mv = result.best_move.ok_or("No move to play")?;
+        Ok((mv, Some(result.score)))
+    }
+}
+
+// the score of an info line in centipawns, mates as the search scores them
+pub fn parse_info_score(line: &str) -> Option<i32> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let at = words.iter().position(|word| *word == "score")?;
+    let value: i32 = words.get(at + 2)?.parse().ok()?;
+    match *words.get(at + 1)? {
+        "cp" => Some(value),
+        "mate" if value > 0 => Some(MATE - (2 * value - 1)),
+        "mate" => Some(-(MATE + 2 * value)),
+        _ => None,
+    }
+}
+
+// an external engine speaking uci on its standard input and output
+pub struct UciEngine {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: mpsc::Receiver<String>,
+}
+
+impl UciEngine {
+    // starts `command` and sets the options, the name is the one the engine gives itself
+    pub fn start(command: &str, args: &[String], options: &[(String, String)]) -> Result<UciEngine, String> {
+        let mut child = Command::new(command).args(args)
+            .stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| format!("Cannot start {}: {}", command, err))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        // lines come in on a thread of their own so a silent engine can be timed out
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut engine = UciEngine { name: command.to_string(), child, stdin, lines };
+        engine.send("uci")?;
+        let id = engine.wait_for("uciok", HANDSHAKE_TIMEOUT)?;
+        if let Some(name) = id.iter().find_map(|line| line.strip_prefix("id name ")) {
+            engine.name = name.to_string();
+        }
+        for (name, value) in options {
+            engine.send(&format!("setoption name {} value {}", name, value))?;
+        }
+        Ok(engine)
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{}", line).and_then(|_| self.stdin.flush())
+            .map_err(|err| format!("{} stopped: {}", self.name, err))
+    }
+
+    // the lines up to and including the first one starting with `prefix`
+    fn wait_for(&mut self, prefix: &str, timeout: Duration) -> Result<Vec<String>, String> {
+        let deadline = Instant::now() + timeout;
+        let mut lines = vec![];
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let line = self.lines.recv_timeout(left)
+                .map_err(|_| format!("{} did not answer with {}", self.name, prefix))?;
+            let done = line.starts_with(prefix);
+            lines.push(line);
+            if done {
+                return Ok(lines);
+            }
+        }
+    }
+}
+
+impl MatchEngine for UciEngine {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn new_game(&mut self) -> Result<(), String> {
+        self.send("ucinewgame")?;
+        self.send("isready")?;
+        self.wait_for("readyok", HANDSHAKE_TIMEOUT).map(|_| ())
+    }
+
+    fn play(&mut self, game: &Game, clocks: &Clocks) -> Result<(Move, Option<i32>), String> {
+        let record = GameRecord::from_game(game);
+        let start = record.starting_position()?;
+        let moves: Vec<String> = record.mainline().iter().map(|mv| mv.to_uci()).collect();
+        let moves = if moves.is_empty() { String::new() } else { format!(" moves {}", moves.join(" ")) };
+        self.send(&format!("position fen {}{}", start.to_fen(), moves))?;
+        let millis = |time: Duration| time.as_millis();
+        let increment = millis(clocks.increment);
+        self.send(&format!("go wtime {} btime {} winc {} binc {}",
+            millis(clocks.wtime), millis(clocks.btime), increment, increment))?;
+        let lines = self.wait_for("bestmove", clocks.time(game.active_color) + Duration::from_secs(1))?;
+        let score = lines.iter().rev().find_map(|line| parse_info_score(line));
+        let uci = lines.last().and_then(|line| line.split_whitespace().nth(1)).unwrap_or("");
+        let mv = Move::from_uci(uci, game).map_err(|_| format!("{} played an illegal move {}", self.name, uci))?;
+        Ok((mv, score))
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        thread::sleep(Duration::from_millis(50));
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// when a game is decided before the rules end it, all off with None
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Adjudication {
+    // a win once both engines have seen one side this far ahead for `resign_moves` moves each
+    pub resign_score: Option<i32>,
+    pub resign_moves: usize,
+    // a draw from move `draw_after` on, once both scores stayed within `draw_score` for `draw_moves` moves each
+    pub draw_score: Option<i32>,
+    pub draw_moves: usize,
+    pub draw_after: usize,
+    // a draw after this many plies
+    pub max_plies: Option<usize>,
+}
+
+impl Default for Adjudication {
+    fn default() -> Adjudication {
+        Adjudication {
+            resign_score: Some(1000), resign_moves: 3, draw_score: Some(10), draw_moves: 8, draw_after: 40,
+            max_plies: Some(400),
+        }
+    }
+}
+
+impl Adjudication {
+    // `scores` are white's point of view, one per ply
+    fn judge(&self, game: &Game, scores: &[Option<i32>]) -> Option<GameResult> {
+        // the scores of the last `moves` moves of each side, if the engines reported them all
+        let last = |moves: usize| -> Option<Vec<i32>> {
+            if moves == 0 || scores.len() < 2 * moves {
+                return None;
+            }
+            scores[scores.len() - 2 * moves..].iter().copied().collect()
+        };
+        if let (Some(margin), Some(recent)) = (self.resign_score, last(self.resign_moves)) {
+            if recent.iter().all(|score| *score >= margin) {
+                return Some(GameResult::WhiteWins);
+            }
+            if recent.iter().all(|score| *score <= -margin) {
+                return Some(GameResult::BlackWins);
+            }
+        }
+        if let (Some(margin), Some(recent)) = (self.draw_score, last(self.draw_moves)) {
+            if game.fullmove_number >= self.draw_after && recent.iter().all(|score| score.abs() <= margin) {
+                return Some(GameResult::Draw);
+            }
+        }
+        match self.max_plies {
+            Some(plies) if scores.len() >= plies => Some(GameResult::Draw),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MatchConfig {
+    pub games: usize,
+    pub time: Duration,
+    pub increment: Duration,
+    // each opening is played twice with the colors swapped, the initial position when empty
+    pub openings: Vec<Game>,
+    pub adjudication: Adjudication,
+    // stops the match early once the test is decided, `games` is then the most it may take
+    pub sprt: Option<Sprt>,
+}
+
+impl Default for MatchConfig {
+    fn default() -> MatchConfig {
+        MatchConfig {
+            games: 2, time: Duration::from_secs(10), increment: Duration::from_millis(100), openings: vec![],
+            adjudication: Adjudication::default(), sprt: None,
+        }
+    }
+}
+
+// `count` lines of up to `plies` book moves, picked by weight with a different seed for each line
+pub fn book_openings(book: &Book, plies: usize, count: usize) -> Vec<Game> {
+    (0..count as u64).map(|seed| {
+        let mut game = Game::initialize();
+        for _ in 0..plies {
+            let moves = book.probe(&game);
+            let total: u64 = moves.iter().map(|(_, weight)| *weight as u64 + 1).sum();
+            if total == 0 {
+                break;
+            }
+            let mut pick = random(game.hash() ^ seed) % total;
+            let Some((mv, _)) = moves.iter().find(|(_, weight)| {
+                let found = pick <= *weight as u64;
+                pick = pick.saturating_sub(*weight as u64 + 1);
+                found
+            }) else { break };
+            game.make_move(*mv);
+        }
+        game
+    }).collect()
+}
+
+// plays one game from `start`, an engine error or an illegal move loses it for that engine
+pub fn play_game(white: &mut dyn MatchEngine, black: &mut dyn MatchEngine, start: &Game, config: &MatchConfig) -> Game {
+    let mut game = start.clone();
+    let mut clocks = Clocks { wtime: config.time, btime: config.time, increment: config.increment };
+    let mut scores = vec![];
+    if white.new_game().is_err() {
+        game.forfeit(Color::White);
+        return game;
+    }
+    if black.new_game().is_err() {
+        game.forfeit(Color::Black);
+        return game;
+    }
+    loop {
+        if game.update_outcome().is_some() || game.claim_draw() {
+            return game;
+        }
+        let mover = game.active_color;
+        let engine: &mut dyn MatchEngine = if mover == Color::White { &mut *white } else { &mut *black };
+        let started = Instant::now();
+        let played = engine.play(&game, &clocks);
+        let elapsed = started.elapsed();
+        let (mv, score) = match played {
+            Ok(played) => played,
+            Err(_) => {
+                game.forfeit(mover);
+                return game;
+            }
+        };
+        if elapsed > clocks.time(mover) + MOVE_GRACE {
+            game.flag_fall(mover);
+            return game;
+        }
+        let increment = clocks.increment;
+        let time = clocks.time_mut(mover);
+        *time = time.saturating_sub(elapsed) + increment;
+        game.make_move(mv);
+        let score = score.map(|score| if mover == Color::White { score } else { -score });
+        scores.push(score);
+        if let Some(result) = config.adjudication.judge(&game, &scores) {
+            game.adjudicate(result);
+            return game;
+        }
+    }
+}
+
+// the results from the first engine's point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchScore {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    // points per game, from 0 to 1
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games().max(1) as f64
+    }
+
+    // the rating difference the score suggests, None after only wins or only losses
+    pub fn elo(&self) -> Option<f64> {
+        elo_from_score(self.score())
+    }
+
+    // half the width of the 95% confidence interval around elo()
+    pub fn elo_margin(&self) -> Option<f64> {
+        let n = self.games() as f64;
+        let mean = self.score();
+        let variance = (self.wins as f64 * (1.0 - mean).powi(2) + self.draws as f64 * (0.5 - mean).powi(2)
+            + self.losses as f64 * mean.powi(2)) / n;
+        let deviation = 1.96 * (variance / n).sqrt();
+        Some((elo_from_score(mean + deviation)? - elo_from_score(mean - deviation)?) / 2.0)
+    }
+
+    pub fn add(&mut self, result: GameResult, first_is_white: bool) {
+        match (result, first_is_white) {
+            (GameResult::Draw, _) => self.draws += 1,
+            (GameResult::WhiteWins, true) | (GameResult::BlackWins, false) => self.wins += 1,
+            _ => self.losses += 1,
+        }
+    }
+}
+
+pub fn elo_from_score(score: f64) -> Option<f64> {
+    (score > 0.0 && score < 1.0).then(|| 400.0 * (score / (1.0 - score)).log10())
+}
+
+// plays config.games games with the colors alternating, fewer if an sprt decides first, `report` gets each finished game
+// with its number and the running score
+pub fn run_match(
+    first: &mut dyn MatchEngine, second: &mut dyn MatchEngine, config: &MatchConfig,
+    mut report: impl FnMut(usize, &GameRecord, &MatchScore),
+) -> MatchScore {
+    let mut score = MatchScore::default();
+    let openings = if config.openings.is_empty() { vec![Game::initialize()] } else { config.openings.clone() };
+    for number in 0..config.games {
+        let start = &openings[number / 2 % openings.len()];
+        let first_is_white = number % 2 == 0;
+        let game = if first_is_white {
+            play_game(first, second, start, config)
+        } else {
+            play_game(second, first, start, config)
+        };
+        let (white, black) = if first_is_white { (first.name(), second.name()) } else { (second.name(), first.name()) };
+        score.add(game.outcome.map_or(GameResult::Draw, |outcome| outcome.result), first_is_white);
+        let mut record = GameRecord::from_game(&game);
+        record.headers.push(("Event".to_string(), "Engine match".to_string()));
+        record.headers.push(("Round".to_string(), (number + 1).to_string()));
+        record.headers.push(("White".to_string(), white));
+        record.headers.push(("Black".to_string(), black));
+        report(number + 1, &record, &score);
+        // only after both colors of an opening, so the result is not skewed by who had white
+        if first_is_white {
+            continue;
+        }
+        if config.sprt.is_some_and(|sprt| sprt.decide(&score).is_some()) {
+            break;
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outcome::TerminationReason;
+
+    // plays the first legal move, or fails
+    struct FirstMove(bool);
+
+    impl MatchEngine for FirstMove {
+        fn name(&self) -> String {
+            "first".to_string()
+        }
+
+        fn new_game(&mut self) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn play(&mut self, game: &Game, _: &Clocks) -> Result<(Move, Option<i32>), String> {
+            if self.0 { Err("crashed".to_string()) } else { Ok((game.legal_moves()[0], None)) }
+        }
+    }
+
+    #[test]
+    fn reads_scores_from_info_lines() {
+        assert_eq!(parse_info_score("info depth 5 score cp -31 nodes 100 pv e2e4"), Some(-31));
+        assert_eq!(parse_info_score("info depth 5 score mate 2 pv a1a8"), Some(MATE - 3));
+        assert_eq!(parse_info_score("info depth 5 score mate -1"), Some(-(MATE - 2)));
+        assert_eq!(parse_info_score("info string hello"), None);
+    }
+
+    #[test]
+    fn computes_elo_from_results() {
+        let score = MatchScore { wins: 30, draws: 40, losses: 30 };
+        assert_eq!(score.elo(), Some(0.0));
+        let score = MatchScore { wins: 60, draws: 20, losses: 20 };
+        assert!((score.elo().unwrap() - 147.2).abs() < 0.1, "{:?}", score.elo());
+        assert!(score.elo_margin().unwrap() > 0.0);
+        assert_eq!(MatchScore { wins: 3, draws: 0, losses: 0 }.elo(), None);
+    }
+
+    #[test]
+    fn adjudicates_and_forfeits() {
+        let config = MatchConfig::default();
+        let start = Game::read_FEN("6k1/8/8/8/8/8/8/QQQ3K1 w - - 0 1");
+        let mut strong = BuiltinEngine::new("strong", Some(2));
+        let mut crashing = FirstMove(true);
+        let game = play_game(&mut strong, &mut crashing, &start, &config);
+        assert_eq!(game.outcome.unwrap().reason, TerminationReason::Forfeit);
+        assert_eq!(game.outcome.unwrap().result, GameResult::WhiteWins);
+
+        let scores = [Some(1200); 6];
+        assert_eq!(config.adjudication.judge(&start, &scores), Some(GameResult::WhiteWins));
+        assert_eq!(config.adjudication.judge(&start, &scores[..5]), None);
+        let quiet = Game::read_FEN("6k1/8/8/8/8/8/8/6K1 w - - 0 60");
+        assert_eq!(config.adjudication.judge(&quiet, &[Some(0); 16]), Some(GameResult::Draw));
+        assert_eq!(config.adjudication.judge(&quiet, &[None; 16]), None);
+        assert_eq!(config.adjudication.judge(&Game::initialize(), &[Some(0); 16]), None);
+    }
+
+    #[test]
+    fn plays_a_match_with_alternating_colors() {
+        let config = MatchConfig { games: 2, adjudication: Adjudication { max_plies: Some(40), ..Adjudication::default() },
+            ..MatchConfig::default() };
+        let mut engine = BuiltinEngine::new("engine", Some(2));
+        let mut weak = FirstMove(false);
+        let mut whites = vec![];
+        let score = run_match(&mut engine, &mut weak, &config, |_, record, _| {
+            whites.push(record.header("White").unwrap().to_string());
+        });
+        assert_eq!(whites, ["engine", "first"]);
+        assert_eq!(score.games(), 2);
+        assert_eq!(score.losses, 0);
+
+        // with error rates of one half any result decides, but only after both colors
+        let sprt = Sprt { alpha: 0.5, beta: 0.5, ..Sprt::default() };
+        let config = MatchConfig { games: 100, sprt: Some(sprt), ..config };
+        let score = run_match(&mut engine, &mut weak, &config, |_, _, _| {});
+        assert_eq!(score.games(), 2);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::game::Game;
+use crate::movegen::Move;
+
+// the shortest forced mate for the side to move within `max_plies` plies, as the line
+// with the most stubborn defence. only proves mates, a None says nothing about the position
+pub fn solve_mate(game: &Game, max_plies: usize) -> Option<Vec<Move>> {
+    let mut game = game.clone();
+    (1..=max_plies).step_by(2).find_map(|plies| attack(&mut game, plies))
+}
+
+// some move mates against every defence with `plies` plies left
+fn attack(game: &mut Game, plies: usize) -> Option<Vec<Move>> {
+    for mv in game.legal_moves() {
+        // the mating move itself has to give check
+        if plies == 1 && !game.gives_check(mv) {
+            continue;
+        }
+        game.make_move(mv);
+        let line = defend(game, plies - 1);
+        game.unmake_move();
+        if let Some(mut line) = line {
+            line.insert(0, mv);
+            return Some(line);
+        }
+    }
+    None
+}
+
+// every reply still gets mated, the line follows the one that holds out longest
+fn defend(game: &mut Game, plies: usize) -> Option<Vec<Move>> {
+    let replies = game.legal_moves();
+    if replies.is_empty() {
+        return game.is_in_check(game.active_color).then(Vec::new);
+    }
+    if plies < 2 || game.halfmove_clock >= 100 {
+        return None;
+    }
+    let mut longest: Option<Vec<Move>> = None;
+    for reply in replies {
+        game.make_move(reply);
+        let line = (1..plies).step_by(2).find_map(|left| attack(game, left));
+        game.unmake_move();
+        let mut line = line?;
+        line.insert(0, reply);
+        if longest.as_ref().is_none_or(|best| line.len() > best.len()) {
+            longest = Some(line);
+        }
+    }
+    longest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uci(line: &[Move]) -> Vec<String> {
+        line.iter().map(|mv| mv.to_uci()).collect()
+    }
+
+    #[test]
+    fn finds_the_shortest_mate() {
+        let back_rank = Game::read_FEN("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        assert_eq!(uci(&solve_mate(&back_rank, 5).unwrap()), ["a1a8"]);
+
+        let ladder = Game::read_FEN("8/4k3/R7/8/8/8/8/1R5K w - - 0 1");
+        let line = solve_mate(&ladder, 3).unwrap();
+        assert_eq!(line.len(), 3);
+        let mut game = ladder.clone();
+        for mv in &line {
+            game.make_move(*mv);
+        }
+        assert!(game.legal_moves().is_empty() && game.is_in_check(game.active_color));
+    }
+
+    #[test]
+    fn proves_nothing_without_a_mate() {
+        assert_eq!(solve_mate(&Game::initialize(), 3), None);
+        // the only mate takes three moves
+        let ladder = Game::read_FEN("8/8/8/3k4/8/8/R7/1R5K w - - 0 1");
+        assert_eq!(solve_mate(&ladder, 3), None);
+        // stalemating is not mating
+        let stalemate = Game::read_FEN("7k/8/5Q2/6K1/8/8/8/8 b - - 0 1");
+        assert_eq!(solve_mate(&stalemate, 3), None);
+    }
+}
@@ -0,0 +1,1094 @@
+use bitflags::bitflags;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use crate::attacks::*;
+use crate::game::*;
+use crate::square::Square;
+use crate::utils::*;
+
+bitflags! {
+    // what kind of move it is in the position it was generated for
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct MoveFlags: u8 {
+        const QUIET = 0;
+        const CAPTURE = 1 << 0;
+        const DOUBLE_PAWN_PUSH = 1 << 1;
+        const EN_PASSANT = 1 << 2;
+        const KINGSIDE_CASTLE = 1 << 3;
+        const QUEENSIDE_CASTLE = 1 << 4;
+    }
+}
+
+// a move from one square index to another, pawns reaching the last rank carry
+// the piece they promote to
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Copy, Clone)]
+pub struct Move {
+    from: usize,
+    to: usize,
+    promotion: Option<PieceType>,
+    flags: MoveFlags,
+}
+
+// flags are derived from the position, so moves parsed from plain coordinates
+// compare equal to the generated ones
+impl PartialEq for Move {
+    fn eq(&self, other: &Move) -> bool {
+        self.from == other.from && self.to == other.to && self.promotion == other.promotion
+    }
+}
+
+impl Eq for Move {}
+
+impl Hash for Move {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.from, self.to, self.promotion).hash(state);
+    }
+}
+
+impl Move {
+    // passes the turn without moving, only meaningful to make_null_move
+    pub fn null() -> Move {
+        Move::new(Square::A1, Square::A1)
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.from == self.to
+    }
+
+    pub fn new(from: Square, to: Square) -> Move {
+        Move::with_flags(from.index(), to.index(), MoveFlags::QUIET)
+    }
+
+    pub fn new_promotion(from: Square, to: Square, piece: PieceType) -> Move {
+        Move { promotion: Some(piece), ..Move::new(from, to) }
+    }
+
+    // the generators work on board indices, the public constructors above on squares
+    pub(crate) fn with_flags(from: usize, to: usize, flags: MoveFlags) -> Move {
+        Move { from, to, promotion: None, flags }
+    }
+
+    pub(crate) fn from(&self) -> usize {
+        self.from
+    }
+
+    pub(crate) fn to(&self) -> usize {
+        self.to
+    }
+
+    pub fn from_square(&self) -> Square {
+        Square::ALL[self.from]
+    }
+
+    pub fn to_square(&self) -> Square {
+        Square::ALL[self.to]
+    }
+
+    pub fn promotion(&self) -> Option<PieceType> {
+        self.promotion
+    }
+
+    pub fn flags(&self) -> MoveFlags {
+        self.flags
+    }
+
+    // reads a move like e2e4 or e7e8q as sent by a UCI gui, returning the generated
+    // move with its flags if it is legal in the position
+    pub fn from_uci(s: &str, game: &Game) -> Result<Move, String> {
+        let parsed: Move = s.parse()?;
+        if parsed.is_null() {
+            return Err(format!("Illegal move {}", s));
+        }
+        game.moves_from(parsed.from_square())
+            .into_iter()
+            .find(|mv| *mv == parsed)
+            .ok_or_else(|| format!("Illegal move {}", s))
+    }
+
+    pub fn to_uci(&self) -> String {
+        self.to_string()
+    }
+
+    pub fn is_capture(&self) -> bool {
+        self.flags.contains(MoveFlags::CAPTURE)
+    }
+
+    pub fn is_en_passant(&self) -> bool {
+        self.flags.contains(MoveFlags::EN_PASSANT)
+    }
+
+    pub fn is_double_pawn_push(&self) -> bool {
+        self.flags.contains(MoveFlags::DOUBLE_PAWN_PUSH)
+    }
+
+    pub fn is_castle(&self) -> bool {
+        self.flags.intersects(MoveFlags::KINGSIDE_CASTLE | MoveFlags::QUEENSIDE_CASTLE)
+    }
+
+    // the squares and the promotion in the low 15 bits and the flags above them, how the
+    // transposition table keeps a move
+    pub(crate) fn to_bits(self) -> u32 {
+        let promotion = self.promotion.map_or(0, |piece| PieceType::ALL.iter().position(|p| *p == piece).unwrap() as u32 + 1);
+        self.from as u32 | (self.to as u32) << 6 | promotion << 12 | (self.flags.bits() as u32) << 15
+    }
+
+    pub(crate) fn from_bits(bits: u32) -> Move {
+        let promotion = match (bits >> 12) & 7 {
+            0 => None,
+            piece => Some(PieceType::ALL[piece as usize - 1]),
+        };
+        let flags = MoveFlags::from_bits_truncate((bits >> 15) as u8);
+        Move { from: (bits & 63) as usize, to: (bits >> 6 & 63) as usize, promotion, flags }
+    }
+}
+
+fn promotion_char(piece: PieceType) -> char {
+    match piece {
+        PieceType::Queen => 'q',
+        PieceType::Rook => 'r',
+        PieceType::Bishop => 'b',
+        PieceType::Knight => 'n',
+        PieceType::Pawn => 'p',
+        PieceType::King => 'k',
+    }
+}
+
+// coordinate notation, e.g. e2e4 or e7e8q
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_null() {
+            return write!(f, "0000");
+        }
+        write!(f, "{}{}", self.from_square(), self.to_square())?;
+        if let Some(piece) = self.promotion {
+            write!(f, "{}", promotion_char(piece))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Move {
+    type Err = String;
+
+    // only the squares and promotion piece are known from the text, the flags stay empty
+    fn from_str(s: &str) -> Result<Move, String> {
+        if s == "0000" {
+            return Ok(Move::null());
+        }
+        if s.len() != 4 && s.len() != 5 {
+            return Err(format!("Invalid move length {}", s.len()));
+        }
+        let square = |range| match s.get(range) {
+            Some(position) => Square::from_str(position),
+            None => Err(format!("Invalid move {}", s)),
+        };
+        let from = square(0..2)?;
+        let to = square(2..4)?;
+
+        match s.get(4..) {
+            Some("") => Ok(Move::new(from, to)),
+            Some("q") => Ok(Move::new_promotion(from, to, PieceType::Queen)),
+            Some("r") => Ok(Move::new_promotion(from, to, PieceType::Rook)),
+            Some("b") => Ok(Move::new_promotion(from, to, PieceType::Bishop)),
+            Some("n") => Ok(Move::new_promotion(from, to, PieceType::Knight)),
+            _ => Err(format!("Invalid promotion piece in {}", s)),
+        }
+    }
+}
+
+static PROMOTION_PIECES: [PieceType; 4] = [
+    PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight
+];
+
+fn push_moves(from: usize, mut targets: u64, enemy: u64, moves: &mut Vec<Move>) {
+    while targets != 0 {
+        let to = pop_lsb(&mut targets);
+        let flags = if enemy & (1u64 << to) != 0 { MoveFlags::CAPTURE } else { MoveFlags::QUIET };
+        moves.push(Move::with_flags(from, to, flags));
+    }
+}
+
+fn shift(bits: u64, offset: i32) -> u64 {
+    if offset > 0 { bits << offset } else { bits >> -offset }
+}
+
+// targets were reached by shifting pawns by `offset`, pawns reaching the last rank promote
+fn push_pawn_moves(mut targets: u64, offset: i32, flags: MoveFlags, en_passant: u64,
+                   last_rank: u64, moves: &mut Vec<Move>) {
+    while targets != 0 {
+        let to = pop_lsb(&mut targets);
+        let from = (to as i32 - offset) as usize;
+        let flags = if en_passant & (1u64 << to) != 0 { flags | MoveFlags::EN_PASSANT } else { flags };
+        if last_rank & (1u64 << to) != 0 {
+            for piece in PROMOTION_PIECES {
+                moves.push(Move { from, to, promotion: Some(piece), flags });
+            }
+        } else {
+            moves.push(Move::with_flags(from, to, flags));
+        }
+    }
+}
+
+// what the side to move has to respect: squares that answer a check and pinned pieces
+struct Restrictions {
+    check_mask: u64,
+    pinned: u64,
+    pins: Vec<(usize, u64)>,
+}
+
+impl Restrictions {
+    fn new(game: &Game) -> Restrictions {
+        Restrictions::for_color(game, game.active_color)
+    }
+
+    fn for_color(game: &Game, us: Color) -> Restrictions {
+        let king = game.bitboard(us, PieceType::King);
+        let checkers = match king {
+            0 => 0,
+            king => game.attackers_to(Square::from_index(bit_scan(king)), us.opponent(), game.occupied()),
+        };
+        let check_mask = match checkers.count_ones() {
+            0 => !0,
+            1 => checkers | between(Square::from_index(bit_scan(king)), Square::from_index(bit_scan(checkers))),
+            _ => 0,
+        };
+        let pins = game.pins(us);
+        let pinned = pins.iter().fold(0, |bb, (square, _)| bb | (1u64 << square));
+        Restrictions { check_mask, pinned, pins }
+    }
+
+    fn in_check(&self) -> bool {
+        self.check_mask != !0
+    }
+
+    fn ray(&self, square: usize) -> u64 {
+        self.pins.iter()
+            .find(|(pinned, _)| *pinned == square)
+            .map_or(!0, |(_, ray)| *ray)
+    }
+}
+
+// number of legal moves per piece type
+#[derive(Debug, PartialEq, Eq, Default, Copy, Clone)]
+pub struct Mobility {
+    pub pawn: usize,
+    pub knight: usize,
+    pub bishop: usize,
+    pub rook: usize,
+    pub queen: usize,
+    pub king: usize,
+}
+
+impl Mobility {
+    pub fn of(&self, piece_type: PieceType) -> usize {
+        match piece_type {
+            PieceType::Pawn => self.pawn,
+            PieceType::Knight => self.knight,
+            PieceType::Bishop => self.bishop,
+            PieceType::Rook => self.rook,
+            PieceType::Queen => self.queen,
+            PieceType::King => self.king,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.pawn + self.knight + self.bishop + self.rook + self.queen + self.king
+    }
+}
+
+impl Game {
+    // every move for the side to move that does not leave its own king in check
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let restrictions = Restrictions::new(self);
+        let mut moves = if restrictions.in_check() {
+            self.evasions(&restrictions)
+        } else {
+            self.pseudo_legal_moves()
+        };
+        moves.retain(|mv| self.is_allowed(mv, &restrictions));
+        moves
+    }
+
+    // legal replies to a check: king moves, blocks and captures of the checker,
+    // outside of check this is the same as legal_moves
+    pub fn evasion_moves(&self) -> Vec<Move> {
+        self.legal_moves()
+    }
+
+    // only generates moves that can possibly answer the check instead of filtering everything
+    fn evasions(&self, restrictions: &Restrictions) -> Vec<Move> {
+        let (own, _) = self.own_and_enemy();
+        let mut moves = Vec::new();
+        self.piece_moves(PieceType::King, !own, &mut moves);
+        // in double check the mask is empty and only the king may move
+        if restrictions.check_mask != 0 {
+            for piece_type in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+                self.piece_moves(piece_type, restrictions.check_mask, &mut moves);
+            }
+            self.pawn_moves_with_pushes_to(restrictions.check_mask, &mut moves);
+        }
+        moves
+    }
+
+    // king moves and en passant change the board around the king in ways pins do not
+    // describe, everything else only has to block or capture a checker and stay on its pin ray
+    fn is_allowed(&self, mv: &Move, restrictions: &Restrictions) -> bool {
+        let piece = self.piece_at(mv.from_square()).unwrap();
+        let to_bit = 1u64 << mv.to();
+        let en_passant = piece.piece_type == PieceType::Pawn && self.en_passant == Some(to_bit);
+        // castling_targets already made sure the king never passes through check
+        if mv.is_castle() {
+            return true;
+        }
+        if piece.piece_type == PieceType::King || en_passant {
+            return self.leaves_king_safe(mv);
+        }
+
+        let pinned = restrictions.pinned & (1u64 << mv.from()) != 0;
+        to_bit & restrictions.check_mask != 0 && (!pinned || to_bit & restrictions.ray(mv.from()) != 0)
+    }
+
+    // legal moves of the piece on one square, e.g. to highlight destinations in a gui
+    pub fn moves_from(&self, square: Square) -> Vec<Move> {
+        let mut moves = self.pseudo_moves_from(square.index());
+        if !moves.is_empty() {
+            let restrictions = Restrictions::new(self);
+            moves.retain(|mv| self.is_allowed(mv, &restrictions));
+        }
+        moves
+    }
+
+    // counts legal moves of `color` as if it were its turn, without building move lists;
+    // en passant is only available to the side to move
+    pub fn mobility(&self, color: Color) -> Mobility {
+        let restrictions = Restrictions::for_color(self, color);
+        let them = color.opponent();
+        let own = self.occupancy(color);
+        let enemy = self.occupancy(them);
+        let occupied = own | enemy;
+        let (forward, push_rank, last_rank) = match color {
+            Color::White => (8, RANK_3, RANK_8),
+            Color::Black => (-8, RANK_6, RANK_1),
+        };
+
+        let mut mobility = Mobility::default();
+        for piece in self.pieces.iter().filter(|p| p.color == color) {
+            let square = bit_scan(piece.position);
+            let mask = restrictions.check_mask & !own & restrictions.ray(square);
+            match piece.piece_type {
+                PieceType::Pawn => {
+                    let single = shift(piece.position, forward) & !occupied;
+                    let double = shift(single & push_rank, forward) & !occupied;
+                    let targets = (single | double | (pawn_attacks(color, square) & enemy)) & mask;
+                    let promotions = if targets & last_rank != 0 { 4 } else { 1 };
+                    mobility.pawn += targets.count_ones() as usize * promotions;
+
+                    if let Some(ep) = self.en_passant {
+                        if color == self.active_color && pawn_attacks(color, square) & ep != 0
+                            && self.leaves_king_safe(&Move::with_flags(square, bit_scan(ep), MoveFlags::EN_PASSANT)) {
+                            mobility.pawn += 1;
+                        }
+                    }
+                }
+                PieceType::Knight => mobility.knight += (knight_attacks(square) & mask).count_ones() as usize,
+                PieceType::Bishop => mobility.bishop += (bishop_attacks(square, occupied) & mask).count_ones() as usize,
+                PieceType::Rook => mobility.rook += (rook_attacks(square, occupied) & mask).count_ones() as usize,
+                PieceType::Queen => mobility.queen += (queen_attacks(square, occupied) & mask).count_ones() as usize,
+                PieceType::King => {
+                    // the king may not hide behind itself from a slider
+                    let mut targets = king_attacks(square) & !own;
+                    while targets != 0 {
+                        let to = pop_lsb(&mut targets);
+                        if !self.is_square_attacked(to, them, occupied & !piece.position, 1u64 << to) {
+                            mobility.king += 1;
+                        }
+                    }
+                    mobility.king += self.castling_targets(color, square, occupied).count_ones() as usize;
+                }
+            }
+        }
+        mobility
+    }
+
+    // validates a single move, e.g. one submitted by a user, without generating every move.
+    // the flags are the generated move's, not the caller's: castling_targets already checked
+    // castles the way legal_moves does, and in chess960 the king "takes" its own rook so
+    // leaves_king_safe would look at the wrong square
+    pub fn is_legal(&self, mv: Move) -> bool {
+        match self.pseudo_moves_from(mv.from()).into_iter().find(|generated| *generated == mv) {
+            Some(generated) => generated.is_castle() || self.leaves_king_safe(&generated),
+            None => false,
+        }
+    }
+
+    fn pseudo_moves_from(&self, square: usize) -> Vec<Move> {
+        let mut moves = Vec::new();
+        let piece = match self.piece_at(Square::from_index(square)) {
+            Some(piece) if piece.color == self.active_color => piece,
+            _ => return moves,
+        };
+        let (own, enemy) = self.own_and_enemy();
+        let occupied = own | enemy;
+        match piece.piece_type {
+            PieceType::Pawn => {
+                self.pawn_moves(&mut moves);
+                moves.retain(|mv| mv.from() == square);
+            }
+            PieceType::Knight => push_moves(square, knight_attacks(square) & !own, enemy, &mut moves),
+            PieceType::Bishop => push_moves(square, bishop_attacks(square, occupied) & !own, enemy, &mut moves),
+            PieceType::Rook => push_moves(square, rook_attacks(square, occupied) & !own, enemy, &mut moves),
+            PieceType::Queen => push_moves(square, queen_attacks(square, occupied) & !own, enemy, &mut moves),
+            PieceType::King => {
+                push_moves(square, king_attacks(square) & !own, enemy, &mut moves);
+                self.castling_moves(square, occupied, &mut moves);
+            }
+        }
+        moves
+    }
+
+    // pieces of `color` that cannot leave the line between their king and an enemy slider
+    pub fn pinned_pieces(&self, color: Color) -> u64 {
+        self.pins(color).iter().fold(0, |bb, (square, _)| bb | (1u64 << square))
+    }
+
+    // squares the piece on `square` may move to without uncovering its king,
+    // the whole board when it is not pinned
+    pub fn pin_ray(&self, square: Square) -> u64 {
+        let color = match self.piece_at(square) {
+            None => return !0,
+            Some(piece) => piece.color,
+        };
+        self.pins(color).into_iter()
+            .find(|(pinned, _)| *pinned == square.index())
+            .map_or(!0, |(_, ray)| ray)
+    }
+
+    // each pinned piece with its ray from the king up to and including the pinner
+    fn pins(&self, color: Color) -> Vec<(usize, u64)> {
+        let mut pins = Vec::new();
+        let king = match self.bitboard(color, PieceType::King) {
+            0 => return pins,
+            bit => bit_scan(bit),
+        };
+        let them = color.opponent();
+        let own = self.occupancy(color);
+        let enemy = self.occupancy(them);
+        let queens = self.bitboard(them, PieceType::Queen);
+
+        // enemy sliders that see the king through exactly one of our pieces
+        let occupied = own | enemy;
+        let mut pinners = (rook_xray_attacks(king, occupied, own) & (self.bitboard(them, PieceType::Rook) | queens))
+            | (bishop_xray_attacks(king, occupied, own) & (self.bitboard(them, PieceType::Bishop) | queens));
+        while pinners != 0 {
+            let pinner = pop_lsb(&mut pinners);
+            let line = between(Square::from_index(king), Square::from_index(pinner));
+            pins.push((bit_scan(line & own), line | (1u64 << pinner)));
+        }
+        pins
+    }
+
+    // moves that follow the piece movement rules but may leave the king in check,
+    // filter them with `leaves_king_safe` when they are actually needed
+    pub fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        self.pawn_moves(&mut moves);
+        self.knight_moves(&mut moves);
+        self.bishop_moves(&mut moves);
+        self.rook_moves(&mut moves);
+        self.queen_moves(&mut moves);
+        self.king_moves(&mut moves);
+        moves
+    }
+
+    pub fn occupied(&self) -> u64 {
+        self.occupancy(Color::White) | self.occupancy(Color::Black)
+    }
+
+    fn own_and_enemy(&self) -> (u64, u64) {
+        (self.occupancy(self.active_color), self.occupancy(self.active_color.opponent()))
+    }
+
+    pub fn knight_moves(&self, moves: &mut Vec<Move>) {
+        let (own, _) = self.own_and_enemy();
+        self.piece_moves(PieceType::Knight, !own, moves);
+    }
+
+    pub fn bishop_moves(&self, moves: &mut Vec<Move>) {
+        let (own, _) = self.own_and_enemy();
+        self.piece_moves(PieceType::Bishop, !own, moves);
+    }
+
+    pub fn rook_moves(&self, moves: &mut Vec<Move>) {
+        let (own, _) = self.own_and_enemy();
+        self.piece_moves(PieceType::Rook, !own, moves);
+    }
+
+    pub fn queen_moves(&self, moves: &mut Vec<Move>) {
+        let (own, _) = self.own_and_enemy();
+        self.piece_moves(PieceType::Queen, !own, moves);
+    }
+
+    pub fn king_moves(&self, moves: &mut Vec<Move>) {
+        let (own, enemy) = self.own_and_enemy();
+        self.piece_moves(PieceType::King, !own, moves);
+        let mut kings = self.bitboard(self.active_color, PieceType::King);
+        while kings != 0 {
+            self.castling_moves(pop_lsb(&mut kings), own | enemy, moves);
+        }
+    }
+
+    // moves of every non-pawn piece of the given type landing on `targets`
+    fn piece_moves(&self, piece_type: PieceType, targets: u64, moves: &mut Vec<Move>) {
+        let (own, enemy) = self.own_and_enemy();
+        let occupied = own | enemy;
+        let mut pieces = self.bitboard(self.active_color, piece_type);
+        while pieces != 0 {
+            let from = pop_lsb(&mut pieces);
+            let attacks = match piece_type {
+                PieceType::Knight => knight_attacks(from),
+                PieceType::Bishop => bishop_attacks(from, occupied),
+                PieceType::Rook => rook_attacks(from, occupied),
+                PieceType::Queen => queen_attacks(from, occupied),
+                PieceType::King => king_attacks(from),
+                PieceType::Pawn => unreachable!("pawns are generated set-wise"),
+            };
+            push_moves(from, attacks & targets & !own, enemy, moves);
+        }
+    }
+
+    pub fn pawn_moves(&self, moves: &mut Vec<Move>) {
+        self.pawn_moves_with_pushes_to(!0, moves);
+    }
+
+    // pawns are generated set-wise: shift the whole pawn bitboard and walk the targets back,
+    // captures are always generated while pushes are limited to `push_targets`
+    fn pawn_moves_with_pushes_to(&self, push_targets: u64, moves: &mut Vec<Move>) {
+        let us = self.active_color;
+        let (own, enemy) = self.own_and_enemy();
+        let occupied = own | enemy;
+        let empty = !occupied;
+        let pawns = self.bitboard(us, PieceType::Pawn);
+        let en_passant = self.en_passant.unwrap_or(0);
+
+        // (forward shift, left capture shift, right capture shift, double push rank, last rank)
+        let (forward, left, right, push_rank, last_rank) = match us {
+            Color::White => (8, 7, 9, RANK_3, RANK_8),
+            Color::Black => (-8, -9, -7, RANK_6, RANK_1),
+        };
+
+        let single = shift(pawns, forward) & empty;
+        let double = shift(single & push_rank, forward) & empty;
+        let left_captures = shift(pawns & !FILE_A, left) & (enemy | en_passant);
+        let right_captures = shift(pawns & !FILE_H, right) & (enemy | en_passant);
+
+        push_pawn_moves(single & push_targets, forward, MoveFlags::QUIET, 0, last_rank, moves);
+        push_pawn_moves(double & push_targets, 2 * forward, MoveFlags::DOUBLE_PAWN_PUSH, 0, last_rank, moves);
+        push_pawn_moves(left_captures, left, MoveFlags::CAPTURE, en_passant, last_rank, moves);
+        push_pawn_moves(right_captures, right, MoveFlags::CAPTURE, en_passant, last_rank, moves);
+    }
+
+    // legal captures and promotions only, the moves a quiescence search looks at
+    pub fn capture_moves(&self) -> Vec<Move> {
+        let (_, enemy) = self.own_and_enemy();
+        let mut moves = Vec::new();
+        self.pawn_moves_with_pushes_to(RANK_1 | RANK_8, &mut moves);
+        for piece_type in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen, PieceType::King] {
+            self.piece_moves(piece_type, enemy, &mut moves);
+        }
+        let restrictions = Restrictions::new(self);
+        moves.retain(|mv| self.is_allowed(mv, &restrictions));
+        moves
+    }
+
+    fn castling_moves(&self, from: usize, occupied: u64, moves: &mut Vec<Move>) {
+        let mut targets = self.castling_targets(self.active_color, from, occupied);
+        while targets != 0 {
+            let to = pop_lsb(&mut targets);
+            // the kingside rook is always on the king's h-file side, in chess960 too
+            let kingside = if self.chess960 { to > from } else { to == from + 2 };
+            let flags = if kingside { MoveFlags::KINGSIDE_CASTLE } else { MoveFlags::QUEENSIDE_CASTLE };
+            moves.push(Move::with_flags(from, to, flags));
+        }
+    }
+
+    // the squares castling moves available to `us` go to, the king's destination in standard
+    // chess and the rook it castles with in chess960, where the king may already stand on the g-file
+    fn castling_targets(&self, us: Color, from: usize, occupied: u64) -> u64 {
+        let them = us.opponent();
+        let back_rank = match us {
+            Color::White => 0,
+            Color::Black => 56,
+        };
+        if from / 8 * 8 != back_rank {
+            return 0;
+        }
+        let rooks = self.bitboard(us, PieceType::Rook);
+        let mut targets = 0;
+        for (i, (right, color)) in CASTLING_SIDES.iter().enumerate() {
+            let rook = self.castling_rooks[i];
+            if *color != us || !self.castling_rights.contains(*right) || rooks & (1u64 << rook) == 0 {
+                continue;
+            }
+            let (king_to, rook_to) = if rook > from { (back_rank + 6, back_rank + 5) } else { (back_rank + 2, back_rank + 3) };
+            let king_path = between(Square::from_index(from), Square::from_index(king_to)) | (1u64 << from) | (1u64 << king_to);
+            let rook_path = between(Square::from_index(rook), Square::from_index(rook_to)) | (1u64 << rook_to);
+            // every square either piece crosses must be empty but for the two of them, and
+            // none of the king's may be attacked once they have both left
+            let others = occupied & !(1u64 << from) & !(1u64 << rook);
+            if others & (king_path | rook_path) != 0 {
+                continue;
+            }
+            let mut path = king_path;
+            let safe = std::iter::from_fn(|| (path != 0).then(|| pop_lsb(&mut path)))
+                .all(|square| !self.is_square_attacked(square, them, others, 0));
+            if safe {
+                targets |= 1u64 << if self.chess960 { rook } else { king_to };
+            }
+        }
+        targets
+    }
+
+    // every piece of color `by` attacking the square with the given occupancy
+    pub fn attackers_to(&self, square: Square, by: Color, occupied: u64) -> u64 {
+        let square = square.index();
+        let queens = self.bitboard(by, PieceType::Queen);
+        let rooks = self.bitboard(by, PieceType::Rook) | queens;
+        let bishops = self.bitboard(by, PieceType::Bishop) | queens;
+
+        (pawn_attacks(by.opponent(), square) & self.bitboard(by, PieceType::Pawn))
+            | (knight_attacks(square) & self.bitboard(by, PieceType::Knight))
+            | (king_attacks(square) & self.bitboard(by, PieceType::King))
+            | (rook_attacks(square, occupied) & rooks)
+            | (bishop_attacks(square, occupied) & bishops)
+    }
+
+    // every square attacked by `color`, including squares holding its own pieces
+    pub fn attacked_squares(&self, color: Color) -> u64 {
+        let occupied = self.occupied();
+        let mut attacked = 0;
+        for piece in self.pieces.iter().filter(|p| p.color == color) {
+            attacked |= piece_attacks(piece.piece_type, color, bit_scan(piece.position), occupied);
+        }
+        attacked
+    }
+
+    pub fn is_in_check(&self, color: Color) -> bool {
+        match self.bitboard(color, PieceType::King) {
+            0 => false,
+            king => self.attackers_to(Square::from_index(bit_scan(king)), color.opponent(), self.occupied()) != 0,
+        }
+    }
+
+    // pieces currently giving check to the side to move
+    pub fn checkers(&self) -> u64 {
+        let us = self.active_color;
+        match self.bitboard(us, PieceType::King) {
+            0 => 0,
+            king => self.attackers_to(Square::from_index(bit_scan(king)), us.opponent(), self.occupied()),
+        }
+    }
+
+    // whether `by` attacks the square given the occupancy, ignoring any pieces in `removed`
+    fn is_square_attacked(&self, square: usize, by: Color, occupied: u64, removed: u64) -> bool {
+        let pawns = self.bitboard(by, PieceType::Pawn) & !removed;
+        let knights = self.bitboard(by, PieceType::Knight) & !removed;
+        let kings = self.bitboard(by, PieceType::King) & !removed;
+        let queens = self.bitboard(by, PieceType::Queen) & !removed;
+        let rooks = (self.bitboard(by, PieceType::Rook) & !removed) | queens;
+        let bishops = (self.bitboard(by, PieceType::Bishop) & !removed) | queens;
+
+        pawn_attacks(by.opponent(), square) & pawns != 0
+            || knight_attacks(square) & knights != 0
+            || king_attacks(square) & kings != 0
+            || rook_attacks(square, occupied) & rooks != 0
+            || bishop_attacks(square, occupied) & bishops != 0
+    }
+
+    // legality filter for pseudo-legal moves: plays the move out on the bitboards only
+    // and checks the king is not attacked afterwards
+    pub fn leaves_king_safe(&self, mv: &Move) -> bool {
+        let us = self.active_color;
+        let piece = self.piece_at(mv.from_square()).unwrap();
+        let from_bit = 1u64 << mv.from();
+        let to_bit = 1u64 << mv.to();
+
+        let mut captured = to_bit;
+        if piece.piece_type == PieceType::Pawn && self.en_passant == Some(to_bit) {
+            captured |= match us {
+                Color::White => to_bit >> 8,
+                Color::Black => to_bit << 8,
+            };
+        }
+
+        let king = if piece.piece_type == PieceType::King {
+            mv.to()
+        } else {
+            match self.bitboard(us, PieceType::King) {
+                0 => return true,
+                bit => bit_scan(bit),
+            }
+        };
+
+        let occupied = (self.occupied() & !from_bit & !captured) | to_bit;
+        !self.is_square_attacked(king, us.opponent(), occupied, captured)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::square::Square;
+
+    fn count(fen: &str) -> usize {
+        Game::read_FEN(fen).legal_moves().len()
+    }
+
+    #[test]
+    fn starting_position_has_twenty_moves() {
+        assert_eq!(Game::initialize().legal_moves().len(), 20);
+    }
+
+    #[test]
+    fn perft_positions_at_depth_one() {
+        assert_eq!(count("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"), 48);
+        assert_eq!(count("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"), 14);
+        assert_eq!(count("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"), 6);
+        assert_eq!(count("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8"), 44);
+    }
+
+    #[test]
+    fn pseudo_legal_moves_include_moves_into_check() {
+        // the king on e1 may not step next to the black king's rook on d8
+        let game = Game::read_FEN("3rk3/8/8/8/8/8/8/4K3 w - - 0 1");
+        let pseudo = game.pseudo_legal_moves();
+        assert_eq!(pseudo.len(), 5);
+        assert!(pseudo.contains(&Move::new(Square::E1, Square::D1)));
+        assert!(!game.leaves_king_safe(&Move::new(Square::E1, Square::D1)));
+        assert_eq!(game.legal_moves().len(), 3);
+    }
+
+    #[test]
+    fn per_piece_generators_cover_all_moves() {
+        let game = Game::initialize();
+        let mut knights = Vec::new();
+        game.knight_moves(&mut knights);
+        let mut pawns = Vec::new();
+        game.pawn_moves(&mut pawns);
+        assert_eq!(knights.len(), 4);
+        assert_eq!(pawns.len(), 16);
+    }
+
+    fn pawn_moves_of(fen: &str) -> Vec<Move> {
+        let mut moves = Vec::new();
+        Game::read_FEN(fen).pawn_moves(&mut moves);
+        moves
+    }
+
+    #[test]
+    fn pawn_pushes_stop_at_blockers() {
+        // e2 can push twice, d2 is blocked on d4 for the double push, c2 is blocked outright
+        let moves = pawn_moves_of("4k3/8/8/8/3p4/2p5/2PPP3/4K3 w - - 0 1");
+        assert!(moves.contains(&Move::new(Square::E2, Square::E3)));
+        assert!(moves.contains(&Move::new(Square::E2, Square::E4)));
+        assert!(moves.contains(&Move::new(Square::D2, Square::D3)));
+        assert!(!moves.contains(&Move::new(Square::D2, Square::D4)));
+        assert!(!moves.iter().any(|mv| mv.from() == 10 && mv.to() == 18));
+    }
+
+    #[test]
+    fn pawn_captures_do_not_wrap_around_the_board() {
+        // the a4 pawn must not capture on h4, the h5 pawn must not capture on a7
+        let moves = pawn_moves_of("4k3/8/p7/7P/P6p/8/8/4K3 w - - 0 1");
+        assert_eq!(moves.len(), 2);
+    }
+
+    #[test]
+    fn black_pawns_capture_en_passant() {
+        let moves = pawn_moves_of("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1");
+        assert!(moves.contains(&Move::new(Square::D4, Square::E3)));
+        assert!(moves.contains(&Move::new(Square::D4, Square::D3)));
+    }
+
+    #[test]
+    fn pawn_reaching_last_rank_promotes() {
+        // straight promotion plus a capture promotion on a8, each with four choices
+        let moves = pawn_moves_of("n3k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(moves.len(), 8);
+        assert!(moves.iter().all(|mv| mv.promotion().is_some()));
+        assert!(moves.contains(&Move::new_promotion(Square::B7, Square::A8, PieceType::Knight)));
+    }
+
+    #[test]
+    fn check_detection_finds_every_checker() {
+        // knight on f3 and rook on e8 both check the white king on e1
+        let game = Game::read_FEN("4r1k1/8/8/8/8/5n2/8/4K3 w - - 0 1");
+        assert!(game.is_in_check(Color::White));
+        assert!(!game.is_in_check(Color::Black));
+        assert_eq!(game.checkers(), (1u64 << 21) | (1u64 << 60));
+
+        let game = Game::initialize();
+        assert!(!game.is_in_check(Color::White));
+        assert_eq!(game.checkers(), 0);
+    }
+
+    #[test]
+    fn attacked_squares_of_a_color() {
+        // rook a1 blocked by its own pawn on a3, king on h1
+        let game = Game::read_FEN("7k/8/8/8/8/P7/8/R6K w - - 0 1");
+        let rook = (1u64 << 8) | (1u64 << 16) | 0xfe;
+        let pawn = 1u64 << 25;
+        let king = (1u64 << 6) | (1u64 << 14) | (1u64 << 15);
+        assert_eq!(game.attacked_squares(Color::White), rook | pawn | king);
+
+        // from the start the first three ranks are covered, except a1 and h1
+        let start = Game::initialize();
+        assert_eq!(start.attacked_squares(Color::White), 0xffff7e);
+    }
+
+    #[test]
+    fn blocked_slider_gives_no_check() {
+        let game = Game::read_FEN("4r1k1/8/8/8/8/8/4P3/4K3 w - - 0 1");
+        assert!(!game.is_in_check(Color::White));
+    }
+
+    #[test]
+    fn generated_moves_carry_flags() {
+        let game = Game::read_FEN("r3k2r/8/8/3pP3/8/8/4P3/R3K2R w KQkq d6 0 1");
+        let moves = game.legal_moves();
+        let find = |text: &str| *moves.iter().find(|mv| mv.to_string() == text).unwrap();
+
+        assert!(find("e5d6").is_en_passant() && find("e5d6").is_capture());
+        assert!(find("e2e4").is_double_pawn_push());
+        assert_eq!(find("e1g1").flags(), MoveFlags::KINGSIDE_CASTLE);
+        assert_eq!(find("e1c1").flags(), MoveFlags::QUEENSIDE_CASTLE);
+        assert!(find("a1a8").is_capture());
+        assert_eq!(find("e2e3").flags(), MoveFlags::QUIET);
+    }
+
+    #[test]
+    fn coordinate_notation_round_trips() {
+        let mv: Move = "e7e8q".parse().unwrap();
+        assert_eq!(mv, Move::new_promotion(Square::E7, Square::E8, PieceType::Queen));
+        assert_eq!(mv.to_string(), "e7e8q");
+        assert_eq!("g1f3".parse::<Move>().unwrap(), Move::new(Square::G1, Square::F3));
+        assert_eq!(Move::new(Square::G1, Square::F3).to_string(), "g1f3");
+
+        // parsed moves compare equal to generated ones regardless of flags
+        assert!(Game::initialize().legal_moves().contains(&"e2e4".parse().unwrap()));
+
+        assert!("e2".parse::<Move>().is_err());
+        assert!("e2e9".parse::<Move>().is_err());
+        assert!("e7e8k".parse::<Move>().is_err());
+        assert!("i2e4".parse::<Move>().is_err());
+    }
+
+    #[test]
+    fn pinned_piece_cannot_leave_the_pin() {
+        // the e2 knight is pinned against the king by the e8 rook
+        let moves = Game::read_FEN("4r1k1/8/8/8/8/8/4N3/4K3 w - - 0 1").legal_moves();
+        assert!(moves.iter().all(|mv| mv.from() != 12));
+    }
+
+    #[test]
+    fn pinned_pieces_and_their_rays() {
+        // the d2 bishop is pinned by the a5 queen, the e2 knight by the e8 rook,
+        // the f2 pawn is shielded by the g3 pawn
+        let game = Game::read_FEN("4r1k1/8/8/q7/7b/6P1/3BNP2/4K3 w - - 0 1");
+        assert_eq!(game.pinned_pieces(Color::White), (1u64 << 11) | (1u64 << 12));
+        assert_eq!(game.pin_ray(Square::D2), (1u64 << 11) | (1u64 << 18) | (1u64 << 25) | (1u64 << 32));
+        assert_eq!(game.pin_ray(Square::F2), !0);
+
+        // the pinned bishop may only slide along the pin and capture the queen
+        let bishop_moves: Vec<Move> = game.legal_moves().into_iter()
+            .filter(|mv| mv.from() == 11)
+            .collect();
+        assert_eq!(bishop_moves.len(), 3);
+        assert!(bishop_moves.contains(&Move::new(Square::D2, Square::A5)));
+    }
+
+    #[test]
+    fn single_check_must_be_blocked_or_captured() {
+        // the a1 rook checks along the back rank, the c3 knight can only block on b1 or d1
+        let moves = Game::read_FEN("6k1/8/8/8/8/2N5/8/r3K3 w - - 0 1").legal_moves();
+        let knight_moves: Vec<Move> = moves.into_iter().filter(|mv| mv.from() == 18).collect();
+        assert_eq!(knight_moves, vec![Move::new(Square::C3, Square::B1), Move::new(Square::C3, Square::D1)]);
+    }
+
+    #[test]
+    fn double_check_allows_only_king_moves() {
+        let game = Game::read_FEN("4r1k1/8/8/8/8/3n4/1N6/4K3 w - - 0 1");
+        assert_eq!(game.checkers().count_ones(), 2);
+        assert!(game.legal_moves().iter().all(|mv| mv.from() == 4));
+    }
+
+    #[test]
+    fn is_legal_validates_single_moves() {
+        let game = Game::initialize();
+        assert!(game.is_legal(Move::new(Square::E2, Square::E4)));
+        assert!(game.is_legal(Move::new(Square::G1, Square::F3)));
+        // too far, blocked, opponent's piece and an empty square
+        assert!(!game.is_legal(Move::new(Square::E2, Square::E5)));
+        assert!(!game.is_legal(Move::new(Square::A1, Square::A3)));
+        assert!(!game.is_legal(Move::new(Square::E7, Square::E5)));
+        assert!(!game.is_legal(Move::new(Square::E3, Square::E4)));
+
+        let game = Game::read_FEN("4r1k1/P7/8/8/8/8/4N3/4K3 w - - 0 1");
+        // the knight is pinned and the pawn has to name its promotion piece
+        assert!(!game.is_legal(Move::new(Square::E2, Square::F4)));
+        assert!(!game.is_legal(Move::new(Square::A7, Square::A8)));
+        assert!(game.is_legal(Move::new_promotion(Square::A7, Square::A8, PieceType::Queen)));
+
+        // chess960 castles are written king takes rook, the h-file rook with the king already on f1
+        // and both rooks next to a king on b1
+        for (fen, castles) in [("1k5r/8/8/8/8/8/8/5K1R w H - 0 1", vec!["f1h1"]),
+            ("rk5r/8/8/8/8/8/8/RK5R w HA - 0 1", vec!["b1a1", "b1h1"])] {
+            let game = Game::read_FEN(fen);
+            for uci in castles {
+                let mv = game.legal_moves().into_iter().find(|mv| mv.to_uci() == uci).unwrap();
+                assert!(mv.is_castle() && game.is_legal(mv), "{} in {}", uci, fen);
+            }
+        }
+        // a knight on g1 is in the king's way
+        let game = Game::read_FEN("1k5r/8/8/8/8/8/8/5KNR w H - 0 1");
+        assert!(!game.is_legal(Move::with_flags(5, 7, MoveFlags::KINGSIDE_CASTLE)));
+        // castle flags from the caller count for nothing, a king step into the rook's file stays
+        // illegal and a castle parsed from plain coordinates is still one
+        let game = Game::read_FEN("4r2k/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert!(!game.is_legal(Move::with_flags(4, 12, MoveFlags::KINGSIDE_CASTLE)));
+        let game = Game::read_FEN("1k5r/8/8/8/8/8/8/5K1R w H - 0 1");
+        assert!(game.is_legal("f1h1".parse().unwrap()));
+    }
+
+    #[test]
+    fn moves_from_a_single_square() {
+        let game = Game::initialize();
+        assert_eq!(game.moves_from(Square::G1), vec![Move::new(Square::G1, Square::F3), Move::new(Square::G1, Square::H3)]);
+        assert_eq!(game.moves_from(Square::E2).len(), 2);
+        // empty squares, opponent pieces and blocked pieces have nothing
+        assert!(game.moves_from(Square::E4).is_empty());
+        assert!(game.moves_from(Square::E7).is_empty());
+        assert!(game.moves_from(Square::A1).is_empty());
+
+        // the pinned knight cannot move at all, the king still can
+        let game = Game::read_FEN("4r1k1/8/8/8/8/8/4N3/4K3 w - - 0 1");
+        assert!(game.moves_from(Square::E2).is_empty());
+        assert_eq!(game.moves_from(Square::E1).len(), 4);
+
+        for square in Square::iter() {
+            let expected: Vec<Move> = game.legal_moves().into_iter().filter(|mv| mv.from_square() == square).collect();
+            assert_eq!(game.moves_from(square), expected);
+        }
+    }
+
+    #[test]
+    fn capture_moves_are_captures_and_promotions() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ];
+        for fen in fens {
+            let game = Game::read_FEN(fen);
+            let expected: Vec<Move> = game.legal_moves().into_iter()
+                .filter(|mv| mv.is_capture() || mv.promotion().is_some())
+                .collect();
+            let captures = game.capture_moves();
+            assert_eq!(captures.len(), expected.len(), "{}", fen);
+            assert!(captures.iter().all(|mv| expected.contains(mv)), "{}", fen);
+        }
+        assert!(Game::initialize().capture_moves().is_empty());
+    }
+
+    #[test]
+    fn evasions_answer_the_check() {
+        let fens = [
+            // rook check that can be blocked or captured
+            "6k1/8/8/8/8/2N5/1P6/r3K3 w - - 0 1",
+            // pawn check that can be taken en passant
+            "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+            // double check
+            "4r1k1/8/8/8/8/3n4/1N6/4K3 w - - 0 1",
+            // knight check with a pinned defender
+            "4r1k1/8/8/8/8/5n2/4B3/4K3 w - - 0 1",
+        ];
+        for fen in fens {
+            let game = Game::read_FEN(fen);
+            let expected: Vec<Move> = game.pseudo_legal_moves().into_iter()
+                .filter(|mv| game.leaves_king_safe(mv))
+                .collect();
+            let evasions = game.evasion_moves();
+            assert_eq!(evasions.len(), expected.len(), "{}", fen);
+            assert!(evasions.iter().all(|mv| expected.contains(mv)), "{}", fen);
+        }
+
+        let game = Game::read_FEN("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1");
+        assert!(game.evasion_moves().contains(&Move::new(Square::E4, Square::D3)));
+        assert_eq!(Game::initialize().evasion_moves().len(), 20);
+    }
+
+    #[test]
+    fn en_passant_capture_is_generated() {
+        let moves = Game::read_FEN("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").legal_moves();
+        assert!(moves.contains(&Move::new(Square::E5, Square::D6)));
+    }
+
+    #[test]
+    fn en_passant_revealing_check_is_illegal() {
+        let moves = Game::read_FEN("8/8/8/K2pP2r/8/8/8/7k w - d6 0 1").legal_moves();
+        assert!(!moves.contains(&Move::new(Square::E5, Square::D6)));
+    }
+
+    #[test]
+    fn castling_through_attack_is_illegal() {
+        // the f8 rook covers f1 so only queenside castling is available
+        let moves = Game::read_FEN("5rk1/8/8/8/8/8/8/R3K2R w KQ - 0 1").legal_moves();
+        assert!(!moves.contains(&Move::new(Square::E1, Square::G1)));
+        assert!(moves.contains(&Move::new(Square::E1, Square::C1)));
+    }
+
+    #[test]
+    fn mobility_matches_legal_move_counts() {
+        let start = Game::initialize();
+        let mobility = start.mobility(Color::White);
+        assert_eq!((mobility.pawn, mobility.knight, mobility.total()), (16, 4, 20));
+        assert_eq!(start.mobility(Color::Black).of(PieceType::Knight), 4);
+
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "4k3/8/8/1b6/8/8/3P4/4K3 w - - 0 1",
+        ] {
+            let mut game = Game::read_FEN(fen);
+            assert_eq!(game.mobility(game.active_color).total(), game.legal_moves().len(), "{}", fen);
+
+            // the side not to move is counted as if it were its turn
+            let them = game.active_color.opponent();
+            let counted = game.mobility(them).total();
+            game.make_null_move();
+            assert_eq!(counted, game.legal_moves().len(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn uci_moves_are_checked_against_the_position() {
+        let game = Game::read_FEN("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1");
+        let castle = Move::from_uci("e1g1", &game).unwrap();
+        assert!(castle.is_castle());
+        assert!(Move::from_uci("e5d6", &game).unwrap().is_en_passant());
+        let promotion = Move::from_uci("b7a8n", &game).unwrap();
+        assert!(promotion.is_capture());
+        assert_eq!(promotion.to_uci(), "b7a8n");
+
+        assert!(Move::from_uci("b7b8", &game).is_err());
+        assert!(Move::from_uci("e1e3", &game).is_err());
+        assert!(Move::from_uci("e8g8", &game).is_err());
+        assert!(Move::from_uci("0000", &game).is_err());
+        assert!(Move::from_uci("e9e4", &game).is_err());
+    }
+}
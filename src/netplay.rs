@@ -0,0 +1,282 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use crate::game::{Color, Game};
+use crate::movegen::Move;
+use crate::outcome::Outcome;
+use crate::search::{SearchOptions, Searcher};
+
+// the lines two instances exchange, moves go over the wire in uci notation.
+// the server owns the game: it tells the client its color and the start position,
+// checks every move the client sends and announces the result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Hello { color: Color, fen: String },
+    Move(String),
+    Illegal(String),
+    Resign,
+    Result(String),
+}
+
+impl Message {
+    pub fn parse(line: &str) -> Result<Message, String> {
+        let (command, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        match command {
+            "hello" => {
+                let (color, fen) = rest.split_once(' ').ok_or("Expected hello <white|black> <fen>")?;
+                let color = match color {
+                    "white" => Color::White,
+                    "black" => Color::Black,
+                    _ => return Err(format!("Unknown color {}", color)),
+                };
+                Ok(Message::Hello { color, fen: fen.to_string() })
+            }
+            "move" if !rest.is_empty() => Ok(Message::Move(rest.to_string())),
+            "illegal" => Ok(Message::Illegal(rest.to_string())),
+            "resign" => Ok(Message::Resign),
+            "result" => Ok(Message::Result(rest.to_string())),
+            _ => Err(format!("Unknown message {}", line.trim())),
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Message::Hello { color: Color::White, fen } => write!(f, "hello white {}", fen),
+            Message::Hello { color: Color::Black, fen } => write!(f, "hello black {}", fen),
+            Message::Move(mv) => write!(f, "move {}", mv),
+            Message::Illegal(mv) => write!(f, "illegal {}", mv),
+            Message::Resign => write!(f, "resign"),
+            Message::Result(result) => write!(f, "result {}", result),
+        }
+    }
+}
+
+// one side at this end of the connection
+pub trait Player {
+    // the move to play, None resigns
+    fn choose(&mut self, game: &mut Game) -> Option<Move>;
+
+    // news about the game, like the opponent's moves or a rejected move
+    fn notice(&mut self, _text: &str) {}
+}
+
+pub struct EnginePlayer {
+    searcher: Searcher,
+    options: SearchOptions,
+}
+
+impl EnginePlayer {
+    pub fn new(options: SearchOptions) -> EnginePlayer {
+        EnginePlayer { searcher: Searcher::new(), options }
+    }
+}
+
+impl Player for EnginePlayer {
+    fn choose(&mut self, game: &mut Game) -> Option<Move> {
+        self.searcher.search_with(game, &self.options).best_move
+    }
+}
+
+// someone at a terminal typing moves in SAN or uci notation, or resign
+pub struct HumanPlayer<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> HumanPlayer<R, W> {
+    pub fn new(input: R, output: W) -> HumanPlayer<R, W> {
+        HumanPlayer { input, output }
+    }
+}
+
+impl<R: BufRead, W: Write> Player for HumanPlayer<R, W> {
+    fn choose(&mut self, game: &mut Game) -> Option<Move> {
+        let _ = writeln!(self.output, "{}", game);
+        loop {
+            let _ = write!(self.output, "your move: ");
+            let _ = self.output.flush();
+            let mut line = String::new();
+            // the end of the input gives the game up as well
+            if self.input.read_line(&mut line).ok()? == 0 || line.trim() == "resign" {
+                return None;
+            }
+            match game.parse_move(line.trim()) {
+                Ok(mv) => return Some(mv),
+                Err(err) => {
+                    let _ = writeln!(self.output, "{}", err);
+                }
+            }
+        }
+    }
+
+    fn notice(&mut self, text: &str) {
+        let _ = writeln!(self.output, "{}", text);
+    }
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Connection> {
+        Ok(Connection { reader: BufReader::new(stream.try_clone()?), writer: stream })
+    }
+
+    fn send(&mut self, message: &Message) -> Result<(), String> {
+        writeln!(self.writer, "{}", message).and_then(|_| self.writer.flush()).map_err(|err| err.to_string())
+    }
+
+    fn receive(&mut self) -> Result<Message, String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line).map_err(|err| err.to_string())? == 0 {
+            return Err("The opponent disconnected".to_string());
+        }
+        Message::parse(&line)
+    }
+}
+
+fn describe(outcome: &Outcome) -> String {
+    format!("{} {}", outcome.result, outcome.reason)
+}
+
+// waits for one opponent and plays `game` against them with `local` on `color`,
+// the opponent's moves are only played once they are legal here
+pub fn serve(listener: &TcpListener, local: &mut dyn Player, color: Color, mut game: Game) -> Result<Outcome, String> {
+    let (stream, address) = listener.accept().map_err(|err| err.to_string())?;
+    local.notice(&format!("{} connected", address));
+    let mut connection = Connection::new(stream).map_err(|err| err.to_string())?;
+    connection.send(&Message::Hello { color: color.opponent(), fen: game.to_fen() })?;
+    let outcome = loop {
+        if let Some(outcome) = game.update_outcome() {
+            break outcome;
+        }
+        if game.active_color == color {
+            match local.choose(&mut game) {
+                Some(mv) => {
+                    game.make_move(mv);
+                    connection.send(&Message::Move(mv.to_uci()))?;
+                }
+                None => {
+                    game.resign(color);
+                    connection.send(&Message::Resign)?;
+                }
+            }
+            continue;
+        }
+        match connection.receive()? {
+            Message::Move(uci) => match Move::from_uci(&uci, &game) {
+                Ok(mv) => {
+                    local.notice(&format!("opponent played {}", game.to_san(mv)));
+                    game.make_move(mv);
+                }
+                Err(_) => connection.send(&Message::Illegal(uci))?,
+            },
+            Message::Resign => game.resign(color.opponent()),
+            message => return Err(format!("Unexpected {}", message)),
+        }
+    };
+    connection.send(&Message::Result(describe(&outcome)))?;
+    Ok(outcome)
+}
+
+// plays the color the server hands out, the game ends when the server sends the result
+pub fn connect(address: impl ToSocketAddrs, local: &mut dyn Player) -> Result<Outcome, String> {
+    let stream = TcpStream::connect(address).map_err(|err| err.to_string())?;
+    let mut connection = Connection::new(stream).map_err(|err| err.to_string())?;
+    let (color, mut game) = match connection.receive()? {
+        Message::Hello { color, fen } => (color, Game::try_from_fen(&fen).map_err(|err| err.to_string())?),
+        message => return Err(format!("Expected hello, got {}", message)),
+    };
+    loop {
+        if game.active_color == color && game.update_outcome().is_none() {
+            match local.choose(&mut game) {
+                Some(mv) => {
+                    game.make_move(mv);
+                    connection.send(&Message::Move(mv.to_uci()))?;
+                }
+                None => {
+                    game.resign(color);
+                    connection.send(&Message::Resign)?;
+                }
+            }
+        }
+        match connection.receive()? {
+            Message::Move(uci) => {
+                let mv = Move::from_uci(&uci, &game)?;
+                local.notice(&format!("opponent played {}", game.to_san(mv)));
+                game.make_move(mv);
+            }
+            // the server saw a different position, take the move back and try again
+            Message::Illegal(uci) => {
+                game.unmake_move();
+                local.notice(&format!("illegal move {}", uci));
+            }
+            Message::Resign => game.resign(color.opponent()),
+            Message::Result(result) => {
+                return game.update_outcome().ok_or_else(|| format!("The server ended the game: {}", result));
+            }
+            message => return Err(format!("Unexpected {}", message)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outcome::{GameResult, TerminationReason};
+
+    // plays a fixed list of uci moves, then resigns
+    struct Scripted(Vec<&'static str>);
+
+    impl Player for Scripted {
+        fn choose(&mut self, game: &mut Game) -> Option<Move> {
+            (!self.0.is_empty()).then(|| Move::from_uci(self.0.remove(0), game).unwrap())
+        }
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        for message in [
+            Message::Hello { color: Color::Black, fen: fen.to_string() }, Message::Move("e7e8q".to_string()),
+            Message::Illegal("e2e5".to_string()), Message::Resign, Message::Result("1-0 checkmate".to_string()),
+        ] {
+            assert_eq!(Message::parse(&message.to_string()), Ok(message));
+        }
+        assert!(Message::parse("move").is_err());
+        assert!(Message::parse("hello green x").is_err());
+    }
+
+    #[test]
+    fn plays_over_a_socket() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || connect(address, &mut Scripted(vec!["e7e5", "b8c6", "g8f6"])));
+        let mut server = Scripted(vec!["e2e4", "f1c4", "d1h5", "h5f7"]);
+        let outcome = serve(&listener, &mut server, Color::White, Game::initialize()).unwrap();
+        let expected = Outcome::new(GameResult::WhiteWins, TerminationReason::Checkmate);
+        assert_eq!(outcome, expected);
+        assert_eq!(client.join().unwrap(), Ok(expected));
+    }
+
+    #[test]
+    fn rejects_illegal_moves_and_resignations() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let mut connection = Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+            assert!(matches!(connection.receive(), Ok(Message::Hello { color: Color::White, .. })));
+            connection.send(&Message::Move("e2e5".to_string())).unwrap();
+            assert_eq!(connection.receive(), Ok(Message::Illegal("e2e5".to_string())));
+            connection.send(&Message::Resign).unwrap();
+            connection.receive()
+        });
+        let outcome = serve(&listener, &mut Scripted(vec![]), Color::Black, Game::initialize()).unwrap();
+        assert_eq!(outcome, Outcome::new(GameResult::BlackWins, TerminationReason::Resignation));
+        assert_eq!(client.join().unwrap(), Ok(Message::Result("0-1 resignation".to_string())));
+    }
+}
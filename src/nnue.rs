@@ -0,0 +1,304 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use crate::game::*;
+
+// the HalfKP 256x2-32-32-1 networks Stockfish 12 introduced: every non-king piece is a feature
+// relative to the square of each side's own king
+pub const VERSION: u32 = 0x7af32f16;
+const PIECE_SQUARES: usize = 10 * 64 + 1;
+pub const FEATURES: usize = 64 * PIECE_SQUARES;
+pub const HIDDEN: usize = 2 * HALF;
+const HALF: usize = 256;
+const L1: usize = 32;
+const L2: usize = 32;
+// hidden layer outputs are fixed point with 6 fraction bits, the final output is 16 times centipawns
+const WEIGHT_SHIFT: u32 = 6;
+const OUTPUT_SCALE: i32 = 16;
+
+// the pieces on all 64 squares, what the accumulator updates are worked out from
+pub type Board = [Option<(Color, PieceType)>; 64];
+
+pub fn snapshot(game: &Game) -> Board {
+    let mut board = [None; 64];
+    for piece in &game.pieces {
+        board[piece.position.trailing_zeros() as usize] = Some((piece.color, piece.piece_type));
+    }
+    board
+}
+
+pub struct Network {
+    pub description: String,
+    feature_biases: Vec<i16>,
+    feature_weights: Vec<i16>,
+    l1_biases: Vec<i32>,
+    l1_weights: Vec<i8>,
+    l2_biases: Vec<i32>,
+    l2_weights: Vec<i8>,
+    output_bias: i32,
+    output_weights: Vec<i8>,
+}
+
+// little endian reads over the network file
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take(&mut self, count: usize) -> Result<&[u8], String> {
+        if self.bytes.len() < count {
+            return Err("Network file is truncated".to_string());
+        }
+        let (head, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i32s(&mut self, count: usize) -> Result<Vec<i32>, String> {
+        Ok(self.take(4 * count)?.chunks_exact(4).map(|b| i32::from_le_bytes(b.try_into().unwrap())).collect())
+    }
+
+    fn i16s(&mut self, count: usize) -> Result<Vec<i16>, String> {
+        Ok(self.take(2 * count)?.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect())
+    }
+
+    fn i8s(&mut self, count: usize) -> Result<Vec<i8>, String> {
+        Ok(self.take(count)?.iter().map(|b| *b as i8).collect())
+    }
+}
+
+impl Network {
+    pub fn load(path: &Path) -> Result<Network, String> {
+        let bytes = fs::read(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Network::from_bytes(&bytes)
+    }
+
+    // the layer hashes are skipped, the layer sizes are what has to match
+    pub fn from_bytes(bytes: &[u8]) -> Result<Network, String> {
+        let mut reader = Reader { bytes };
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(format!("Unsupported network version {:#x}", version));
+        }
+        reader.u32()?;
+        let length = reader.u32()? as usize;
+        let description = String::from_utf8_lossy(reader.take(length)?).into_owned();
+
+        reader.u32()?;
+        let feature_biases = reader.i16s(HALF)?;
+        let feature_weights = reader.i16s(HALF * FEATURES)?;
+        reader.u32()?;
+        let l1_biases = reader.i32s(L1)?;
+        let l1_weights = reader.i8s(L1 * HIDDEN)?;
+        let l2_biases = reader.i32s(L2)?;
+        let l2_weights = reader.i8s(L2 * L1)?;
+        let output_bias = reader.i32s(1)?[0];
+        let output_weights = reader.i8s(L2)?;
+        if !reader.bytes.is_empty() {
+            return Err("Network file is not a HalfKP 256x2-32-32 network".to_string());
+        }
+        Ok(Network {
+            description, feature_biases, feature_weights, l1_biases, l1_weights,
+            l2_biases, l2_weights, output_bias, output_weights,
+        })
+    }
+
+    // black sees the board turned around
+    fn feature(perspective: Color, king: usize, square: usize, color: Color, piece_type: PieceType) -> Option<usize> {
+        let kind = match piece_type {
+            PieceType::Pawn => 0,
+            PieceType::Knight => 1,
+            PieceType::Bishop => 2,
+            PieceType::Rook => 3,
+            PieceType::Queen => 4,
+            PieceType::King => return None,
+        };
+        let orient = |square: usize| if perspective == Color::White { square } else { square ^ 63 };
+        let piece = 1 + 64 * (2 * kind + (color != perspective) as usize);
+        Some(orient(square) + piece + PIECE_SQUARES * orient(king))
+    }
+
+    fn add(&self, values: &mut [i16; HALF], feature: usize, sign: i16) {
+        let weights = &self.feature_weights[feature * HALF..(feature + 1) * HALF];
+        for (value, weight) in values.iter_mut().zip(weights) {
+            *value = value.wrapping_add(sign.wrapping_mul(*weight));
+        }
+    }
+
+    fn refresh(&self, board: &Board, perspective: Color) -> [i16; HALF] {
+        let mut values: [i16; HALF] = self.feature_biases[..].try_into().unwrap();
+        let king = king_square(board, perspective);
+        for (square, piece) in board.iter().enumerate() {
+            if let Some((color, piece_type)) = piece {
+                if let Some(feature) = Network::feature(perspective, king, square, *color, *piece_type) {
+                    self.add(&mut values, feature, 1);
+                }
+            }
+        }
+        values
+    }
+
+    pub fn accumulate(&self, board: &Board) -> Accumulator {
+        Accumulator { values: [self.refresh(board, Color::White), self.refresh(board, Color::Black)] }
+    }
+
+    // centipawns for the side to move
+    pub fn output(&self, accumulator: &Accumulator, side: Color) -> i32 {
+        let (us, them) = match side {
+            Color::White => (&accumulator.values[0], &accumulator.values[1]),
+            Color::Black => (&accumulator.values[1], &accumulator.values[0]),
+        };
+        let input: Vec<i32> = us.iter().chain(them.iter()).map(|value| (*value as i32).clamp(0, 127)).collect();
+        let hidden = layer(&input, &self.l1_weights, &self.l1_biases);
+        let hidden = layer(&hidden, &self.l2_weights, &self.l2_biases);
+        let output = self.output_bias + hidden.iter().zip(&self.output_weights).map(|(x, w)| x * *w as i32).sum::<i32>();
+        output / OUTPUT_SCALE
+    }
+
+    pub fn evaluate(&self, game: &Game) -> i32 {
+        self.output(&self.accumulate(&snapshot(game)), game.active_color)
+    }
+}
+
+fn layer(input: &[i32], weights: &[i8], biases: &[i32]) -> Vec<i32> {
+    biases.iter().enumerate().map(|(i, bias)| {
+        let row = &weights[i * input.len()..(i + 1) * input.len()];
+        let sum = bias + input.iter().zip(row).map(|(x, w)| x * *w as i32).sum::<i32>();
+        (sum >> WEIGHT_SHIFT).clamp(0, 127)
+    }).collect()
+}
+
+fn king_square(board: &Board, color: Color) -> usize {
+    board.iter().position(|piece| *piece == Some((color, PieceType::King))).unwrap_or(0)
+}
+
+// the first layer's sums from white's and black's side
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Accumulator {
+    values: [[i16; HALF]; 2],
+}
+
+// accumulators for the line being searched, one pushed on every move
+pub struct NnueState {
+    network: Arc<Network>,
+    stack: Vec<Accumulator>,
+}
+
+impl NnueState {
+    pub fn new(network: Arc<Network>, game: &Game) -> NnueState {
+        let root = network.accumulate(&snapshot(game));
+        NnueState { network, stack: vec![root] }
+    }
+
+    // `before` is the board before the move `game` just made. only the squares that changed
+    // turn features on and off, a side whose king moved starts over from its new square
+    pub fn push(&mut self, before: &Board, game: &Game) {
+        let after = snapshot(game);
+        let mut accumulator = self.stack.last().unwrap().clone();
+        for (index, perspective) in [Color::White, Color::Black].into_iter().enumerate() {
+            let king = king_square(&after, perspective);
+            if king_square(before, perspective) != king {
+                accumulator.values[index] = self.network.refresh(&after, perspective);
+                continue;
+            }
+            for square in (0..64).filter(|square| before[*square] != after[*square]) {
+                if let Some((color, piece_type)) = before[square] {
+                    if let Some(feature) = Network::feature(perspective, king, square, color, piece_type) {
+                        self.network.add(&mut accumulator.values[index], feature, -1);
+                    }
+                }
+                if let Some((color, piece_type)) = after[square] {
+                    if let Some(feature) = Network::feature(perspective, king, square, color, piece_type) {
+                        self.network.add(&mut accumulator.values[index], feature, 1);
+                    }
+                }
+            }
+        }
+        self.stack.push(accumulator);
+    }
+
+    // how many accumulators are on the stack, one more than the moves pushed
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn pop(&mut self) {
+        if self.stack.len() > 1 {
+            self.stack.pop();
+        }
+    }
+
+    pub fn evaluate(&self, game: &Game) -> i32 {
+        self.network.output(self.stack.last().unwrap(), game.active_color)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // a network of small made up weights, in the file layout
+    pub(crate) fn test_network() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let description = b"test network";
+        for word in [VERSION, 0, description.len() as u32] {
+            bytes.extend(word.to_le_bytes());
+        }
+        bytes.extend(description);
+        bytes.extend(0u32.to_le_bytes());
+        bytes.extend((0..HALF).flat_map(|i| (i as i16 % 7).to_le_bytes()));
+        bytes.extend((0..HALF * FEATURES).flat_map(|i| ((i * 7919 % 13) as i16 - 6).to_le_bytes()));
+        bytes.extend(0u32.to_le_bytes());
+        bytes.extend((0..L1).flat_map(|i| (i as i32 * 10).to_le_bytes()));
+        bytes.extend((0..L1 * HIDDEN).map(|i| (i * 31 % 9) as u8 ^ 0x04));
+        bytes.extend((0..L2).flat_map(|i| (i as i32 * 5).to_le_bytes()));
+        bytes.extend((0..L2 * L1).map(|i| (i * 17 % 11) as u8));
+        bytes.extend(100i32.to_le_bytes());
+        bytes.extend((0..L2).map(|i| (i % 5) as u8));
+        bytes
+    }
+
+    #[test]
+    fn loads_and_rejects_networks() {
+        let bytes = test_network();
+        let network = Network::from_bytes(&bytes).unwrap();
+        assert_eq!(network.description, "test network");
+        assert!(Network::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] ^= 1;
+        assert!(Network::from_bytes(&wrong_version).is_err());
+    }
+
+    fn walk(state: &mut NnueState, network: &Network, game: &mut Game, depth: usize) {
+        if depth == 0 {
+            return;
+        }
+        for mv in game.legal_moves() {
+            let before = snapshot(game);
+            game.make_move(mv);
+            state.push(&before, game);
+            assert_eq!(state.stack.last().unwrap(), &network.accumulate(&snapshot(game)), "after {}", mv.to_uci());
+            assert_eq!(state.evaluate(game), network.evaluate(game));
+            walk(state, network, game, depth - 1);
+            game.unmake_move();
+            state.pop();
+        }
+    }
+
+    #[test]
+    fn incremental_updates_match_a_refresh() {
+        let network = Arc::new(Network::from_bytes(&test_network()).unwrap());
+        // castling, en passant, promotions and king moves
+        for fen in ["r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"] {
+            let mut game = Game::read_FEN(fen);
+            let mut state = NnueState::new(network.clone(), &game);
+            walk(&mut state, &network, &mut game, 1);
+            assert_eq!(state.depth(), 1);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::fmt;
+use crate::game::*;
+use crate::status::GameStatus;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl GameResult {
+    pub fn win_for(color: Color) -> GameResult {
+        match color {
+            Color::White => GameResult::WhiteWins,
+            Color::Black => GameResult::BlackWins,
+        }
+    }
+}
+
+// the result token used in PGN
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let token = match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+        };
+        write!(f, "{}", token)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TerminationReason {
+    Checkmate,
+    Resignation,
+    FlagFall,
+    Agreement,
+    Repetition,
+    FiftyMove,
+    Stalemate,
+    InsufficientMaterial,
+    // decided by a match runner from the engines' scores or the game length
+    Adjudication,
+    // an illegal move or an engine that stopped answering
+    Forfeit,
+}
+
+impl TerminationReason {
+    // value for the PGN Termination tag
+    pub fn pgn_termination(&self) -> &'static str {
+        match self {
+            TerminationReason::FlagFall => "time forfeit",
+            TerminationReason::Adjudication => "adjudication",
+            TerminationReason::Forfeit => "rules infraction",
+            _ => "normal",
+        }
+    }
+}
+
+impl fmt::Display for TerminationReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            TerminationReason::Checkmate => "checkmate",
+            TerminationReason::Resignation => "resignation",
+            TerminationReason::FlagFall => "flag fall",
+            TerminationReason::Agreement => "agreement",
+            TerminationReason::Repetition => "repetition",
+            TerminationReason::FiftyMove => "fifty move rule",
+            TerminationReason::Stalemate => "stalemate",
+            TerminationReason::InsufficientMaterial => "insufficient material",
+            TerminationReason::Adjudication => "adjudication",
+            TerminationReason::Forfeit => "forfeit",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Outcome {
+    pub result: GameResult,
+    pub reason: TerminationReason,
+}
+
+impl Outcome {
+    pub fn new(result: GameResult, reason: TerminationReason) -> Outcome {
+        Outcome { result, reason }
+    }
+}
+
+impl Game {
+    // records the outcome when the position itself ends the game, e.g. after make_move
+    pub fn update_outcome(&mut self) -> Option<Outcome> {
+        let outcome = match self.status() {
+            GameStatus::Ongoing => None,
+            GameStatus::Checkmate(winner) => Some(Outcome::new(GameResult::win_for(winner), TerminationReason::Checkmate)),
+            GameStatus::Stalemate => Some(Outcome::new(GameResult::Draw, TerminationReason::Stalemate)),
+            GameStatus::InsufficientMaterial => Some(Outcome::new(GameResult::Draw, TerminationReason::InsufficientMaterial)),
+            GameStatus::SeventyFiveMoveRule => Some(Outcome::new(GameResult::Draw, TerminationReason::FiftyMove)),
+            GameStatus::FivefoldRepetition => Some(Outcome::new(GameResult::Draw, TerminationReason::Repetition)),
+        };
+        if outcome.is_some() {
+            self.outcome = outcome;
+        }
+        self.outcome
+    }
+
+    pub fn resign(&mut self, color: Color) {
+        self.outcome = Some(Outcome::new(GameResult::win_for(color.opponent()), TerminationReason::Resignation));
+    }
+
+    // running out of time loses, unless the opponent has nothing left to mate with
+    pub fn flag_fall(&mut self, color: Color) {
+        let opponent = color.opponent();
+        let bare_king = self.pieces.iter()
+            .all(|p| p.color != opponent || p.piece_type == PieceType::King);
+        let result = if bare_king { GameResult::Draw } else { GameResult::win_for(opponent) };
+        self.outcome = Some(Outcome::new(result, TerminationReason::FlagFall));
+    }
+
+    // the game loses for `color` without the position deciding it
+    pub fn forfeit(&mut self, color: Color) {
+        self.outcome = Some(Outcome::new(GameResult::win_for(color.opponent()), TerminationReason::Forfeit));
+    }
+
+    pub fn adjudicate(&mut self, result: GameResult) {
+        self.outcome = Some(Outcome::new(result, TerminationReason::Adjudication));
+    }
+
+    pub fn agree_draw(&mut self) {
+        self.outcome = Some(Outcome::new(GameResult::Draw, TerminationReason::Agreement));
+    }
+
+    // ends the game as drawn if the fifty move rule or threefold repetition applies
+    pub fn claim_draw(&mut self) -> bool {
+        let reason = if self.halfmove_clock >= 100 {
+            TerminationReason::FiftyMove
+        } else if self.repetition_count() >= 3 {
+            TerminationReason::Repetition
+        } else {
+            return false;
+        };
+        self.outcome = Some(Outcome::new(GameResult::Draw, reason));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkmate_is_recorded_for_the_winner() {
+        let mut game = Game::read_FEN("6k1/5ppp/8/8/8/8/8/3R2K1 w - - 0 1");
+        assert_eq!(game.update_outcome(), None);
+        game.make_move("d1d8".parse().unwrap());
+        let outcome = game.update_outcome().unwrap();
+        assert_eq!(outcome, Outcome::new(GameResult::WhiteWins, TerminationReason::Checkmate));
+        assert_eq!(outcome.result.to_string(), "1-0");
+
+        // taking the move back reopens the game
+        game.unmake_move();
+        assert_eq!(game.outcome, None);
+    }
+
+    #[test]
+    fn resignation_agreement_and_flag_fall() {
+        let mut game = Game::initialize();
+        game.resign(Color::White);
+        assert_eq!(game.outcome.unwrap().result, GameResult::BlackWins);
+
+        game.agree_draw();
+        assert_eq!(game.outcome.unwrap().result.to_string(), "1/2-1/2");
+
+        game.flag_fall(Color::Black);
+        assert_eq!(game.outcome, Some(Outcome::new(GameResult::WhiteWins, TerminationReason::FlagFall)));
+        assert_eq!(game.outcome.unwrap().reason.pgn_termination(), "time forfeit");
+
+        // nothing to mate with, so running out of time is only a draw
+        let mut game = Game::read_FEN("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+        game.flag_fall(Color::White);
+        assert_eq!(game.outcome.unwrap().result, GameResult::Draw);
+    }
+
+    #[test]
+    fn draws_need_a_reason_to_be_claimed() {
+        let mut game = Game::initialize();
+        assert!(!game.claim_draw());
+        assert_eq!(game.outcome, None);
+
+        let mut game = Game::read_FEN("4k3/8/8/8/8/8/8/R3K3 w - - 100 80");
+        assert!(game.claim_draw());
+        assert_eq!(game.outcome.unwrap().reason, TerminationReason::FiftyMove);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::attacks::{pawn_attacks, FILE_A, FILE_H};
+use crate::game::*;
+use crate::square::Square;
+use crate::utils::pop_lsb;
+
+// (middlegame, endgame) penalties per pawn
+const DOUBLED: (i32, i32) = (-10, -20);
+const ISOLATED: (i32, i32) = (-10, -15);
+const BACKWARD: (i32, i32) = (-8, -10);
+// passed pawn bonus by rank counted from the pawn's own side
+const PASSED_MG: [i32; 8] = [0, 5, 10, 15, 25, 40, 60, 0];
+const PASSED_EG: [i32; 8] = [0, 10, 20, 35, 60, 90, 130, 0];
+
+// entries in a PawnTable unless asked for otherwise, a power of two
+pub const PAWN_TABLE_SIZE: usize = 1 << 14;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct PawnEntry {
+    key: u64,
+    // pawn_structure for white and black
+    scores: [(i32, i32); 2],
+}
+
+// pawn structure scores by pawn hash, the same pawns come back far more often than whole positions
+pub struct PawnTable {
+    entries: Vec<PawnEntry>,
+    hits: u64,
+}
+
+impl Default for PawnTable {
+    fn default() -> PawnTable {
+        PawnTable::new(PAWN_TABLE_SIZE)
+    }
+}
+
+impl PawnTable {
+    pub fn new(size: usize) -> PawnTable {
+        PawnTable { entries: vec![PawnEntry::default(); size.next_power_of_two()], hits: 0 }
+    }
+
+    pub fn probe(&mut self, game: &Game) -> [(i32, i32); 2] {
+        let key = game.pawn_hash();
+        let index = key as usize & (self.entries.len() - 1);
+        let entry = &mut self.entries[index];
+        // a position without pawns has key 0 and scores nothing either way
+        if entry.key == key {
+            self.hits += 1;
+        } else {
+            *entry = PawnEntry { key, scores: [game.pawn_structure(Color::White), game.pawn_structure(Color::Black)] };
+        }
+        entry.scores
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(PawnEntry::default());
+        self.hits = 0;
+    }
+}
+
+pub(crate) fn file_mask(file: usize) -> u64 {
+    FILE_A << file
+}
+
+pub(crate) fn adjacent_files(file: usize) -> u64 {
+    let file = file_mask(file);
+    ((file << 1) & !FILE_A) | ((file >> 1) & !FILE_H)
+}
+
+// every rank in front of `square` from `color`'s side
+fn ranks_ahead(color: Color, square: usize) -> u64 {
+    let rank = square / 8;
+    match color {
+        Color::White => u64::MAX.checked_shl(8 * (rank as u32 + 1)).unwrap_or(0),
+        Color::Black => (1u64 << (8 * rank)) - 1,
+    }
+}
+
+// the squares an enemy pawn must stand on to stop a pawn on `square`
+pub(crate) fn passed_span(color: Color, square: usize) -> u64 {
+    ranks_ahead(color, square) & (file_mask(square % 8) | adjacent_files(square % 8))
+}
+
+impl Game {
+    // no enemy pawn stands ahead of it on its own or a neighbouring file
+    pub fn is_passed_pawn(&self, square: Square) -> bool {
+        match self.piece_at(square) {
+            Some(piece) if piece.piece_type == PieceType::Pawn =>
+                passed_span(piece.color, square.index()) & self.bitboard(piece.color.opponent(), PieceType::Pawn) == 0,
+            _ => false,
+        }
+    }
+
+    // doubled, isolated, backward and passed pawns of one side, as a middlegame and an endgame score
+    pub fn pawn_structure(&self, color: Color) -> (i32, i32) {
+        let ours = self.bitboard(color, PieceType::Pawn);
+        let theirs = self.bitboard(color.opponent(), PieceType::Pawn);
+        let (mut mg, mut eg) = (0, 0);
+        let mut add = |(term_mg, term_eg): (i32, i32)| {
+            mg += term_mg;
+            eg += term_eg;
+        };
+
+        for file in 0..8 {
+            let count = (ours & file_mask(file)).count_ones() as i32;
+            if count > 1 {
+                add((DOUBLED.0 * (count - 1), DOUBLED.1 * (count - 1)));
+            }
+        }
+
+        let mut pawns = ours;
+        while pawns != 0 {
+            let square = pop_lsb(&mut pawns);
+            let file = square % 8;
+            let neighbours = ours & adjacent_files(file);
+            if neighbours == 0 {
+                add(ISOLATED);
+            } else if neighbours & !ranks_ahead(color, square) == 0 {
+                // every neighbour has advanced past it and the square in front is held by an enemy pawn
+                let stop = match color {
+                    Color::White => square + 8,
+                    Color::Black => square - 8,
+                };
+                if pawn_attacks(color, stop) & theirs != 0 {
+                    add(BACKWARD);
+                }
+            }
+            if passed_span(color, square) & theirs == 0 {
+                let rank = match color {
+                    Color::White => square / 8,
+                    Color::Black => 7 - square / 8,
+                };
+                add((PASSED_MG[rank], PASSED_EG[rank]));
+            }
+        }
+        (mg, eg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_masks() {
+        assert_eq!(adjacent_files(0), FILE_A << 1);
+        assert_eq!(adjacent_files(7), FILE_A << 6);
+        assert_eq!(passed_span(Color::White, Square::E6.index()), (0b111 << 51) | (0b111 << 59));
+        assert_eq!(passed_span(Color::Black, Square::A2.index()), 0b11);
+    }
+
+    #[test]
+    fn finds_passed_pawns() {
+        let game = Game::read_FEN("4k3/8/1p6/8/P2P3P/8/6p1/4K3 w - - 0 1");
+        assert!(!game.is_passed_pawn(Square::A4));
+        assert!(game.is_passed_pawn(Square::D4));
+        assert!(game.is_passed_pawn(Square::H4));
+        assert!(game.is_passed_pawn(Square::G2));
+        assert!(!game.is_passed_pawn(Square::E1));
+    }
+
+    #[test]
+    fn caches_by_pawn_hash() {
+        let mut table = PawnTable::new(64);
+        let game = Game::read_FEN("4k3/5pp1/8/8/8/2P5/2P5/4K3 w - - 0 1");
+        assert_eq!(table.probe(&game), [game.pawn_structure(Color::White), game.pawn_structure(Color::Black)]);
+        // the king moved, the pawns did not
+        let moved = Game::read_FEN("8/4kpp1/8/8/8/2P5/2P5/4K3 w - - 0 1");
+        assert_eq!(table.probe(&moved), table.probe(&game));
+        assert_eq!(table.hits(), 2);
+        assert_eq!(moved.evaluate_cached(&mut table), moved.evaluate());
+        table.clear();
+        assert_eq!(table.hits(), 0);
+    }
+
+    #[test]
+    fn scores_structure_terms() {
+        // doubled and isolated c pawns against a healthy pair
+        let game = Game::read_FEN("4k3/5pp1/8/8/8/2P5/2P5/4K3 w - - 0 1");
+        assert_eq!(game.pawn_structure(Color::White),
+            (DOUBLED.0 + 2 * ISOLATED.0 + PASSED_MG[1] + PASSED_MG[2], DOUBLED.1 + 2 * ISOLATED.1 + PASSED_EG[1] + PASSED_EG[2]));
+        assert_eq!(game.pawn_structure(Color::Black), (2 * PASSED_MG[1], 2 * PASSED_EG[1]));
+
+        // d3 is left behind by c4 and e4, and d4 is covered by the c5 pawn
+        let backward = Game::read_FEN("4k3/8/8/2p5/2P1P3/3P4/8/4K3 w - - 0 1");
+        assert_eq!(backward.pawn_structure(Color::White).0, BACKWARD.0 + PASSED_MG[3]);
+    }
+}
@@ -0,0 +1,421 @@
+use crate::game::*;
+use crate::movegen::Move;
+use crate::outcome::GameResult;
+
+// tags every PGN game carries, in this order, with the values used when unknown
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+const LINE_WIDTH: usize = 80;
+
+// a move with the alternative lines that could have been played instead of it
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveNode {
+    pub mv: Move,
+    pub variations: Vec<Vec<MoveNode>>,
+}
+
+impl MoveNode {
+    pub fn new(mv: Move) -> MoveNode {
+        MoveNode { mv, variations: vec![] }
+    }
+}
+
+// one game read from or written to PGN, `moves` is the main line
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRecord {
+    pub headers: Vec<(String, String)>,
+    pub moves: Vec<MoveNode>,
+    pub result: Option<GameResult>,
+}
+
+impl GameRecord {
+    pub fn mainline(&self) -> Vec<Move> {
+        self.moves.iter().map(|node| node.mv).collect()
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    // the FEN tag if the game did not start from the initial position
+    pub fn starting_position(&self) -> Result<Game, String> {
+        match self.header("FEN") {
+            Some(fen) => Game::try_from_fen(fen).map_err(|err| err.to_string()),
+            None => Ok(Game::initialize()),
+        }
+    }
+
+    // the moves played in a game so far, with its outcome if it has ended
+    pub fn from_game(game: &Game) -> GameRecord {
+        let mut start = game.clone();
+        while start.unmake_move().is_some() {}
+        let moves = game.history.iter().map(|undo| MoveNode::new(undo.mv)).collect();
+
+        let mut headers = Vec::new();
+        if start != Game::initialize() {
+            headers.push(("SetUp".to_string(), "1".to_string()));
+            headers.push(("FEN".to_string(), start.to_fen()));
+        }
+        if let Some(outcome) = game.outcome {
+            headers.push(("Termination".to_string(), outcome.reason.pgn_termination().to_string()));
+        }
+        GameRecord { headers, moves, result: game.outcome.map(|outcome| outcome.result) }
+    }
+
+    pub fn to_pgn(&self) -> Result<String, String> {
+        let result = self.result.map_or("*".to_string(), |result| result.to_string());
+        let mut pgn = String::new();
+        for (name, default) in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => result.as_str(),
+                _ => self.header(name).unwrap_or(default),
+            };
+            pgn.push_str(&format_tag(name, value));
+        }
+        for (name, value) in &self.headers {
+            if !SEVEN_TAG_ROSTER.iter().any(|(tag, _)| tag == name) {
+                pgn.push_str(&format_tag(name, value));
+            }
+        }
+        pgn.push('\n');
+
+        let mut game = self.starting_position()?;
+        let mut tokens = Vec::new();
+        write_line(&mut game, &self.moves, &mut tokens);
+        tokens.push(result);
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > LINE_WIDTH {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        pgn.push_str(&line);
+        pgn.push('\n');
+        Ok(pgn)
+    }
+
+    pub fn final_position(&self) -> Result<Game, String> {
+        let mut game = self.starting_position()?;
+        for mv in self.mainline() {
+            game.make_move(mv);
+        }
+        Ok(game)
+    }
+}
+
+// SAN tokens of a line and its variations, a variation is written right after the
+// move it replaces and the move number is repeated when black continues after it
+fn write_line(game: &mut Game, line: &[MoveNode], tokens: &mut Vec<String>) {
+    let mut restate_number = true;
+    for node in line {
+        if game.active_color == Color::White {
+            tokens.push(format!("{}.", game.fullmove_number));
+        } else if restate_number {
+            tokens.push(format!("{}...", game.fullmove_number));
+        }
+        tokens.push(game.to_san(node.mv));
+
+        for variation in &node.variations {
+            let mut before = game.clone();
+            let start = tokens.len();
+            write_line(&mut before, variation, tokens);
+            if tokens.len() > start {
+                tokens[start].insert(0, '(');
+                tokens.last_mut().unwrap().push(')');
+            }
+        }
+        restate_number = !node.variations.is_empty();
+        game.make_move(node.mv);
+    }
+}
+
+fn format_tag(name: &str, value: &str) -> String {
+    format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn parse_result(token: &str) -> Option<Option<GameResult>> {
+    match token {
+        "1-0" => Some(Some(GameResult::WhiteWins)),
+        "0-1" => Some(Some(GameResult::BlackWins)),
+        "1/2-1/2" => Some(Some(GameResult::Draw)),
+        "*" => Some(None),
+        _ => None,
+    }
+}
+
+// [Name "value"], the value may contain escaped quotes and backslashes
+fn parse_tag(line: &str) -> Result<(String, String), String> {
+    let inner = line.strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .ok_or_else(|| format!("Invalid tag pair {}", line))?;
+    let (name, value) = inner.split_once(char::is_whitespace)
+        .ok_or_else(|| format!("Invalid tag pair {}", line))?;
+    let value = value.trim()
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .ok_or_else(|| format!("Invalid tag value in {}", line))?;
+
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => unescaped.extend(chars.next()),
+            ch => unescaped.push(ch),
+        }
+    }
+    Ok((name.to_string(), unescaped))
+}
+
+// movetext without comments and annotation glyphs split into tokens, parentheses
+// around variations are tokens of their own
+fn movetext_tokens(movetext: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut chars = movetext.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' => {
+                for ch in chars.by_ref() {
+                    if ch == '}' {
+                        break;
+                    }
+                }
+            }
+            ';' => {
+                for ch in chars.by_ref() {
+                    if ch == '\n' {
+                        break;
+                    }
+                }
+            }
+            '(' | ')' => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+                tokens.push(ch.to_string());
+            }
+            ch if ch.is_whitespace() || ch == '.' => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            '$' => {
+                while chars.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+                    chars.next();
+                }
+            }
+            ch => token.push(ch),
+        }
+        // a new token starts after a comment closes
+        if ch == '{' && !token.is_empty() {
+            tokens.push(std::mem::take(&mut token));
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+// plays the tokens of one line from the position, until the closing parenthesis of a
+// variation or the result token of the game
+fn read_line<'a>(game: &mut Game, tokens: &mut impl Iterator<Item = &'a String>,
+                 result: &mut Option<GameResult>) -> Result<Vec<MoveNode>, String> {
+    let mut line: Vec<MoveNode> = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token.as_str() {
+            ")" => break,
+            "(" => {
+                // the variation replaces the last move, so it starts from the position before it
+                let mut before = game.clone();
+                before.unmake_move().ok_or("Variation before the first move")?;
+                let variation = read_line(&mut before, tokens, &mut None)?;
+                let last = line.last_mut().ok_or("Variation before the first move")?;
+                last.variations.push(variation);
+            }
+            // move numbers, the dots were already dropped by the tokenizer
+            token if token.chars().all(|ch| ch.is_ascii_digit()) => (),
+            token => {
+                if let Some(parsed) = parse_result(token) {
+                    *result = parsed;
+                    break;
+                }
+                let mv = game.parse_san(token)?;
+                game.make_move(mv);
+                line.push(MoveNode::new(mv));
+            }
+        }
+    }
+    Ok(line)
+}
+
+fn replay(headers: Vec<(String, String)>, movetext: &str) -> Result<GameRecord, String> {
+    let mut record = GameRecord { headers, moves: vec![], result: None };
+    let mut game = record.starting_position()?;
+    let tokens = movetext_tokens(movetext);
+    record.moves = read_line(&mut game, &mut tokens.iter(), &mut record.result)?;
+    Ok(record)
+}
+
+// every game in a PGN file, a game ends at its result token or where the next tag section starts
+pub fn parse_pgn(text: &str) -> Result<Vec<GameRecord>, String> {
+    let mut games = Vec::new();
+    let mut headers = Vec::new();
+    let mut movetext = String::new();
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        // lines starting with % are escaped and ignored
+        if trimmed.starts_with('%') {
+            continue;
+        }
+        if trimmed.starts_with('[') {
+            if !movetext.trim().is_empty() {
+                games.push(replay(std::mem::take(&mut headers), &movetext)?);
+                movetext.clear();
+            }
+            headers.push(parse_tag(trimmed)?);
+            continue;
+        }
+        movetext.push_str(line);
+        movetext.push('\n');
+        let ends_game = trimmed.split_whitespace()
+            .last()
+            .is_some_and(|token| parse_result(token).is_some());
+        if ends_game && movetext_tokens(&movetext).last().is_some_and(|token| parse_result(token).is_some()) {
+            games.push(replay(std::mem::take(&mut headers), &movetext)?);
+            movetext.clear();
+        }
+    }
+    if !headers.is_empty() || !movetext.trim().is_empty() {
+        games.push(replay(headers, &movetext)?);
+    }
+    Ok(games)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWO_GAMES: &str = r#"[Event "Casual"]
+[Site "Somewhere \"nice\""]
+[White "A"]
+[Black "B"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 {the usual} Nc6 3. Bb5 (3. Bc4 Bc5) a6 4. Ba4 $1 Nf6
+5. O-O Be7 ; a comment to the end of the line
+6. Re1 b5 7. Bb3 d6 8. c3 O-O 1-0
+
+[Event "Mate"]
+[Result "0-1"]
+
+1.f3 e5 2.g4?? Qh4# 0-1
+"#;
+
+    #[test]
+    fn reads_several_games_with_comments() {
+        let games = parse_pgn(TWO_GAMES).unwrap();
+        assert_eq!(games.len(), 2);
+
+        let first = &games[0];
+        assert_eq!(first.header("Site"), Some("Somewhere \"nice\""));
+        assert_eq!(first.moves.len(), 16);
+        assert_eq!(first.result, Some(GameResult::WhiteWins));
+        assert_eq!(first.final_position().unwrap().to_fen(),
+                   "r1bq1rk1/2p1bppp/p1np1n2/1p2p3/4P3/1BP2N2/PP1P1PPP/RNBQR1K1 w - - 1 9");
+
+        assert_eq!(first.moves[4].variations[0].len(), 2);
+        assert_eq!(first.moves[4].variations[0][1].mv.to_string(), "f8c5");
+
+        let second = &games[1];
+        assert_eq!(second.moves.len(), 4);
+        assert_eq!(second.result, Some(GameResult::BlackWins));
+        assert!(second.final_position().unwrap().legal_moves().is_empty());
+    }
+
+    #[test]
+    fn starts_from_the_fen_tag_and_reports_bad_moves() {
+        let pgn = "[FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]\n\n1. O-O Kd7 *\n";
+        let games = parse_pgn(pgn).unwrap();
+        assert_eq!(games[0].moves.len(), 2);
+        assert_eq!(games[0].result, None);
+
+        assert!(parse_pgn("1. e4 e4 *").is_err());
+        assert!(parse_pgn("[Event Casual]\n1. e4 *").is_err());
+    }
+
+    #[test]
+    fn exports_the_seven_tag_roster_and_wrapped_movetext() {
+        let games = parse_pgn(TWO_GAMES).unwrap();
+        let pgn = games[0].to_pgn().unwrap();
+        let lines: Vec<&str> = pgn.lines().collect();
+        assert_eq!(&lines[..8], [
+            "[Event \"Casual\"]",
+            "[Site \"Somewhere \\\"nice\\\"\"]",
+            "[Date \"????.??.??\"]",
+            "[Round \"?\"]",
+            "[White \"A\"]",
+            "[Black \"B\"]",
+            "[Result \"1-0\"]",
+            "",
+        ]);
+        assert!(lines.iter().all(|line| line.len() <= 80));
+        assert_eq!(lines[8], "1. e4 e5 2. Nf3 Nc6 3. Bb5 (3. Bc4 Bc5) 3... a6 4. Ba4 Nf6 5. O-O Be7 6. Re1 b5");
+        assert!(pgn.ends_with("O-O 1-0\n"));
+
+        // what we write reads back as the same game
+        let read_back = &parse_pgn(&pgn).unwrap()[0];
+        assert_eq!((&read_back.moves, read_back.result), (&games[0].moves, games[0].result));
+    }
+
+    #[test]
+    fn variations_form_a_tree() {
+        let pgn = "1. e4 (1. d4 d5 (1... Nf6 2. c4) 2. c4) 1... e5 (1... c5 2. Nf3) 2. Nf3 *";
+        let record = &parse_pgn(pgn).unwrap()[0];
+        assert_eq!(record.mainline().iter().map(|mv| mv.to_string()).collect::<Vec<_>>(), ["e2e4", "e7e5", "g1f3"]);
+
+        let queens_pawn = &record.moves[0].variations[0];
+        assert_eq!(queens_pawn.len(), 3);
+        assert_eq!(queens_pawn[1].variations[0][0].mv.to_string(), "g8f6");
+        assert_eq!(record.moves[1].variations[0][1].mv.to_string(), "g1f3");
+
+        assert_eq!(record.to_pgn().unwrap().lines().last().unwrap(), pgn);
+        assert!(parse_pgn("(1. d4) 1. e4 *").is_err());
+        assert!(parse_pgn("1. e4 (1... e5) *").is_err());
+    }
+
+    #[test]
+    fn records_games_played_on_the_board() {
+        let mut game = Game::read_FEN("6k1/p4ppp/8/8/8/8/8/3R2K1 b - - 0 30");
+        game.make_move("a7a6".parse().unwrap());
+        game.make_move("d1d8".parse().unwrap());
+        game.update_outcome();
+
+        let record = GameRecord::from_game(&game);
+        assert_eq!(record.header("FEN"), Some("6k1/p4ppp/8/8/8/8/8/3R2K1 b - - 0 30"));
+        assert_eq!(record.header("Termination"), Some("normal"));
+        let pgn = record.to_pgn().unwrap();
+        assert!(pgn.contains("[Result \"1-0\"]"));
+        assert!(pgn.ends_with("\n30... a6 31. Rd8# 1-0\n"));
+    }
+}
@@ -0,0 +1,387 @@
+use crate::game::{Color, Game, PieceType};
+use crate::render::{cell, rows, HighlightStyle, SvgOptions, ARROW_COLOR, HIGHLIGHT_COLOR, SVG_DARK, SVG_LIGHT};
+use crate::utils::bit_scan;
+
+// the pieces as 16x16 masks, scaled up to the square size and outlined when drawn
+const SPRITE_SIZE: usize = 16;
+
+const PAWN: [&str; SPRITE_SIZE] = [
+    "................",
+    "................",
+    "................",
+    "......####......",
+    ".....######.....",
+    ".....######.....",
+    "......####......",
+    ".......##.......",
+    "......####......",
+    ".....######.....",
+    ".....######.....",
+    "....########....",
+    "...##########...",
+    "...##########...",
+    "................",
+    "................",
+];
+
+const ROOK: [&str; SPRITE_SIZE] = [
+    "................",
+    "................",
+    "...##..##..##...",
+    "...##########...",
+    "....########....",
+    ".....######.....",
+    ".....######.....",
+    ".....######.....",
+    ".....######.....",
+    ".....######.....",
+    "....########....",
+    "...##########...",
+    "...##########...",
+    "..############..",
+    "................",
+    "................",
+];
+
+const KNIGHT: [&str; SPRITE_SIZE] = [
+    "................",
+    "................",
+    ".......#.#......",
+    "......######....",
+    ".....########...",
+    "....##.#######..",
+    "...###########..",
+    "...##########...",
+    "....###..#####..",
+    ".......#######..",
+    "......#######...",
+    ".....########...",
+    "....##########..",
+    "....##########..",
+    "................",
+    "................",
+];
+
+const BISHOP: [&str; SPRITE_SIZE] = [
+    "................",
+    ".......##.......",
+    "......####......",
+    ".....###.##.....",
+    ".....##.###.....",
+    ".....######.....",
+    "......####......",
+    ".......##.......",
+    "......####......",
+    ".....######.....",
+    "....########....",
+    "...##########...",
+    "...##########...",
+    "................",
+    "................",
+    "................",
+];
+
+const QUEEN: [&str; SPRITE_SIZE] = [
+    "................",
+    "..#...#..#...#..",
+    "..#...#..#...#..",
+    "..##.##..##.##..",
+    "..############..",
+    "...##########...",
+    "...##########...",
+    "....########....",
+    "....########....",
+    ".....######.....",
+    "....########....",
+    "...##########...",
+    "...##########...",
+    "..############..",
+    "................",
+    "................",
+];
+
+const KING: [&str; SPRITE_SIZE] = [
+    ".......##.......",
+    "......####......",
+    ".......##.......",
+    "....##.##.##....",
+    "..############..",
+    "..############..",
+    "..############..",
+    "...##########...",
+    "....########....",
+    ".....######.....",
+    ".....######.....",
+    "....########....",
+    "...##########...",
+    "...##########...",
+    "..############..",
+    "................",
+];
+
+fn sprite(piece: PieceType) -> &'static [&'static str; SPRITE_SIZE] {
+    match piece {
+        PieceType::Pawn => &PAWN,
+        PieceType::Rook => &ROOK,
+        PieceType::Knight => &KNIGHT,
+        PieceType::Bishop => &BISHOP,
+        PieceType::Queen => &QUEEN,
+        PieceType::King => &KING,
+    }
+}
+
+// a 3x5 font for the coordinates
+fn glyph(c: char) -> [&'static str; 5] {
+    match c {
+        'a' => [".#.", "#.#", "###", "#.#", "#.#"],
+        'b' => ["##.", "#.#", "##.", "#.#", "##."],
+        'c' => [".##", "#..", "#..", "#..", ".##"],
+        'd' => ["##.", "#.#", "#.#", "#.#", "##."],
+        'e' => ["###", "#..", "##.", "#..", "###"],
+        'f' => ["###", "#..", "##.", "#..", "#.."],
+        'g' => [".##", "#..", "#.#", "#.#", ".##"],
+        'h' => ["#.#", "#.#", "###", "#.#", "#.#"],
+        '1' => [".#.", "##.", ".#.", ".#.", "###"],
+        '2' => ["##.", "..#", ".#.", "#..", "###"],
+        '3' => ["##.", "..#", ".#.", "..#", "##."],
+        '4' => ["#.#", "#.#", "###", "..#", "..#"],
+        '5' => ["###", "#..", "##.", "..#", "##."],
+        '6' => [".##", "#..", "###", "#.#", "###"],
+        '7' => ["###", "..#", ".#.", ".#.", ".#."],
+        _ => ["###", "#.#", "###", "#.#", "###"],
+    }
+}
+
+type Rgb = [u8; 3];
+
+// "#rrggbb", anything else falls back to `default`
+fn parse_color(color: &str, default: &str) -> Rgb {
+    let parse = |color: &str| -> Option<Rgb> {
+        let hex = color.strip_prefix('#').filter(|hex| hex.len() == 6)?;
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        Some([channel(0)?, channel(2)?, channel(4)?])
+    };
+    parse(color).or_else(|| parse(default)).unwrap_or([0, 0, 0])
+}
+
+// an rgb image, row by row from the top left
+struct Canvas {
+    width: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn blend(&mut self, x: usize, y: usize, color: Rgb, alpha: f64) {
+        if x >= self.width || y >= self.width {
+            return;
+        }
+        let at = (y * self.width + x) * 3;
+        for (channel, value) in self.pixels[at..at + 3].iter_mut().zip(color) {
+            *channel = (*channel as f64 * (1.0 - alpha) + value as f64 * alpha).round() as u8;
+        }
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb, alpha: f64) {
+        for py in y..y + height {
+            for px in x..x + width {
+                self.blend(px, py, color, alpha);
+            }
+        }
+    }
+}
+
+fn draw_piece(canvas: &mut Canvas, left: usize, top: usize, size: usize, piece: PieceType, color: Color) {
+    let mask = sprite(piece);
+    let inside = |x: isize, y: isize| {
+        if x < 0 || y < 0 || x as usize >= size || y as usize >= size {
+            return false;
+        }
+        let (sx, sy) = (x as usize * SPRITE_SIZE / size, y as usize * SPRITE_SIZE / size);
+        mask[sy].as_bytes()[sx] == b'#'
+    };
+    // the outline is as thick at every size instead of growing with the sprite's pixels
+    let thickness = (size / 30).max(1) as isize;
+    let fill = match color {
+        Color::White => [255, 255, 255],
+        Color::Black => [0, 0, 0],
+    };
+    for y in 0..size as isize {
+        for x in 0..size as isize {
+            if !inside(x, y) {
+                continue;
+            }
+            let edge = [(-thickness, 0), (thickness, 0), (0, -thickness), (0, thickness)]
+                .iter().any(|(dx, dy)| !inside(x + dx, y + dy));
+            canvas.blend(left + x as usize, top + y as usize, if edge { [0, 0, 0] } else { fill }, 1.0);
+        }
+    }
+}
+
+fn draw_text(canvas: &mut Canvas, x: usize, y: usize, scale: usize, text: char, color: Rgb) {
+    for (row, line) in glyph(text).iter().enumerate() {
+        for (column, pixel) in line.bytes().enumerate() {
+            if pixel == b'#' {
+                canvas.fill(x + column * scale, y + row * scale, scale, scale, color, 1.0);
+            }
+        }
+    }
+}
+
+fn draw_arrow(canvas: &mut Canvas, from: (f64, f64), to: (f64, f64), size: f64, color: Rgb) {
+    let length = ((to.0 - from.0).powi(2) + (to.1 - from.1).powi(2)).sqrt();
+    if length == 0.0 {
+        return;
+    }
+    // the same shape as in the svg, a shaft up to a triangular head
+    let (dx, dy) = ((to.0 - from.0) / length, (to.1 - from.1) / length);
+    let (head, half_width, shaft) = (size * 0.4, size * 0.25, size * 0.08);
+    let base = (to.0 - dx * head, to.1 - dy * head);
+    let corners = [to, (base.0 - dy * half_width, base.1 + dx * half_width), (base.0 + dy * half_width, base.1 - dx * half_width)];
+    let cross = |a: (f64, f64), b: (f64, f64), p: (f64, f64)| (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0);
+    let (min_x, max_x) = (from.0.min(to.0) - half_width, from.0.max(to.0) + half_width);
+    let (min_y, max_y) = (from.1.min(to.1) - half_width, from.1.max(to.1) + half_width);
+    for y in min_y.max(0.0) as usize..=max_y as usize {
+        for x in min_x.max(0.0) as usize..=max_x as usize {
+            let p = (x as f64 + 0.5, y as f64 + 0.5);
+            // distance to the shaft, rounded off at the tail like the svg's line cap
+            let along = ((p.0 - from.0) * dx + (p.1 - from.1) * dy).clamp(0.0, length - head);
+            let nearest = (from.0 + dx * along, from.1 + dy * along);
+            let in_shaft = (p.0 - nearest.0).powi(2) + (p.1 - nearest.1).powi(2) <= shaft * shaft;
+            let sides = [cross(corners[0], corners[1], p), cross(corners[1], corners[2], p), cross(corners[2], corners[0], p)];
+            let in_head = sides.iter().all(|side| *side >= 0.0) || sides.iter().all(|side| *side <= 0.0);
+            if in_shaft || in_head {
+                canvas.blend(x, y, color, 0.8);
+            }
+        }
+    }
+}
+
+// the position as rgb pixels, drawn like to_svg draws it but with the built-in sprites
+// instead of font glyphs, so it looks the same wherever the image ends up and whatever
+// the options' piece style
+pub fn rasterize(game: &Game, options: &SvgOptions) -> (usize, Vec<u8>) {
+    let size = options.size as usize;
+    let width = size * 8;
+    let mut canvas = Canvas { width, pixels: vec![0; width * width * 3] };
+    let corner = |square: usize| {
+        let (x, y) = cell(square, options.flipped);
+        (x as usize * size, y as usize * size)
+    };
+    let square_color = |square: usize, inverted: bool| {
+        let dark = (square / 8 + square % 8).is_multiple_of(2) != inverted;
+        if dark { parse_color(&options.dark, SVG_DARK) } else { parse_color(&options.light, SVG_LIGHT) }
+    };
+    for square in 0..64 {
+        let (x, y) = corner(square);
+        canvas.fill(x, y, size, size, square_color(square, false), 1.0);
+    }
+    for (square, color) in &options.highlights {
+        let (x, y) = corner(square.index());
+        let color = parse_color(color, HIGHLIGHT_COLOR);
+        match options.highlight {
+            HighlightStyle::Fill => canvas.fill(x, y, size, size, color, 0.6),
+            HighlightStyle::Outline => {
+                let stroke = (size / 15).max(1);
+                canvas.fill(x, y, size, stroke, color, 1.0);
+                canvas.fill(x, y + size - stroke, size, stroke, color, 1.0);
+                canvas.fill(x, y, stroke, size, color, 1.0);
+                canvas.fill(x + size - stroke, y, stroke, size, color, 1.0);
+            }
+            HighlightStyle::Off => {}
+        }
+    }
+    if options.coordinates {
+        let scale = (size / 20).max(1);
+        let margin = scale;
+        let rows = rows(options.flipped);
+        for &square in &rows[7] {
+            let (x, y) = corner(square);
+            let file = (b'a' + (square % 8) as u8) as char;
+            draw_text(&mut canvas, x + size - margin - scale * 3, y + size - margin - scale * 5, scale, file,
+                square_color(square, true));
+        }
+        for row in &rows {
+            let (x, y) = corner(row[0]);
+            let rank = (b'1' + (row[0] / 8) as u8) as char;
+            draw_text(&mut canvas, x + margin, y + margin, scale, rank, square_color(row[0], true));
+        }
+    }
+    for piece in &game.pieces {
+        let (x, y) = corner(bit_scan(piece.position));
+        draw_piece(&mut canvas, x, y, size, piece.piece_type, piece.color);
+    }
+    for (from, to, color) in &options.arrows {
+        let center = |square: usize| {
+            let (x, y) = cell(square, options.flipped);
+            ((x + 0.5) * size as f64, (y + 0.5) * size as f64)
+        };
+        draw_arrow(&mut canvas, center(from.index()), center(to.index()), size as f64, parse_color(color, ARROW_COLOR));
+    }
+    (width, canvas.pixels)
+}
+
+// an 8 bit rgb png
+pub fn encode_png(width: usize, height: usize, rgb: &[u8]) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    let mut encoder = ::png::Encoder::new(&mut bytes, width as u32, height as u32);
+    encoder.set_color(::png::ColorType::Rgb);
+    encoder.set_depth(::png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+    writer.write_image_data(rgb).map_err(|err| err.to_string())?;
+    writer.finish().map_err(|err| err.to_string())?;
+    Ok(bytes)
+}
+
+pub fn to_png(game: &Game, options: &SvgOptions) -> Result<Vec<u8>, String> {
+    let (width, pixels) = rasterize(game, options);
+    encode_png(width, width, &pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_valid_pngs() {
+        let png = encode_png(2, 1, &[255, 0, 0, 0, 0, 255]).unwrap();
+        let mut reader = ::png::Decoder::new(std::io::Cursor::new(png)).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height, info.color_type), (2, 1, ::png::ColorType::Rgb));
+        assert_eq!(pixels, [255, 0, 0, 0, 0, 255]);
+        assert!(encode_png(2, 2, &[0; 6]).is_err());
+    }
+
+    #[test]
+    fn draws_the_position() {
+        for sprite in [PAWN, ROOK, KNIGHT, BISHOP, QUEEN, KING] {
+            assert!(sprite.iter().all(|row| row.len() == SPRITE_SIZE));
+        }
+        let game = Game::read_FEN("4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+        let options = SvgOptions { size: 32, coordinates: false, ..SvgOptions::default() };
+        let (width, pixels) = rasterize(&game, &options);
+        assert_eq!(width, 256);
+        let pixel = |x: usize, y: usize| &pixels[(y * width + x) * 3..(y * width + x) * 3 + 3];
+        // a1 dark in the bottom left, a8 light, the white king's body on e1 and the black one on e8
+        assert_eq!(pixel(1, 254), &[0xb5, 0x88, 0x63]);
+        assert_eq!(pixel(1, 1), &[0xf0, 0xd9, 0xb5]);
+        assert_eq!(pixel(4 * 32 + 16, 7 * 32 + 12), &[255, 255, 255]);
+        assert_eq!(pixel(4 * 32 + 16, 12), &[0, 0, 0]);
+
+        let arrow = SvgOptions { arrows: vec![("h1".parse().unwrap(), "h8".parse().unwrap(), "#ff0000".to_string())], ..options.clone() };
+        let (_, pixels) = rasterize(&game, &arrow);
+        let at = (4 * 32 * width + 7 * 32 + 16) * 3;
+        assert!(pixels[at] > 200 && pixels[at + 1] < 100, "{:?}", &pixels[at..at + 3]);
+
+        // a framed e4 on a grey board
+        let e4 = "e4".parse().unwrap();
+        let framed = SvgOptions { dark: "#808080".to_string(), highlight: HighlightStyle::Outline,
+            highlights: vec![(e4, "#0000ff".to_string())], ..options };
+        let (_, pixels) = rasterize(&game, &framed);
+        let pixel = |x: usize, y: usize| &pixels[(y * width + x) * 3..(y * width + x) * 3 + 3];
+        assert_eq!(pixel(1, 254), &[0x80, 0x80, 0x80]);
+        assert_eq!(pixel(4 * 32, 4 * 32 + 16), &[0, 0, 255]);
+        assert_eq!(pixel(4 * 32 + 16, 4 * 32 + 16), &[0xf0, 0xd9, 0xb5]);
+        assert!(to_png(&game, &arrow).unwrap().starts_with(b"\x89PNG"));
+    }
+}
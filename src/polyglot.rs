@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use crate::game::*;
+use crate::movegen::Move;
+use crate::outcome::GameResult;
+use crate::pgn::*;
+use crate::square::Square;
+use crate::zobrist;
+
+// the key Polyglot books are indexed by, Game::hash uses the same keys
+pub fn polyglot_key(game: &Game) -> u64 {
+    zobrist::compute(game)
+}
+
+// to square, from square and promotion piece packed in 16 bits, castling is
+// written as the king taking its own rook
+pub fn encode_move(game: &Game, mv: Move) -> u16 {
+    let (from, mut to) = (mv.from(), mv.to());
+    if let Some(castling) = game.castling(mv) {
+        to = castling.rook_from;
+    }
+    let promotion = match mv.promotion() {
+        Some(PieceType::Knight) => 1,
+        Some(PieceType::Bishop) => 2,
+        Some(PieceType::Rook) => 3,
+        Some(PieceType::Queen) => 4,
+        _ => 0,
+    };
+    (promotion << 12 | from << 6 | to) as u16
+}
+
+// the legal move an encoded book move stands for
+pub fn decode_move(game: &Game, encoded: u16) -> Option<Move> {
+    let from = Square::from_index((encoded >> 6 & 0x3f) as usize);
+    game.moves_from(from).into_iter().find(|mv| encode_move(game, *mv) == encoded)
+}
+
+// one 16 byte record of a .bin book, stored big endian
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub mv: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.mv.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; 16]) -> BookEntry {
+        BookEntry {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            mv: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+}
+
+// entries sorted by key as Polyglot expects
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Book {
+    pub entries: Vec<BookEntry>,
+}
+
+impl Book {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Book, String> {
+        if !bytes.len().is_multiple_of(16) {
+            return Err(format!("Book size {} is not a multiple of 16", bytes.len()));
+        }
+        let entries = bytes.chunks_exact(16)
+            .map(|chunk| BookEntry::from_bytes(chunk.try_into().unwrap()))
+            .collect();
+        Ok(Book { entries })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.entries.iter().flat_map(|entry| entry.to_bytes()).collect()
+    }
+
+    // book moves for the position with their weights, best first
+    pub fn probe(&self, game: &Game) -> Vec<(Move, u16)> {
+        let key = polyglot_key(game);
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        self.entries[start..].iter()
+            .take_while(|entry| entry.key == key)
+            .filter_map(|entry| decode_move(game, entry.mv).map(|mv| (mv, entry.weight)))
+            .collect()
+    }
+}
+
+// points a move earns for the result of each game it was played in, from the
+// point of view of the side that played it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Weighting {
+    pub win: u32,
+    pub draw: u32,
+    pub loss: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookOptions {
+    // moves played in fewer games than this are left out
+    pub min_games: u32,
+    // plies from the start of each game that go into the book
+    pub max_depth: usize,
+    pub weighting: Weighting,
+}
+
+impl Default for BookOptions {
+    fn default() -> BookOptions {
+        BookOptions {
+            min_games: 1,
+            max_depth: 20,
+            weighting: Weighting { win: 2, draw: 1, loss: 0 },
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+struct MoveStats {
+    games: u32,
+    score: u64,
+}
+
+// collects the opening moves of many games into a book
+pub struct BookBuilder {
+    options: BookOptions,
+    stats: HashMap<(u64, u16), MoveStats>,
+}
+
+impl BookBuilder {
+    pub fn new(options: BookOptions) -> BookBuilder {
+        BookBuilder { options, stats: HashMap::new() }
+    }
+
+    pub fn add_game(&mut self, record: &GameRecord) -> Result<(), String> {
+        let mut game = record.starting_position()?;
+        for mv in record.mainline().into_iter().take(self.options.max_depth) {
+            let weighting = self.options.weighting;
+            let points = match (record.result, game.active_color) {
+                (Some(GameResult::Draw), _) | (None, _) => weighting.draw,
+                (Some(GameResult::WhiteWins), Color::White) | (Some(GameResult::BlackWins), Color::Black) => weighting.win,
+                _ => weighting.loss,
+            };
+            let stats = self.stats.entry((polyglot_key(&game), encode_move(&game, mv))).or_default();
+            stats.games += 1;
+            stats.score += points as u64;
+            game.make_move(mv);
+        }
+        Ok(())
+    }
+
+    pub fn add_pgn(&mut self, text: &str) -> Result<(), String> {
+        for record in parse_pgn(text)? {
+            self.add_game(&record)?;
+        }
+        Ok(())
+    }
+
+    // every .pgn file directly inside the directory
+    pub fn add_pgn_dir(&mut self, dir: &Path) -> Result<(), String> {
+        let entries = fs::read_dir(dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+        let mut paths: Vec<_> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("pgn")))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let text = fs::read_to_string(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+            self.add_pgn(&text).map_err(|err| format!("{}: {}", path.display(), err))?;
+        }
+        Ok(())
+    }
+
+    // weights are scaled down to fit 16 bits, moves that never scored are dropped
+    pub fn build(&self) -> Book {
+        let kept: Vec<(&(u64, u16), &MoveStats)> = self.stats.iter()
+            .filter(|(_, stats)| stats.games >= self.options.min_games && stats.score > 0)
+            .collect();
+        let max = kept.iter().map(|(_, stats)| stats.score).max().unwrap_or(0);
+        let scale = if max > u16::MAX as u64 { max.div_ceil(u16::MAX as u64) } else { 1 };
+
+        let mut entries: Vec<BookEntry> = kept.into_iter()
+            .map(|((key, mv), stats)| BookEntry {
+                key: *key,
+                mv: *mv,
+                weight: (stats.score / scale).max(1) as u16,
+                learn: 0,
+            })
+            .collect();
+        entries.sort_by(|a, b| a.key.cmp(&b.key).then(b.weight.cmp(&a.weight)).then(a.mv.cmp(&b.mv)));
+        Book { entries }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_match_the_polyglot_reference() {
+        let positions = [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 0x463b96181691fc9c),
+            ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1", 0x823c9b50fd114196),
+            ("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2", 0x0756b94461c50fb0),
+            ("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2", 0x662fafb965db29d4),
+            ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", 0x22a48b5a8e47ff78),
+            ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 1 3", 0x652a607ca3f242c1),
+            ("rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 2 4", 0x00fdd303c946bdd9),
+            ("rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3", 0x3c8123ea7b067637),
+            ("rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4", 0x5c3f9b829b279560),
+        ];
+        for (fen, key) in positions {
+            assert_eq!(polyglot_key(&Game::read_FEN(fen)), key, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn moves_encode_like_polyglot() {
+        let game = Game::read_FEN("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        for (uci, encoded) in [("e1g1", 4 << 6 | 7), ("e1c1", 4 << 6), ("b7b8q", 4 << 12 | 49 << 6 | 57)] {
+            let mv = Move::from_uci(uci, &game).unwrap();
+            assert_eq!(encode_move(&game, mv), encoded);
+            assert_eq!(decode_move(&game, encoded), Some(mv));
+        }
+    }
+
+    #[test]
+    fn builds_weighted_books() {
+        let pgn = "[Result \"1-0\"]\n1. e4 e5 1-0\n\n[Result \"0-1\"]\n1. e4 c5 0-1\n\n[Result \"1/2-1/2\"]\n1. d4 d5 1/2-1/2\n";
+        let mut builder = BookBuilder::new(BookOptions { max_depth: 1, ..BookOptions::default() });
+        builder.add_pgn(pgn).unwrap();
+        let book = builder.build();
+
+        let start = Game::initialize();
+        let moves: Vec<(String, u16)> = book.probe(&start).into_iter()
+            .map(|(mv, weight)| (mv.to_string(), weight))
+            .collect();
+        assert_eq!(moves, [("e2e4".to_string(), 2), ("d2d4".to_string(), 1)]);
+        // nothing past the depth limit
+        assert_eq!(book.entries.len(), 2);
+        assert_eq!(Book::from_bytes(&book.to_bytes()).unwrap(), book);
+
+        let mut builder = BookBuilder::new(BookOptions { min_games: 2, ..BookOptions::default() });
+        builder.add_pgn(pgn).unwrap();
+        assert_eq!(builder.build().probe(&start).len(), 1);
+    }
+
+    #[test]
+    fn imports_every_pgn_file_in_a_directory() {
+        let dir = std::env::temp_dir().join(format!("rustic-book-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.pgn"), "1. e4 e5 1-0\n\n1. e4 c5 0-1\n").unwrap();
+        fs::write(dir.join("b.PGN"), "1. d4 d5 1/2-1/2\n").unwrap();
+        // other files are left alone, even when they hold games
+        fs::write(dir.join("notes.txt"), "1. c4 e5 1-0\n").unwrap();
+        fs::write(dir.join("README"), "not a game").unwrap();
+
+        let mut builder = BookBuilder::new(BookOptions { max_depth: 1, ..BookOptions::default() });
+        builder.add_pgn_dir(&dir).unwrap();
+        let start = polyglot_key(&Game::initialize());
+        let games: u32 = builder.stats.iter().filter(|((key, _), _)| *key == start).map(|(_, stats)| stats.games).sum();
+        assert_eq!(games, 3);
+        let moves: Vec<String> = builder.build().probe(&Game::initialize()).into_iter().map(|(mv, _)| mv.to_string()).collect();
+        assert_eq!(moves, ["e2e4", "d2d4"]);
+
+        fs::write(dir.join("c.pgn"), "1. e4 Ke7??").unwrap();
+        let err = builder.add_pgn_dir(&dir).err().unwrap();
+        assert!(err.contains("c.pgn"), "{}", err);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(builder.add_pgn_dir(&dir).is_err());
+    }
+}
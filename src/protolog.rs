@@ -0,0 +1,208 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+// rotate once the log passes this size, keeping this many old logs next to it
+pub const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+pub const DEFAULT_KEEP: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    // what the gui sent
+    Gui,
+    // what the engine answered, including its search info and best move
+    Engine,
+}
+
+// a session's traffic, one "<seconds> <gui|engine> <line>" record per line, the seconds
+// counted from the start of the session. path.1 is the most recent rotated log
+pub struct ProtocolLog {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: File,
+    written: u64,
+    start: Instant,
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+impl ProtocolLog {
+    // appends to the log at `path`, a new session starts with a marker line
+    pub fn open(path: impl Into<PathBuf>, max_bytes: u64, keep: usize) -> io::Result<ProtocolLog> {
+        let path = path.into();
+        let file = open_append(&path)?;
+        let written = file.metadata()?.len();
+        let mut log = ProtocolLog { path, max_bytes, keep, file, written, start: Instant::now() };
+        log.write_line(&format!("# session started by {}", crate::uci::ENGINE_NAME))?;
+        Ok(log)
+    }
+
+    pub fn record(&mut self, direction: Direction, line: &str) -> io::Result<()> {
+        let direction = match direction {
+            Direction::Gui => "gui",
+            Direction::Engine => "engine",
+        };
+        let seconds = self.start.elapsed().as_secs_f64();
+        self.write_line(&format!("{:.3} {} {}", seconds, direction, line.trim_end_matches(['\r', '\n'])))
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.written > 0 && self.written + line.len() as u64 >= self.max_bytes {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.file.flush()?;
+        self.written += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    // path.n-1 becomes path.n down to path becoming path.1, the oldest falls off the end
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                let from = rotated(&self.path, n);
+                if from.exists() {
+                    fs::rename(&from, rotated(&self.path, n + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated(&self.path, 1))?;
+            self.file = open_append(&self.path)?;
+        }
+        self.written = 0;
+        Ok(())
+    }
+}
+
+pub type SharedLog = Arc<Mutex<ProtocolLog>>;
+
+// records every complete line that passes through `buffer` as coming from `direction`,
+// a failing log never gets in the way of the session
+fn record_lines(log: &SharedLog, direction: Direction, buffer: &mut Vec<u8>, bytes: &[u8]) {
+    buffer.extend_from_slice(bytes);
+    while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+        let line: Vec<u8> = buffer.drain(..=end).collect();
+        let _ = log.lock().unwrap().record(direction, &String::from_utf8_lossy(&line));
+    }
+}
+
+// the gui's side of the session, logged as the engine reads it
+pub struct LoggedInput<R> {
+    inner: R,
+    log: SharedLog,
+    pending: Vec<u8>,
+}
+
+impl<R: BufRead> LoggedInput<R> {
+    pub fn new(inner: R, log: SharedLog) -> LoggedInput<R> {
+        LoggedInput { inner, log, pending: vec![] }
+    }
+}
+
+impl<R: BufRead> Read for LoggedInput<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        record_lines(&self.log, Direction::Gui, &mut self.pending, &buf[..read]);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for LoggedInput<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        if let Ok(bytes) = self.inner.fill_buf() {
+            let bytes = &bytes[..amount.min(bytes.len())];
+            record_lines(&self.log, Direction::Gui, &mut self.pending, bytes);
+        }
+        self.inner.consume(amount);
+    }
+}
+
+// the engine's side, logged as it is written
+pub struct LoggedOutput<W> {
+    inner: W,
+    log: SharedLog,
+    pending: Vec<u8>,
+}
+
+impl<W: Write> LoggedOutput<W> {
+    pub fn new(inner: W, log: SharedLog) -> LoggedOutput<W> {
+        LoggedOutput { inner, log, pending: vec![] }
+    }
+}
+
+impl<W: Write> Write for LoggedOutput<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        record_lines(&self.log, Direction::Engine, &mut self.pending, &buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// the input and output of a session wrapped so both end up in `log`
+pub fn logged<R: BufRead, W: Write>(input: R, output: W, log: ProtocolLog) -> (LoggedInput<R>, LoggedOutput<W>) {
+    let log = Arc::new(Mutex::new(log));
+    (LoggedInput::new(input, log.clone()), LoggedOutput::new(output, log))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rustic-log-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn logs_both_sides_of_a_uci_session() {
+        let dir = log_dir("session");
+        let path = dir.join("uci.log");
+        let (input, output) = logged(&b"uci\nisready\nquit\n"[..], vec![], ProtocolLog::open(&path, 1 << 20, 2).unwrap());
+        crate::uci::run(input, output).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        let records: Vec<(&str, &str)> = text.lines().skip(1)
+            .map(|line| line.split_once(' ').unwrap().1.split_once(' ').unwrap())
+            .collect();
+        assert_eq!(records[0], ("gui", "uci"));
+        assert!(records[1].0 == "engine" && records[1].1.starts_with("id name "), "{}", text);
+        assert!(records.ends_with(&[("engine", "uciok"), ("gui", "isready"), ("engine", "readyok"), ("gui", "quit")]), "{}", text);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotates_full_logs() {
+        let dir = log_dir("rotate");
+        let path = dir.join("engine.log");
+        let mut log = ProtocolLog::open(&path, 100, 2).unwrap();
+        for n in 0..20 {
+            log.record(Direction::Gui, &format!("go depth {}", n)).unwrap();
+        }
+        assert!(fs::metadata(&path).unwrap().len() < 100);
+        assert!(rotated(&path, 1).exists() && rotated(&path, 2).exists());
+        assert!(!rotated(&path, 3).exists());
+        assert!(fs::read_to_string(&path).unwrap().ends_with("gui go depth 19\n"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,352 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use crate::epd::Epd;
+use crate::game::{Color, Game};
+use crate::movegen::Move;
+use crate::render::{render, RenderOptions};
+
+// a position to solve, `game` is where the solver takes over and `solution` alternates
+// between the solver's moves and the forced replies
+#[derive(Clone)]
+pub struct Puzzle {
+    pub id: String,
+    pub game: Game,
+    pub solution: Vec<Move>,
+    // other first moves that solve it as well, from an EPD's bm
+    pub alternatives: Vec<Move>,
+    pub rating: Option<u32>,
+    pub themes: Vec<String>,
+}
+
+fn uci_line(game: &Game, moves: &[&str]) -> Result<Vec<Move>, String> {
+    let mut replay = game.clone();
+    moves.iter().map(|text| {
+        let mv = Move::from_uci(text, &replay)?;
+        replay.make_move(mv);
+        Ok(mv)
+    }).collect()
+}
+
+impl Puzzle {
+    // the pv is the solution when there is one, otherwise any of the bm moves solves it
+    pub fn from_epd(epd: &Epd, number: usize) -> Result<Puzzle, String> {
+        let id = epd.id().map_or_else(|| format!("#{}", number), str::to_string);
+        let best = epd.best_moves()?;
+        let solution = match epd.operands("pv") {
+            Some(pv) => {
+                let mut replay = epd.game.clone();
+                pv.iter().map(|san| {
+                    let mv = replay.parse_san(san)?;
+                    replay.make_move(mv);
+                    Ok(mv)
+                }).collect::<Result<Vec<Move>, String>>()?
+            }
+            None => best.first().copied().into_iter().collect(),
+        };
+        if solution.is_empty() {
+            return Err(format!("Puzzle {} has no bm or pv", id));
+        }
+        let themes = epd.operands("c0").map(|comment| comment.to_vec()).unwrap_or_default();
+        Ok(Puzzle { id, game: epd.game.clone(), solution, alternatives: best, rating: None, themes })
+    }
+
+    // PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,... as in the
+    // lichess puzzle database, where the first move is the opponent's and sets the puzzle up
+    pub fn from_lichess_csv(line: &str) -> Result<Puzzle, String> {
+        let fields: Vec<&str> = line.split(',').collect();
+        if fields.len() < 3 {
+            return Err(format!("Expected PuzzleId,FEN,Moves,... in {}", line));
+        }
+        let mut game = Game::try_from_fen(fields[1]).map_err(|err| err.to_string())?;
+        let moves: Vec<&str> = fields[2].split_whitespace().collect();
+        let line_moves = uci_line(&game, &moves)?;
+        let Some((setup, solution)) = line_moves.split_first() else {
+            return Err(format!("Puzzle {} has no moves", fields[0]));
+        };
+        game.make_move(*setup);
+        Ok(Puzzle {
+            id: fields[0].to_string(),
+            game,
+            solution: solution.to_vec(),
+            alternatives: vec![],
+            rating: fields.get(3).and_then(|rating| rating.parse().ok()),
+            themes: fields.get(7).map(|themes| themes.split_whitespace().map(str::to_string).collect()).unwrap_or_default(),
+        })
+    }
+
+    pub fn solver(&self) -> Color {
+        self.game.active_color
+    }
+}
+
+// an EPD file or a lichess csv, told apart line by line, skipping the csv header and # comments
+pub fn parse_puzzles(text: &str) -> Result<Vec<Puzzle>, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with("PuzzleId,"))
+        .enumerate()
+        .map(|(number, line)| {
+            let csv = line.split(',').nth(1).is_some_and(|fen| fen.contains('/'));
+            if csv { Puzzle::from_lichess_csv(line) } else { Puzzle::from_epd(&Epd::parse(line)?, number + 1) }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    // right, and the opponent's reply has been played
+    Correct,
+    Solved,
+    Wrong,
+}
+
+// working through one puzzle
+pub struct Attempt<'a> {
+    pub puzzle: &'a Puzzle,
+    pub game: Game,
+    ply: usize,
+}
+
+impl<'a> Attempt<'a> {
+    pub fn new(puzzle: &'a Puzzle) -> Attempt<'a> {
+        Attempt { puzzle, game: puzzle.game.clone(), ply: 0 }
+    }
+
+    // the move the solution expects next
+    pub fn expected(&self) -> Move {
+        self.puzzle.solution[self.ply]
+    }
+
+    // the solution's move, a bm alternative on the first move or any mate counts, a mate
+    // ends the puzzle even if the solution goes on longer
+    pub fn play(&mut self, mv: Move) -> Verdict {
+        let same = |a: &Move, b: &Move| a.to_uci() == b.to_uci();
+        let expected = same(&mv, &self.expected()) || (self.ply == 0 && self.puzzle.alternatives.iter().any(|alt| same(alt, &mv)));
+        self.game.make_move(mv);
+        let mate = self.game.legal_moves().is_empty() && self.game.is_in_check(self.game.active_color);
+        if mate {
+            return Verdict::Solved;
+        }
+        if !expected {
+            self.game.unmake_move();
+            return Verdict::Wrong;
+        }
+        self.ply += 1;
+        match self.puzzle.solution.get(self.ply) {
+            None => Verdict::Solved,
+            Some(reply) => {
+                self.game.make_move(*reply);
+                self.ply += 1;
+                if self.ply == self.puzzle.solution.len() { Verdict::Solved } else { Verdict::Correct }
+            }
+        }
+    }
+}
+
+// how the solver has done over every session, kept in a small "key value" file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PuzzleStats {
+    pub attempted: u32,
+    pub solved: u32,
+    pub streak: u32,
+    pub best_streak: u32,
+}
+
+impl PuzzleStats {
+    pub fn record(&mut self, solved: bool) {
+        self.attempted += 1;
+        if solved {
+            self.solved += 1;
+            self.streak += 1;
+            self.best_streak = self.best_streak.max(self.streak);
+        } else {
+            self.streak = 0;
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<PuzzleStats, String> {
+        let mut stats = PuzzleStats::default();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let (key, value) = line.split_once(' ').ok_or_else(|| format!("Invalid stats line {}", line))?;
+            let value = value.trim().parse().map_err(|_| format!("Invalid {} '{}'", key, value))?;
+            match key {
+                "attempted" => stats.attempted = value,
+                "solved" => stats.solved = value,
+                "streak" => stats.streak = value,
+                "best_streak" => stats.best_streak = value,
+                _ => return Err(format!("Unknown stat {}", key)),
+            }
+        }
+        Ok(stats)
+    }
+
+    // a missing file is a first session
+    pub fn load(path: &Path) -> Result<PuzzleStats, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => PuzzleStats::parse(&text).map_err(|err| format!("{}: {}", path.display(), err)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(PuzzleStats::default()),
+            Err(err) => Err(format!("{}: {}", path.display(), err)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = format!("attempted {}\nsolved {}\nstreak {}\nbest_streak {}\n", self.attempted, self.solved, self.streak, self.best_streak);
+        fs::write(path, text).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+}
+
+impl std::fmt::Display for PuzzleStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let percent = if self.attempted == 0 { 0.0 } else { 100.0 * self.solved as f64 / self.attempted as f64 };
+        write!(f, "solved {} of {} ({:.0}%), streak {}, best streak {}", self.solved, self.attempted, percent, self.streak, self.best_streak)
+    }
+}
+
+// the stats for a puzzle file live next to it, so each set is tracked on its own
+pub fn stats_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".stats");
+    PathBuf::from(name)
+}
+
+// one puzzle after another at the terminal, a wrong move fails the puzzle and shows the answer
+pub struct Trainer<R: BufRead, W: Write> {
+    pub stats: PuzzleStats,
+    pub render: RenderOptions,
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Trainer<R, W> {
+    pub fn new(stats: PuzzleStats, render: RenderOptions, input: R, output: W) -> Trainer<R, W> {
+        Trainer { stats, render, input, output }
+    }
+
+    // false when the solver quit or the input ran out
+    pub fn run(&mut self, puzzles: &[Puzzle]) -> io::Result<bool> {
+        for puzzle in puzzles {
+            if !self.solve(puzzle)? {
+                return Ok(false);
+            }
+            writeln!(self.output, "{}", self.stats)?;
+        }
+        Ok(true)
+    }
+
+    fn solve(&mut self, puzzle: &Puzzle) -> io::Result<bool> {
+        let mut attempt = Attempt::new(puzzle);
+        let rating = puzzle.rating.map(|rating| format!(", rated {}", rating)).unwrap_or_default();
+        let side = match puzzle.solver() {
+            Color::White => "white",
+            Color::Black => "black",
+        };
+        let options = RenderOptions { flipped: puzzle.solver() == Color::Black, ..self.render };
+        writeln!(self.output, "\npuzzle {}{}, {} to play", puzzle.id, rating, side)?;
+        loop {
+            write!(self.output, "{}your move (or skip, solution, quit): ", render(&attempt.game, &options))?;
+            self.output.flush()?;
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(false);
+            }
+            let text = line.trim();
+            let mv = match text {
+                "" => continue,
+                "quit" | "exit" => return Ok(false),
+                "skip" => return Ok(true),
+                "solution" => {
+                    self.fail(&attempt)?;
+                    return Ok(true);
+                }
+                text => match attempt.game.parse_move(text) {
+                    Ok(mv) => mv,
+                    Err(err) => {
+                        writeln!(self.output, "{}", err)?;
+                        continue;
+                    }
+                },
+            };
+            let san = attempt.game.to_san(mv);
+            match attempt.play(mv) {
+                Verdict::Correct => {
+                    let mut before = attempt.game.clone();
+                    let reply = before.unmake_move().map(|reply| before.to_san(reply)).unwrap_or_default();
+                    writeln!(self.output, "{} is right, the reply is {}", san, reply)?;
+                }
+                Verdict::Solved => {
+                    self.stats.record(true);
+                    writeln!(self.output, "{} solves it", san)?;
+                    return Ok(true);
+                }
+                Verdict::Wrong => {
+                    writeln!(self.output, "{} is not it", san)?;
+                    self.fail(&attempt)?;
+                    return Ok(true);
+                }
+            }
+        }
+    }
+
+    fn fail(&mut self, attempt: &Attempt) -> io::Result<()> {
+        self.stats.record(false);
+        let mut replay = attempt.game.clone();
+        let moves: Vec<String> = attempt.puzzle.solution[attempt.ply..].iter().map(|mv| {
+            let san = replay.to_san(*mv);
+            replay.make_move(*mv);
+            san
+        }).collect();
+        writeln!(self.output, "the solution is {}", moves.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,GameUrl,OpeningTags
+00008,r6k/pp2r2p/4Rp1Q/3p4/8/1N1P2R1/PqP2bPP/7K b - - 0 24,f2g3 e6e7 b2b1 b3c1 b1c1 h6c1,1913,75,94,6230,crushing hangingPiece long middlegame,https://lichess.org/787zsVup/black#48,";
+
+    #[test]
+    fn reads_epd_and_lichess_puzzles() {
+        let puzzles = parse_puzzles(&format!("{}\n6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id \"back rank\";\n", CSV)).unwrap();
+        assert_eq!(puzzles.len(), 2);
+        let lichess = &puzzles[0];
+        assert_eq!((lichess.id.as_str(), lichess.rating, lichess.themes.len()), ("00008", Some(1913), 4));
+        // the setup move is played, white solves it
+        assert_eq!(lichess.solver(), Color::White);
+        assert_eq!(lichess.solution.len(), 5);
+        assert_eq!(puzzles[1].id, "back rank");
+        assert_eq!(puzzles[1].solution[0].to_uci(), "a1a8");
+    }
+
+    #[test]
+    fn checks_moves_against_the_solution() {
+        let puzzle = Puzzle::from_lichess_csv(CSV.lines().nth(1).unwrap()).unwrap();
+        let mv = |attempt: &Attempt, text: &str| Move::from_uci(text, &attempt.game).unwrap();
+        let mut attempt = Attempt::new(&puzzle);
+        let wrong = mv(&attempt, "h6h7");
+        assert_eq!(attempt.play(wrong), Verdict::Wrong);
+        let right = mv(&attempt, "e6e7");
+        assert_eq!(attempt.play(right), Verdict::Correct);
+        assert_eq!(attempt.game.history.last().unwrap().mv.to_uci(), "b2b1");
+        let right = mv(&attempt, "b3c1");
+        assert_eq!(attempt.play(right), Verdict::Correct);
+        let last = mv(&attempt, "h6c1");
+        assert_eq!(attempt.play(last), Verdict::Solved);
+    }
+
+    #[test]
+    fn trains_and_keeps_stats() {
+        let puzzles = parse_puzzles("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#;\n6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#;\n6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#;").unwrap();
+        let stats = PuzzleStats { attempted: 4, solved: 2, streak: 2, best_streak: 3 };
+        let mut trainer = Trainer::new(stats, RenderOptions::default(), &b"Ra8\nRb1\nsolution\n"[..], vec![]);
+        assert!(trainer.run(&puzzles).unwrap());
+        let out = String::from_utf8(trainer.output).unwrap();
+        assert!(out.contains("Ra8# solves it"), "{}", out);
+        assert!(out.contains("Rb1 is not it\nthe solution is Ra8#"), "{}", out);
+        assert_eq!(trainer.stats, PuzzleStats { attempted: 7, solved: 3, streak: 0, best_streak: 3 });
+        assert!(out.ends_with("solved 3 of 7 (43%), streak 0, best streak 3\n"), "{}", out);
+        assert_eq!(PuzzleStats::parse("attempted 7\nsolved 3\nstreak 0\nbest_streak 3\n").unwrap(), trainer.stats);
+    }
+}
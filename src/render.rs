@@ -0,0 +1,550 @@
+use std::str::FromStr;
+use crate::game::{Color, Game, Piece, PieceType};
+use crate::square::Square;
+use crate::utils::bit_scan;
+
+// how pieces and the board edge are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Style {
+    // letters, upper case for white, with dots on the empty squares
+    #[default]
+    Ascii,
+    // chess glyphs in a box drawn grid
+    Unicode,
+    // the solid glyphs for both sides, told apart by color, so the outlines stand in
+    // for white when the board is drawn without color
+    Figurine,
+}
+
+impl FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Style, String> {
+        match s {
+            "ascii" | "letters" => Ok(Style::Ascii),
+            "unicode" => Ok(Style::Unicode),
+            "figurine" => Ok(Style::Figurine),
+            _ => Err(format!("Unknown board style {}", s)),
+        }
+    }
+}
+
+// how much of the position a blindfold player gets to see
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Blindfold {
+    #[default]
+    Off,
+    // the pawn structure only, the pieces have to be kept in mind
+    Pawns,
+    // nothing on the board, the game is followed from the moves alone
+    Hidden,
+}
+
+impl FromStr for Blindfold {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Blindfold, String> {
+        match s {
+            "off" => Ok(Blindfold::Off),
+            "pawns" => Ok(Blindfold::Pawns),
+            "hidden" => Ok(Blindfold::Hidden),
+            _ => Err(format!("Unknown blindfold mode {}", s)),
+        }
+    }
+}
+
+// how the last move, or a highlighted square in a diagram, is marked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HighlightStyle {
+    // the square's background
+    #[default]
+    Fill,
+    // brackets around the piece in a terminal, a frame around the square in a diagram
+    Outline,
+    Off,
+}
+
+impl FromStr for HighlightStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<HighlightStyle, String> {
+        match s {
+            "fill" => Ok(HighlightStyle::Fill),
+            "outline" => Ok(HighlightStyle::Outline),
+            "off" => Ok(HighlightStyle::Off),
+            _ => Err(format!("Unknown highlight style {}", s)),
+        }
+    }
+}
+
+// the 256 color codes of a board in the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub light: u8,
+    pub dark: u8,
+    pub last_move: u8,
+    pub check: u8,
+    pub hint: u8,
+    pub white_piece: u8,
+    pub black_piece: u8,
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette { light: 180, dark: 137, last_move: 143, check: 160, hint: 71, white_piece: 15, black_piece: 0 }
+    }
+}
+
+pub(crate) fn background(color: u8) -> String {
+    format!("\x1b[48;5;{}m", color)
+}
+
+// bold, so the pieces stand out from the squares
+pub(crate) fn foreground(color: u8) -> String {
+    format!("\x1b[1;38;5;{}m", color)
+}
+
+pub(crate) const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RenderOptions {
+    pub style: Style,
+    // ANSI colors for the squares, the last move and a king in check, in place of the grid
+    pub color: bool,
+    // black at the bottom, for whoever plays black
+    pub flipped: bool,
+    // rank numbers down the side and file letters along the bottom
+    pub coordinates: bool,
+    // the game's moves so far in SAN beside the board
+    pub move_list: bool,
+    // the from and to squares of a suggested move, marked when the board is in color
+    pub hint: Option<(usize, usize)>,
+    pub blindfold: Blindfold,
+    pub palette: Palette,
+    pub highlight: HighlightStyle,
+}
+
+pub fn piece_char(piece: &Piece, style: Style) -> char {
+    let index = match piece.piece_type {
+        PieceType::King => 0,
+        PieceType::Queen => 1,
+        PieceType::Rook => 2,
+        PieceType::Bishop => 3,
+        PieceType::Knight => 4,
+        PieceType::Pawn => 5,
+    };
+    let chars = match (style, piece.color) {
+        (Style::Ascii, Color::White) => ['K', 'Q', 'R', 'B', 'N', 'P'],
+        (Style::Ascii, Color::Black) => ['k', 'q', 'r', 'b', 'n', 'p'],
+        (Style::Unicode, Color::White) => ['♔', '♕', '♖', '♗', '♘', '♙'],
+        (Style::Unicode, Color::Black) | (Style::Figurine, _) => ['♚', '♛', '♜', '♝', '♞', '♟'],
+    };
+    chars[index]
+}
+
+// the piece on a square unless the blindfold hides it
+fn visible_piece(game: &Game, square: usize, blindfold: Blindfold) -> Option<&Piece> {
+    game.piece_at(Square::from_index(square)).filter(|piece| match blindfold {
+        Blindfold::Off => true,
+        Blindfold::Pawns => piece.piece_type == PieceType::Pawn,
+        Blindfold::Hidden => false,
+    })
+}
+
+// a square on a board drawn without color
+fn square_char(game: &Game, square: usize, options: &RenderOptions) -> char {
+    let style = if options.style == Style::Figurine { Style::Unicode } else { options.style };
+    match visible_piece(game, square, options.blindfold) {
+        Some(piece) => piece_char(piece, style),
+        None if options.style == Style::Ascii => '.',
+        None => ' ',
+    }
+}
+
+// the square of the side to move's king when it is in check
+pub(crate) fn checked_king(game: &Game) -> Option<usize> {
+    if !game.is_in_check(game.active_color) {
+        return None;
+    }
+    game.pieces.iter()
+        .find(|piece| piece.piece_type == PieceType::King && piece.color == game.active_color)
+        .map(|piece| bit_scan(piece.position))
+}
+
+fn colored_square(game: &Game, square: usize, options: &RenderOptions, highlights: &[usize], check: Option<usize>) -> String {
+    let palette = options.palette;
+    let last_move = highlights.contains(&square);
+    let square_color = if check == Some(square) {
+        palette.check
+    } else if options.hint.is_some_and(|(from, to)| square == from || square == to) {
+        palette.hint
+    } else if last_move && options.highlight == HighlightStyle::Fill {
+        palette.last_move
+    } else if (square / 8 + square % 8).is_multiple_of(2) {
+        palette.dark
+    } else {
+        palette.light
+    };
+    let piece = visible_piece(game, square, options.blindfold);
+    let piece_color = match piece {
+        Some(piece) if piece.color == Color::White => palette.white_piece,
+        _ => palette.black_piece,
+    };
+    // the background shows an empty square well enough without a dot
+    let glyph = match piece {
+        Some(piece) => piece_char(piece, options.style),
+        None => ' ',
+    };
+    let (open, close) = if last_move && options.highlight == HighlightStyle::Outline { ('[', ']') } else { (' ', ' ') };
+    format!("{}{}{}{}{}{}", background(square_color), foreground(piece_color), open, glyph, close, RESET)
+}
+
+// the squares of each line from the top, a8 first unless the board is flipped
+pub(crate) fn rows(flipped: bool) -> Vec<Vec<usize>> {
+    let rows: Vec<Vec<usize>> = (0..8).rev().map(|rank| (0..8).map(|file| rank * 8 + file).collect()).collect();
+    if !flipped {
+        return rows;
+    }
+    rows.into_iter().rev().map(|row| row.into_iter().rev().collect()).collect()
+}
+
+// the moves played so far in SAN, "1. e4 e5" per line, numbered from the position they started in
+pub fn move_list(game: &Game) -> Vec<String> {
+    let mut start = game.clone();
+    let mut moves = vec![];
+    while let Some(mv) = start.unmake_move() {
+        moves.push(mv);
+    }
+    let mut lines: Vec<String> = vec![];
+    // whether the last line still waits for black's reply
+    let mut open = false;
+    for mv in moves.into_iter().rev() {
+        if mv.is_null() {
+            start.make_null_move();
+            continue;
+        }
+        let san = start.to_san(mv);
+        match start.active_color {
+            Color::White => lines.push(format!("{}. {}", start.fullmove_number, san)),
+            Color::Black if open => *lines.last_mut().unwrap() += &format!(" {}", san),
+            Color::Black => lines.push(format!("{}... {}", start.fullmove_number, san)),
+        }
+        open = start.active_color == Color::White;
+        start.make_move(mv);
+    }
+    lines
+}
+
+// the squares of each line of the board, with a rank label when they have one
+fn board_lines(game: &Game, options: &RenderOptions, rows: &[Vec<usize>]) -> Vec<(Option<usize>, String)> {
+    let rank = |row: &[usize]| Some(row[0] / 8 + 1);
+    let mut lines = vec![];
+    if options.color {
+        let highlights: Vec<usize> = game.history.last()
+            .filter(|undo| !undo.mv.is_null())
+            .map(|undo| vec![undo.mv.from(), undo.mv.to()])
+            .unwrap_or_default();
+        // a checked king would give away where it stands
+        let check = checked_king(game).filter(|_| options.blindfold == Blindfold::Off);
+        for row in rows {
+            let line = row.iter().map(|square| colored_square(game, *square, options, &highlights, check)).collect();
+            lines.push((rank(row), line));
+        }
+        return lines;
+    }
+    match options.style {
+        Style::Ascii => {
+            for row in rows {
+                let squares: Vec<String> = row.iter().map(|square| square_char(game, *square, options).to_string()).collect();
+                lines.push((rank(row), squares.join(" ")));
+            }
+        }
+        Style::Unicode | Style::Figurine => {
+            lines.push((None, format!("┌{}┐", ["───"; 8].join("┬"))));
+            for (i, row) in rows.iter().enumerate() {
+                let squares: Vec<String> = row.iter().map(|square| format!(" {} ", square_char(game, *square, options))).collect();
+                lines.push((rank(row), format!("│{}│", squares.join("│"))));
+                if i < 7 {
+                    lines.push((None, format!("├{}┤", ["───"; 8].join("┼"))));
+                }
+            }
+            lines.push((None, format!("└{}┘", ["───"; 8].join("┴"))));
+        }
+    }
+    lines
+}
+
+// the board with white at the bottom unless flipped, one line per rank, then the
+// file labels and the move list to the right when asked for
+pub fn render(game: &Game, options: &RenderOptions) -> String {
+    let rows = rows(options.flipped);
+    let mut lines: Vec<String> = board_lines(game, options, &rows).into_iter().map(|(rank, line)| {
+        match rank {
+            _ if !options.coordinates => line,
+            Some(rank) => format!("{} {}", rank, line),
+            None => format!("  {}", line),
+        }
+    }).collect();
+    if options.coordinates {
+        let files = rows[7].iter().map(|square| (b'a' + (square % 8) as u8) as char);
+        let labels: String = match (options.color, options.style) {
+            (true, _) => files.map(|file| format!(" {} ", file)).collect(),
+            (false, Style::Ascii) => files.map(|file| format!("{} ", file)).collect(),
+            (false, _) => files.map(|file| format!("  {} ", file)).collect(),
+        };
+        lines.push(format!("  {}", labels.trim_end()));
+    }
+    if options.move_list {
+        // the latest moves when there are more than the board has lines
+        let moves = move_list(game);
+        let skip = moves.len().saturating_sub(lines.len());
+        for (line, moves) in lines.iter_mut().zip(&moves[skip..]) {
+            *line += &format!("   {}", moves);
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+// an svg diagram, size is the width of a square in pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgOptions {
+    pub size: u32,
+    pub flipped: bool,
+    pub coordinates: bool,
+    // squares tinted with a css color, and arrows drawn from one square to another
+    pub highlights: Vec<(Square, String)>,
+    pub arrows: Vec<(Square, Square, String)>,
+    pub pieces: Style,
+    // css colors of the squares
+    pub light: String,
+    pub dark: String,
+    pub highlight: HighlightStyle,
+}
+
+impl Default for SvgOptions {
+    fn default() -> SvgOptions {
+        SvgOptions { size: 45, flipped: false, coordinates: true, highlights: vec![], arrows: vec![], pieces: Style::Figurine,
+            light: SVG_LIGHT.to_string(), dark: SVG_DARK.to_string(), highlight: HighlightStyle::Fill }
+    }
+}
+
+pub const SVG_LIGHT: &str = "#f0d9b5";
+pub const SVG_DARK: &str = "#b58863";
+pub const ARROW_COLOR: &str = "#15781b";
+pub const HIGHLIGHT_COLOR: &str = "#cdd26a";
+
+// the top left corner of a square's cell, in squares from the top left of the board
+pub(crate) fn cell(square: usize, flipped: bool) -> (f64, f64) {
+    let (file, rank) = ((square % 8) as f64, (square / 8) as f64);
+    if flipped { (7.0 - file, rank) } else { (file, 7.0 - rank) }
+}
+
+// the position as an svg document, pieces are drawn with the unicode glyphs so the
+// file stays small and needs nothing besides a font with chess symbols
+pub fn to_svg(game: &Game, options: &SvgOptions) -> String {
+    let size = options.size as f64;
+    let width = size * 8.0;
+    let square_color = |square: usize, inverted: bool| {
+        if (square / 8 + square % 8).is_multiple_of(2) != inverted { &options.dark } else { &options.light }
+    };
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {w} {w}\" width=\"{w}\" height=\"{w}\">\n", w = width);
+    for square in 0..64 {
+        let (x, y) = cell(square, options.flipped);
+        svg += &format!("<rect x=\"{}\" y=\"{}\" width=\"{s}\" height=\"{s}\" fill=\"{}\"/>\n", x * size, y * size,
+            square_color(square, false), s = size);
+    }
+    for (square, color) in &options.highlights {
+        let (x, y) = cell(square.index(), options.flipped);
+        match options.highlight {
+            HighlightStyle::Fill => svg += &format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{s}\" height=\"{s}\" fill=\"{}\" fill-opacity=\"0.6\"/>\n",
+                x * size, y * size, color, s = size),
+            // the stroke is centered on the edge, so the frame is inset by half its width
+            HighlightStyle::Outline => {
+                let stroke = size / 15.0;
+                svg += &format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{s}\" height=\"{s}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"/>\n",
+                    x * size + stroke / 2.0, y * size + stroke / 2.0, color, stroke, s = size - stroke);
+            }
+            HighlightStyle::Off => {}
+        }
+    }
+    if options.coordinates {
+        // in the corners of the edge squares, in the other square color to stay readable
+        let font = size / 5.0;
+        for (i, square) in rows(options.flipped)[7].iter().enumerate() {
+            svg += &format!("<text x=\"{}\" y=\"{}\" font-size=\"{}\" font-family=\"sans-serif\" fill=\"{}\">{}</text>\n",
+                (i as f64 + 1.0) * size - font * 0.8, width - font * 0.3, font, square_color(*square, true),
+                (b'a' + (square % 8) as u8) as char);
+        }
+        for (i, row) in rows(options.flipped).iter().enumerate() {
+            svg += &format!("<text x=\"{}\" y=\"{}\" font-size=\"{}\" font-family=\"sans-serif\" fill=\"{}\">{}</text>\n",
+                font * 0.3, i as f64 * size + font, font, square_color(row[0], true), row[0] / 8 + 1);
+        }
+    }
+    for piece in &game.pieces {
+        let (x, y) = cell(bit_scan(piece.position), options.flipped);
+        // white figurines and letters are filled white with a dark outline, the unicode
+        // outlines are glyphs of their own
+        let glyph = piece_char(piece, options.pieces);
+        let fill = if piece.color == Color::White && options.pieces != Style::Unicode { "#ffffff" } else { "#000000" };
+        svg += &format!(
+            "<text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"{}\" stroke=\"#000000\" stroke-width=\"{}\">{}</text>\n",
+            (x + 0.5) * size, (y + 0.5) * size, size * 0.8, fill, size / 45.0, glyph);
+    }
+    for (from, to, color) in &options.arrows {
+        let (fx, fy) = cell(from.index(), options.flipped);
+        let (tx, ty) = cell(to.index(), options.flipped);
+        let (fx, fy, tx, ty) = ((fx + 0.5) * size, (fy + 0.5) * size, (tx + 0.5) * size, (ty + 0.5) * size);
+        let length = ((tx - fx).powi(2) + (ty - fy).powi(2)).sqrt();
+        if length == 0.0 {
+            continue;
+        }
+        // the shaft stops where the head starts so the round cap does not poke through the tip
+        let (dx, dy) = ((tx - fx) / length, (ty - fy) / length);
+        let (head, half_width) = (size * 0.4, size * 0.25);
+        let (bx, by) = (tx - dx * head, ty - dy * head);
+        svg += &format!(
+            "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" stroke-width=\"{:.1}\" stroke-linecap=\"round\" opacity=\"0.8\"/>\n",
+            fx, fy, bx, by, color, size * 0.16);
+        svg += &format!("<polygon points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\" fill=\"{}\" opacity=\"0.8\"/>\n",
+            tx, ty, bx - dy * half_width, by + dx * half_width, bx + dy * half_width, by - dx * half_width, color);
+    }
+    svg + "</svg>\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_letters_or_glyphs() {
+        let game = Game::read_FEN("4k3/8/8/8/8/8/4P3/4K2R w K - 0 1");
+        let ascii = render(&game, &RenderOptions::default());
+        let lines: Vec<&str> = ascii.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], ". . . . k . . .");
+        assert_eq!(lines[7], ". . . . K . . R");
+
+        let unicode = render(&game, &RenderOptions { style: Style::Unicode, ..RenderOptions::default() });
+        let lines: Vec<&str> = unicode.lines().collect();
+        assert_eq!(lines.len(), 17);
+        assert_eq!(lines[0], "┌───┬───┬───┬───┬───┬───┬───┬───┐");
+        assert_eq!(lines[1], "│   │   │   │   │ ♚ │   │   │   │");
+        assert_eq!(lines[13], "│   │   │   │   │ ♙ │   │   │   │");
+        assert_eq!(lines[15], "│   │   │   │   │ ♔ │   │   │ ♖ │");
+        assert_eq!(lines[16], "└───┴───┴───┴───┴───┴───┴───┴───┘");
+        assert_eq!("unicode".parse(), Ok(Style::Unicode));
+
+        // without color the figurines fall back to the outlines
+        assert_eq!(render(&game, &RenderOptions { style: Style::Figurine, ..RenderOptions::default() }), unicode);
+    }
+
+    #[test]
+    fn flips_the_board_for_black() {
+        let game = Game::read_FEN("4k3/8/8/8/8/8/4P3/4K2R w K - 0 1");
+        let text = render(&game, &RenderOptions { flipped: true, ..RenderOptions::default() });
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "R . . K . . . .");
+        assert_eq!(lines[1], ". . . P . . . .");
+        assert_eq!(lines[7], ". . . k . . . .");
+    }
+
+    #[test]
+    fn labels_the_board_and_lists_the_moves() {
+        let mut game = Game::initialize();
+        for uci in ["e2e4", "e7e5", "g1f3"] {
+            game.make_move(crate::movegen::Move::from_uci(uci, &game).unwrap());
+        }
+        assert_eq!(move_list(&game), ["1. e4 e5", "2. Nf3"]);
+        let options = RenderOptions { coordinates: true, move_list: true, ..RenderOptions::default() };
+        let text = render(&game, &options);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "8 r n b q k b n r   1. e4 e5");
+        assert_eq!(lines[1], "7 p p p p . p p p   2. Nf3");
+        assert_eq!(lines[7], "1 R N B Q K B . R");
+        assert_eq!(lines[8], "  a b c d e f g h");
+
+        let text = render(&game, &RenderOptions { style: Style::Unicode, coordinates: true, flipped: true, ..RenderOptions::default() });
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "  ┌───┬───┬───┬───┬───┬───┬───┬───┐");
+        assert_eq!(lines[1], "1 │ ♖ │   │ ♗ │ ♔ │ ♕ │ ♗ │ ♘ │ ♖ │");
+        assert_eq!(lines[17], "    h   g   f   e   d   c   b   a");
+
+        // a game that starts with black to move
+        let mut game = Game::read_FEN("4k3/8/8/8/8/8/8/4K3 b - - 0 30");
+        for uci in ["e8d8", "e1d1", "d8c8"] {
+            game.make_move(crate::movegen::Move::from_uci(uci, &game).unwrap());
+        }
+        assert_eq!(move_list(&game), ["30... Kd8", "31. Kd1 Kc8"]);
+    }
+
+    #[test]
+    fn draws_svg_diagrams() {
+        let game = Game::read_FEN("4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+        let options = SvgOptions {
+            highlights: vec![("e1".parse().unwrap(), HIGHLIGHT_COLOR.to_string())],
+            arrows: vec![("h1".parse().unwrap(), "h8".parse().unwrap(), ARROW_COLOR.to_string())],
+            ..SvgOptions::default()
+        };
+        let svg = to_svg(&game, &options);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 360 360\""), "{}", svg);
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<rect ").count(), 65);
+        // a1 is dark in the bottom left corner, and the white king is on e1
+        assert!(svg.contains("<rect x=\"0\" y=\"315\" width=\"45\" height=\"45\" fill=\"#b58863\"/>"), "{}", svg);
+        assert!(svg.contains("<rect x=\"180\" y=\"315\" width=\"45\" height=\"45\" fill=\"#cdd26a\" fill-opacity=\"0.6\"/>"));
+        assert!(svg.contains("x=\"202.5\" y=\"337.5\" font-size=\"36\" text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"#ffffff\""));
+        assert!(svg.contains("<line x1=\"337.5\" y1=\"337.5\" x2=\"337.5\" y2=\"40.5\""), "{}", svg);
+        assert!(svg.contains("<polygon points=\"337.5,22.5 "));
+        assert_eq!(svg.matches("♚").count(), 2);
+
+        let flipped = to_svg(&game, &SvgOptions { flipped: true, coordinates: false, ..SvgOptions::default() });
+        assert!(flipped.contains("x=\"157.5\" y=\"22.5\""), "{}", flipped);
+        assert!(!flipped.contains("sans-serif"));
+
+        let themed = to_svg(&game, &SvgOptions { pieces: Style::Ascii, dark: "#777777".to_string(), highlight: HighlightStyle::Outline, ..options });
+        assert!(themed.contains("fill=\"#777777\"/>") && !themed.contains(SVG_DARK), "{}", themed);
+        assert!(themed.contains("fill=\"none\" stroke=\"#cdd26a\" stroke-width=\"3\""), "{}", themed);
+        assert!(themed.contains(">K</text>") && themed.contains(">R</text>"), "{}", themed);
+    }
+
+    #[test]
+    fn hides_pieces_behind_a_blindfold() {
+        let game = Game::initialize();
+        let pawns = render(&game, &RenderOptions { blindfold: Blindfold::Pawns, ..RenderOptions::default() });
+        assert_eq!(pawns.lines().next(), Some(". . . . . . . ."));
+        assert_eq!(pawns.lines().nth(1), Some("p p p p p p p p"));
+        let hidden = render(&game, &RenderOptions { blindfold: Blindfold::Hidden, ..RenderOptions::default() });
+        assert!(hidden.chars().all(|c| c == '.' || c == ' ' || c == '\n'), "{}", hidden);
+    }
+
+    #[test]
+    fn colors_the_last_move_and_a_checked_king() {
+        let mut game = Game::read_FEN("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        let options = RenderOptions { color: true, ..RenderOptions::default() };
+        let palette = options.palette;
+        let (light, dark, last_move, check) =
+            (background(palette.light), background(palette.dark), background(palette.last_move), background(palette.check));
+        let (white, black) = (foreground(palette.white_piece), foreground(palette.black_piece));
+        let text = render(&game, &options);
+        let lines: Vec<&str> = text.lines().collect();
+        // a1 is dark, b1 light, and nothing stands out before a move
+        assert!(lines[7].starts_with(&format!("{}{} R {}{}{}   {}", dark, white, RESET, light, black, RESET)));
+        assert!(!text.contains(&last_move) && !text.contains(&check));
+
+        game.make_move(crate::movegen::Move::from_uci("a1a8", &game).unwrap());
+        let text = render(&game, &options);
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].starts_with(&format!("{}{} R {}", last_move, white, RESET)));
+        assert!(lines[0].contains(&format!("{}{} k {}", check, black, RESET)));
+        assert!(lines[7].starts_with(&format!("{}{}   {}", last_move, black, RESET)));
+
+        // framed instead of filled, or not marked at all
+        let outlined = render(&game, &RenderOptions { highlight: HighlightStyle::Outline, style: Style::Figurine, ..options });
+        assert!(outlined.lines().next().unwrap().starts_with(&format!("{}{}[♜]{}", light, white, RESET)), "{}", outlined);
+        let off = render(&game, &RenderOptions { highlight: HighlightStyle::Off, ..options });
+        assert!(!off.contains(&last_move) && !off.contains("[R]"));
+    }
+}
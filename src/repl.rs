@@ -0,0 +1,234 @@
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
+use crate::eval::{evaluate_detailed, EvalTerms};
+use crate::game::{Color, Game};
+use crate::makemove::divide;
+use crate::search::{SearchOptions, Searcher, MAX_DEPTH};
+use crate::timeman::TimeLimits;
+use crate::uci::format_score;
+
+const HELP: &str = "\
+position [startpos | fen] <fen> [moves <move>...]  set up a position
+move <move>...                                    play moves, in SAN or uci
+moves                                             list the legal moves
+undo                                              take back the last move
+go [depth <n>] [movetime <ms>]                    search for the best move
+eval                                              the static evaluation term by term
+perft <depth>                                     count the leaf nodes below each move
+fen | board                                       show the position
+quit";
+
+type Term = fn(&EvalTerms) -> (i32, i32);
+
+// a terminal session for poking at positions, one command per line
+pub struct Repl<W: Write> {
+    game: Game,
+    searcher: Searcher,
+    out: W,
+}
+
+impl<W: Write> Repl<W> {
+    pub fn new(out: W) -> Repl<W> {
+        Repl { game: Game::initialize(), searcher: Searcher::new(), out }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    // false once the user is done, errors are printed and the session goes on
+    pub fn handle(&mut self, line: &str) -> io::Result<bool> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((command, args)) = words.split_first() else {
+            return Ok(true);
+        };
+        let result = match *command {
+            "position" => self.position(args),
+            "move" => self.play(args),
+            "moves" => self.moves(),
+            "undo" => self.undo(),
+            "go" => self.go(args),
+            "eval" => self.eval(),
+            "perft" => self.perft(args),
+            "fen" => Ok(writeln!(self.out, "{}", self.game.to_fen())?),
+            "board" | "d" => Ok(write!(self.out, "{}", self.game)?),
+            "help" => Ok(writeln!(self.out, "{}", HELP)?),
+            "quit" | "exit" => return Ok(false),
+            _ => Err(format!("Unknown command {}, try help", command)),
+        };
+        if let Err(err) = result {
+            writeln!(self.out, "error: {}", err)?;
+        }
+        self.out.flush()?;
+        Ok(true)
+    }
+
+    fn position(&mut self, args: &[&str]) -> Result<(), String> {
+        let moves_at = args.iter().position(|word| *word == "moves").unwrap_or(args.len());
+        let fen = match args.first() {
+            None | Some(&"startpos") => None,
+            Some(&"fen") => Some(&args[1..moves_at]),
+            Some(_) => Some(&args[..moves_at]),
+        };
+        let mut game = match fen {
+            Some(fen) => Game::try_from_fen(&fen.join(" ")).map_err(|err| err.to_string())?,
+            None => Game::initialize(),
+        };
+        for text in args.iter().skip(moves_at + 1) {
+            let mv = game.parse_move(text)?;
+            game.make_move(mv);
+        }
+        self.game = game;
+        Ok(())
+    }
+
+    fn play(&mut self, args: &[&str]) -> Result<(), String> {
+        if args.is_empty() {
+            return Err("Usage: move <move>...".to_string());
+        }
+        // all of them or none
+        let mut game = self.game.clone();
+        for text in args {
+            let mv = game.parse_move(text)?;
+            game.make_move(mv);
+        }
+        self.game = game;
+        Ok(())
+    }
+
+    fn moves(&mut self) -> Result<(), String> {
+        let moves: Vec<String> = self.game.legal_moves().iter().map(|mv| self.game.to_san(*mv)).collect();
+        writeln!(self.out, "{} moves: {}", moves.len(), moves.join(" ")).map_err(|err| err.to_string())
+    }
+
+    fn undo(&mut self) -> Result<(), String> {
+        self.game.unmake_move().map(|_| ()).ok_or_else(|| "No move to take back".to_string())
+    }
+
+    fn go(&mut self, args: &[&str]) -> Result<(), String> {
+        let mut options = SearchOptions::default();
+        let mut words = args.iter();
+        while let Some(word) = words.next() {
+            let value = words.next().ok_or_else(|| format!("Missing value after {}", word))?;
+            let number: u64 = value.parse().map_err(|_| format!("Expected a number after {}, got '{}'", word, value))?;
+            match *word {
+                "depth" => options.depth = (number as usize).clamp(1, MAX_DEPTH),
+                "movetime" => {
+                    options.time = Some(TimeLimits::fixed(Duration::from_millis(number)));
+                    if !args.contains(&"depth") {
+                        options.depth = MAX_DEPTH;
+                    }
+                }
+                _ => return Err(format!("Unknown go parameter: {}", word)),
+            }
+        }
+        let start = Instant::now();
+        let result = self.searcher.search_with(&mut self.game, &options);
+        let Some(best) = result.best_move else {
+            return Err("No legal moves".to_string());
+        };
+        writeln!(self.out, "bestmove {} score {} nodes {} time {}ms pv {}", self.game.to_san(best),
+            format_score(result.score), result.nodes, start.elapsed().as_millis(), result.pv_san(&self.game))
+            .map_err(|err| err.to_string())
+    }
+
+    // every term as white minus black in centipawns, tapered by the phase
+    fn eval(&mut self) -> Result<(), String> {
+        let eval = evaluate_detailed(&self.game);
+        let terms: [(&str, Term); 5] = [
+            ("material", |terms| terms.material),
+            ("pst", |terms| terms.pst),
+            ("pawns", |terms| terms.pawns),
+            ("king safety", |terms| terms.king_safety),
+            ("mobility", |terms| terms.mobility),
+        ];
+        let mut text = String::new();
+        for (name, term) in terms {
+            text += &format!("{:<12} {:>6}\n", name, eval.net(term));
+        }
+        let side = match self.game.active_color {
+            Color::White => "white",
+            Color::Black => "black",
+        };
+        text += &format!("{:<12} {:>6} (phase {}, {} to move: {})", "total", eval.total, eval.phase, side,
+            self.game.evaluate());
+        writeln!(self.out, "{}", text).map_err(|err| err.to_string())
+    }
+
+    fn perft(&mut self, args: &[&str]) -> Result<(), String> {
+        let depth: usize = args.first().and_then(|depth| depth.parse().ok()).ok_or("Usage: perft <depth>")?;
+        let start = Instant::now();
+        let mut lines = vec![];
+        let mut total = 0;
+        if depth == 0 {
+            total = 1;
+        } else {
+            for (mv, nodes) in divide(&mut self.game, depth) {
+                lines.push(format!("{}: {}", mv.to_uci(), nodes));
+                total += nodes;
+            }
+        }
+        lines.push(format!("nodes {} time {}ms", total, start.elapsed().as_millis()));
+        writeln!(self.out, "{}", lines.join("\n")).map_err(|err| err.to_string())
+    }
+}
+
+// reads commands until quit or the end of the input, `prompt` is for a terminal and off when piped
+pub fn run(input: impl BufRead, output: impl Write, prompt: bool) -> io::Result<()> {
+    let mut repl = Repl::new(output);
+    let mut lines = input.lines();
+    loop {
+        if prompt {
+            write!(repl.out, "> ")?;
+            repl.out.flush()?;
+        }
+        let Some(line) = lines.next() else {
+            return Ok(());
+        };
+        if !repl.handle(&line?)? {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(lines: &[&str]) -> (String, Game) {
+        let mut repl = Repl::new(vec![]);
+        for line in lines {
+            assert!(repl.handle(line).unwrap());
+        }
+        (String::from_utf8(repl.out.clone()).unwrap(), repl.game.clone())
+    }
+
+    #[test]
+    fn plays_and_takes_back_moves() {
+        let (out, game) = session(&["move e4 e5 Nf3", "undo", "fen"]);
+        assert_eq!(out, "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2\n");
+        assert_eq!(game.history.len(), 2);
+        // a bad move anywhere leaves the position alone
+        let (out, game) = session(&["move e4 e4", "undo", "moves", "bogus"]);
+        assert!(out.starts_with("error: "), "{}", out);
+        assert!(out.contains("error: No move to take back\n20 moves: "), "{}", out);
+        assert!(out.ends_with("error: Unknown command bogus, try help\n"), "{}", out);
+        assert_eq!(game.to_fen(), Game::initialize().to_fen());
+        let (_, game) = session(&["position 4k3/8/8/8/8/8/8/4K2R w K - 0 1 moves e1g1"]);
+        assert_eq!(game.to_fen(), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+    }
+
+    #[test]
+    fn searches_counts_and_evaluates() {
+        let (out, _) = session(&["position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "go depth 3"]);
+        assert!(out.starts_with("bestmove Ra8# score mate 1 "), "{}", out);
+        let (out, _) = session(&["perft 2"]);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 21);
+        assert!(lines.contains(&"g1f3: 20"));
+        assert!(lines[20].starts_with("nodes 400 "), "{}", out);
+        let (out, _) = session(&["eval"]);
+        assert!(out.starts_with("material          0\n"), "{}", out);
+        assert!(out.contains("total"), "{}", out);
+    }
+}
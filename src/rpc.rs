@@ -0,0 +1,202 @@
+use std::io::{self, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use serde_json::{json, Value};
+use crate::game::Game;
+use crate::http::{read_request, write_response};
+use crate::search::{mate_in, SearchOptions, Searcher, MAX_DEPTH};
+use crate::timeman::TimeLimits;
+
+// analysis depth when the request names none, and the deepest a request may ask for
+pub const DEFAULT_DEPTH: usize = 6;
+pub const MAX_REQUEST_DEPTH: usize = 20;
+
+// the error codes of the JSON-RPC 2.0 spec
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+fn position(params: &Value) -> Result<Game, String> {
+    match params["fen"].as_str() {
+        Some(fen) => {
+            let game = Game::try_from_fen(fen).map_err(|err| err.to_string())?;
+            game.validate().map_err(|err| err.to_string())?;
+            Ok(game)
+        }
+        None => Ok(Game::initialize()),
+    }
+}
+
+// {"cp": 25} or {"mate": -3}, from the side to move's point of view
+pub fn score_json(score: i32) -> Value {
+    match mate_in(score) {
+        Some(moves) => json!({ "mate": moves }),
+        None => json!({ "cp": score }),
+    }
+}
+
+// analyze {fen, depth, movetime}: the best move with its score and line, movetime in milliseconds
+pub fn analyze(searcher: &mut Searcher, params: &Value) -> Result<Value, String> {
+    let mut game = position(params)?;
+    let depth = match &params["depth"] {
+        Value::Null => DEFAULT_DEPTH,
+        depth => depth.as_u64().filter(|depth| *depth >= 1).ok_or("depth must be a positive integer")? as usize,
+    };
+    let mut options = SearchOptions { depth: depth.min(MAX_REQUEST_DEPTH), ..SearchOptions::default() };
+    if let Some(millis) = params["movetime"].as_u64() {
+        options.time = Some(TimeLimits::fixed(Duration::from_millis(millis)));
+        if params["depth"].is_null() {
+            options.depth = MAX_DEPTH;
+        }
+    }
+    let result = searcher.search_with(&mut game, &options);
+    let pv: Vec<String> = result.pv.iter().map(|mv| mv.to_uci()).collect();
+    Ok(json!({
+        "bestmove": result.best_move.map(|mv| mv.to_uci()),
+        "score": score_json(result.score),
+        "pv": pv,
+        "san": result.pv_san(&game),
+        "nodes": result.nodes,
+    }))
+}
+
+// legal_moves {fen}: every legal move in uci and SAN
+pub fn legal_moves(params: &Value) -> Result<Value, String> {
+    let game = position(params)?;
+    let moves = game.legal_moves();
+    let uci: Vec<String> = moves.iter().map(|mv| mv.to_uci()).collect();
+    let san: Vec<String> = moves.iter().map(|mv| game.to_san(*mv)).collect();
+    Ok(json!({ "moves": uci, "san": san }))
+}
+
+fn call(searcher: &Mutex<Searcher>, method: &str, params: &Value) -> Option<Result<Value, String>> {
+    match method {
+        "analyze" => Some(analyze(&mut searcher.lock().unwrap(), params)),
+        "legal_moves" => Some(legal_moves(params)),
+        _ => None,
+    }
+}
+
+fn rpc_error(id: Value, code: i32, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+// answers one JSON-RPC 2.0 request, notifications get no reply
+pub fn handle_rpc(searcher: &Mutex<Searcher>, body: &str) -> Option<Value> {
+    let request: Value = match serde_json::from_str(body) {
+        Ok(request) => request,
+        Err(err) => return Some(rpc_error(Value::Null, PARSE_ERROR, &err.to_string())),
+    };
+    let id = request.get("id").cloned();
+    let (Some(method), Some("2.0")) = (request["method"].as_str(), request["jsonrpc"].as_str()) else {
+        return Some(rpc_error(id.unwrap_or(Value::Null), INVALID_REQUEST, "Invalid request"));
+    };
+    let result = call(searcher, method, &request["params"]);
+    let id = id?;
+    Some(match result {
+        Some(Ok(result)) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Some(Err(err)) => rpc_error(id, INVALID_PARAMS, &err),
+        None => rpc_error(id, METHOD_NOT_FOUND, &format!("Unknown method {}", method)),
+    })
+}
+
+// POST /rpc takes JSON-RPC, POST /analyze and /legal_moves take the params as the body
+fn route(searcher: &Mutex<Searcher>, method: &str, path: &str, body: &str) -> (&'static str, Value) {
+    if method != "POST" {
+        return ("405 Method Not Allowed", json!({ "error": "Use POST" }));
+    }
+    if path == "/rpc" {
+        return ("200 OK", handle_rpc(searcher, body).unwrap_or(Value::Null));
+    }
+    let params = if body.trim().is_empty() { Ok(json!({})) } else { serde_json::from_str(body) };
+    let params: Value = match params {
+        Ok(params) => params,
+        Err(err) => return ("400 Bad Request", json!({ "error": err.to_string() })),
+    };
+    match call(searcher, path.trim_start_matches('/'), &params) {
+        Some(Ok(result)) => ("200 OK", result),
+        Some(Err(err)) => ("400 Bad Request", json!({ "error": err })),
+        None => ("404 Not Found", json!({ "error": format!("No endpoint {}", path) })),
+    }
+}
+
+fn handle_connection(stream: TcpStream, searcher: &Mutex<Searcher>) -> io::Result<()> {
+    let request = read_request(&mut BufReader::new(stream.try_clone()?))?;
+    let (status, body) = route(searcher, &request.head.method, &request.head.path, &request.body);
+    write_response(&mut &stream, status, "application/json", &body.to_string())
+}
+
+// answers requests on their own threads, searches share one hash table and run one at a time
+pub fn serve(listener: TcpListener) -> io::Result<()> {
+    let searcher = Arc::new(Mutex::new(Searcher::new()));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("could not accept a connection: {}", err);
+                continue;
+            }
+        };
+        let searcher = searcher.clone();
+        thread::spawn(move || {
+            if let Err(err) = handle_connection(stream, &searcher) {
+                eprintln!("request failed: {}", err);
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn answers_json_rpc() {
+        let searcher = Mutex::new(Searcher::new());
+        let reply = handle_rpc(&searcher, r#"{"jsonrpc":"2.0","id":1,"method":"analyze",
+            "params":{"fen":"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1","depth":3}}"#).unwrap();
+        assert_eq!(reply["id"], 1);
+        assert_eq!(reply["result"]["bestmove"], "a1a8");
+        assert_eq!(reply["result"]["score"], json!({ "mate": 1 }));
+        assert_eq!(reply["result"]["san"], "Ra8#");
+
+        let reply = handle_rpc(&searcher, r#"{"jsonrpc":"2.0","id":"x","method":"legal_moves","params":{}}"#).unwrap();
+        assert_eq!(reply["result"]["moves"].as_array().unwrap().len(), 20);
+        let reply = handle_rpc(&searcher, r#"{"jsonrpc":"2.0","id":2,"method":"analyze","params":{"fen":"bad"}}"#);
+        assert_eq!(reply.unwrap()["error"]["code"], INVALID_PARAMS);
+        let reply = handle_rpc(&searcher, r#"{"jsonrpc":"2.0","id":4,"method":"legal_moves","params":{"fen":"8/8/8/8/8/8/8/4K3 w - - 0 1"}}"#).unwrap();
+        assert_eq!(reply["error"], json!({ "code": INVALID_PARAMS, "message": "Black has no king" }));
+        let reply = handle_rpc(&searcher, r#"{"jsonrpc":"2.0","id":3,"method":"perft"}"#);
+        assert_eq!(reply.unwrap()["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(handle_rpc(&searcher, "{").unwrap()["error"]["code"], PARSE_ERROR);
+        assert_eq!(handle_rpc(&searcher, r#"{"jsonrpc":"2.0","method":"legal_moves"}"#), None);
+    }
+
+    #[test]
+    fn serves_rest_endpoints() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener));
+        let body = r#"{"fen":"4k3/8/8/8/8/8/8/4K2R w K - 0 1"}"#;
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "POST /legal_moves HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        let (_, json) = response.split_once("\r\n\r\n").unwrap();
+        let json: Value = serde_json::from_str(json).unwrap();
+        assert!(json["san"].as_array().unwrap().contains(&json!("Rh8+")), "{}", json);
+
+        let searcher = Mutex::new(Searcher::new());
+        assert_eq!(route(&searcher, "POST", "/nothing", "").0, "404 Not Found");
+        assert_eq!(route(&searcher, "GET", "/analyze", "").0, "405 Method Not Allowed");
+        assert_eq!(route(&searcher, "POST", "/analyze", r#"{"depth":0}"#).0, "400 Bad Request");
+        let (status, body) = route(&searcher, "POST", "/analyze", r#"{"fen":"4k3/8/8/8/8/8/8/4K2P w - - 0 1"}"#);
+        assert_eq!((status, body["error"].as_str()), ("400 Bad Request", Some("Pawn on the back rank at h1")));
+    }
+}
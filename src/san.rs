@@ -0,0 +1,206 @@
+use crate::game::*;
+use crate::movegen::Move;
+use crate::square::Square;
+
+fn piece_from_char(ch: char) -> Option<PieceType> {
+    match ch {
+        'N' => Some(PieceType::Knight),
+        'B' => Some(PieceType::Bishop),
+        'R' => Some(PieceType::Rook),
+        'Q' => Some(PieceType::Queen),
+        'K' => Some(PieceType::King),
+        _ => None,
+    }
+}
+
+fn piece_char(piece_type: PieceType) -> char {
+    match piece_type {
+        PieceType::Pawn => 'P',
+        PieceType::Knight => 'N',
+        PieceType::Bishop => 'B',
+        PieceType::Rook => 'R',
+        PieceType::Queen => 'Q',
+        PieceType::King => 'K',
+    }
+}
+
+impl Game {
+    // standard algebraic notation of a legal move, with + or # when it checks or mates
+    pub fn to_san(&self, mv: Move) -> String {
+        let piece_type = self.piece_at(mv.from_square()).unwrap().piece_type;
+        let mut san = String::new();
+
+        if let Some(castling) = self.castling(mv) {
+            san.push_str(if castling.king_to > castling.rook_to { "O-O" } else { "O-O-O" });
+        } else {
+            let capture = self.piece_at(mv.to_square()).is_some()
+                || (piece_type == PieceType::Pawn && mv.from() % 8 != mv.to() % 8);
+            let from = Square::ALL[mv.from()];
+
+            if piece_type == PieceType::Pawn {
+                if capture {
+                    san.push(from.file().to_char());
+                }
+            } else {
+                san.push(piece_char(piece_type));
+                // other pieces of the same type that could go to the same square
+                let rivals: Vec<Move> = self.legal_moves().into_iter()
+                    .filter(|other| other.to() == mv.to() && other.from() != mv.from()
+                        && self.piece_at(other.from_square()).unwrap().piece_type == piece_type)
+                    .collect();
+                if !rivals.is_empty() {
+                    let same_file = rivals.iter().any(|other| other.from() % 8 == mv.from() % 8);
+                    let same_rank = rivals.iter().any(|other| other.from() / 8 == mv.from() / 8);
+                    if !same_file {
+                        san.push(from.file().to_char());
+                    } else if !same_rank {
+                        san.push(from.rank().to_char());
+                    } else {
+                        san.push_str(&from.to_string());
+                    }
+                }
+            }
+            if capture {
+                san.push('x');
+            }
+            san.push_str(&Square::ALL[mv.to()].to_string());
+            if let Some(piece) = mv.promotion() {
+                san.push('=');
+                san.push(piece_char(piece));
+            }
+        }
+
+        if self.gives_check(mv) {
+            let mut after = self.clone();
+            after.make_move(mv);
+            san.push(if after.legal_moves().is_empty() { '#' } else { '+' });
+        }
+        san
+    }
+
+    // reads standard algebraic notation like Nbd7, exd6, e8=Q+ or O-O-O, the move
+    // has to be legal and unambiguous in the position
+    pub fn parse_san(&self, san: &str) -> Result<Move, String> {
+        let text = san.trim_end_matches(['+', '#', '!', '?']);
+        let legal = self.legal_moves();
+
+        let castle = match text {
+            "O-O" | "0-0" => Some(true),
+            "O-O-O" | "0-0-0" => Some(false),
+            _ => None,
+        };
+        if let Some(kingside) = castle {
+            return legal.into_iter()
+                .find(|mv| mv.is_castle() && (mv.to() > mv.from()) == kingside)
+                .ok_or_else(|| format!("Illegal move {}", san));
+        }
+
+        let mut chars: Vec<char> = text.chars().collect();
+        let piece_type = match chars.first().copied().and_then(piece_from_char) {
+            Some(piece_type) => {
+                chars.remove(0);
+                piece_type
+            }
+            None => PieceType::Pawn,
+        };
+
+        // promotion is written e8=Q, some tools leave out the =
+        let mut promotion = None;
+        if let Some(piece) = chars.last().copied().and_then(piece_from_char) {
+            if piece_type == PieceType::Pawn && piece != PieceType::King {
+                promotion = Some(piece);
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+            }
+        }
+
+        if chars.len() < 2 {
+            return Err(format!("Invalid move {}", san));
+        }
+        let to: Square = chars[chars.len() - 2..].iter().collect::<String>().parse()?;
+        let mut from_file = None;
+        let mut from_rank = None;
+        for ch in &chars[..chars.len() - 2] {
+            match ch {
+                'a'..='h' => from_file = Some(*ch as usize - 'a' as usize),
+                '1'..='8' => from_rank = Some(*ch as usize - '1' as usize),
+                'x' => (),
+                _ => return Err(format!("Invalid move {}", san)),
+            }
+        }
+
+        let mut candidates = legal.into_iter().filter(|mv| {
+            mv.to() == to.index()
+                && mv.promotion() == promotion
+                && !mv.is_castle()
+                && self.piece_at(mv.from_square()).unwrap().piece_type == piece_type
+                && from_file.is_none_or(|file| mv.from() % 8 == file)
+                && from_rank.is_none_or(|rank| mv.from() / 8 == rank)
+        });
+        match (candidates.next(), candidates.next()) {
+            (Some(mv), None) => Ok(mv),
+            (Some(_), Some(_)) => Err(format!("Ambiguous move {}", san)),
+            (None, _) => Err(format!("Illegal move {}", san)),
+        }
+    }
+
+    // a move the way a user types it, uci like e2e4 or san like Nf3, a mistake is reported as san
+    pub fn parse_move(&self, text: &str) -> Result<Move, String> {
+        Move::from_uci(text, self).or_else(|_| self.parse_san(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_san_moves() {
+        let game = Game::initialize();
+        assert_eq!(game.parse_san("e4").unwrap().to_string(), "e2e4");
+        assert_eq!(game.parse_san("Nf3").unwrap().to_string(), "g1f3");
+        assert!(game.parse_san("e5").is_err());
+        assert!(game.parse_san("Ke2").is_err());
+
+        let game = Game::read_FEN("r3k2r/1P6/8/3pP3/8/8/8/R3K1NR w KQkq d6 0 1");
+        assert_eq!(game.parse_san("exd6").unwrap().to_string(), "e5d6");
+        assert_eq!(game.parse_san("bxa8=Q+").unwrap().to_string(), "b7a8q");
+        assert_eq!(game.parse_san("b8N").unwrap().to_string(), "b7b8n");
+        assert_eq!(game.parse_san("O-O-O").unwrap().to_string(), "e1c1");
+        assert!(game.parse_san("O-O").is_err());
+
+        let game = Game::read_FEN("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1");
+        assert!(game.parse_san("Nd2").unwrap_err().starts_with("Ambiguous"));
+        assert_eq!(game.parse_san("Nbd2").unwrap().to_string(), "b1d2");
+        assert_eq!(game.parse_san("N3d2!?").unwrap().to_string(), "f3d2");
+        assert!(game.parse_san("Nh3").is_err());
+
+        assert_eq!(game.parse_move("b1d2").unwrap().to_string(), "b1d2");
+        assert_eq!(game.parse_move("N3d2").unwrap().to_string(), "f3d2");
+        assert!(game.parse_move("Nd2").unwrap_err().starts_with("Ambiguous"));
+    }
+
+    #[test]
+    fn writes_san_moves() {
+        let game = Game::read_FEN("r3k2r/1P6/8/3pP3/8/5N2/8/RN2K2R w KQkq d6 0 1");
+        let san = |uci: &str| game.to_san(uci.parse().unwrap());
+        assert_eq!(san("e5d6"), "exd6");
+        assert_eq!(san("b7a8q"), "bxa8=Q+");
+        assert_eq!(san("b7b8n"), "b8=N");
+        assert_eq!(san("e1g1"), "O-O");
+        assert_eq!(san("b1d2"), "Nbd2");
+        assert_eq!(san("f3d4"), "Nd4");
+        assert_eq!(san("h1h8"), "Rxh8+");
+
+        let game = Game::read_FEN("6k1/5ppp/8/8/8/8/R7/R5K1 w - - 0 1");
+        assert_eq!(game.to_san("a2a8".parse().unwrap()), "Ra8#");
+
+        // every legal move reads back from its own notation
+        let game = Game::read_FEN("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        for mv in game.legal_moves() {
+            assert_eq!(game.parse_san(&game.to_san(mv)), Ok(mv), "{}", mv);
+        }
+    }
+}
@@ -0,0 +1,962 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use crate::game::{Color, Game, PieceType};
+use crate::movegen::Move;
+use crate::nnue::{snapshot, Network, NnueState};
+use crate::pawns::PawnTable;
+use crate::square::{Rank, Square};
+use crate::timeman::{Instant, TimeLimits, TimeManager};
+use crate::tt::{Bound, TranspositionTable};
+
+// scores at or beyond this are mates, the distance to the mate is taken off so shorter mates score higher
+pub const MATE: i32 = 100_000;
+pub const INFINITY: i32 = MATE + 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    // None when the side to move has no legal moves
+    pub best_move: Option<Move>,
+    // centipawns from the side to move's point of view
+    pub score: i32,
+    pub nodes: u64,
+    // the line both sides are expected to play, starting with best_move
+    pub pv: Vec<Move>,
+    // the best SearchOptions::multi_pv lines, best first, the first is the one above
+    pub lines: Vec<PvLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PvLine {
+    pub score: i32,
+    pub pv: Vec<Move>,
+}
+
+impl SearchResult {
+    // the reply the search expects, what to ponder on
+    pub fn ponder_move(&self) -> Option<Move> {
+        self.pv.get(1).copied()
+    }
+
+    pub fn pv_uci(&self) -> String {
+        self.pv.iter().map(|mv| mv.to_uci()).collect::<Vec<_>>().join(" ")
+    }
+
+    // `game` is the position the search started from
+    pub fn pv_san(&self, game: &Game) -> String {
+        let mut game = game.clone();
+        let mut moves = Vec::new();
+        for mv in &self.pv {
+            moves.push(game.to_san(*mv));
+            game.make_move(*mv);
+        }
+        moves.join(" ")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub depth: usize,
+    // half width of the aspiration window in centipawns, None searches every iteration with a full window
+    pub aspiration_window: Option<i32>,
+    pub futility_pruning: bool,
+    pub delta_pruning: bool,
+    // search one ply deeper after a check, and after a passed pawn reaches the seventh rank
+    pub check_extensions: bool,
+    pub pawn_push_extensions: bool,
+    // how many of the best moves get a line of their own in the result
+    pub multi_pv: usize,
+    // how many of those lines go to the listener, all of them when None
+    pub reported_pv: Option<usize>,
+    // stop on time as well as at `depth`, set depth to MAX_DEPTH for a purely timed search
+    pub time: Option<TimeLimits>,
+    // stop after this many nodes, the deepest finished iteration counts
+    pub nodes: Option<u64>,
+    // centipawns a draw is worth less than equality to the side to move, negative to seek draws
+    pub contempt: i32,
+    // stop as soon as a mate in this many moves or fewer is found
+    pub mate: Option<usize>,
+}
+
+impl Default for SearchOptions {
+    fn default() -> SearchOptions {
+        SearchOptions { depth: 4, aspiration_window: Some(50), futility_pruning: true, delta_pruning: true,
+            check_extensions: true, pawn_push_extensions: false, multi_pv: 1, reported_pv: None, time: None, nodes: None,
+            contempt: 0, mate: None }
+    }
+}
+
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE - 1000
+}
+
+// the moves to a mate score's mate, negative when the side to move is the one mated,
+// every protocol and display counts them this way
+pub fn mate_in(score: i32) -> Option<i32> {
+    if !is_mate_score(score) {
+        return None;
+    }
+    Some(if score > 0 { (MATE - score + 1) / 2 } else { -(MATE + score) / 2 })
+}
+
+// deepest iteration a search without a depth limit, like pondering, goes to
+pub const MAX_DEPTH: usize = 64;
+
+// how many nodes pass between looks at the stop flag
+const CHECK_INTERVAL: u64 = 1024;
+
+// shared with the thread that runs the search so it can be stopped or told the ponder move was played
+#[derive(Debug, Clone, Default)]
+pub struct SearchControl {
+    stop: Arc<AtomicBool>,
+    pondering: Arc<AtomicBool>,
+}
+
+impl SearchControl {
+    pub fn new() -> SearchControl {
+        SearchControl::default()
+    }
+
+    // the search returns the deepest iteration it finished
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    // the opponent played the predicted move, from now on the normal limits apply
+    pub fn ponderhit(&self) {
+        self.pondering.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_pondering(&self) -> bool {
+        self.pondering.load(Ordering::Relaxed)
+    }
+
+    // readies it for a new search, the searcher does this itself unless told not to with search_prepared
+    pub fn reset(&self, pondering: bool) {
+        self.stop.store(false, Ordering::Relaxed);
+        self.pondering.store(pondering, Ordering::Relaxed);
+    }
+}
+
+// progress of a running search, sent after every finished iteration for each of its lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchInfo {
+    pub depth: usize,
+    // the longest line looked at in the iteration, quiescence included
+    pub seldepth: usize,
+    pub score: i32,
+    // 1 for the best line, higher for the others with multi_pv
+    pub multipv: usize,
+    pub nodes: u64,
+    pub nps: u64,
+    pub time: Duration,
+    pub pv: Vec<Move>,
+    // how full the transposition table is in permille
+    pub hashfull: u32,
+}
+
+// anything that wants to follow the search, closures taking a &SearchInfo are listeners too
+pub trait SearchListener: Send {
+    fn info(&mut self, info: &SearchInfo);
+}
+
+impl<F: FnMut(&SearchInfo) + Send> SearchListener for F {
+    fn info(&mut self, info: &SearchInfo) {
+        self(info)
+    }
+}
+
+// the static evaluation the search uses, picked at runtime
+#[derive(Clone, Default)]
+pub enum Evaluator {
+    #[default]
+    Classical,
+    Nnue(Arc<Network>),
+}
+
+// captures are tried before every quiet move
+const CAPTURE_SCORE: i32 = 1 << 24;
+
+// how much a quiet move or a capture is allowed to gain beyond material before it is pruned
+const FUTILITY_MARGIN: i32 = 200;
+const DELTA_MARGIN: i32 = 200;
+
+pub struct Searcher {
+    options: SearchOptions,
+    evaluator: Evaluator,
+    // accumulators for the line being searched when the evaluator is a network
+    nnue: Option<NnueState>,
+    pawns: PawnTable,
+    // shared with the helper threads, which fill it for the main one
+    tt: Arc<TranspositionTable>,
+    // one main thread and the rest helpers searching the same position, lazy smp
+    threads: usize,
+    // the side to move at the root, contempt is from its point of view
+    root_color: Color,
+    control: SearchControl,
+    // set once the stop flag was seen, every node returns straight away after that
+    aborted: bool,
+    timer: Option<TimeManager>,
+    // whether the last look at the control saw a ponder search, the clock starts on ponderhit
+    pondering: bool,
+    // triangular principal variation table, pv[ply] is the best line found from that ply on
+    pv: Vec<Vec<Move>>,
+    // depth of the current iteration, extensions stop once a line is twice as long
+    root_depth: usize,
+    seldepth: usize,
+    nodes: u64,
+    started: Instant,
+    listener: Option<Box<dyn SearchListener>>,
+    // how often a quiet move from one square to another caused a cutoff, weighted by depth
+    history: Box<[[i32; 64]; 64]>,
+}
+
+impl Default for Searcher {
+    fn default() -> Searcher {
+        Searcher::with_table(Arc::new(TranspositionTable::default()))
+    }
+}
+
+impl Searcher {
+    pub fn new() -> Searcher {
+        Searcher::default()
+    }
+
+    pub fn with_control(control: SearchControl) -> Searcher {
+        Searcher { control, ..Searcher::default() }
+    }
+
+    fn with_table(tt: Arc<TranspositionTable>) -> Searcher {
+        Searcher {
+            options: SearchOptions::default(), evaluator: Evaluator::Classical, nnue: None, pawns: PawnTable::default(), tt, threads: 1, root_color: Color::White, control: SearchControl::new(), aborted: false, timer: None, pondering: false, pv: vec![], root_depth: 0, seldepth: 0, nodes: 0,
+            started: Instant::now(), listener: None,
+            history: Box::new([[0; 64]; 64]),
+        }
+    }
+
+    // a helper for a lazy smp search, the same table and evaluator but a control of its own
+    fn helper(&self, control: SearchControl) -> Searcher {
+        Searcher { evaluator: self.evaluator.clone(), control, ..Searcher::with_table(self.tt.clone()) }
+    }
+
+    // threads beyond the first search the same position and share what they find through the table
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn set_evaluator(&mut self, evaluator: Evaluator) {
+        self.evaluator = evaluator;
+    }
+
+    // receives a SearchInfo for every line of every finished iteration
+    pub fn set_listener(&mut self, listener: impl SearchListener + 'static) {
+        self.listener = Some(Box::new(listener));
+    }
+
+    pub fn clear_listener(&mut self) {
+        self.listener = None;
+    }
+
+    // the search borrows the searcher mutably, so the table is never resized while it runs
+    pub fn set_hash_mb(&mut self, mb: usize) {
+        match Arc::get_mut(&mut self.tt) {
+            Some(tt) => tt.resize(mb),
+            None => self.tt = Arc::new(TranspositionTable::new(mb)),
+        }
+    }
+
+    // forgets everything learned from earlier positions, for a new game
+    pub fn clear_hash(&mut self) {
+        self.tt.clear();
+        self.pawns.clear();
+        self.history.iter_mut().for_each(|row| row.fill(0));
+    }
+
+    pub fn hashfull(&self) -> u32 {
+        self.tt.hashfull()
+    }
+
+    pub fn control(&self) -> &SearchControl {
+        &self.control
+    }
+
+    // thinks on the position after `predicted` on the opponent's time, without a depth limit
+    // until ponderhit and then as a normal search, or until stopped
+    pub fn ponder(&mut self, game: &mut Game, predicted: Move, options: &SearchOptions) -> SearchResult {
+        game.make_move(predicted);
+        let result = self.ponder_moves(game, options, &[]);
+        game.unmake_move();
+        result
+    }
+
+    // the same on a position the predicted move was already played in, as uci's go ponder sends it
+    pub fn ponder_moves(&mut self, game: &mut Game, options: &SearchOptions, root_moves: &[Move]) -> SearchResult {
+        self.control.reset(true);
+        self.run(game, options, root_moves)
+    }
+
+    pub fn search(&mut self, game: &mut Game, depth: usize) -> SearchResult {
+        self.search_with(game, &SearchOptions { depth, ..SearchOptions::default() })
+    }
+
+    // iterative deepening up to options.depth, every iteration after the first starts with a
+    // window around the previous score and only widens it when the score falls outside
+    pub fn search_with(&mut self, game: &mut Game, options: &SearchOptions) -> SearchResult {
+        self.control.reset(false);
+        self.run(game, options, &[])
+    }
+
+    // the same, only looking at the root moves in `root_moves`, all of them when it is empty
+    pub fn search_moves(&mut self, game: &mut Game, options: &SearchOptions, root_moves: &[Move]) -> SearchResult {
+        self.control.reset(false);
+        self.run(game, options, root_moves)
+    }
+
+    // searches with the control as the caller left it, so a stop or ponderhit sent before the
+    // search got going on another thread is not lost
+    pub fn search_prepared(&mut self, game: &mut Game, options: &SearchOptions, root_moves: &[Move]) -> SearchResult {
+        self.run(game, options, root_moves)
+    }
+
+    fn run(&mut self, game: &mut Game, options: &SearchOptions, root_moves: &[Move]) -> SearchResult {
+        self.tt.new_search();
+        if self.threads == 1 {
+            return self.iterate(game, options, root_moves);
+        }
+        // the helpers go on without limits until the main thread is done, only its result counts
+        let helpers = SearchControl::new();
+        let helper_options = SearchOptions { depth: MAX_DEPTH, multi_pv: 1, time: None, nodes: None, mate: None, ..*options };
+        thread::scope(|scope| {
+            let running: Vec<_> = (1..self.threads).map(|_| {
+                let (mut helper, mut game) = (self.helper(helpers.clone()), game.clone());
+                scope.spawn(move || helper.iterate(&mut game, &helper_options, root_moves).nodes)
+            }).collect();
+            let mut result = self.iterate(game, options, root_moves);
+            helpers.stop();
+            result.nodes += running.into_iter().map(|helper| helper.join().unwrap()).sum::<u64>();
+            result
+        })
+    }
+
+    fn iterate(&mut self, game: &mut Game, options: &SearchOptions, root_moves: &[Move]) -> SearchResult {
+        self.options = *options;
+        self.root_color = game.active_color;
+        self.nnue = match &self.evaluator {
+            Evaluator::Classical => None,
+            Evaluator::Nnue(network) => Some(NnueState::new(network.clone(), game)),
+        };
+        self.aborted = false;
+        self.timer = options.time.map(TimeManager::new);
+        self.pondering = self.control.is_pondering();
+        self.nodes = 0;
+        self.started = Instant::now();
+        self.age_history();
+        let mut moves = game.legal_moves();
+        if !root_moves.is_empty() {
+            moves.retain(|mv| root_moves.contains(mv));
+        }
+        if moves.is_empty() {
+            return SearchResult { best_move: None, score: self.terminal_score(game, 0), nodes: 0, pv: vec![], lines: vec![] };
+        }
+        self.order_moves(game, &mut moves);
+
+        let mut lines: Vec<PvLine> = vec![];
+        for depth in 1..=MAX_DEPTH {
+            if depth > options.depth.max(1) && !self.control.is_pondering() {
+                break;
+            }
+            self.root_depth = depth;
+            self.seldepth = 0;
+            // each further line is the best among the moves not already leading one
+            let mut remaining = moves.clone();
+            let mut iteration = vec![];
+            while iteration.len() < options.multi_pv.max(1) && !remaining.is_empty() {
+                let previous = lines.get(iteration.len()).map(|line| line.score);
+                let (mv, line) = self.search_line(game, &remaining, depth, previous);
+                if self.aborted {
+                    break;
+                }
+                remaining.retain(|other| *other != mv);
+                iteration.push(line);
+            }
+            // an unfinished iteration is thrown away
+            if self.aborted {
+                break;
+            }
+            lines = iteration;
+            self.report(depth, &lines);
+            if options.mate.is_some_and(|moves| lines[0].score >= MATE - (2 * moves as i32 - 1)) {
+                break;
+            }
+            if self.iteration_out_of_time(lines[0].pv[0]) {
+                break;
+            }
+            // the lines found so far lead the next iteration, best first
+            moves.sort_by_key(|mv| lines.iter().position(|line| line.pv[0] == *mv).unwrap_or(lines.len()));
+        }
+        let best = &lines[0];
+        SearchResult { best_move: Some(best.pv[0]), score: best.score, nodes: self.nodes, pv: best.pv.clone(), lines }
+    }
+
+    // the best move among `moves` with its line, searched with a window around the score
+    // from the previous iteration when there is one
+    fn search_line(&mut self, game: &mut Game, moves: &[Move], depth: usize, previous: Option<i32>) -> (Move, PvLine) {
+        let mut window = previous.and_then(|score| self.options.aspiration_window.filter(|_| !is_mate_score(score)));
+        let previous = previous.unwrap_or(0);
+        loop {
+            let (alpha, beta) = match window {
+                Some(width) => (previous - width, previous + width),
+                None => (-INFINITY, INFINITY),
+            };
+            let (mv, score) = self.search_root(game, moves, depth, alpha, beta);
+            if self.aborted {
+                return (mv, PvLine { score, pv: vec![] });
+            }
+            if score <= alpha || score >= beta {
+                window = window.map(|width| width * 4).filter(|width| *width < MATE);
+                continue;
+            }
+            return (mv, PvLine { score, pv: self.pv[0].clone() });
+        }
+    }
+
+    // negamax to a fixed depth, the alpha-beta cutoffs only skip moves that cannot change the result
+    fn search_root(&mut self, game: &mut Game, moves: &[Move], depth: usize, mut alpha: i32, beta: i32) -> (Move, i32) {
+        let mut best_move = moves[0];
+        self.clear_pv(0);
+        for mv in moves.iter().copied() {
+            self.make(game, mv);
+            let extension = self.extension(game, mv, 0);
+            let score = -self.negamax(game, depth - 1 + extension, 1, -beta, -alpha);
+            self.unmake(game);
+            if self.aborted {
+                break;
+            }
+            if score >= beta {
+                return (mv, beta);
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = mv;
+                self.update_pv(0, mv);
+            }
+        }
+        (best_move, alpha)
+    }
+
+    fn negamax(&mut self, game: &mut Game, depth: usize, ply: i32, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply as usize);
+        if self.should_stop() {
+            return 0;
+        }
+        self.clear_pv(ply as usize);
+        // a position seen before in the line is as good as a threefold repetition
+        if game.halfmove_clock >= 100 || game.is_insufficient_material() || game.repetition_count() > 1 {
+            return self.draw_score(game);
+        }
+        let hit = self.tt.probe(game.hash, ply).filter(|_| depth > 0);
+        if let Some(hit) = hit.filter(|hit| hit.depth >= depth) {
+            // an exact score inside the window is left alone so the principal variation stays whole
+            if hit.score >= beta && hit.bound != Bound::Upper {
+                return beta;
+            }
+            if hit.score <= alpha && hit.bound != Bound::Lower {
+                return alpha;
+            }
+        }
+        let moves = game.legal_moves();
+        if moves.is_empty() {
+            return self.terminal_score(game, ply);
+        }
+        if depth == 0 {
+            return self.quiescence(game, ply, alpha, beta);
+        }
+        let mut moves = moves;
+        self.order_moves(game, &mut moves);
+        // the best move from the last time this position was searched goes first
+        if let Some(index) = hit.and_then(|hit| hit.best_move).and_then(|best| moves.iter().position(|mv| *mv == best)) {
+            moves[..=index].rotate_right(1);
+        }
+        let original_alpha = alpha;
+        let mut best_move = None;
+        // one ply from the horizon a quiet move that cannot lift the static score anywhere near
+        // alpha is not worth searching, unless it checks or there is a mate to find or escape
+        let futile = depth == 1 && self.options.futility_pruning && !is_mate_score(alpha)
+            && !game.is_in_check(game.active_color) && self.evaluate(game) + FUTILITY_MARGIN <= alpha;
+        for mv in moves {
+            if futile && !mv.is_capture() && mv.promotion().is_none() && !game.gives_check(mv) {
+                continue;
+            }
+            self.make(game, mv);
+            let extension = self.extension(game, mv, ply);
+            let score = -self.negamax(game, depth - 1 + extension, ply + 1, -beta, -alpha);
+            self.unmake(game);
+            if self.aborted {
+                return 0;
+            }
+            if score >= beta {
+                if !mv.is_capture() && mv.promotion().is_none() {
+                    self.history[mv.from()][mv.to()] += (depth * depth) as i32;
+                }
+                self.tt.store(game.hash, depth, beta, Bound::Lower, Some(mv), ply);
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = Some(mv);
+                self.update_pv(ply as usize, mv);
+            }
+        }
+        let bound = if alpha > original_alpha { Bound::Exact } else { Bound::Upper };
+        self.tt.store(game.hash, depth, alpha, bound, best_move, ply);
+        alpha
+    }
+
+    // only captures and promotions are searched past the horizon, the side to move can stand pat
+    // on the static evaluation instead of making a losing capture. in check every evasion is tried
+    fn quiescence(&mut self, game: &mut Game, ply: i32, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply as usize);
+        if self.should_stop() {
+            return 0;
+        }
+        self.clear_pv(ply as usize);
+        let in_check = game.is_in_check(game.active_color);
+        let mut stand_pat = None;
+        let moves = if in_check {
+            let moves = game.legal_moves();
+            if moves.is_empty() {
+                return -MATE + ply;
+            }
+            moves
+        } else {
+            let score = self.evaluate(game);
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+            stand_pat = Some(score);
+            game.capture_moves()
+        };
+        let mut moves = moves;
+        self.order_moves(game, &mut moves);
+        for mv in moves {
+            // even winning the captured piece for free would leave us short of alpha
+            if let (Some(score), true) = (stand_pat, self.options.delta_pruning) {
+                let victim = game.piece_at(mv.to_square()).map_or(PieceType::Pawn.value(), |piece| piece.piece_type.value());
+                if mv.promotion().is_none() && score + victim + DELTA_MARGIN < alpha {
+                    continue;
+                }
+            }
+            self.make(game, mv);
+            let score = -self.quiescence(game, ply + 1, -beta, -alpha);
+            self.unmake(game);
+            if score >= beta {
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+                self.update_pv(ply as usize, mv);
+            }
+        }
+        alpha
+    }
+
+    fn report(&mut self, depth: usize, lines: &[PvLine]) {
+        let Some(listener) = &mut self.listener else {
+            return;
+        };
+        let time = self.started.elapsed();
+        let nps = (self.nodes as u128 * 1000 / time.as_millis().max(1)) as u64;
+        for (index, line) in lines.iter().take(self.options.reported_pv.unwrap_or(lines.len())).enumerate() {
+            listener.info(&SearchInfo {
+                depth, seldepth: self.seldepth.max(depth), score: line.score, multipv: index + 1, nodes: self.nodes,
+                nps, time, pv: line.pv.clone(), hashfull: self.tt.hashfull(),
+            });
+        }
+    }
+
+    fn make(&mut self, game: &mut Game, mv: Move) {
+        match &mut self.nnue {
+            Some(state) => {
+                let before = snapshot(game);
+                game.make_move(mv);
+                state.push(&before, game);
+            }
+            None => game.make_move(mv),
+        }
+    }
+
+    fn unmake(&mut self, game: &mut Game) {
+        game.unmake_move();
+        if let Some(state) = &mut self.nnue {
+            state.pop();
+        }
+    }
+
+    fn evaluate(&mut self, game: &Game) -> i32 {
+        match &self.nnue {
+            Some(state) => state.evaluate(game),
+            None => game.evaluate_cached(&mut self.pawns),
+        }
+    }
+
+    // the first iteration always finishes so there is a move to return
+    fn should_stop(&mut self) -> bool {
+        if !self.aborted && self.root_depth > 1 && self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            self.aborted = self.control.is_stopped() || (!self.still_pondering()
+                && (self.timer.as_ref().is_some_and(|timer| timer.hard_limit_reached())
+                    || self.options.nodes.is_some_and(|limit| self.nodes >= limit)));
+        }
+        self.aborted
+    }
+
+    fn iteration_out_of_time(&mut self, best_move: Move) -> bool {
+        let pondering = self.still_pondering();
+        match &mut self.timer {
+            Some(timer) => timer.stop_after_iteration(best_move) && !pondering,
+            None => false,
+        }
+    }
+
+    // time spent pondering is the opponent's, so the clock restarts on ponderhit
+    fn still_pondering(&mut self) -> bool {
+        let pondering = self.control.is_pondering();
+        if self.pondering && !pondering {
+            if let Some(timer) = &mut self.timer {
+                timer.restart();
+            }
+        }
+        self.pondering = pondering;
+        pondering
+    }
+
+    fn clear_pv(&mut self, ply: usize) {
+        if self.pv.len() < ply + 2 {
+            self.pv.resize(ply + 2, Vec::new());
+        }
+        self.pv[ply].clear();
+    }
+
+    // `mv` followed by the line the child node found
+    fn update_pv(&mut self, ply: usize, mv: Move) {
+        let (line, rest) = self.pv.split_at_mut(ply + 1);
+        line[ply].clear();
+        line[ply].push(mv);
+        line[ply].extend_from_slice(&rest[0]);
+    }
+
+    // called after `mv` was made, so forcing moves are not cut off at the horizon
+    fn extension(&self, game: &Game, mv: Move, ply: i32) -> usize {
+        if ply as usize >= 2 * self.root_depth {
+            return 0;
+        }
+        if self.options.check_extensions && game.is_in_check(game.active_color) {
+            return 1;
+        }
+        let mover = game.active_color.opponent();
+        let pushed_to_seventh = game.piece_at(mv.to_square()).is_some_and(|piece| piece.piece_type == PieceType::Pawn)
+            && Square::ALL[mv.to()].rank().relative_to(mover) == Rank::Seventh;
+        if self.options.pawn_push_extensions && pushed_to_seventh && game.is_passed_pawn(mv.to_square()) {
+            return 1;
+        }
+        0
+    }
+
+    // most valuable victim by least valuable attacker first, quiet moves by their history
+    fn order_moves(&self, game: &Game, moves: &mut [Move]) {
+        moves.sort_by_cached_key(|mv| std::cmp::Reverse(self.move_score(game, *mv)));
+    }
+
+    fn move_score(&self, game: &Game, mv: Move) -> i32 {
+        let promotion = mv.promotion().map_or(0, |piece| piece.value());
+        if mv.is_capture() || promotion > 0 {
+            let victim = game.piece_at(mv.to_square()).map_or(PieceType::Pawn.value(), |piece| piece.piece_type.value());
+            let attacker = game.piece_at(mv.from_square()).map_or(0, |piece| piece.piece_type.value());
+            CAPTURE_SCORE + 10 * (victim + promotion) - attacker / 10
+        } else {
+            self.history[mv.from()][mv.to()]
+        }
+    }
+
+    // older searches count for less so the table follows the game as it moves on
+    fn age_history(&mut self) {
+        for row in self.history.iter_mut() {
+            for score in row.iter_mut() {
+                *score /= 2;
+            }
+        }
+    }
+
+    // checkmated or stalemated, `ply` moves from the root
+    fn terminal_score(&self, game: &Game, ply: i32) -> i32 {
+        if game.is_in_check(game.active_color) {
+            -MATE + ply
+        } else {
+            self.draw_score(game)
+        }
+    }
+
+    // with contempt the side the search is for counts a draw as that much worse than equal
+    fn draw_score(&self, game: &Game) -> i32 {
+        if game.active_color == self.root_color {
+            -self.options.contempt
+        } else {
+            self.options.contempt
+        }
+    }
+}
+
+impl Game {
+    pub fn search(&self, depth: usize) -> SearchResult {
+        Searcher::new().search(&mut self.clone(), depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_mate_in_one() {
+        let game = Game::read_FEN("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let result = game.search(2);
+        assert_eq!(result.best_move.map(|mv| mv.to_uci()), Some("a1a8".to_string()));
+        assert_eq!(result.score, MATE - 1);
+        assert!(is_mate_score(result.score));
+    }
+
+    #[test]
+    fn wins_material() {
+        // the queen on e5 hangs to the knight
+        let game = Game::read_FEN("4k3/8/8/4q3/8/5N2/P7/4K3 w - - 0 1");
+        let result = game.search(2);
+        assert_eq!(result.best_move.map(|mv| mv.to_uci()), Some("f3e5".to_string()));
+        assert!(result.score > 0);
+    }
+
+    #[test]
+    fn sees_past_the_horizon() {
+        // at depth 1 without quiescence the defended pawn looks free
+        let game = Game::read_FEN("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1");
+        let result = game.search(1);
+        assert_ne!(result.best_move.map(|mv| mv.to_uci()), Some("d1d5".to_string()));
+        assert!(result.score > 0);
+    }
+
+    #[test]
+    fn history_rewards_cutoffs_and_ages() {
+        let mut game = Game::read_FEN("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+        let mut searcher = Searcher::new();
+        let result = searcher.search(&mut game, 3);
+        assert_eq!(game.to_fen(), "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+        let total: i32 = searcher.history.iter().flatten().sum();
+        assert!(total > 0);
+        // ordering must not change the result of a fixed depth search
+        assert_eq!(result.score, Searcher::new().search(&mut game, 3).score);
+        searcher.age_history();
+        assert!(searcher.history.iter().flatten().sum::<i32>() <= total / 2);
+    }
+
+    #[test]
+    fn aspiration_windows_do_not_change_the_result() {
+        let game = Game::read_FEN("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        let narrow = SearchOptions { depth: 3, aspiration_window: Some(10), ..SearchOptions::default() };
+        let full = SearchOptions { depth: 3, aspiration_window: None, ..SearchOptions::default() };
+        let with_window = Searcher::new().search_with(&mut game.clone(), &narrow);
+        let without = Searcher::new().search_with(&mut game.clone(), &full);
+        assert_eq!(with_window.score, without.score);
+    }
+
+    #[test]
+    fn pruning_can_be_switched_off() {
+        let game = Game::read_FEN("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4");
+        let pruned = SearchOptions { depth: 3, ..SearchOptions::default() };
+        let plain = SearchOptions { futility_pruning: false, delta_pruning: false, ..pruned };
+        let with_pruning = Searcher::new().search_with(&mut game.clone(), &pruned);
+        let without = Searcher::new().search_with(&mut game.clone(), &plain);
+        // scholar's mate is still found, with fewer nodes
+        assert_eq!(with_pruning.best_move.map(|mv| mv.to_uci()), Some("h5f7".to_string()));
+        assert_eq!(with_pruning.best_move, without.best_move);
+        assert!(with_pruning.nodes < without.nodes);
+    }
+
+    #[test]
+    fn check_extensions_see_mates_past_the_horizon() {
+        // Rb7+ drives the king to the back rank and Ra8 mates, three plies from a depth 2 search
+        let game = Game::read_FEN("8/4k3/R7/8/8/8/8/1R5K w - - 0 1");
+        let extended = SearchOptions { depth: 2, ..SearchOptions::default() };
+        let plain = SearchOptions { check_extensions: false, ..extended };
+        assert!(is_mate_score(Searcher::new().search_with(&mut game.clone(), &extended).score));
+        assert!(!is_mate_score(Searcher::new().search_with(&mut game.clone(), &plain).score));
+    }
+
+    #[test]
+    fn returns_the_principal_variation() {
+        let game = Game::read_FEN("8/4k3/R7/8/8/8/8/1R5K w - - 0 1");
+        let result = game.search(3);
+        assert_eq!(result.pv.len(), 3);
+        assert_eq!(result.pv[0], result.best_move.unwrap());
+        assert!(result.pv_uci().starts_with("b1b7 "));
+        assert!(result.pv_san(&game).starts_with("Rb7+ "));
+        assert!(result.pv_san(&game).ends_with("Ra8#"));
+    }
+
+    #[test]
+    fn multi_pv_ranks_the_best_moves() {
+        // two ways to win the queen, everything else keeps the material level
+        let game = Game::read_FEN("4k3/8/8/4q3/8/3N1N2/P7/4K3 w - - 0 1");
+        let options = SearchOptions { depth: 2, multi_pv: 3, ..SearchOptions::default() };
+        let result = Searcher::new().search_with(&mut game.clone(), &options);
+        assert_eq!(result.lines.len(), 3);
+        assert_eq!(result.lines[0].pv, result.pv);
+        let mut captures: Vec<_> = result.lines[..2].iter().map(|line| line.pv[0].to_uci()).collect();
+        captures.sort();
+        assert_eq!(captures, ["d3e5", "f3e5"]);
+        assert!(result.lines[1].score > 500 && result.lines[2].score < 500);
+        assert!(result.lines.windows(2).all(|pair| pair[0].score >= pair[1].score));
+    }
+
+    #[test]
+    fn ponders_until_stopped() {
+        let mut game = Game::read_FEN("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+        let result = game.search(2);
+        let predicted = result.ponder_move().unwrap();
+        let control = SearchControl::new();
+        let remote = control.clone();
+        let stopper = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            assert!(remote.is_pondering());
+            remote.stop();
+        });
+        let mut searcher = Searcher::with_control(control);
+        let options = SearchOptions { depth: 1, ..SearchOptions::default() };
+        let pondered = searcher.ponder(&mut game, predicted, &options);
+        stopper.join().unwrap();
+        // searched past the depth limit while pondering and left the position as it was
+        assert!(pondered.best_move.is_some());
+        assert_eq!(game.to_fen(), "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+        assert!(searcher.control().is_stopped());
+    }
+
+    #[test]
+    fn ponderhit_falls_back_to_the_depth_limit() {
+        let mut game = Game::initialize();
+        let control = SearchControl::new();
+        let mut searcher = Searcher::with_control(control.clone());
+        let options = SearchOptions { depth: 2, ..SearchOptions::default() };
+        let e4 = Move::from_uci("e2e4", &game).unwrap();
+        let remote = control.clone();
+        let hit = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            remote.ponderhit();
+        });
+        let result = searcher.ponder(&mut game, e4, &options);
+        hit.join().unwrap();
+        assert!(!control.is_stopped());
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn stops_on_time() {
+        let mut game = Game::initialize();
+        let limits = TimeLimits::fixed(std::time::Duration::from_millis(100));
+        let options = SearchOptions { depth: MAX_DEPTH, time: Some(limits), ..SearchOptions::default() };
+        let start = std::time::Instant::now();
+        let result = Searcher::new().search_with(&mut game, &options);
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn helper_threads_share_the_search() {
+        let mut game = Game::read_FEN("4k3/8/8/4q3/8/5N2/P7/4K3 w - - 0 1");
+        let mut searcher = Searcher::new();
+        searcher.set_threads(3);
+        let result = searcher.search(&mut game, 4);
+        assert_eq!(result.best_move.map(|mv| mv.to_uci()), Some("f3e5".to_string()));
+        // the helpers have no limits of their own, they stop with the main thread
+        let mut game = Game::initialize();
+        let options = SearchOptions { depth: MAX_DEPTH, time: Some(TimeLimits::fixed(std::time::Duration::from_millis(100))),
+            ..SearchOptions::default() };
+        let start = std::time::Instant::now();
+        assert!(searcher.search_with(&mut game, &options).best_move.is_some());
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+    }
+
+    #[test]
+    fn contempt_shifts_draw_scores() {
+        let stalemate = Game::read_FEN("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        let seek = SearchOptions { depth: 1, contempt: -50, ..SearchOptions::default() };
+        let avoid = SearchOptions { depth: 1, contempt: 50, ..SearchOptions::default() };
+        assert_eq!(Searcher::new().search_with(&mut stalemate.clone(), &seek).score, 50);
+        assert_eq!(Searcher::new().search_with(&mut stalemate.clone(), &avoid).score, -50);
+
+        // a repetition inside the search is a draw, worth the opposite to the opponent
+        let mut game = Game::initialize();
+        for uci in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+            game.make_move(Move::from_uci(uci, &game).unwrap());
+        }
+        assert_eq!(game.repetition_count(), 2);
+        let mut searcher = Searcher::new();
+        searcher.options = avoid;
+        searcher.root_color = Color::Black;
+        assert_eq!(searcher.negamax(&mut game, 3, 1, -INFINITY, INFINITY), 50);
+    }
+
+    #[test]
+    fn searches_with_a_network() {
+        let network = Arc::new(Network::from_bytes(&crate::nnue::tests::test_network()).unwrap());
+        let mut searcher = Searcher::new();
+        searcher.set_evaluator(Evaluator::Nnue(network));
+        let mut game = Game::read_FEN("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let result = searcher.search_with(&mut game, &SearchOptions { depth: 2, ..SearchOptions::default() });
+        assert_eq!(result.best_move.map(|mv| mv.to_uci()), Some("a1a8".to_string()));
+        assert_eq!(searcher.nnue.as_ref().map(|state| state.depth()), Some(1));
+    }
+
+    #[test]
+    fn scores_finished_games() {
+        let mated = Game::read_FEN("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1");
+        assert_eq!(mated.search(3), SearchResult { best_move: None, score: -MATE, nodes: 0, pv: vec![], lines: vec![] });
+        let stalemate = Game::read_FEN("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        assert_eq!(stalemate.search(3).score, 0);
+    }
+
+    #[test]
+    fn reports_every_iteration() {
+        use std::sync::Mutex;
+        let infos = Arc::new(Mutex::new(vec![]));
+        let mut searcher = Searcher::new();
+        let sink = infos.clone();
+        searcher.set_listener(move |info: &SearchInfo| sink.lock().unwrap().push(info.clone()));
+        let mut game = Game::initialize();
+        let result = searcher.search_with(&mut game, &SearchOptions { depth: 3, multi_pv: 2, ..SearchOptions::default() });
+        let infos = infos.lock().unwrap();
+        let depths: Vec<_> = infos.iter().map(|info| (info.depth, info.multipv)).collect();
+        assert_eq!(depths, [(1, 1), (1, 2), (2, 1), (2, 2), (3, 1), (3, 2)]);
+        assert!(infos.iter().all(|info| info.seldepth >= info.depth && info.nodes > 0));
+        let last = &infos[4];
+        assert_eq!((last.score, &last.pv, last.nodes), (result.score, &result.pv, result.nodes));
+    }
+
+    #[test]
+    fn reuses_and_clears_the_hash_table() {
+        let mut searcher = Searcher::new();
+        searcher.set_hash_mb(1);
+        let mut game = Game::read_FEN("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        let first = searcher.search(&mut game, 4);
+        assert!(searcher.hashfull() > 0);
+        let again = searcher.search(&mut game, 4);
+        assert!(again.nodes < first.nodes);
+        searcher.clear_hash();
+        assert_eq!(searcher.hashfull(), 0);
+        assert_eq!(searcher.search(&mut game, 4), first);
+    }
+}
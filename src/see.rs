@@ -0,0 +1,108 @@
+use crate::game::*;
+use crate::movegen::Move;
+use crate::square::Square;
+use crate::utils::*;
+
+impl Game {
+    // static exchange evaluation: the material the side to move wins (or loses, if negative)
+    // on the destination square when both sides keep recapturing with their least valuable attacker
+    pub fn see(&self, mv: Move) -> i32 {
+        let (from, to) = (mv.from(), mv.to());
+        let to_bit = 1u64 << to;
+        let mover = self.piece_at(mv.from_square()).unwrap().piece_type;
+
+        let mut occupied = self.occupied() & !(1u64 << from);
+        let mut gain = vec![match self.piece_at(mv.to_square()) {
+            Some(piece) => piece.piece_type.value(),
+            None if mover == PieceType::Pawn && self.en_passant == Some(to_bit) => {
+                occupied &= match self.active_color {
+                    Color::White => !(to_bit >> 8),
+                    Color::Black => !(to_bit << 8),
+                };
+                PieceType::Pawn.value()
+            }
+            None => 0,
+        }];
+        let mut on_square = mover;
+        if let Some(piece) = mv.promotion() {
+            gain[0] += piece.value() - PieceType::Pawn.value();
+            on_square = piece;
+        }
+
+        let mut side = self.active_color.opponent();
+        loop {
+            // the attacker set is recomputed with the shrinking occupancy so x-rays join in
+            let attackers = self.attackers_to(mv.to_square(), side, occupied) & occupied;
+            let (square, piece_type) = match self.least_valuable(attackers) {
+                None => break,
+                Some(attacker) => attacker,
+            };
+            gain.push(on_square.value() - gain[gain.len() - 1]);
+            occupied &= !(1u64 << square);
+            on_square = piece_type;
+            side = side.opponent();
+        }
+
+        // either side may stop capturing when continuing would lose material
+        while gain.len() > 1 {
+            let last = gain.pop().unwrap();
+            let previous = gain.last_mut().unwrap();
+            *previous = -(-*previous).max(last);
+        }
+        gain[0]
+    }
+
+    fn least_valuable(&self, mut attackers: u64) -> Option<(usize, PieceType)> {
+        let mut best: Option<(usize, PieceType)> = None;
+        while attackers != 0 {
+            let square = pop_lsb(&mut attackers);
+            let piece_type = self.piece_at(Square::from_index(square)).unwrap().piece_type;
+            if best.is_none_or(|(_, current)| piece_type.value() < current.value()) {
+                best = Some((square, piece_type));
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn see(fen: &str, mv: &str) -> i32 {
+        Game::read_FEN(fen).see(mv.parse().unwrap())
+    }
+
+    #[test]
+    fn undefended_pawn_is_won() {
+        assert_eq!(see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"), 100);
+    }
+
+    #[test]
+    fn exchange_with_x_rays_behind_both_sides() {
+        // Nxe5 Nxe5 Rxe5 Bxe5 Qxe5 Qxe5 leaves white a knight down for a pawn
+        assert_eq!(see("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5"), -220);
+    }
+
+    #[test]
+    fn defended_piece_taken_by_a_cheaper_one() {
+        // pawn takes a knight defended by a pawn
+        assert_eq!(see("4k3/8/2p5/3n4/4P3/8/8/4K3 w - - 0 1", "e4d5"), 220);
+        // queen takes the same knight and is lost to the pawn
+        assert_eq!(see("4k3/8/2p5/3n4/8/8/8/3QK3 w - - 0 1", "d1d5"), -580);
+    }
+
+    #[test]
+    fn quiet_move_onto_an_attacked_square() {
+        assert_eq!(see("4k3/8/8/2p5/8/8/8/3QK3 w - - 0 1", "d1d4"), -900);
+        assert_eq!(see("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", "d1d4"), 0);
+    }
+
+    #[test]
+    fn en_passant_and_promotion_gains() {
+        assert_eq!(see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), 100);
+        // the king takes the new queen back, white is still a rook for a pawn up
+        assert_eq!(see("3rk3/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7d8q"), 400);
+        assert_eq!(see("3r4/4P3/8/8/8/8/8/k3K3 w - - 0 1", "e7d8q"), 1300);
+    }
+}
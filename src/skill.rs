@@ -0,0 +1,129 @@
+use crate::game::Game;
+use crate::movegen::Move;
+use crate::search::*;
+
+pub const MAX_SKILL: u8 = 20;
+pub const MIN_ELO: u32 = 800;
+pub const MAX_ELO: u32 = 2400;
+
+// a weaker opponent: level 0 barely looks ahead and often plays a worse move, level 20 is
+// the full engine. the same position and seed always give the same move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Skill {
+    pub level: u8,
+    pub seed: u64,
+}
+
+impl Skill {
+    pub fn new(level: u8) -> Skill {
+        Skill { level: level.min(MAX_SKILL), seed: 0 }
+    }
+
+    // UCI_Elo style, spread linearly over the skill levels
+    pub fn from_elo(elo: u32) -> Skill {
+        let elo = elo.clamp(MIN_ELO, MAX_ELO);
+        Skill::new(((elo - MIN_ELO) * MAX_SKILL as u32 / (MAX_ELO - MIN_ELO)) as u8)
+    }
+
+    pub fn is_full_strength(&self) -> bool {
+        self.level >= MAX_SKILL
+    }
+
+    pub fn depth(&self) -> usize {
+        1 + self.level as usize / 4
+    }
+
+    pub fn node_limit(&self) -> u64 {
+        1000 << self.level.min(16)
+    }
+
+    // how many centipawns worse than the best a move may be and still get picked
+    pub fn margin(&self) -> i32 {
+        (MAX_SKILL - self.level) as i32 * 15
+    }
+
+    // the options capped to this level, with a few lines to choose among that are searched
+    // but not reported, a gui only hears about as many as it asked for
+    pub fn limit(&self, options: &SearchOptions) -> SearchOptions {
+        if self.is_full_strength() {
+            return *options;
+        }
+        SearchOptions {
+            depth: options.depth.min(self.depth()),
+            nodes: Some(options.nodes.map_or(self.node_limit(), |nodes| nodes.min(self.node_limit()))),
+            multi_pv: options.multi_pv.max(4),
+            reported_pv: Some(options.reported_pv.unwrap_or(options.multi_pv)),
+            ..*options
+        }
+    }
+
+    // one of the lines within the margin of the best, mates are never given away
+    pub fn choose(&self, game: &Game, result: &SearchResult) -> Option<Move> {
+        let best = result.lines.first()?;
+        if self.is_full_strength() || is_mate_score(best.score) {
+            return result.best_move;
+        }
+        let candidates: Vec<_> = result.lines.iter()
+            .filter(|line| line.score >= best.score - self.margin())
+            .collect();
+        let index = random(game.hash() ^ self.seed) as usize % candidates.len();
+        Some(candidates[index].pv[0])
+    }
+
+    pub fn pick_move(&self, searcher: &mut Searcher, game: &mut Game, options: &SearchOptions) -> Option<Move> {
+        let result = searcher.search_with(game, &self.limit(options));
+        self.choose(game, &result)
+    }
+}
+
+// splitmix64, enough to spread the position hash over the candidates
+pub(crate) fn random(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_follow_elo() {
+        assert_eq!(Skill::from_elo(500).level, 0);
+        assert_eq!(Skill::from_elo(1600).level, 10);
+        assert!(Skill::from_elo(3000).is_full_strength());
+        let options = Skill::new(0).limit(&SearchOptions::default());
+        assert_eq!((options.depth, options.nodes, options.multi_pv, options.reported_pv), (1, Some(1000), 4, Some(1)));
+        assert_eq!(Skill::new(MAX_SKILL).limit(&SearchOptions::default()), SearchOptions::default());
+    }
+
+    #[test]
+    fn weak_play_is_deterministic() {
+        let mut game = Game::initialize();
+        let weak = Skill::new(0);
+        let first = weak.pick_move(&mut Searcher::new(), &mut game, &SearchOptions::default());
+        let again = weak.pick_move(&mut Searcher::new(), &mut game, &SearchOptions::default());
+        assert!(first.is_some());
+        assert_eq!(first, again);
+    }
+
+    #[test]
+    fn reports_only_the_lines_asked_for() {
+        use std::sync::{Arc, Mutex};
+        let lines = Arc::new(Mutex::new(vec![]));
+        let mut searcher = Searcher::new();
+        let sink = lines.clone();
+        searcher.set_listener(move |info: &SearchInfo| sink.lock().unwrap().push(info.multipv));
+        let result = searcher.search_with(&mut Game::initialize(), &Skill::new(0).limit(&SearchOptions::default()));
+        assert_eq!(result.lines.len(), 4);
+        assert!(lines.lock().unwrap().iter().all(|multipv| *multipv == 1));
+    }
+
+    #[test]
+    fn never_misses_a_mate() {
+        let mut game = Game::read_FEN("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let mv = Skill::new(0).pick_move(&mut Searcher::new(), &mut game, &SearchOptions::default());
+        assert_eq!(mv.map(|mv| mv.to_uci()), Some("a1a8".to_string()));
+    }
+}
@@ -0,0 +1,98 @@
+use std::fmt;
+use crate::matchrunner::MatchScore;
+
+// which hypothesis a finished test accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtResult {
+    // the patch is no better than elo0
+    H0,
+    // the patch gains at least elo1
+    H1,
+}
+
+impl fmt::Display for SprtResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SprtResult::H0 => write!(f, "H0 accepted"),
+            SprtResult::H1 => write!(f, "H1 accepted"),
+        }
+    }
+}
+
+// a sequential probability ratio test of elo0 against elo1, alpha and beta are the
+// chances of accepting H1 when H0 holds and the other way around
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Default for Sprt {
+    fn default() -> Sprt {
+        Sprt { elo0: 0.0, elo1: 5.0, alpha: 0.05, beta: 0.05 }
+    }
+}
+
+// the expected points per game of an elo advantage
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+impl Sprt {
+    // the log likelihood ratio on the first engine's results, with the game results
+    // taken as normally distributed around their mean. it stays 0 while every game
+    // ended the same way, as there is no spread to measure yet
+    pub fn llr(&self, score: &MatchScore) -> f64 {
+        let games = score.games() as f64;
+        if games == 0.0 {
+            return 0.0;
+        }
+        let mean = score.score();
+        let variance = (score.wins as f64 + score.draws as f64 / 4.0) / games - mean * mean;
+        if variance <= 0.0 {
+            return 0.0;
+        }
+        let (s0, s1) = (expected_score(self.elo0), expected_score(self.elo1));
+        games * (s1 - s0) * (2.0 * mean - s0 - s1) / (2.0 * variance)
+    }
+
+    // the test stops once the llr leaves (lower, upper)
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    pub fn decide(&self, score: &MatchScore) -> Option<SprtResult> {
+        let llr = self.llr(score);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            Some(SprtResult::H1)
+        } else if llr <= lower {
+            Some(SprtResult::H0)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_the_hypothesis_the_results_favour() {
+        let sprt = Sprt::default();
+        let (lower, upper) = sprt.bounds();
+        assert!((lower + 2.944).abs() < 0.001 && (upper - 2.944).abs() < 0.001);
+
+        assert_eq!(sprt.decide(&MatchScore { wins: 10, draws: 10, losses: 10 }), None);
+        // about 29 elo over 4000 games is well past elo1
+        let strong = MatchScore { wins: 1300, draws: 1733, losses: 967 };
+        assert!(sprt.llr(&strong) > upper);
+        assert_eq!(sprt.decide(&strong), Some(SprtResult::H1));
+        let weak = MatchScore { wins: 967, draws: 1733, losses: 1300 };
+        assert_eq!(sprt.decide(&weak), Some(SprtResult::H0));
+        assert_eq!(sprt.llr(&MatchScore { wins: 0, draws: 5, losses: 0 }), 0.0);
+    }
+}
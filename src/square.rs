@@ -0,0 +1,268 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use crate::game::Color;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub enum File {
+    A, B, C, D, E, F, G, H,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub enum Rank {
+    First, Second, Third, Fourth, Fifth, Sixth, Seventh, Eighth,
+}
+
+// a1 = 0, b1 = 1, ..., h8 = 63, same as the board indices everywhere else
+#[rustfmt::skip]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub enum Square {
+    A1, B1, C1, D1, E1, F1, G1, H1,
+    A2, B2, C2, D2, E2, F2, G2, H2,
+    A3, B3, C3, D3, E3, F3, G3, H3,
+    A4, B4, C4, D4, E4, F4, G4, H4,
+    A5, B5, C5, D5, E5, F5, G5, H5,
+    A6, B6, C6, D6, E6, F6, G6, H6,
+    A7, B7, C7, D7, E7, F7, G7, H7,
+    A8, B8, C8, D8, E8, F8, G8, H8,
+}
+
+impl File {
+    pub const ALL: [File; 8] = [File::A, File::B, File::C, File::D, File::E, File::F, File::G, File::H];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn offset(self, delta: i32) -> Option<File> {
+        let index = self as i32 + delta;
+        if (0..8).contains(&index) { Some(File::ALL[index as usize]) } else { None }
+    }
+
+    pub fn to_char(self) -> char {
+        (b'a' + self as u8) as char
+    }
+}
+
+impl Rank {
+    pub const ALL: [Rank; 8] = [Rank::First, Rank::Second, Rank::Third, Rank::Fourth,
+                                Rank::Fifth, Rank::Sixth, Rank::Seventh, Rank::Eighth];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn offset(self, delta: i32) -> Option<Rank> {
+        let index = self as i32 + delta;
+        if (0..8).contains(&index) { Some(Rank::ALL[index as usize]) } else { None }
+    }
+
+    // the rank as seen from the given side, so the first rank for black is the eighth
+    pub fn relative_to(self, color: Color) -> Rank {
+        match color {
+            Color::White => self,
+            Color::Black => Rank::ALL[7 - self as usize],
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        (b'1' + self as u8) as char
+    }
+}
+
+impl Square {
+    #[rustfmt::skip]
+    pub const ALL: [Square; 64] = [
+        Square::A1, Square::B1, Square::C1, Square::D1, Square::E1, Square::F1, Square::G1, Square::H1,
+        Square::A2, Square::B2, Square::C2, Square::D2, Square::E2, Square::F2, Square::G2, Square::H2,
+        Square::A3, Square::B3, Square::C3, Square::D3, Square::E3, Square::F3, Square::G3, Square::H3,
+        Square::A4, Square::B4, Square::C4, Square::D4, Square::E4, Square::F4, Square::G4, Square::H4,
+        Square::A5, Square::B5, Square::C5, Square::D5, Square::E5, Square::F5, Square::G5, Square::H5,
+        Square::A6, Square::B6, Square::C6, Square::D6, Square::E6, Square::F6, Square::G6, Square::H6,
+        Square::A7, Square::B7, Square::C7, Square::D7, Square::E7, Square::F7, Square::G7, Square::H7,
+        Square::A8, Square::B8, Square::C8, Square::D8, Square::E8, Square::F8, Square::G8, Square::H8,
+    ];
+
+    pub fn new(file: File, rank: Rank) -> Square {
+        Square::from_index(rank as usize * 8 + file as usize)
+    }
+
+    pub(crate) fn from_index(index: usize) -> Square {
+        Square::ALL[index]
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn bit(self) -> u64 {
+        1u64 << self as usize
+    }
+
+    pub fn file(self) -> File {
+        File::ALL[self as usize % 8]
+    }
+
+    pub fn rank(self) -> Rank {
+        Rank::ALL[self as usize / 8]
+    }
+
+    // None when the step would leave the board
+    pub fn offset(self, file_delta: i32, rank_delta: i32) -> Option<Square> {
+        let file = self.file().offset(file_delta)?;
+        let rank = self.rank().offset(rank_delta)?;
+        Some(Square::new(file, rank))
+    }
+
+    // one square towards the opponent's side
+    pub fn forward(self, color: Color) -> Option<Square> {
+        match color {
+            Color::White => self.offset(0, 1),
+            Color::Black => self.offset(0, -1),
+        }
+    }
+
+    // mirror across the middle of the board, a1 <-> a8
+    pub fn flip(self) -> Square {
+        Square::from_index(self as usize ^ 56)
+    }
+
+    // number of king steps between the two squares
+    pub fn distance(self, other: Square) -> usize {
+        let files = (self.file() as i32 - other.file() as i32).unsigned_abs();
+        let ranks = (self.rank() as i32 - other.rank() as i32).unsigned_abs();
+        files.max(ranks) as usize
+    }
+
+    pub fn iter() -> impl Iterator<Item = Square> {
+        Square::ALL.into_iter()
+    }
+}
+
+impl From<Square> for usize {
+    fn from(square: Square) -> usize {
+        square as usize
+    }
+}
+
+impl From<Square> for u64 {
+    fn from(square: Square) -> u64 {
+        square.bit()
+    }
+}
+
+impl TryFrom<usize> for Square {
+    type Error = String;
+
+    fn try_from(index: usize) -> Result<Square, String> {
+        if index < 64 {
+            Ok(Square::from_index(index))
+        } else {
+            Err(format!("Invalid square index {}", index))
+        }
+    }
+}
+
+// a bitboard with exactly one bit set
+impl TryFrom<u64> for Square {
+    type Error = String;
+
+    fn try_from(bit: u64) -> Result<Square, String> {
+        if bit.count_ones() == 1 {
+            Ok(Square::from_index(bit.trailing_zeros() as usize))
+        } else {
+            Err(format!("Expected a single bit, got {:#x}", bit))
+        }
+    }
+}
+
+impl TryFrom<char> for File {
+    type Error = String;
+
+    fn try_from(ch: char) -> Result<File, String> {
+        match ch {
+            'a'..='h' => Ok(File::ALL[ch as usize - 'a' as usize]),
+            _ => Err(format!("Invalid column character {}", ch)),
+        }
+    }
+}
+
+impl TryFrom<char> for Rank {
+    type Error = String;
+
+    fn try_from(ch: char) -> Result<Rank, String> {
+        match ch {
+            '1'..='8' => Ok(Rank::ALL[ch as usize - '1' as usize]),
+            _ => Err(format!("Invalid row character {}", ch)),
+        }
+    }
+}
+
+impl FromStr for Square {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Square, String> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(file), Some(rank), None) => Ok(Square::new(File::try_from(file)?, Rank::try_from(rank)?)),
+            _ => Err(format!("Invalid length of position {}", s.len())),
+        }
+    }
+}
+
+impl fmt::Display for File {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.file(), self.rank())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn squares_round_trip() {
+        for square in Square::iter() {
+            assert_eq!(Square::try_from(square.index()), Ok(square));
+            assert_eq!(Square::try_from(square.bit()), Ok(square));
+            assert_eq!(square.to_string().parse::<Square>(), Ok(square));
+            assert_eq!(Square::new(square.file(), square.rank()), square);
+        }
+        assert_eq!(Square::E4.to_string(), "e4");
+        assert_eq!(usize::from(Square::H8), 63);
+        assert!(Square::try_from(64usize).is_err());
+        assert!(Square::try_from(0b11u64).is_err());
+        assert!("i1".parse::<Square>().is_err());
+        assert!("e9".parse::<Square>().is_err());
+        assert!("e10".parse::<Square>().is_err());
+    }
+
+    #[test]
+    fn square_arithmetic() {
+        assert_eq!(Square::E4.offset(1, 2), Some(Square::F6));
+        assert_eq!(Square::H4.offset(1, 0), None);
+        assert_eq!(Square::A1.offset(0, -1), None);
+        assert_eq!(Square::E2.forward(Color::White), Some(Square::E3));
+        assert_eq!(Square::E7.forward(Color::Black), Some(Square::E6));
+        assert_eq!(Square::C2.flip(), Square::C7);
+        assert_eq!(Square::A1.distance(Square::H8), 7);
+        assert_eq!(Square::E4.distance(Square::F6), 2);
+        assert_eq!(File::G.offset(-6), Some(File::A));
+        assert_eq!(Rank::Second.relative_to(Color::Black), Rank::Seventh);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::game::*;
+
+const LIGHT_SQUARES: u64 = 0x55aa55aa55aa55aa;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum GameStatus {
+    Ongoing,
+    // carries the color that delivered the mate
+    Checkmate(Color),
+    Stalemate,
+    InsufficientMaterial,
+    // the automatic draws, unlike the fifty move rule and threefold repetition
+    // these end the game without either player claiming them
+    SeventyFiveMoveRule,
+    FivefoldRepetition,
+}
+
+impl Game {
+    pub fn status(&self) -> GameStatus {
+        if self.legal_moves().is_empty() {
+            return if self.is_in_check(self.active_color) {
+                GameStatus::Checkmate(self.active_color.opponent())
+            } else {
+                GameStatus::Stalemate
+            };
+        }
+        if self.is_insufficient_material() {
+            GameStatus::InsufficientMaterial
+        } else if self.repetition_count() >= 5 {
+            GameStatus::FivefoldRepetition
+        } else if self.halfmove_clock >= 150 {
+            GameStatus::SeventyFiveMoveRule
+        } else {
+            GameStatus::Ongoing
+        }
+    }
+
+    // fifty move rule or threefold repetition, the game only ends if a player claims it
+    pub fn can_claim_draw(&self) -> bool {
+        self.halfmove_clock >= 100 || self.repetition_count() >= 3
+    }
+
+    // how many times the current position has occurred, only looking back to the last
+    // capture or pawn move since earlier positions cannot repeat
+    pub fn repetition_count(&self) -> usize {
+        let reversible = self.halfmove_clock.min(self.history.len());
+        1 + self.history[self.history.len() - reversible..].iter()
+            .filter(|undo| undo.hash == self.hash)
+            .count()
+    }
+
+    // neither side can ever mate: bare kings, a single minor piece, or only bishops on one color
+    pub fn is_insufficient_material(&self) -> bool {
+        let mut minors = 0;
+        let mut bishops = 0;
+        for piece in &self.pieces {
+            match piece.piece_type {
+                PieceType::King => (),
+                PieceType::Knight => minors += 1,
+                PieceType::Bishop => {
+                    minors += 1;
+                    bishops |= piece.position;
+                }
+                _ => return false,
+            }
+        }
+        minors <= 1 || (minors == bishops.count_ones()
+            && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starting_position_is_ongoing() {
+        assert_eq!(Game::initialize().status(), GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn back_rank_mate_is_checkmate() {
+        let game = Game::read_FEN("3R2k1/5ppp/8/8/8/8/8/6K1 b - - 1 1");
+        assert_eq!(game.status(), GameStatus::Checkmate(Color::White));
+    }
+
+    #[test]
+    fn cornered_king_without_moves_is_stalemate() {
+        let game = Game::read_FEN("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        assert_eq!(game.status(), GameStatus::Stalemate);
+    }
+
+    #[test]
+    fn insufficient_material_combinations() {
+        let drawn = [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1N2K3 b - - 0 1",
+            // bishops on c1 and f8, both dark squares
+            "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+        ];
+        for fen in drawn {
+            let game = Game::read_FEN(fen);
+            assert!(game.is_insufficient_material(), "{}", fen);
+            assert_eq!(game.status(), GameStatus::InsufficientMaterial);
+        }
+
+        let playable = [
+            "4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1N2KB2 w - - 0 1",
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/R3K3 w - - 0 1",
+        ];
+        for fen in playable {
+            assert!(!Game::read_FEN(fen).is_insufficient_material(), "{}", fen);
+        }
+    }
+
+    fn shuffle_knights(game: &mut Game, times: usize) {
+        for _ in 0..times {
+            for mv in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+                game.make_move(mv.parse().unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn repetitions_become_claimable_then_automatic() {
+        let mut game = Game::initialize();
+        shuffle_knights(&mut game, 1);
+        assert_eq!(game.repetition_count(), 2);
+        assert!(!game.can_claim_draw());
+
+        shuffle_knights(&mut game, 1);
+        assert_eq!(game.repetition_count(), 3);
+        assert!(game.can_claim_draw());
+        assert_eq!(game.status(), GameStatus::Ongoing);
+
+        shuffle_knights(&mut game, 2);
+        assert_eq!(game.repetition_count(), 5);
+        assert_eq!(game.status(), GameStatus::FivefoldRepetition);
+    }
+
+    #[test]
+    fn pawn_move_resets_repetitions() {
+        let mut game = Game::initialize();
+        shuffle_knights(&mut game, 2);
+        game.make_move("e2e4".parse().unwrap());
+        game.make_move("e7e5".parse().unwrap());
+        shuffle_knights(&mut game, 1);
+        assert_eq!(game.repetition_count(), 2);
+    }
+
+    #[test]
+    fn move_counters_reach_the_draw_rules() {
+        let game = Game::read_FEN("4k3/8/8/8/8/8/8/R3K3 w - - 99 80");
+        assert!(!game.can_claim_draw());
+        let game = Game::read_FEN("4k3/8/8/8/8/8/8/R3K3 w - - 100 80");
+        assert!(game.can_claim_draw());
+        assert_eq!(game.status(), GameStatus::Ongoing);
+        let game = Game::read_FEN("4k3/8/8/8/8/8/8/R3K3 w - - 150 100");
+        assert_eq!(game.status(), GameStatus::SeventyFiveMoveRule);
+        // mate on the last move still counts as mate
+        let game = Game::read_FEN("3R2k1/5ppp/8/8/8/8/8/6K1 b - - 150 100");
+        assert_eq!(game.status(), GameStatus::Checkmate(Color::White));
+    }
+
+    #[test]
+    fn check_with_an_escape_is_ongoing() {
+        let game = Game::read_FEN("3R2k1/6pp/8/8/8/8/8/6K1 b - - 1 1");
+        assert_eq!(game.status(), GameStatus::Ongoing);
+    }
+}
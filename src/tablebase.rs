@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+use reqwest::Client;
+use serde_json::Value;
+use crate::game::Game;
+use crate::movegen::Move;
+
+// the engine has no tablebase files of its own, positions this small are looked up here
+const TABLEBASE_URL: &str = "https://tablebase.lichess.ovh/standard";
+pub const MAX_PIECES: usize = 7;
+// a slow answer is worth less than none during analysis
+const TIMEOUT: Duration = Duration::from_secs(3);
+const USER_AGENT: &str = concat!("rustic_chess/", env!("CARGO_PKG_VERSION"));
+
+// the result with perfect play for the side to move, cursed wins and blessed losses
+// are the ones the fifty move rule turns into draws
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Win,
+    CursedWin,
+    Draw,
+    BlessedLoss,
+    Loss,
+    Unknown,
+}
+
+impl Category {
+    fn parse(key: &str) -> Category {
+        match key {
+            "win" => Category::Win,
+            "cursed-win" => Category::CursedWin,
+            "draw" => Category::Draw,
+            "blessed-loss" => Category::BlessedLoss,
+            "loss" => Category::Loss,
+            _ => Category::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let key = match self {
+            Category::Win => "win",
+            Category::CursedWin => "cursed-win",
+            Category::Draw => "draw",
+            Category::BlessedLoss => "blessed-loss",
+            Category::Loss => "loss",
+            Category::Unknown => "unknown",
+        };
+        write!(f, "{}", key)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProbeResult {
+    pub category: Category,
+    // distance to zeroing the fifty move counter and to mate, in plies
+    pub dtz: Option<i32>,
+    pub dtm: Option<i32>,
+    pub best_move: Option<Move>,
+}
+
+// few enough pieces, and no castling rights as the tables have none
+pub fn in_range(game: &Game) -> bool {
+    game.pieces.len() <= MAX_PIECES && game.castling_rights.is_empty()
+}
+
+pub fn probe_url(game: &Game) -> String {
+    format!("{}?fen={}", TABLEBASE_URL, game.to_fen().replace(' ', "_"))
+}
+
+// the moves are listed best first
+pub fn parse_response(text: &str, game: &Game) -> Result<ProbeResult, String> {
+    let value: Value = serde_json::from_str(text).map_err(|err| err.to_string())?;
+    let best_move = value["moves"].as_array()
+        .and_then(|moves| moves.first())
+        .and_then(|mv| mv["uci"].as_str())
+        .map(|uci| Move::from_uci(uci, game))
+        .transpose()?;
+    Ok(ProbeResult {
+        category: Category::parse(value["category"].as_str().unwrap_or("")),
+        dtz: value["dtz"].as_i64().map(|dtz| dtz as i32),
+        dtm: value["dtm"].as_i64().map(|dtm| dtm as i32),
+        best_move,
+    })
+}
+
+// the position without the move counters, which the tables do not care about
+fn cache_key(game: &Game) -> String {
+    game.to_fen().split(' ').take(4).collect::<Vec<_>>().join(" ")
+}
+
+// looks positions up on lichess and remembers the answers, `offline` keeps it off the network
+// entirely so only positions already in the cache are answered
+pub struct OnlineTablebase {
+    client: Client,
+    runtime: tokio::runtime::Runtime,
+    cache: HashMap<String, ProbeResult>,
+    pub offline: bool,
+}
+
+impl OnlineTablebase {
+    pub fn new(offline: bool) -> Result<OnlineTablebase, String> {
+        let client = Client::builder().user_agent(USER_AGENT).timeout(TIMEOUT).build().map_err(|err| err.to_string())?;
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().map_err(|err| err.to_string())?;
+        Ok(OnlineTablebase { client, runtime, cache: HashMap::new(), offline })
+    }
+
+    pub fn cached(&self) -> usize {
+        self.cache.len()
+    }
+
+    // None for positions out of range, when offline and on network errors, errors are not cached
+    pub fn probe(&mut self, game: &Game) -> Option<ProbeResult> {
+        if !in_range(game) {
+            return None;
+        }
+        let key = cache_key(game);
+        if let Some(result) = self.cache.get(&key) {
+            return Some(*result);
+        }
+        if self.offline {
+            return None;
+        }
+        let url = probe_url(game);
+        let text = self.runtime.block_on(async {
+            let response = self.client.get(&url).send().await?.error_for_status()?;
+            response.text().await
+        }).ok()?;
+        let result = parse_response(&text, game).ok()?;
+        self.cache.insert(key, result);
+        Some(result)
+    }
+
+    pub fn insert(&mut self, game: &Game, result: ProbeResult) {
+        self.cache.insert(cache_key(game), result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_probe_responses() {
+        let game = Game::read_FEN("4k3/8/8/8/8/8/8/4K2R w - - 0 1");
+        assert!(in_range(&game));
+        assert!(!in_range(&Game::initialize()));
+        assert_eq!(probe_url(&game), "https://tablebase.lichess.ovh/standard?fen=4k3/8/8/8/8/8/8/4K2R_w_-_-_0_1");
+
+        let text = r#"{"category":"win","dtz":21,"dtm":23,"moves":[{"uci":"h1h7","san":"Rh7","category":"loss"},
+            {"uci":"e1d1","san":"Kd1","category":"loss"}]}"#;
+        let result = parse_response(text, &game).unwrap();
+        assert_eq!((result.category, result.dtz, result.dtm), (Category::Win, Some(21), Some(23)));
+        assert_eq!(result.best_move.unwrap().to_uci(), "h1h7");
+        assert_eq!(parse_response(r#"{"category":"maybe-win","moves":[]}"#, &game).unwrap().category, Category::Unknown);
+        assert!(parse_response(r#"{"moves":[{"uci":"a1a8"}]}"#, &game).is_err());
+    }
+
+    #[test]
+    fn answers_from_the_cache_only_when_offline() {
+        let mut tablebase = OnlineTablebase::new(true).unwrap();
+        let game = Game::read_FEN("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(tablebase.probe(&game), None);
+        let draw = ProbeResult { category: Category::Draw, dtz: Some(0), dtm: None, best_move: None };
+        tablebase.insert(&game, draw);
+        // the move counters do not matter
+        assert_eq!(tablebase.probe(&Game::read_FEN("4k3/8/8/8/8/8/8/4K3 w - - 12 40")), Some(draw));
+        assert_eq!(tablebase.cached(), 1);
+    }
+}
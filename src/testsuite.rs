@@ -0,0 +1,127 @@
+use std::time::Duration;
+use crate::epd::*;
+use crate::game::Game;
+use crate::movegen::Move;
+use crate::search::{SearchOptions, Searcher, MAX_DEPTH};
+use crate::timeman::TimeLimits;
+
+// how long the search may think about each position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SuiteLimit {
+    pub depth: Option<usize>,
+    pub time: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionResult {
+    pub id: String,
+    pub found: Option<Move>,
+    pub solved: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SuiteReport {
+    pub results: Vec<PositionResult>,
+}
+
+impl SuiteReport {
+    pub fn solved(&self) -> usize {
+        self.results.iter().filter(|result| result.solved).count()
+    }
+
+    pub fn total(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &PositionResult> {
+        self.results.iter().filter(|result| !result.solved)
+    }
+}
+
+// a position counts as solved when the search plays one of the bm moves and none of the am moves
+fn is_solved(epd: &Epd, found: Option<Move>) -> Result<bool, String> {
+    let found = match found {
+        None => return Ok(false),
+        Some(mv) => mv,
+    };
+    let best = epd.best_moves()?;
+    let avoid = epd.avoid_moves()?;
+    Ok((best.is_empty() || best.contains(&found)) && !avoid.contains(&found))
+}
+
+// runs `search` on every position, it gets the position and the limit and returns its move
+pub fn run_suite<F>(positions: &[Epd], limit: SuiteLimit, mut search: F) -> Result<SuiteReport, String>
+where F: FnMut(&Game, SuiteLimit) -> Option<Move> {
+    let mut report = SuiteReport::default();
+    for (i, epd) in positions.iter().enumerate() {
+        let found = search(&epd.game, limit);
+        let id = epd.id().map_or_else(|| format!("#{}", i + 1), |id| id.to_string());
+        report.results.push(PositionResult { id, found, solved: is_solved(epd, found)? });
+    }
+    Ok(report)
+}
+
+// same as run_suite for the text of an EPD file, e.g. WAC or STS
+pub fn run_suite_file<F>(text: &str, limit: SuiteLimit, search: F) -> Result<SuiteReport, String>
+where F: FnMut(&Game, SuiteLimit) -> Option<Move> {
+    run_suite(&parse_epd_file(text)?, limit, search)
+}
+
+// a time limit on its own searches as deep as the time allows, neither limit is the default depth
+pub fn search_options(limit: SuiteLimit) -> SearchOptions {
+    let defaults = SearchOptions::default();
+    let depth = limit.depth.unwrap_or(if limit.time.is_some() { MAX_DEPTH } else { defaults.depth });
+    SearchOptions { depth, time: limit.time.map(TimeLimits::fixed), ..defaults }
+}
+
+// the engine's own search on every position, the hash is cleared in between so each one is solved on its own
+pub fn run_engine_suite(positions: &[Epd], limit: SuiteLimit) -> Result<SuiteReport, String> {
+    let mut searcher = Searcher::new();
+    run_suite(positions, limit, |game, limit| {
+        searcher.clear_hash();
+        searcher.search_with(&mut game.clone(), &search_options(limit)).best_move
+    })
+}
+
+pub fn run_engine_suite_file(text: &str, limit: SuiteLimit) -> Result<SuiteReport, String> {
+    run_engine_suite(&parse_epd_file(text)?, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUITE: &str = "\
+2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";
+4k3/8/8/8/8/8/8/4K2R w K - am Rh8+;
+";
+
+    #[test]
+    fn counts_solved_positions() {
+        let limit = SuiteLimit { depth: Some(4), time: None };
+        // stands in for a search that always finds the expected move
+        let report = run_suite_file(SUITE, limit, |game, given| {
+            assert_eq!(given, limit);
+            game.legal_moves().into_iter().find(|mv| game.to_san(*mv) == "Qg6" || game.to_san(*mv) == "Kd1")
+        }).unwrap();
+        assert_eq!(report.total(), 2);
+        assert_eq!(report.solved(), 2);
+        assert_eq!(report.results[1].id, "#2");
+
+        let report = run_suite_file(SUITE, limit, |_, _| None).unwrap();
+        assert_eq!(report.solved(), 0);
+        assert_eq!(report.failed().count(), 2);
+    }
+
+    #[test]
+    fn runs_the_search() {
+        let options = search_options(SuiteLimit { depth: None, time: Some(Duration::from_millis(50)) });
+        assert_eq!((options.depth, options.time), (MAX_DEPTH, Some(TimeLimits::fixed(Duration::from_millis(50)))));
+        assert_eq!(search_options(SuiteLimit { depth: Some(3), time: None }).depth, 3);
+
+        let report = run_engine_suite_file("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id \"back rank\";\n",
+            SuiteLimit { depth: Some(3), time: None }).unwrap();
+        assert_eq!((report.solved(), report.total()), (1, 1));
+        assert_eq!(report.results[0].id, "back rank");
+    }
+}
@@ -0,0 +1,147 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::render::{HighlightStyle, Palette, RenderOptions, Style, SvgOptions, ARROW_COLOR, HIGHLIGHT_COLOR, SVG_DARK, SVG_LIGHT};
+#[cfg(feature = "tui")]
+use crate::tui::TuiConfig;
+
+// the variable naming a theme file, ahead of the one in the config directory
+pub const THEME_VAR: &str = "RUSTIC_CHESS_THEME";
+
+// how every board looks, the terminal, the tui and svg or png diagrams, read from lines like
+//   pieces figurine
+//   dark 94
+//   highlight outline
+//   svg_dark #8ca2ad
+// the terminal colors are 256 color codes and the svg ones css colors
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    // every renderer keeps its own piece style unless the theme picks one
+    pub pieces: Option<Style>,
+    pub palette: Palette,
+    pub highlight: HighlightStyle,
+    pub svg_light: String,
+    pub svg_dark: String,
+    pub svg_highlight: String,
+    pub svg_arrow: String,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme { pieces: None, palette: Palette::default(), highlight: HighlightStyle::Fill, svg_light: SVG_LIGHT.to_string(),
+            svg_dark: SVG_DARK.to_string(), svg_highlight: HIGHLIGHT_COLOR.to_string(), svg_arrow: ARROW_COLOR.to_string() }
+    }
+}
+
+impl Theme {
+    pub fn parse(text: &str) -> Result<Theme, String> {
+        let mut theme = Theme::default();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let (key, value) = line.split_once(char::is_whitespace).ok_or_else(|| format!("Invalid theme line {}", line))?;
+            let value = value.trim();
+            let code = || value.parse::<u8>().map_err(|_| format!("Invalid {} '{}', expected a 256 color code", key, value));
+            match key {
+                "pieces" => theme.pieces = Some(value.parse()?),
+                "highlight" => theme.highlight = value.parse()?,
+                "light" => theme.palette.light = code()?,
+                "dark" => theme.palette.dark = code()?,
+                "last_move" => theme.palette.last_move = code()?,
+                "check" => theme.palette.check = code()?,
+                "hint" => theme.palette.hint = code()?,
+                "white_piece" => theme.palette.white_piece = code()?,
+                "black_piece" => theme.palette.black_piece = code()?,
+                "svg_light" => theme.svg_light = value.to_string(),
+                "svg_dark" => theme.svg_dark = value.to_string(),
+                "svg_highlight" => theme.svg_highlight = value.to_string(),
+                "svg_arrow" => theme.svg_arrow = value.to_string(),
+                _ => return Err(format!("Unknown theme setting {}", key)),
+            }
+        }
+        Ok(theme)
+    }
+
+    pub fn load(path: &Path) -> Result<Theme, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Theme::parse(&text).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    // $RUSTIC_CHESS_THEME, else rustic_chess/theme in the XDG config directory
+    pub fn user_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os(THEME_VAR) {
+            return Some(PathBuf::from(path));
+        }
+        let config = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(config.join("rustic_chess").join("theme"))
+    }
+
+    // no theme file is the default look, a broken one is an error rather than a surprise
+    pub fn user() -> Result<Theme, String> {
+        match Theme::user_path() {
+            Some(path) => match fs::read_to_string(&path) {
+                Ok(text) => Theme::parse(&text).map_err(|err| format!("{}: {}", path.display(), err)),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Theme::default()),
+                Err(err) => Err(format!("{}: {}", path.display(), err)),
+            },
+            None => Ok(Theme::default()),
+        }
+    }
+
+    pub fn apply_render(&self, options: &mut RenderOptions) {
+        if let Some(pieces) = self.pieces {
+            options.style = pieces;
+        }
+        options.palette = self.palette;
+        options.highlight = self.highlight;
+    }
+
+    pub fn apply_svg(&self, options: &mut SvgOptions) {
+        if let Some(pieces) = self.pieces {
+            options.pieces = pieces;
+        }
+        options.light = self.svg_light.clone();
+        options.dark = self.svg_dark.clone();
+        options.highlight = self.highlight;
+    }
+
+    #[cfg(feature = "tui")]
+    pub fn apply_tui(&self, config: &mut TuiConfig) {
+        if let Some(pieces) = self.pieces {
+            config.pieces = pieces;
+        }
+        config.palette = self.palette;
+        config.highlight = self.highlight;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_themes() {
+        let theme = Theme::parse("# a blue board\npieces letters\n\ndark 24\nhighlight outline\nsvg_dark #8ca2ad\n").unwrap();
+        assert_eq!(theme.pieces, Some(Style::Ascii));
+        assert_eq!(theme.palette, Palette { dark: 24, ..Palette::default() });
+        assert_eq!(theme.highlight, HighlightStyle::Outline);
+
+        let mut render = RenderOptions::default();
+        theme.apply_render(&mut render);
+        assert_eq!((render.style, render.palette.dark, render.highlight), (Style::Ascii, 24, HighlightStyle::Outline));
+        let mut svg = SvgOptions::default();
+        theme.apply_svg(&mut svg);
+        assert_eq!((svg.pieces, svg.dark.as_str(), svg.light.as_str()), (Style::Ascii, "#8ca2ad", SVG_LIGHT));
+
+        // without a piece style each renderer keeps its own
+        let mut svg = SvgOptions::default();
+        Theme::default().apply_svg(&mut svg);
+        assert_eq!(svg, SvgOptions::default());
+
+        assert!(Theme::parse("dark 300").is_err());
+        assert!(Theme::parse("pieces wooden").is_err());
+        assert!(Theme::parse("border 3").is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use std::time::Duration;
+use crate::movegen::Move;
+
+// std has no clock in the browser
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+pub(crate) use web_time::Instant;
+
+// kept back from every move for the time it takes to send it
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+// moves the remaining time is spread over when the time control does not say
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+// iterations in a row with the same best move before the search stops early
+const STABLE_ITERATIONS: u32 = 3;
+
+// the clock of the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeControl {
+    pub time: Duration,
+    pub increment: Duration,
+    pub moves_to_go: Option<u32>,
+}
+
+// no new iteration starts after `soft`, the search is cut off at `hard`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeLimits {
+    pub soft: Duration,
+    pub hard: Duration,
+}
+
+impl TimeLimits {
+    pub fn fixed(time: Duration) -> TimeLimits {
+        TimeLimits { soft: time, hard: time }
+    }
+}
+
+impl TimeControl {
+    pub fn limits(&self) -> TimeLimits {
+        let available = self.time.saturating_sub(MOVE_OVERHEAD);
+        let moves = self.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).clamp(1, DEFAULT_MOVES_TO_GO);
+        // with one move to the next time control most of the clock can go into it
+        let hard = if moves == 1 { available * 3 / 4 } else { (available / 3).min(available * 4 / moves) };
+        let soft = (available / moves + self.increment * 3 / 4).min(hard);
+        TimeLimits { soft, hard }
+    }
+}
+
+pub struct TimeManager {
+    start: Instant,
+    limits: TimeLimits,
+    best_move: Option<Move>,
+    stable: u32,
+}
+
+impl TimeManager {
+    pub fn new(limits: TimeLimits) -> TimeManager {
+        TimeManager { start: Instant::now(), limits, best_move: None, stable: 0 }
+    }
+
+    // the clock starts again, after a ponderhit
+    pub fn restart(&mut self) {
+        self.start = Instant::now();
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    pub fn hard_limit_reached(&self) -> bool {
+        self.elapsed() >= self.limits.hard
+    }
+
+    // called after every finished iteration, a best move that keeps coming back
+    // halves the soft limit since more thinking is unlikely to change it
+    pub fn stop_after_iteration(&mut self, best_move: Move) -> bool {
+        if self.best_move == Some(best_move) {
+            self.stable += 1;
+        } else {
+            self.best_move = Some(best_move);
+            self.stable = 0;
+        }
+        let soft = if self.stable >= STABLE_ITERATIONS { self.limits.soft / 2 } else { self.limits.soft };
+        self.elapsed() >= soft
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::square::Square;
+
+    fn control(time: u64, increment: u64, moves_to_go: Option<u32>) -> TimeControl {
+        TimeControl { time: Duration::from_millis(time), increment: Duration::from_millis(increment), moves_to_go }
+    }
+
+    #[test]
+    fn spreads_the_clock() {
+        let limits = control(60_030, 0, None).limits();
+        assert_eq!(limits.soft, Duration::from_secs(2));
+        assert_eq!(limits.hard, Duration::from_secs(8));
+        // the increment is mostly spent on every move
+        assert_eq!(control(60_030, 1000, None).limits().soft, Duration::from_millis(2750));
+        // the last move before the time control may use more
+        let last = control(10_030, 0, Some(1)).limits();
+        assert_eq!(last.hard, Duration::from_millis(7500));
+        assert!(last.soft <= last.hard);
+        // nothing left to think with
+        assert_eq!(control(10, 0, None).limits(), TimeLimits { soft: Duration::ZERO, hard: Duration::ZERO });
+    }
+
+    #[test]
+    fn stops_early_on_a_stable_best_move() {
+        let mv = Move::new(Square::E2, Square::E4);
+        let mut manager = TimeManager::new(TimeLimits { soft: Duration::from_millis(40), hard: Duration::from_secs(1) });
+        assert!(!manager.stop_after_iteration(mv));
+        std::thread::sleep(Duration::from_millis(25));
+        for _ in 0..STABLE_ITERATIONS - 1 {
+            assert!(!manager.stop_after_iteration(mv));
+        }
+        assert!(manager.stop_after_iteration(mv));
+        assert!(!manager.hard_limit_reached());
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::matchrunner::{run_match, MatchConfig, MatchEngine, MatchScore};
+use crate::pgn::GameRecord;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    // everyone plays everyone
+    RoundRobin,
+    // the first engine plays each of the others, who do not play each other
+    Gauntlet,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "round-robin" | "roundrobin" => Ok(Format::RoundRobin),
+            "gauntlet" => Ok(Format::Gauntlet),
+            _ => Err(format!("Unknown tournament format {}", s)),
+        }
+    }
+}
+
+// the matches of a tournament among `engines` engines, by index
+pub fn pairings(format: Format, engines: usize) -> Vec<(usize, usize)> {
+    match format {
+        Format::RoundRobin => (0..engines).flat_map(|i| (i + 1..engines).map(move |j| (i, j))).collect(),
+        Format::Gauntlet => (1..engines).map(|j| (0, j)).collect(),
+    }
+}
+
+// every pairing's score, scores[i][j] from engine i's point of view against engine j
+#[derive(Debug, Clone, PartialEq)]
+pub struct Crosstable {
+    pub names: Vec<String>,
+    pub scores: Vec<Vec<Option<MatchScore>>>,
+}
+
+impl Crosstable {
+    pub fn new(names: Vec<String>) -> Crosstable {
+        let scores = vec![vec![None; names.len()]; names.len()];
+        Crosstable { names, scores }
+    }
+
+    pub fn record(&mut self, first: usize, second: usize, score: MatchScore) {
+        self.scores[first][second] = Some(score);
+        self.scores[second][first] = Some(MatchScore { wins: score.losses, draws: score.draws, losses: score.wins });
+    }
+
+    // points and games of engine `i` over all its matches
+    pub fn total(&self, i: usize) -> (f64, u32) {
+        self.scores[i].iter().flatten().fold((0.0, 0), |(points, games), score| {
+            (points + score.wins as f64 + score.draws as f64 / 2.0, games + score.games())
+        })
+    }
+
+    // engine indices from the most points to the fewest
+    pub fn standings(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.names.len()).collect();
+        order.sort_by(|a, b| self.total(*b).0.total_cmp(&self.total(*a).0));
+        order
+    }
+}
+
+// the standings with each engine's points against every other, in standings order
+impl fmt::Display for Crosstable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let order = self.standings();
+        let width = self.names.iter().map(|name| name.len()).max().unwrap_or(0).max(6);
+        write!(f, "{:>3} {:<width$} {:>9}", "#", "Engine", "Score", width = width)?;
+        for rank in 1..=order.len() {
+            write!(f, " {:>5}", rank)?;
+        }
+        writeln!(f)?;
+        for (rank, i) in order.iter().enumerate() {
+            let (points, games) = self.total(*i);
+            write!(f, "{:>3} {:<width$} {:>9}", rank + 1, self.names[*i], format!("{}/{}", points, games), width = width)?;
+            for j in &order {
+                match &self.scores[*i][*j] {
+                    _ if i == j => write!(f, " {:>5}", "-")?,
+                    Some(score) => write!(f, " {:>5}", score.wins as f64 + score.draws as f64 / 2.0)?,
+                    None => write!(f, " {:>5}", "")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+// plays every pairing as a match of config.games games, `report` gets each game with the
+// indices of the engines in the pairing
+pub fn run_tournament(
+    engines: &mut [Box<dyn MatchEngine>], format: Format, config: &MatchConfig,
+    mut report: impl FnMut(usize, usize, &GameRecord),
+) -> Crosstable {
+    // the same engine entered twice gets a number to tell the two apart in the table
+    let mut names: Vec<String> = vec![];
+    for engine in engines.iter() {
+        let name = engine.name();
+        let entered = names.iter().filter(|other| other.split(" #").next() == Some(name.as_str())).count();
+        names.push(if entered == 0 { name } else { format!("{} #{}", name, entered + 1) });
+    }
+    let mut table = Crosstable::new(names);
+    for (i, j) in pairings(format, engines.len()) {
+        let (left, right) = engines.split_at_mut(j);
+        let score = run_match(left[i].as_mut(), right[0].as_mut(), config, |_, record, _| report(i, j, record));
+        table.record(i, j, score);
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matchrunner::{Adjudication, BuiltinEngine};
+
+    #[test]
+    fn pairs_engines_by_format() {
+        assert_eq!(pairings(Format::RoundRobin, 3), [(0, 1), (0, 2), (1, 2)]);
+        assert_eq!(pairings(Format::Gauntlet, 3), [(0, 1), (0, 2)]);
+        assert_eq!("gauntlet".parse(), Ok(Format::Gauntlet));
+        assert!("swiss".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn fills_the_crosstable() {
+        let mut table = Crosstable::new(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        table.record(0, 1, MatchScore { wins: 0, draws: 1, losses: 1 });
+        table.record(1, 2, MatchScore { wins: 2, draws: 0, losses: 0 });
+        assert_eq!(table.total(1), (3.5, 4));
+        assert_eq!(table.standings(), [1, 0, 2]);
+        let text = table.to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[1].starts_with("  1 b") && lines[1].contains("3.5/4"), "{}", text);
+        assert!(lines[1].ends_with("    -   1.5     2"), "{}", text);
+    }
+
+    #[test]
+    fn plays_a_gauntlet() {
+        let config = MatchConfig { games: 2, adjudication: Adjudication { max_plies: Some(10), ..Adjudication::default() },
+            ..MatchConfig::default() };
+        let mut engines: Vec<Box<dyn MatchEngine>> = ["a", "b", "a"].iter()
+            .map(|name| Box::new(BuiltinEngine::new(name, Some(1))) as Box<dyn MatchEngine>)
+            .collect();
+        let mut games = vec![];
+        let table = run_tournament(&mut engines, Format::Gauntlet, &config, |i, j, _| games.push((i, j)));
+        assert_eq!(games, [(0, 1), (0, 1), (0, 2), (0, 2)]);
+        assert_eq!(table.total(0).1, 4);
+        assert_eq!(table.scores[1][2], None);
+        assert_eq!(table.names, ["a", "b", "a #2"]);
+    }
+}
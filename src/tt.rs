@@ -0,0 +1,251 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use crate::movegen::Move;
+use crate::search::MATE;
+
+// size of the table a Searcher starts with, what the UCI Hash option defaults to
+pub const DEFAULT_HASH_MB: usize = 16;
+
+// how the stored score relates to the real one, the search failed high or low when not exact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Bound {
+    #[default]
+    Exact,
+    Lower,
+    Upper,
+}
+
+// bits of the packed entry data, the move, the score offset to be positive, depth, bound and generation
+const SCORE_SHIFT: u32 = 24;
+const SCORE_OFFSET: i32 = 1 << 19;
+const DEPTH_SHIFT: u32 = 44;
+const BOUND_SHIFT: u32 = 52;
+const GENERATION_SHIFT: u32 = 54;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Entry {
+    best_move: Option<Move>,
+    score: i32,
+    depth: u8,
+    bound: Bound,
+    // the search that stored it, entries from older searches are replaced first
+    generation: u8,
+}
+
+impl Entry {
+    fn pack(&self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+        // a null move never gets stored, all zeros is no move
+        self.best_move.map_or(0, |mv| mv.to_bits() as u64)
+            | ((self.score + SCORE_OFFSET) as u64) << SCORE_SHIFT
+            | (self.depth as u64) << DEPTH_SHIFT
+            | bound << BOUND_SHIFT
+            | (self.generation as u64) << GENERATION_SHIFT
+    }
+
+    fn unpack(data: u64) -> Entry {
+        let best_move = Some(Move::from_bits(data as u32 & 0xff_ffff)).filter(|mv| !mv.is_null());
+        let bound = match data >> BOUND_SHIFT & 3 {
+            1 => Bound::Lower,
+            2 => Bound::Upper,
+            _ => Bound::Exact,
+        };
+        Entry { best_move, score: (data >> SCORE_SHIFT & 0xf_ffff) as i32 - SCORE_OFFSET, depth: (data >> DEPTH_SHIFT) as u8,
+            bound, generation: (data >> GENERATION_SHIFT) as u8 }
+    }
+}
+
+// the key is stored xored with the data, so an entry torn by two threads writing it at
+// once no longer matches its key and reads as a miss
+#[derive(Debug, Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+// what a probe finds, the score is relative to the root again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TtHit {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: usize,
+    pub bound: Bound,
+}
+
+// search results by zobrist hash, one entry per slot, shared by every thread of a search
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    generation: AtomicU8,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> TranspositionTable {
+        TranspositionTable::new(DEFAULT_HASH_MB)
+    }
+}
+
+impl TranspositionTable {
+    // the largest power of two number of entries that fits in `mb` megabytes, at least one
+    pub fn new(mb: usize) -> TranspositionTable {
+        TranspositionTable { slots: (0..entries_for(mb)).map(|_| Slot::default()).collect(), generation: AtomicU8::new(0) }
+    }
+
+    // drops everything stored, only reallocates when the size changes
+    pub fn resize(&mut self, mb: usize) {
+        let len = entries_for(mb);
+        if len == self.slots.len() {
+            self.clear();
+        } else {
+            *self = TranspositionTable::new(mb);
+        }
+    }
+
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+        self.generation.store(0, Ordering::Relaxed);
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    // called when a search starts so the entries it stores win over the ones left from before
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn entry(&self, key: u64) -> Option<Entry> {
+        let slot = &self.slots[self.index(key)];
+        let data = slot.data.load(Ordering::Relaxed);
+        (slot.key.load(Ordering::Relaxed) ^ data == key).then(|| Entry::unpack(data))
+    }
+
+    pub fn probe(&self, key: u64, ply: i32) -> Option<TtHit> {
+        let entry = self.entry(key).filter(|entry| entry.depth > 0)?;
+        Some(TtHit { best_move: entry.best_move, score: from_tt(entry.score, ply), depth: entry.depth as usize, bound: entry.bound })
+    }
+
+    pub fn store(&self, key: u64, depth: usize, score: i32, bound: Bound, best_move: Option<Move>, ply: i32) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let slot = &self.slots[self.index(key)];
+        let same = self.entry(key);
+        // a deeper result from this search is worth more than a shallow one for another position
+        let other = Entry::unpack(slot.data.load(Ordering::Relaxed));
+        if same.is_none() && other.generation == generation && other.depth as usize > depth {
+            return;
+        }
+        // a fail low has no best move, keep the one found before for the ordering
+        let best_move = best_move.or(same.and_then(|entry| entry.best_move));
+        let data = Entry { best_move, score: to_tt(score, ply), depth: depth.min(u8::MAX as usize) as u8, bound, generation }.pack();
+        slot.data.store(data, Ordering::Relaxed);
+        slot.key.store(key ^ data, Ordering::Relaxed);
+    }
+
+    // permille of the first thousand slots used by the current search, for UCI's hashfull
+    pub fn hashfull(&self) -> u32 {
+        let sample = &self.slots[..self.slots.len().min(1000)];
+        let generation = self.generation.load(Ordering::Relaxed);
+        let used = sample.iter().map(|slot| Entry::unpack(slot.data.load(Ordering::Relaxed)))
+            .filter(|entry| entry.depth > 0 && entry.generation == generation).count();
+        (used * 1000 / sample.len()) as u32
+    }
+
+    fn index(&self, key: u64) -> usize {
+        key as usize & (self.slots.len() - 1)
+    }
+}
+
+fn entries_for(mb: usize) -> usize {
+    let len = (mb * 1024 * 1024 / std::mem::size_of::<Slot>()).max(1);
+    // round down to a power of two so indexing is a mask
+    1 << (usize::BITS - 1 - len.leading_zeros())
+}
+
+// mate scores count the distance from the root, stored they count it from the node so
+// the entry holds for the same position found at any other ply
+fn to_tt(score: i32, ply: i32) -> i32 {
+    if score >= MATE - 1000 {
+        score + ply
+    } else if score <= -(MATE - 1000) {
+        score - ply
+    } else {
+        score
+    }
+}
+
+fn from_tt(score: i32, ply: i32) -> i32 {
+    if score >= MATE - 1000 {
+        score - ply
+    } else if score <= -(MATE - 1000) {
+        score + ply
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    #[test]
+    fn stores_and_finds_entries() {
+        let game = Game::initialize();
+        let mv = Move::from_uci("e2e4", &game).unwrap();
+        let table = TranspositionTable::new(1);
+        assert!(table.len().is_power_of_two());
+        assert_eq!(table.probe(game.hash(), 0), None);
+        table.store(game.hash(), 3, 25, Bound::Lower, Some(mv), 0);
+        assert_eq!(table.probe(game.hash(), 0), Some(TtHit { best_move: Some(mv), score: 25, depth: 3, bound: Bound::Lower }));
+        // an upper bound keeps the move it had
+        table.store(game.hash(), 4, 10, Bound::Upper, None, 0);
+        assert_eq!(table.probe(game.hash(), 0).and_then(|hit| hit.best_move), Some(mv));
+        // hashfull samples the first slots
+        table.store(1, 1, 0, Bound::Exact, None, 0);
+        assert_eq!(table.hashfull(), 1);
+        table.new_search();
+        assert_eq!(table.hashfull(), 0);
+        table.clear();
+        assert_eq!(table.probe(game.hash(), 0), None);
+
+        // moves keep their flags and promotions, scores their sign
+        let castle = Game::read_FEN("4k3/P7/8/8/8/8/8/4K2R w K - 0 1");
+        for uci in ["e1g1", "a7a8n"] {
+            let mv = Move::from_uci(uci, &castle).unwrap();
+            table.store(castle.hash(), 2, -MATE + 7, Bound::Upper, Some(mv), 0);
+            let hit = table.probe(castle.hash(), 0).unwrap();
+            assert_eq!((hit.best_move.unwrap().flags(), hit.best_move), (mv.flags(), Some(mv)));
+            assert_eq!((hit.score, hit.depth, hit.bound), (-MATE + 7, 2, Bound::Upper));
+        }
+    }
+
+    #[test]
+    fn mate_scores_are_relative_to_the_node() {
+        let table = TranspositionTable::new(1);
+        table.store(7, 2, MATE - 5, Bound::Exact, None, 3);
+        assert_eq!(table.probe(7, 1).map(|hit| hit.score), Some(MATE - 3));
+        table.store(9, 2, -MATE + 4, Bound::Exact, None, 4);
+        assert_eq!(table.probe(9, 6).map(|hit| hit.score), Some(-MATE + 6));
+    }
+
+    #[test]
+    fn resizing_to_the_same_size_keeps_the_allocation() {
+        let mut table = TranspositionTable::new(2);
+        let len = table.len();
+        table.store(1, 1, 0, Bound::Exact, None, 0);
+        table.resize(2);
+        assert_eq!((table.len(), table.probe(1, 0)), (len, None));
+        table.resize(8);
+        assert_eq!(table.len(), len * 4);
+    }
+}
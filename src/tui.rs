@@ -0,0 +1,711 @@
+use std::io;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+use ratatui::buffer::Buffer;
+use ratatui::crossterm::event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+    MouseButton, MouseEventKind};
+use ratatui::crossterm::execute;
+use ratatui::layout::{Constraint, Layout, Position, Rect};
+use ratatui::style::{Color as Shade, Modifier, Style as CellStyle};
+use ratatui::text::Line;
+use ratatui::widgets::{Paragraph, Widget};
+use ratatui::Frame;
+use crate::cligame::result_message;
+use crate::game::{Color, Game, PieceType};
+use crate::movegen::Move;
+use crate::outcome::Outcome;
+use crate::render::{self, piece_char, move_list, HighlightStyle, Palette, Style};
+use crate::search::{mate_in, SearchControl, SearchInfo, SearchOptions, SearchResult, Searcher, MAX_DEPTH};
+use crate::skill::{Skill, MAX_SKILL};
+use crate::square::Square;
+use crate::timeman::TimeControl;
+
+// 256 color codes
+const CURSOR: u8 = 67;
+const SELECTED: u8 = 71;
+const BAR_WHITE: u8 = 255;
+const BAR_BLACK: u8 = 238;
+const HELP: &str = "arrows or click to pick squares, Enter to move, or type a move in SAN or uci. Tab flips, Esc clears, Ctrl-C quits";
+const ANALYSIS_HELP: &str = "move for either side by arrows, clicks or typing, PgUp and PgDn step through the moves. Tab flips, Ctrl-C quits";
+// how many of the best lines the analysis shows
+const ANALYSIS_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Tab,
+    Esc,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    // a left button press at a 0-based column and row of the screen
+    Click { column: u16, row: u16 },
+    Quit,
+}
+
+// the key a terminal event is, key releases and everything but left clicks are not
+pub fn key_from_event(event: &Event) -> Option<Key> {
+    match event {
+        Event::Key(key) if key.kind != KeyEventKind::Release => match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Key::Quit),
+            KeyCode::Char(ch) => Some(Key::Char(ch)),
+            KeyCode::Enter => Some(Key::Enter),
+            KeyCode::Backspace => Some(Key::Backspace),
+            KeyCode::Tab => Some(Key::Tab),
+            KeyCode::Esc => Some(Key::Esc),
+            KeyCode::Up => Some(Key::Up),
+            KeyCode::Down => Some(Key::Down),
+            KeyCode::Left => Some(Key::Left),
+            KeyCode::Right => Some(Key::Right),
+            KeyCode::PageUp => Some(Key::PageUp),
+            KeyCode::PageDown => Some(Key::PageDown),
+            _ => None,
+        },
+        Event::Mouse(mouse) if mouse.kind == MouseEventKind::Down(MouseButton::Left) => {
+            Some(Key::Click { column: mouse.column, row: mouse.row })
+        }
+        _ => None,
+    }
+}
+
+pub fn format_clock(time: Duration) -> String {
+    let tenths = time.as_millis() / 100;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
+
+// "+0.35" in pawns or "#-3" for a mate, from white's point of view
+pub fn format_eval(score: i32) -> String {
+    match mate_in(score) {
+        Some(moves) => format!("#{}", moves),
+        None => format!("{:+.2}", score as f64 / 100.0),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TuiConfig {
+    pub human: Color,
+    pub skill: Skill,
+    // both sides start with `time` and get `increment` after each move
+    pub time: Duration,
+    pub increment: Duration,
+    // no opponent and no clocks, the engine analyses whatever position is on the board
+    pub analyze: bool,
+    // how the board looks, see theme.rs
+    pub pieces: Style,
+    pub palette: Palette,
+    pub highlight: HighlightStyle,
+}
+
+impl Default for TuiConfig {
+    fn default() -> TuiConfig {
+        TuiConfig { human: Color::White, skill: Skill::new(MAX_SKILL), time: Duration::from_secs(300), increment: Duration::from_secs(2),
+            analyze: false, pieces: Style::Figurine, palette: Palette::default(), highlight: HighlightStyle::Fill }
+    }
+}
+
+type Thinking = Receiver<(Searcher, SearchResult, Option<Move>)>;
+
+enum Progress {
+    Info(SearchInfo),
+    // the search is over, stopped or at MAX_DEPTH, and the searcher is back
+    Done(Box<Searcher>),
+}
+
+// the search running on the analysis thread, for the position in `fen`
+struct Analysis {
+    fen: String,
+    control: SearchControl,
+    progress: Receiver<Progress>,
+}
+
+// a line's moves in SAN, up to `count` of them
+fn line_san(game: &Game, pv: &[Move], count: usize) -> String {
+    let mut replay = game.clone();
+    let moves: Vec<String> = pv.iter().take(count).map(|mv| {
+        let san = replay.to_san(*mv);
+        replay.make_move(*mv);
+        san
+    }).collect();
+    moves.join(" ")
+}
+
+// everything on the screen, kept apart from the terminal so it can be driven by tests
+pub struct App {
+    game: Game,
+    config: TuiConfig,
+    // the searcher is away on the engine's thread while it thinks
+    searcher: Option<Searcher>,
+    thinking: Option<Thinking>,
+    clocks: [Duration; 2],
+    turn_started: Instant,
+    // the cursor as a screen row and column of the board, so flipping leaves it where it is on screen
+    cursor: (usize, usize),
+    selected: Option<usize>,
+    input: String,
+    message: String,
+    // from white's point of view, the engine's last search
+    eval: Option<i32>,
+    flipped: bool,
+    analysis: Option<Analysis>,
+    // the position the analysis last started on, and the deepest lines it has sent for it
+    analyzed: String,
+    lines: Vec<SearchInfo>,
+    // moves stepped back over, the next one last
+    future: Vec<Move>,
+    // where the squares were last drawn, for mouse clicks
+    board: Rect,
+}
+
+impl App {
+    pub fn new(game: Game, config: TuiConfig) -> App {
+        App {
+            game, config, searcher: Some(Searcher::new()), thinking: None, clocks: [config.time; 2],
+            turn_started: Instant::now(), cursor: (6, 4), selected: None, input: String::new(),
+            message: String::new(), eval: None, flipped: config.human == Color::Black, analysis: None,
+            analyzed: String::new(), lines: vec![], future: vec![], board: Rect::default(),
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    // the square drawn at a screen position, a square is three cells wide
+    pub fn square_at(&self, column: u16, row: u16) -> Option<Square> {
+        let board = self.board;
+        if !board.contains(Position::new(column, row)) {
+            return None;
+        }
+        Some(Square::from_index(render::rows(self.flipped)[(row - board.top()) as usize][(column - board.left()) as usize / 3]))
+    }
+
+    pub fn remaining(&self, color: Color) -> Duration {
+        let clock = self.clocks[color as usize];
+        if color == self.game.active_color && self.game.outcome.is_none() {
+            clock.saturating_sub(self.turn_started.elapsed())
+        } else {
+            clock
+        }
+    }
+
+    fn play(&mut self, mv: Move) {
+        let side = self.game.active_color;
+        self.clocks[side as usize] = self.remaining(side) + self.config.increment;
+        // stepping forward over the same move keeps the rest of the line
+        match self.future.last() {
+            Some(next) if next.to_uci() == mv.to_uci() => {
+                self.future.pop();
+            }
+            _ => self.future.clear(),
+        }
+        self.game.make_move(mv);
+        self.game.update_outcome();
+        self.turn_started = Instant::now();
+        self.selected = None;
+        self.input.clear();
+    }
+
+    // back or forward a move in analysis
+    fn step(&mut self, back: bool) {
+        if back {
+            if let Some(mv) = self.game.unmake_move() {
+                self.future.push(mv);
+            }
+        } else if let Some(mv) = self.future.pop() {
+            self.game.make_move(mv);
+            self.game.update_outcome();
+        }
+        self.selected = None;
+    }
+
+    // flags, the engine's reply and its next search, called between keys
+    pub fn tick(&mut self) {
+        if self.config.analyze {
+            return self.analyze();
+        }
+        if self.game.outcome.is_some() {
+            return;
+        }
+        let side = self.game.active_color;
+        if self.remaining(side).is_zero() {
+            self.game.flag_fall(side);
+            return;
+        }
+        if side == self.config.human {
+            return;
+        }
+        let Some(thinking) = &self.thinking else {
+            return self.start_engine();
+        };
+        let Ok((searcher, result, mv)) = thinking.try_recv() else {
+            return;
+        };
+        self.thinking = None;
+        self.searcher = Some(searcher);
+        self.eval = Some(if side == Color::White { result.score } else { -result.score });
+        if let Some(mv) = mv {
+            self.message = format!("engine plays {}", self.game.to_san(mv));
+            self.play(mv);
+        }
+    }
+
+    // keeps the analysis on the position on the board, a search for an old one is stopped
+    // and a new one started once the searcher is back from it
+    fn analyze(&mut self) {
+        let fen = self.game.to_fen();
+        if let Some(analysis) = &self.analysis {
+            let current = analysis.fen == fen;
+            for progress in analysis.progress.try_iter() {
+                match progress {
+                    Progress::Info(info) if current => {
+                        if info.multipv == 1 {
+                            self.lines.clear();
+                            let side = self.game.active_color;
+                            self.eval = Some(if side == Color::White { info.score } else { -info.score });
+                        }
+                        self.lines.push(info);
+                    }
+                    Progress::Info(_) => {}
+                    Progress::Done(searcher) => self.searcher = Some(*searcher),
+                }
+            }
+            if self.searcher.is_some() {
+                self.analysis = None;
+            } else if !current {
+                analysis.control.stop();
+            }
+            // coming back to a position left part way through has it analysed afresh
+            if !current {
+                self.analyzed.clear();
+            }
+        }
+        if self.analyzed != fen {
+            self.lines.clear();
+            self.eval = None;
+        }
+        if self.analysis.is_some() || self.analyzed == fen || self.game.outcome.is_some() {
+            return;
+        }
+        let Some(mut searcher) = self.searcher.take() else {
+            return;
+        };
+        let (sender, progress) = mpsc::channel();
+        let info = sender.clone();
+        searcher.set_listener(move |search: &SearchInfo| {
+            let _ = info.send(Progress::Info(search.clone()));
+        });
+        // reset here rather than on the thread, so a stop sent before the search starts still counts
+        let control = searcher.control().clone();
+        control.reset(false);
+        let options = SearchOptions { depth: MAX_DEPTH, multi_pv: ANALYSIS_LINES, ..SearchOptions::default() };
+        let mut game = self.game.clone();
+        thread::spawn(move || {
+            searcher.search_prepared(&mut game, &options, &[]);
+            searcher.clear_listener();
+            let _ = sender.send(Progress::Done(Box::new(searcher)));
+        });
+        self.analyzed = fen.clone();
+        self.analysis = Some(Analysis { fen, control, progress });
+    }
+
+    fn start_engine(&mut self) {
+        let Some(mut searcher) = self.searcher.take() else {
+            return;
+        };
+        let clock = TimeControl { time: self.remaining(self.game.active_color), increment: self.config.increment, moves_to_go: None };
+        let options = SearchOptions { depth: MAX_DEPTH, time: Some(clock.limits()), ..SearchOptions::default() };
+        let (skill, mut game) = (self.config.skill, self.game.clone());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let result = searcher.search_with(&mut game, &skill.limit(&options));
+            let mv = skill.choose(&game, &result);
+            let _ = sender.send((searcher, result, mv));
+        });
+        self.thinking = Some(receiver);
+    }
+
+    // false when the user is leaving, any key does once the game is over
+    pub fn key(&mut self, key: Key) -> bool {
+        if key == Key::Quit || (self.game.outcome.is_some() && !self.config.analyze) {
+            if let Some(analysis) = &self.analysis {
+                analysis.control.stop();
+            }
+            return false;
+        }
+        let (row, column) = self.cursor;
+        match key {
+            Key::Up => self.cursor.0 = row.saturating_sub(1),
+            Key::Down => self.cursor.0 = (row + 1).min(7),
+            Key::Left => self.cursor.1 = column.saturating_sub(1),
+            Key::Right => self.cursor.1 = (column + 1).min(7),
+            Key::Tab => self.flipped = !self.flipped,
+            Key::PageUp | Key::PageDown if self.config.analyze => self.step(key == Key::PageUp),
+            Key::PageUp | Key::PageDown => {}
+            Key::Esc => {
+                self.selected = None;
+                self.input.clear();
+            }
+            Key::Char(ch) => self.input.push(ch),
+            Key::Backspace => {
+                self.input.pop();
+            }
+            Key::Enter if self.input.trim().is_empty() => self.select(render::rows(self.flipped)[row][column]),
+            Key::Enter => {
+                let text = self.input.trim().to_string();
+                match self.game.parse_move(&text) {
+                    Ok(mv) if self.is_human_turn() => {
+                        self.message.clear();
+                        self.play(mv);
+                    }
+                    Ok(_) => self.message = "Wait for the engine to move".to_string(),
+                    Err(err) => self.message = err,
+                }
+                self.input.clear();
+            }
+            Key::Click { column, row } => {
+                if let Some(square) = self.square_at(column, row) {
+                    self.cursor = ((row - self.board.top()) as usize, (column - self.board.left()) as usize / 3);
+                    self.select(square.index());
+                }
+            }
+            Key::Quit => {}
+        }
+        true
+    }
+
+    // both sides are the user's in analysis
+    fn is_human_turn(&self) -> bool {
+        self.config.analyze || self.game.active_color == self.config.human
+    }
+
+    // picks up one of your pieces, or moves the one picked up to `square`
+    fn select(&mut self, square: usize) {
+        if !self.is_human_turn() {
+            self.message = "Wait for the engine to move".to_string();
+            return;
+        }
+        let own_piece = self.game.piece_at(Square::from_index(square)).is_some_and(|piece| piece.color == self.game.active_color);
+        let Some(from) = self.selected else {
+            self.selected = own_piece.then_some(square);
+            return;
+        };
+        // a pawn reaching the last rank becomes a queen
+        let mv = self.game.legal_moves().into_iter()
+            .filter(|mv| mv.from() == from && mv.to() == square)
+            .find(|mv| mv.promotion().is_none_or(|piece| piece == PieceType::Queen));
+        match mv {
+            Some(mv) => {
+                self.message.clear();
+                self.play(mv);
+            }
+            None if own_piece && square != from => self.selected = Some(square),
+            None => self.selected = None,
+        }
+    }
+
+    // a square's three cells, the piece in the middle and the outline of a last move around it
+    fn square(&self, square: usize, highlights: &[usize], check: Option<usize>) -> (CellStyle, String) {
+        let (row, column) = self.cursor;
+        let palette = self.config.palette;
+        let last_move = highlights.contains(&square);
+        let square_color = if render::rows(self.flipped)[row][column] == square {
+            CURSOR
+        } else if self.selected == Some(square) {
+            SELECTED
+        } else if check == Some(square) {
+            palette.check
+        } else if last_move && self.config.highlight == HighlightStyle::Fill {
+            palette.last_move
+        } else if (square / 8 + square % 8).is_multiple_of(2) {
+            palette.dark
+        } else {
+            palette.light
+        };
+        let (piece_color, glyph) = match self.game.piece_at(Square::from_index(square)) {
+            Some(piece) => {
+                let color = if piece.color == Color::White { palette.white_piece } else { palette.black_piece };
+                (color, piece_char(piece, self.config.pieces))
+            }
+            None => (palette.black_piece, ' '),
+        };
+        let (open, close) = if last_move && self.config.highlight == HighlightStyle::Outline { ('[', ']') } else { (' ', ' ') };
+        let style = CellStyle::new().bg(Shade::Indexed(square_color)).fg(Shade::Indexed(piece_color)).add_modifier(Modifier::BOLD);
+        (style, format!("{}{}{}", open, glyph, close))
+    }
+
+    fn clock_line(&self, color: Color) -> String {
+        let name = if color == Color::White { "white" } else { "black" };
+        let to_move = if color == self.game.active_color && self.game.outcome.is_none() { " <" } else { "" };
+        format!("{} {}{}", name, format_clock(self.remaining(color)), to_move)
+    }
+
+    // what goes beside the board's eight ranks in a game
+    fn game_panel(&self, moves: &[String], bottom: Color) -> Vec<String> {
+        let mut panel = vec![self.clock_line(bottom.opponent()), String::new()];
+        panel.push(match (self.eval, &self.thinking) {
+            (_, Some(_)) => "engine thinking...".to_string(),
+            (Some(eval), None) => format!("eval {}", format_eval(eval)),
+            (None, None) => String::new(),
+        });
+        panel.extend(moves[moves.len().saturating_sub(4)..].iter().cloned());
+        panel.resize(7, String::new());
+        panel.push(self.clock_line(bottom));
+        panel
+    }
+
+    // and in analysis, the search's depth and best lines with the scores from white's side
+    fn analysis_panel(&self, moves: &[String]) -> Vec<String> {
+        let white = |score: i32| if self.game.active_color == Color::White { score } else { -score };
+        let mut panel = vec![match (self.lines.first(), &self.analysis) {
+            (Some(line), Some(_)) => format!("depth {}, {} nodes", line.depth, line.nodes),
+            (Some(line), None) => format!("depth {}, done", line.depth),
+            (None, _) if self.game.outcome.is_some() => "game over".to_string(),
+            (None, _) => "analysing...".to_string(),
+        }, String::new()];
+        for line in &self.lines {
+            panel.push(format!("{:>6} {}", format_eval(white(line.score)), line_san(&self.game, &line.pv, 6)));
+        }
+        panel.resize(2 + ANALYSIS_LINES + 1, String::new());
+        panel.extend(moves.last().cloned());
+        panel.resize(7, String::new());
+        panel.push(format!("ply {} of {}", self.game.history.len(), self.game.history.len() + self.future.len()));
+        panel
+    }
+
+    // the whole screen: the eval bar, the board and the panel beside it, then the input line,
+    // the last message and the keys
+    pub fn render(&mut self, frame: &mut Frame) {
+        let [title, _, main, _, input, message, help] = Layout::vertical([
+            Constraint::Length(1), Constraint::Length(1), Constraint::Length(9), Constraint::Length(1),
+            Constraint::Length(1), Constraint::Length(1), Constraint::Length(1),
+        ]).areas(frame.area());
+        let [bar, _, ranks, board, _, panel] = Layout::horizontal([
+            Constraint::Length(2), Constraint::Length(1), Constraint::Length(2), Constraint::Length(24),
+            Constraint::Length(3), Constraint::Min(0),
+        ]).areas(main);
+        let rows = render::rows(self.flipped);
+        let bottom = if self.flipped { Color::Black } else { Color::White };
+        self.board = Rect { height: 8, ..board };
+
+        let heading = if self.config.analyze {
+            "Rustic Chess analysis".to_string()
+        } else {
+            format!("Rustic Chess, you play {} against level {}", if self.config.human == Color::White { "white" } else { "black" },
+                self.config.skill.level)
+        };
+        frame.render_widget(Paragraph::new(heading).style(CellStyle::new().add_modifier(Modifier::BOLD)), title);
+
+        // white's share of the bar by the engine's winning chances
+        let share = self.eval.map_or(0.5, |eval| 1.0 / (1.0 + 10f64.powf(-eval as f64 / 400.0)));
+        frame.render_widget(EvalBar { white_rows: (share * 8.0).round() as u16, bottom }, Rect { height: 8, ..bar });
+        let labels: Vec<Line> = rows.iter().map(|row| Line::from(format!("{}", row[0] / 8 + 1))).collect();
+        frame.render_widget(Paragraph::new(labels), ranks);
+        frame.render_widget(Board { app: self }, self.board);
+        let files: String = rows[7].iter().map(|square| format!(" {} ", (b'a' + (square % 8) as u8) as char)).collect();
+        frame.render_widget(Paragraph::new(files), Rect { y: board.y + 8, height: 1, ..board });
+
+        let moves = move_list(&self.game);
+        let lines = if self.config.analyze { self.analysis_panel(&moves) } else { self.game_panel(&moves, bottom) };
+        frame.render_widget(Paragraph::new(lines.into_iter().map(Line::from).collect::<Vec<_>>()), panel);
+
+        frame.render_widget(Paragraph::new(format!("> {}", self.input)), input);
+        let text = match self.game.outcome {
+            Some(outcome) if self.config.analyze => format!("{} {}", outcome.result, outcome.reason),
+            Some(outcome) => format!("{}, press any key", result_message(&outcome, self.config.human)),
+            None => self.message.clone(),
+        };
+        frame.render_widget(Paragraph::new(text), message);
+        let keys = if self.config.analyze { ANALYSIS_HELP } else { HELP };
+        frame.render_widget(Paragraph::new(keys).style(CellStyle::new().add_modifier(Modifier::DIM)), help);
+    }
+}
+
+// the eight ranks of squares as the app sees them
+struct Board<'a> {
+    app: &'a App,
+}
+
+impl Widget for Board<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let game = &self.app.game;
+        let highlights: Vec<usize> = game.history.last()
+            .filter(|undo| !undo.mv.is_null())
+            .map(|undo| vec![undo.mv.from(), undo.mv.to()])
+            .unwrap_or_default();
+        let check = render::checked_king(game);
+        for (y, row) in render::rows(self.app.flipped).iter().enumerate() {
+            for (x, square) in row.iter().enumerate() {
+                // a terminal too small for the board gets what fits of it
+                let (column, line) = (area.x + x as u16 * 3, area.y + y as u16);
+                if column + 3 > area.right() || line >= area.bottom() {
+                    continue;
+                }
+                let (style, text) = self.app.square(*square, &highlights, check);
+                buf.set_string(column, line, text, style);
+            }
+        }
+    }
+}
+
+// the engine's view of the game, white filling it from white's side of the board
+struct EvalBar {
+    white_rows: u16,
+    bottom: Color,
+}
+
+impl Widget for EvalBar {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        for y in 0..area.height.min(8) {
+            let white = if self.bottom == Color::White { 7 - y < self.white_rows } else { y < self.white_rows };
+            let color = Shade::Indexed(if white { BAR_WHITE } else { BAR_BLACK });
+            buf.set_string(area.x, area.y + y, " ".repeat(area.width as usize), CellStyle::new().bg(color));
+        }
+    }
+}
+
+// mouse reports while it lives, and the terminal as it was after. ratatui's panic hook
+// restores raw mode and the alternate screen before a panic message is printed
+struct MouseCapture;
+
+impl Drop for MouseCapture {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), DisableMouseCapture);
+        ratatui::restore();
+    }
+}
+
+// plays `game` full screen until it is over and a key is pressed or the user quits
+pub fn run(game: Game, config: TuiConfig) -> io::Result<Option<Outcome>> {
+    let mut terminal = ratatui::try_init()?;
+    let _capture = MouseCapture;
+    execute!(io::stdout(), EnableMouseCapture)?;
+    let mut app = App::new(game, config);
+    loop {
+        app.tick();
+        terminal.draw(|frame| app.render(frame))?;
+        // often enough for the clocks to tick by the tenth
+        if !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
+        if let Some(key) = key_from_event(&event::read()?) {
+            if !app.key(key) {
+                return Ok(app.game.outcome);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::crossterm::event::{KeyEvent, MouseEvent};
+    use ratatui::Terminal;
+
+    // the text of the screen, a line per row
+    fn screen(app: &mut App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(100, 16)).unwrap();
+        terminal.draw(|frame| app.render(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| (0..buffer.area.width).map(|x| buffer[(x, y)].symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn type_move(app: &mut App, text: &str) {
+        for ch in text.chars() {
+            app.key(Key::Char(ch));
+        }
+        app.key(Key::Enter);
+    }
+
+    #[test]
+    fn reads_keys_and_mouse_clicks() {
+        let key = |code, modifiers| key_from_event(&Event::Key(KeyEvent::new(code, modifiers)));
+        assert_eq!(key(KeyCode::Char('e'), KeyModifiers::NONE), Some(Key::Char('e')));
+        assert_eq!(key(KeyCode::Char('c'), KeyModifiers::CONTROL), Some(Key::Quit));
+        assert_eq!(key(KeyCode::PageUp, KeyModifiers::NONE), Some(Key::PageUp));
+        assert_eq!(key(KeyCode::F(1), KeyModifiers::NONE), None);
+        let mouse = |kind| key_from_event(&Event::Mouse(MouseEvent { kind, column: 12, row: 4, modifiers: KeyModifiers::NONE }));
+        // a press and its release, only the press counts
+        assert_eq!(mouse(MouseEventKind::Down(MouseButton::Left)), Some(Key::Click { column: 12, row: 4 }));
+        assert_eq!(mouse(MouseEventKind::Up(MouseButton::Left)), None);
+
+        // the squares start after the eval bar and the rank labels, under the title
+        let mut app = App::new(Game::initialize(), TuiConfig::default());
+        assert_eq!(app.square_at(5, 2), None);
+        screen(&mut app);
+        assert_eq!(app.square_at(5, 2), Some(Square::A8));
+        assert_eq!(app.square_at(28, 9), Some(Square::H1));
+        assert_eq!(app.square_at(4, 9), None);
+        app.key(Key::Tab);
+        screen(&mut app);
+        assert_eq!(app.square_at(5, 2), Some(Square::H1));
+        assert_eq!(format_clock(Duration::from_millis(65_432)), "1:05.4");
+        assert_eq!(format_eval(-35), "-0.35");
+    }
+
+    #[test]
+    fn analyses_the_position_on_the_board() {
+        let mut app = App::new(Game::read_FEN("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"), TuiConfig { analyze: true, ..TuiConfig::default() });
+        let wait_for = |app: &mut App, depth: usize| {
+            let start = Instant::now();
+            while app.lines.first().is_none_or(|line| line.depth < depth) && start.elapsed() < Duration::from_secs(10) {
+                app.tick();
+                thread::sleep(Duration::from_millis(10));
+            }
+        };
+        wait_for(&mut app, 2);
+        let frame = screen(&mut app);
+        assert!(frame.contains("#1 Ra8#"), "{}", frame);
+        assert_eq!(app.eval.map(format_eval), Some("#1".to_string()));
+
+        // black's moves are the user's too, and stepping back restarts the search on the old position
+        type_move(&mut app, "Ra8");
+        assert!(app.game().outcome.is_some() && app.key(Key::Char('x')));
+        app.tick();
+        assert!(screen(&mut app).contains("1-0 checkmate"));
+        app.key(Key::PageUp);
+        assert_eq!(app.game().history.len(), 0);
+        wait_for(&mut app, 2);
+        assert!(screen(&mut app).contains("ply 0 of 1"));
+        app.key(Key::PageDown);
+        assert_eq!(app.game().history.len(), 1);
+        assert!(!app.key(Key::Quit));
+    }
+
+    #[test]
+    fn moves_with_the_cursor_clicks_or_typing() {
+        let mut app = App::new(Game::initialize(), TuiConfig::default());
+        // the cursor starts on e2
+        assert!(app.key(Key::Enter));
+        app.key(Key::Up);
+        app.key(Key::Up);
+        app.key(Key::Enter);
+        assert_eq!(app.game().history.last().unwrap().mv.to_uci(), "e2e4");
+        assert!(app.remaining(Color::Black) <= Duration::from_secs(300));
+
+        let mut app = App::new(Game::initialize(), TuiConfig::default());
+        screen(&mut app);
+        // g1 and f3 on screen
+        app.key(Key::Click { column: 24, row: 9 });
+        app.key(Key::Click { column: 21, row: 7 });
+        assert_eq!(app.game().history.last().unwrap().mv.to_uci(), "g1f3");
+        let frame = screen(&mut app);
+        assert!(frame.contains("1. Nf3") && frame.contains("white 5:01"), "{}", frame);
+
+        let mut app = App::new(Game::initialize(), TuiConfig::default());
+        type_move(&mut app, "Nc4");
+        assert!(screen(&mut app).contains("Illegal move Nc4"));
+        type_move(&mut app, "d4");
+        assert_eq!(app.game().history.len(), 1);
+        assert!(!app.key(Key::Quit));
+    }
+}
@@ -0,0 +1,690 @@
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use crate::game::{Color, Game};
+use crate::movegen::Move;
+use crate::nnue::Network;
+use crate::polyglot::Book;
+use crate::search::{mate_in, Evaluator, SearchControl, SearchInfo, SearchOptions, SearchResult, Searcher, MAX_DEPTH};
+use crate::skill::{Skill, MAX_ELO, MAX_SKILL, MIN_ELO};
+#[cfg(feature = "online-tablebase")]
+use crate::tablebase::OnlineTablebase;
+use crate::timeman::{TimeControl, TimeLimits};
+use crate::tt::DEFAULT_HASH_MB;
+use crate::ucioption::{parse_setoption, OptionKind, OptionRegistry};
+
+pub const ENGINE_NAME: &str = concat!("Rustic Chess ", env!("CARGO_PKG_VERSION"));
+pub const ENGINE_AUTHOR: &str = "Swaymaw";
+// set to anything to keep the engine off the network whatever the options say
+#[cfg(feature = "online-tablebase")]
+pub const OFFLINE_VAR: &str = "RUSTIC_CHESS_OFFLINE";
+
+// everything the gui can set, see Uci::apply for what each one changes
+fn engine_options() -> OptionRegistry {
+    let mut options = OptionRegistry::new();
+    options.add("Hash", OptionKind::Spin { default: DEFAULT_HASH_MB as i64, min: 1, max: 4096 })
+        .add("Clear Hash", OptionKind::Button)
+        .add("Threads", OptionKind::Spin { default: 1, min: 1, max: 512 })
+        .add("MultiPV", OptionKind::Spin { default: 1, min: 1, max: 256 })
+        .add("Ponder", OptionKind::Check { default: false })
+        .add("Skill Level", OptionKind::Spin { default: MAX_SKILL as i64, min: 0, max: MAX_SKILL as i64 })
+        // UCI_Elo takes over from Skill Level while UCI_LimitStrength is on
+        .add("UCI_LimitStrength", OptionKind::Check { default: false })
+        .add("UCI_Elo", OptionKind::Spin { default: MIN_ELO as i64, min: MIN_ELO as i64, max: MAX_ELO as i64 })
+        .add("BookFile", OptionKind::String { default: String::new() })
+        // the network in EvalFile replaces the classical evaluation while Use NNUE is on
+        .add("EvalFile", OptionKind::String { default: String::new() })
+        .add("Use NNUE", OptionKind::Check { default: false })
+        // castling moves are sent as the king taking its rook, chess960 fens turn this on by themselves
+        .add("UCI_Chess960", OptionKind::Check { default: false });
+    // small endgames are looked up on lichess while analysing, Offline rules out any network access
+    #[cfg(feature = "online-tablebase")]
+    options.add("UCI_AnalyseMode", OptionKind::Check { default: false })
+        .add("OnlineTablebase", OptionKind::Check { default: false })
+        .add("Offline", OptionKind::Check { default: false });
+    options
+}
+
+// everything a go command can say, times are in milliseconds on the wire
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GoParams {
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movestogo: Option<u32>,
+    pub depth: Option<usize>,
+    pub nodes: Option<u64>,
+    pub mate: Option<usize>,
+    pub movetime: Option<Duration>,
+    pub infinite: bool,
+    pub ponder: bool,
+    // only these root moves are searched, all of them when empty
+    pub searchmoves: Vec<Move>,
+}
+
+impl GoParams {
+    // the words after go, searchmoves are read as moves in `game` up to the next keyword
+    pub fn parse(args: &[&str], game: &Game) -> Result<GoParams, String> {
+        let mut params = GoParams::default();
+        let mut words = args.iter().copied().peekable();
+        while let Some(word) = words.next() {
+            let mut number = || -> Result<u64, String> {
+                let value = words.next().ok_or_else(|| format!("Missing value after {}", word))?;
+                value.parse().map_err(|_| format!("Expected a number after {}, got '{}'", word, value))
+            };
+            match word {
+                "wtime" => params.wtime = Some(Duration::from_millis(number()?)),
+                "btime" => params.btime = Some(Duration::from_millis(number()?)),
+                "winc" => params.winc = Some(Duration::from_millis(number()?)),
+                "binc" => params.binc = Some(Duration::from_millis(number()?)),
+                "movestogo" => params.movestogo = Some(number()? as u32),
+                "depth" => params.depth = Some(number()? as usize),
+                "nodes" => params.nodes = Some(number()?),
+                "mate" => params.mate = Some(number()? as usize),
+                "movetime" => params.movetime = Some(Duration::from_millis(number()?)),
+                "infinite" => params.infinite = true,
+                "ponder" => params.ponder = true,
+                "searchmoves" => {
+                    while let Some(uci) = words.next_if(|word| !GO_KEYWORDS.contains(word)) {
+                        params.searchmoves.push(Move::from_uci(uci, game)?);
+                    }
+                }
+                _ => return Err(format!("Unknown go parameter: {}", word)),
+            }
+        }
+        Ok(params)
+    }
+
+    // the search limits for the side to move, on top of `base`. without a depth the search
+    // goes as deep as the other limits allow, and with no limit at all it goes to base.depth
+    pub fn search_options(&self, side: Color, base: &SearchOptions) -> SearchOptions {
+        let mut options = SearchOptions { nodes: self.nodes, mate: self.mate, ..*base };
+        let (time, increment) = match side {
+            Color::White => (self.wtime, self.winc),
+            Color::Black => (self.btime, self.binc),
+        };
+        if let Some(movetime) = self.movetime {
+            options.time = Some(TimeLimits::fixed(movetime));
+        } else if let Some(time) = time {
+            let clock = TimeControl { time, increment: increment.unwrap_or_default(), moves_to_go: self.movestogo };
+            options.time = Some(clock.limits());
+        }
+        // a mate in n moves is at most 2n - 1 plies away
+        let unlimited = self.infinite || self.ponder || options.time.is_some() || options.nodes.is_some();
+        options.depth = match (self.depth, self.mate) {
+            (Some(depth), _) => depth,
+            (None, Some(moves)) => (2 * moves).saturating_sub(1).max(1),
+            (None, None) if unlimited => MAX_DEPTH,
+            (None, None) => base.depth,
+        };
+        // a ponder search keeps its time limits, they apply from ponderhit on
+        if self.infinite {
+            options.time = None;
+        }
+        options
+    }
+}
+
+const GO_KEYWORDS: [&str; 11] =
+    ["wtime", "btime", "winc", "binc", "movestogo", "depth", "nodes", "mate", "movetime", "infinite", "ponder"];
+
+// "cp 35", or "mate 3" with the number of moves, negative when the side to move gets mated
+pub fn format_score(score: i32) -> String {
+    match mate_in(score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", score),
+    }
+}
+
+pub fn format_info(info: &SearchInfo) -> String {
+    let pv: Vec<String> = info.pv.iter().map(|mv| mv.to_uci()).collect();
+    format!("info depth {} seldepth {} multipv {} score {} nodes {} nps {} hashfull {} time {} pv {}",
+        info.depth, info.seldepth, info.multipv, format_score(info.score), info.nodes, info.nps, info.hashfull,
+        info.time.as_millis(), pv.join(" "))
+}
+
+// the engine side of a UCI session, fed one line from the gui at a time
+pub struct Uci<W: Write + Send + 'static> {
+    game: Game,
+    // taken by the worker thread while it searches and handed back when it is done
+    searcher: Option<Searcher>,
+    worker: Option<JoinHandle<Searcher>>,
+    control: SearchControl,
+    options: OptionRegistry,
+    // loaded from the BookFile option, moves in it are played without searching
+    book: Option<Book>,
+    // made the first time a position is looked up
+    #[cfg(feature = "online-tablebase")]
+    tablebase: Option<OnlineTablebase>,
+    out: Arc<Mutex<W>>,
+}
+
+impl<W: Write + Send + 'static> Uci<W> {
+    pub fn new(out: W) -> Uci<W> {
+        let out = Arc::new(Mutex::new(out));
+        let control = SearchControl::new();
+        let mut searcher = Searcher::with_control(control.clone());
+        let info_out = out.clone();
+        searcher.set_listener(move |info: &SearchInfo| {
+            let mut out = info_out.lock().unwrap();
+            let _ = writeln!(out, "{}", format_info(info));
+            let _ = out.flush();
+        });
+        Uci {
+            game: Game::initialize(), searcher: Some(searcher), worker: None, control, options: engine_options(), book: None,
+            #[cfg(feature = "online-tablebase")]
+            tablebase: None,
+            out,
+        }
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.worker.is_some()
+    }
+
+    // blocks until the running search, if any, has sent its bestmove
+    pub fn wait(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            self.searcher = Some(worker.join().expect("search thread panicked"));
+        }
+    }
+
+    // the searcher once no search is using it, a running one is stopped first
+    fn searcher(&mut self) -> &mut Searcher {
+        if self.is_searching() {
+            self.control.stop();
+            self.wait();
+        }
+        self.searcher.as_mut().unwrap()
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn options(&self) -> &OptionRegistry {
+        &self.options
+    }
+
+    // false once the gui sent quit
+    pub fn handle(&mut self, line: &str) -> bool {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((command, args)) = words.split_first() else {
+            return true;
+        };
+        match *command {
+            "uci" => {
+                self.send(&format!("id name {}", ENGINE_NAME));
+                self.send(&format!("id author {}", ENGINE_AUTHOR));
+                let lines: Vec<String> = self.options.iter().map(|option| option.to_string()).collect();
+                lines.iter().for_each(|line| self.send(line));
+                self.send("uciok");
+            }
+            "isready" => self.send("readyok"),
+            "ucinewgame" => {
+                self.searcher().clear_hash();
+                self.game = Game::initialize();
+            }
+            "position" => {
+                self.searcher();
+                if let Err(err) = self.position(args) {
+                    self.send(&format!("info string {}", err));
+                }
+            }
+            "setoption" => {
+                if let Err(err) = self.setoption(args) {
+                    self.send(&format!("info string {}", err));
+                }
+            }
+            "go" => {
+                self.searcher();
+                self.go(args);
+            }
+            // the worker sends the best move it has so far
+            "stop" => {
+                self.searcher();
+            }
+            "ponderhit" => self.control.ponderhit(),
+            "quit" => {
+                self.searcher();
+                return false;
+            }
+            _ => self.send(&format!("info string Unknown command: {}", line.trim())),
+        }
+        true
+    }
+
+    // position [startpos | fen <fen>] [moves <move>...], the position is left alone on an error
+    fn position(&mut self, args: &[&str]) -> Result<(), String> {
+        let moves_at = args.iter().position(|word| *word == "moves").unwrap_or(args.len());
+        let mut game = match args.first() {
+            Some(&"startpos") => Game::initialize(),
+            Some(&"fen") => Game::try_from_fen(&args[1..moves_at].join(" ")).map_err(|err| err.to_string())?,
+            _ => return Err("Expected startpos or fen".to_string()),
+        };
+        game.chess960 |= self.options.check("UCI_Chess960");
+        for uci in args.iter().skip(moves_at + 1) {
+            let mv = Move::from_uci(uci, &game)?;
+            game.make_move(mv);
+        }
+        self.game = game;
+        Ok(())
+    }
+
+    fn setoption(&mut self, args: &[&str]) -> Result<(), String> {
+        let (name, value) = parse_setoption(args)?;
+        let name = self.options.set(&name, &value)?.name.clone();
+        self.apply(&name)
+    }
+
+    // makes the engine follow an option that was just set
+    fn apply(&mut self, name: &str) -> Result<(), String> {
+        match name {
+            "Hash" => {
+                let mb = self.options.spin("Hash") as usize;
+                self.searcher().set_hash_mb(mb);
+            }
+            "Clear Hash" => self.searcher().clear_hash(),
+            "Threads" => {
+                let threads = self.options.spin("Threads") as usize;
+                self.searcher().set_threads(threads);
+            }
+            "BookFile" => {
+                let path = self.options.string("BookFile");
+                self.book = None;
+                if !path.is_empty() {
+                    let bytes = std::fs::read(path).map_err(|err| format!("Cannot read {}: {}", path, err))?;
+                    self.book = Some(Book::from_bytes(&bytes)?);
+                }
+            }
+            "EvalFile" | "Use NNUE" => {
+                let path = self.options.string("EvalFile");
+                let evaluator = match self.options.check("Use NNUE") {
+                    true if path.is_empty() => return Err("Use NNUE needs an EvalFile".to_string()),
+                    true => Evaluator::Nnue(Arc::new(Network::load(Path::new(path))?)),
+                    false => Evaluator::Classical,
+                };
+                self.searcher().set_evaluator(evaluator);
+            }
+            // the rest are read when a search starts
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn skill(&self) -> Skill {
+        if self.options.check("UCI_LimitStrength") {
+            Skill::from_elo(self.options.spin("UCI_Elo") as u32)
+        } else {
+            Skill::new(self.options.spin("Skill Level") as u8)
+        }
+    }
+
+    fn go(&mut self, args: &[&str]) {
+        let params = match GoParams::parse(args, &self.game) {
+            Ok(params) => params,
+            Err(err) => {
+                self.send(&format!("info string {}", err));
+                return;
+            }
+        };
+        // an infinite or ponder search may not answer before stop or ponderhit, so it searches instead
+        if !params.infinite && !params.ponder {
+            if let Some((mv, _)) = self.book.as_ref().and_then(|book| book.probe(&self.game).first().copied()) {
+                self.send(&format!("bestmove {}", mv.to_uci()));
+                return;
+            }
+        }
+        #[cfg(feature = "online-tablebase")]
+        self.probe_tablebase();
+        let base = SearchOptions { multi_pv: self.options.spin("MultiPV") as usize, ..SearchOptions::default() };
+        let options = params.search_options(self.game.active_color, &base);
+        let skill = self.skill();
+        let send_ponder = self.options.check("Ponder");
+        let mut searcher = self.searcher.take().unwrap();
+        let mut game = self.game.clone();
+        let control = self.control.clone();
+        let out = self.out.clone();
+        control.reset(params.ponder);
+        self.worker = Some(thread::spawn(move || {
+            let result = searcher.search_prepared(&mut game, &skill.limit(&options), &params.searchmoves);
+            // uci wants no bestmove during an infinite or ponder search until it is stopped or the ponder move played
+            while !control.is_stopped() && (params.infinite || control.is_pondering()) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            let mut out = out.lock().unwrap();
+            let _ = writeln!(out, "{}", bestmove(&game, &result, skill, send_ponder));
+            let _ = out.flush();
+            searcher
+        }));
+    }
+
+    // the tablebase verdict goes to the gui as an info string, the search runs all the same
+    #[cfg(feature = "online-tablebase")]
+    fn probe_tablebase(&mut self) {
+        if !self.options.check("UCI_AnalyseMode") || !self.options.check("OnlineTablebase") {
+            return;
+        }
+        let offline = self.options.check("Offline") || std::env::var_os(OFFLINE_VAR).is_some();
+        if self.tablebase.is_none() {
+            match OnlineTablebase::new(offline) {
+                Ok(tablebase) => self.tablebase = Some(tablebase),
+                Err(err) => return self.send(&format!("info string {}", err)),
+            }
+        }
+        let tablebase = self.tablebase.as_mut().unwrap();
+        tablebase.offline = offline;
+        let Some(result) = tablebase.probe(&self.game) else {
+            return;
+        };
+        let mut line = format!("info string tablebase {}", result.category);
+        if let Some(dtz) = result.dtz {
+            line += &format!(" dtz {}", dtz);
+        }
+        if let Some(dtm) = result.dtm {
+            line += &format!(" dtm {}", dtm);
+        }
+        if let Some(mv) = result.best_move {
+            line += &format!(" best {}", mv.to_uci());
+        }
+        self.send(&line);
+    }
+
+    fn send(&self, line: &str) {
+        let mut out = self.out.lock().unwrap();
+        let _ = writeln!(out, "{}", line);
+        let _ = out.flush();
+    }
+}
+
+fn bestmove(game: &Game, result: &SearchResult, skill: Skill, send_ponder: bool) -> String {
+    let best_move = skill.choose(game, result);
+    // the predicted reply only belongs to the best line
+    let ponder = result.ponder_move().filter(|_| best_move == result.best_move && send_ponder);
+    match (best_move, ponder) {
+        (Some(best), Some(ponder)) => format!("bestmove {} ponder {}", best.to_uci(), ponder.to_uci()),
+        (Some(best), None) => format!("bestmove {}", best.to_uci()),
+        (None, _) => "bestmove 0000".to_string(),
+    }
+}
+
+// reads commands until quit or the end of the input, the search runs on its own thread so
+// stop and ponderhit are read while it thinks
+pub fn run(input: impl BufRead, output: impl Write + Send + 'static) -> io::Result<()> {
+    let mut uci = Uci::new(output);
+    for line in input.lines() {
+        if !uci.handle(&line?) {
+            return Ok(());
+        }
+    }
+    // a search started by the last line still gets to finish
+    uci.wait();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::MATE;
+
+    fn output(uci: &Uci<Vec<u8>>) -> String {
+        String::from_utf8(uci.out.lock().unwrap().clone()).unwrap()
+    }
+
+    #[test]
+    fn identifies_itself() {
+        let mut uci = Uci::new(vec![]);
+        assert!(uci.handle("uci"));
+        assert!(uci.handle("isready"));
+        assert!(!uci.handle("quit"));
+        let out = output(&uci);
+        assert!(out.starts_with("id name Rustic Chess"));
+        assert!(out.contains("option name Hash type spin default 16 min 1 max 4096\n"));
+        assert!(out.contains("option name Skill Level type spin default 20 min 0 max 20\n"));
+        assert!(out.ends_with("uciok\nreadyok\n"));
+    }
+
+    #[test]
+    fn sets_up_positions() {
+        let mut uci = Uci::new(vec![]);
+        uci.handle("position startpos moves e2e4 e7e5 g1f3");
+        assert_eq!(uci.game().to_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        uci.handle(&format!("position fen {} moves e1g1", fen));
+        assert_eq!(uci.game().to_fen(), "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R4RK1 b kq - 1 1");
+        // an illegal move leaves the last good position in place
+        uci.handle("position startpos moves e2e5");
+        assert!(output(&uci).contains("info string Illegal move e2e5"));
+        assert_eq!(uci.game().active_color, crate::game::Color::Black);
+        uci.handle("ucinewgame");
+        assert_eq!(uci.game().to_fen(), Game::initialize().to_fen());
+    }
+
+    #[cfg(feature = "online-tablebase")]
+    #[test]
+    fn stays_off_the_network_when_offline() {
+        let mut uci = Uci::new(vec![]);
+        for name in ["UCI_AnalyseMode", "OnlineTablebase", "Offline"] {
+            uci.handle(&format!("setoption name {} value true", name));
+        }
+        uci.handle("position fen 4k3/8/8/8/8/8/8/4K2R w - - 0 1");
+        uci.handle("go depth 1");
+        uci.wait();
+        let out = output(&uci);
+        assert!(!out.contains("tablebase"), "{}", out);
+        assert!(out.contains("bestmove"));
+        assert_eq!(uci.tablebase.as_ref().map(|tablebase| tablebase.offline), Some(true));
+    }
+
+    #[test]
+    fn searches_and_reports() {
+        let mut uci = Uci::new(vec![]);
+        uci.handle("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        uci.handle("go depth 2");
+        uci.wait();
+        let out = output(&uci);
+        assert!(out.contains("info depth 1 "));
+        assert!(out.contains("score mate 1 "));
+        assert!(out.ends_with("bestmove a1a8\n"));
+    }
+
+    #[test]
+    fn follows_options() {
+        let mut uci = Uci::new(vec![]);
+        uci.handle("setoption name MultiPV value 3");
+        uci.handle("setoption name Hash value 1");
+        uci.handle("setoption name Ponder value true");
+        assert_eq!(uci.options().spin("MultiPV"), 3);
+        uci.handle("go depth 2");
+        uci.wait();
+        let out = output(&uci);
+        assert!(out.contains("multipv 3 "));
+        assert!(out.lines().last().unwrap().contains(" ponder "));
+        uci.handle("setoption name Hash value 0");
+        uci.handle("setoption name BookFile value /no/such/book.bin");
+        let out = output(&uci);
+        assert!(out.contains("info string 0 is outside 1..4096"));
+        assert!(out.contains("info string Cannot read /no/such/book.bin"));
+
+        // a weak engine picks among extra lines without sending them
+        let mut uci = Uci::new(vec![]);
+        uci.handle("setoption name Skill Level value 0");
+        uci.handle("go depth 2");
+        uci.wait();
+        let out = output(&uci);
+        assert!(out.contains("multipv 1 ") && !out.contains("multipv 2 "), "{}", out);
+    }
+
+    #[test]
+    fn limits_strength_by_elo() {
+        let mut uci = Uci::new(vec![]);
+        uci.handle("setoption name UCI_Elo value 1600");
+        uci.handle("setoption name Skill Level value 3");
+        assert_eq!(uci.skill(), Skill::new(3));
+        uci.handle("setoption name UCI_LimitStrength value true");
+        assert_eq!(uci.skill(), Skill::from_elo(1600));
+        assert_eq!(uci.skill().level, 10);
+        uci.handle("setoption name UCI_Elo value 700");
+        assert!(output(&uci).contains("info string 700 is outside 800..2400"));
+        uci.handle("setoption name UCI_Elo value 800");
+        uci.handle("go depth 2");
+        uci.wait();
+        let out = output(&uci);
+        assert_eq!(uci.skill().level, 0);
+        assert!(out.contains("multipv 1 ") && !out.contains("multipv 2 "), "{}", out);
+        uci.handle("setoption name UCI_LimitStrength value false");
+        assert_eq!(uci.skill(), Skill::new(3));
+    }
+
+    #[test]
+    fn evaluates_with_a_network_file() {
+        let path = std::env::temp_dir().join(format!("rustic-uci-{}.nnue", std::process::id()));
+        std::fs::write(&path, crate::nnue::tests::test_network()).unwrap();
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let score = |uci: &mut Uci<Vec<u8>>| {
+            uci.out.lock().unwrap().clear();
+            uci.handle("go depth 1");
+            uci.wait();
+            let out = output(uci);
+            let info = out.lines().find(|line| line.contains(" score cp ")).unwrap().to_string();
+            info.split_whitespace().skip_while(|word| *word != "cp").nth(1).unwrap().parse::<i32>().unwrap()
+        };
+        let expected = |evaluator: Evaluator| {
+            let mut searcher = Searcher::new();
+            searcher.set_evaluator(evaluator);
+            searcher.search_with(&mut Game::read_FEN(fen), &SearchOptions { depth: 1, ..SearchOptions::default() }).score
+        };
+
+        let mut uci = Uci::new(vec![]);
+        uci.handle(&format!("position fen {}", fen));
+        uci.handle("setoption name Use NNUE value true");
+        assert!(output(&uci).contains("info string Use NNUE needs an EvalFile"));
+        uci.handle(&format!("setoption name EvalFile value {}", path.display()));
+        let network = Arc::new(Network::load(&path).unwrap());
+        let nnue = score(&mut uci);
+        assert_eq!(nnue, expected(Evaluator::Nnue(network)));
+        uci.handle("setoption name Use NNUE value false");
+        let classical = score(&mut uci);
+        assert_eq!(classical, expected(Evaluator::Classical));
+        assert_ne!(nnue, classical);
+        uci.handle("setoption name EvalFile value /no/such/net.nnue");
+        uci.handle("setoption name Use NNUE value true");
+        assert!(output(&uci).contains("info string /no/such/net.nnue"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn plays_from_the_book() {
+        use crate::polyglot::{BookBuilder, BookOptions};
+        let mut builder = BookBuilder::new(BookOptions::default());
+        builder.add_pgn("1. d4 d5 2. c4 *").unwrap();
+        let path = std::env::temp_dir().join(format!("uci-book-{}.bin", std::process::id()));
+        std::fs::write(&path, builder.build().to_bytes()).unwrap();
+        let mut uci = Uci::new(vec![]);
+        uci.handle(&format!("setoption name BookFile value {}", path.display()));
+        uci.handle("position startpos moves d2d4");
+        uci.handle("go depth 3");
+        uci.wait();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(output(&uci), "bestmove d7d5\n");
+
+        // analysis gets a search, and no bestmove until the gui says stop
+        uci.handle("go infinite");
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(output(&uci).matches("bestmove").count(), 1);
+        uci.handle("stop");
+        uci.wait();
+        let out = output(&uci);
+        assert!(out.contains("info depth 1 "));
+        assert_eq!(out.matches("bestmove").count(), 2);
+    }
+
+    #[test]
+    fn parses_go_commands() {
+        let game = Game::initialize();
+        let words: Vec<&str> = "wtime 60000 btime 30000 winc 1000 binc 500 movestogo 20 searchmoves e2e4 d2d4 nodes 5000"
+            .split_whitespace().collect();
+        let params = GoParams::parse(&words, &game).unwrap();
+        assert_eq!((params.wtime, params.binc), (Some(Duration::from_secs(60)), Some(Duration::from_millis(500))));
+        assert_eq!((params.movestogo, params.nodes), (Some(20), Some(5000)));
+        assert_eq!(params.searchmoves.iter().map(|mv| mv.to_uci()).collect::<Vec<_>>(), ["e2e4", "d2d4"]);
+        let base = SearchOptions::default();
+        let white = params.search_options(Color::White, &base);
+        let black = params.search_options(Color::Black, &base);
+        assert_eq!((white.depth, white.nodes), (MAX_DEPTH, Some(5000)));
+        assert!(white.time.unwrap().soft > black.time.unwrap().soft);
+
+        let parse = |line: &str| GoParams::parse(&line.split_whitespace().collect::<Vec<_>>(), &game);
+        assert_eq!(parse("movetime 250").unwrap().search_options(Color::White, &base).time,
+            Some(TimeLimits::fixed(Duration::from_millis(250))));
+        let infinite = parse("infinite").unwrap().search_options(Color::White, &base);
+        assert_eq!((infinite.depth, infinite.time), (MAX_DEPTH, None));
+        let mate = parse("mate 3").unwrap().search_options(Color::White, &base);
+        assert_eq!((mate.depth, mate.mate), (5, Some(3)));
+        assert_eq!(parse("").unwrap().search_options(Color::White, &base).depth, base.depth);
+        assert!(parse("depth").is_err());
+        assert!(parse("wtime soon").is_err());
+        assert!(parse("searchmoves e2e5").is_err());
+    }
+
+    #[test]
+    fn searches_only_the_given_moves() {
+        let mut uci = Uci::new(vec![]);
+        uci.handle("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        uci.handle("go depth 2 searchmoves a1a2 g1f1");
+        uci.wait();
+        let out = output(&uci);
+        let best = out.lines().last().unwrap();
+        assert!(best == "bestmove a1a2" || best == "bestmove g1f1", "{}", best);
+        uci.handle("go mate 1");
+        uci.wait();
+        assert!(output(&uci).ends_with("bestmove a1a8\n"));
+    }
+
+    #[test]
+    fn stops_and_ponders_on_request() {
+        let mut uci = Uci::new(vec![]);
+        uci.handle("go infinite");
+        uci.handle("isready");
+        thread::sleep(Duration::from_millis(50));
+        assert!(uci.is_searching());
+        assert!(!output(&uci).contains("bestmove"));
+        // stop hands back the best move straight away
+        uci.handle("stop");
+        assert!(!uci.is_searching());
+        assert!(output(&uci).contains("readyok\n"));
+        assert!(output(&uci).lines().last().unwrap().starts_with("bestmove "));
+
+        let mut uci = Uci::new(vec![]);
+        uci.handle("position startpos moves e2e4 e7e5");
+        uci.handle("go ponder depth 1");
+        thread::sleep(Duration::from_millis(50));
+        assert!(!output(&uci).contains("bestmove"));
+        uci.handle("ponderhit");
+        uci.wait();
+        assert!(output(&uci).lines().last().unwrap().starts_with("bestmove "));
+    }
+
+    #[test]
+    fn castles_as_king_takes_rook_in_chess960() {
+        let mut uci = Uci::new(vec![]);
+        uci.handle("setoption name UCI_Chess960 value true");
+        uci.handle("position startpos moves e2e4 e7e5 g1f3 b8c6 f1c4 g8f6 e1h1");
+        assert_eq!(uci.game().to_fen(), "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 5 4");
+        uci.handle("position fen rk2r3/8/8/8/8/8/8/RK2R3 w EAea - 0 1");
+        uci.handle("go depth 1 searchmoves b1a1");
+        uci.wait();
+        assert!(output(&uci).ends_with("bestmove b1a1\n"));
+    }
+
+    #[test]
+    fn formats_scores() {
+        assert_eq!(format_score(-35), "cp -35");
+        assert_eq!(format_score(MATE - 1), "mate 1");
+        assert_eq!(format_score(MATE - 3), "mate 2");
+        assert_eq!(format_score(-MATE + 2), "mate -1");
+    }
+}
@@ -0,0 +1,204 @@
+use std::fmt;
+
+// what a gui is told about an option, with the value it starts with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionKind {
+    Spin { default: i64, min: i64, max: i64 },
+    Check { default: bool },
+    Combo { default: String, values: Vec<String> },
+    String { default: String },
+    Button,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionValue {
+    Int(i64),
+    Bool(bool),
+    Str(String),
+    // buttons have no value, setting one presses it
+    Pressed,
+}
+
+impl OptionKind {
+    fn default_value(&self) -> OptionValue {
+        match self {
+            OptionKind::Spin { default, .. } => OptionValue::Int(*default),
+            OptionKind::Check { default } => OptionValue::Bool(*default),
+            OptionKind::Combo { default, .. } | OptionKind::String { default } => OptionValue::Str(default.clone()),
+            OptionKind::Button => OptionValue::Pressed,
+        }
+    }
+
+    // the value a setoption command carries, checked against the limits
+    fn parse(&self, value: &str) -> Result<OptionValue, String> {
+        match self {
+            OptionKind::Spin { min, max, .. } => {
+                let number: i64 = value.parse().map_err(|_| format!("Expected a number, got '{}'", value))?;
+                if number < *min || number > *max {
+                    return Err(format!("{} is outside {}..{}", number, min, max));
+                }
+                Ok(OptionValue::Int(number))
+            }
+            OptionKind::Check { .. } => match value {
+                "true" => Ok(OptionValue::Bool(true)),
+                "false" => Ok(OptionValue::Bool(false)),
+                _ => Err(format!("Expected true or false, got '{}'", value)),
+            },
+            OptionKind::Combo { values, .. } => values.iter()
+                .find(|allowed| allowed.eq_ignore_ascii_case(value))
+                .map(|allowed| OptionValue::Str(allowed.clone()))
+                .ok_or_else(|| format!("'{}' is not one of {}", value, values.join(", "))),
+            // the empty string is written <empty> in the uci output
+            OptionKind::String { .. } => Ok(OptionValue::Str(if value == "<empty>" { String::new() } else { value.to_string() })),
+            OptionKind::Button => Ok(OptionValue::Pressed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineOption {
+    pub name: String,
+    pub kind: OptionKind,
+    pub value: OptionValue,
+}
+
+// the `option name ...` line sent in reply to uci
+impl fmt::Display for EngineOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "option name {} type ", self.name)?;
+        match &self.kind {
+            OptionKind::Spin { default, min, max } => write!(f, "spin default {} min {} max {}", default, min, max),
+            OptionKind::Check { default } => write!(f, "check default {}", default),
+            OptionKind::Combo { default, values } => {
+                write!(f, "combo default {}", default)?;
+                values.iter().try_for_each(|value| write!(f, " var {}", value))
+            }
+            OptionKind::String { default } if default.is_empty() => write!(f, "string default <empty>"),
+            OptionKind::String { default } => write!(f, "string default {}", default),
+            OptionKind::Button => write!(f, "button"),
+        }
+    }
+}
+
+// the options an engine offers in the order they are listed, names match without case as uci asks
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct OptionRegistry {
+    options: Vec<EngineOption>,
+}
+
+impl OptionRegistry {
+    pub fn new() -> OptionRegistry {
+        OptionRegistry::default()
+    }
+
+    pub fn add(&mut self, name: &str, kind: OptionKind) -> &mut OptionRegistry {
+        let value = kind.default_value();
+        self.options.push(EngineOption { name: name.to_string(), kind, value });
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &EngineOption> {
+        self.options.iter()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&EngineOption> {
+        self.options.iter().find(|option| option.name.eq_ignore_ascii_case(name))
+    }
+
+    // stores the new value and hands back the option so the caller can act on it
+    pub fn set(&mut self, name: &str, value: &str) -> Result<&EngineOption, String> {
+        let option = self.options.iter_mut()
+            .find(|option| option.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("No such option: {}", name))?;
+        option.value = option.kind.parse(value)?;
+        Ok(option)
+    }
+
+    // the current value of a spin, 0 for anything else
+    pub fn spin(&self, name: &str) -> i64 {
+        match self.get(name).map(|option| &option.value) {
+            Some(OptionValue::Int(value)) => *value,
+            _ => 0,
+        }
+    }
+
+    pub fn check(&self, name: &str) -> bool {
+        matches!(self.get(name).map(|option| &option.value), Some(OptionValue::Bool(true)))
+    }
+
+    // the current value of a string or combo, empty for anything else
+    pub fn string(&self, name: &str) -> &str {
+        match self.get(name).map(|option| &option.value) {
+            Some(OptionValue::Str(value)) => value,
+            _ => "",
+        }
+    }
+}
+
+// splits the words after setoption into the name and the value, both may contain spaces
+pub fn parse_setoption(args: &[&str]) -> Result<(String, String), String> {
+    if args.first() != Some(&"name") {
+        return Err("Usage: setoption name <name> [value <value>]".to_string());
+    }
+    let value_at = args.iter().position(|word| *word == "value").unwrap_or(args.len());
+    let name = args[1..value_at].join(" ");
+    if name.is_empty() {
+        return Err("Missing option name".to_string());
+    }
+    Ok((name, args.get(value_at + 1..).map_or(String::new(), |value| value.join(" "))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> OptionRegistry {
+        let mut options = OptionRegistry::new();
+        options.add("Hash", OptionKind::Spin { default: 16, min: 1, max: 1024 })
+            .add("Ponder", OptionKind::Check { default: false })
+            .add("Style", OptionKind::Combo { default: "Normal".to_string(), values: vec!["Solid".to_string(), "Normal".to_string()] })
+            .add("BookFile", OptionKind::String { default: String::new() })
+            .add("Clear Hash", OptionKind::Button);
+        options
+    }
+
+    #[test]
+    fn lists_options_for_the_gui() {
+        let lines: Vec<String> = registry().iter().map(|option| option.to_string()).collect();
+        assert_eq!(lines, [
+            "option name Hash type spin default 16 min 1 max 1024",
+            "option name Ponder type check default false",
+            "option name Style type combo default Normal var Solid var Normal",
+            "option name BookFile type string default <empty>",
+            "option name Clear Hash type button",
+        ]);
+    }
+
+    #[test]
+    fn sets_values_within_their_limits() {
+        let mut options = registry();
+        assert_eq!(options.set("hash", "64").map(|option| option.name.clone()), Ok("Hash".to_string()));
+        assert_eq!(options.spin("Hash"), 64);
+        assert!(options.set("Hash", "0").is_err());
+        assert!(options.set("Hash", "lots").is_err());
+        assert_eq!(options.spin("Hash"), 64);
+        options.set("Ponder", "true").unwrap();
+        assert!(options.check("Ponder"));
+        options.set("Style", "solid").unwrap();
+        assert_eq!(options.string("Style"), "Solid");
+        assert!(options.set("Style", "Wild").is_err());
+        options.set("BookFile", "/books/my book.bin").unwrap();
+        assert_eq!(options.string("BookFile"), "/books/my book.bin");
+        assert_eq!(options.set("Clear Hash", "").map(|option| option.value.clone()), Ok(OptionValue::Pressed));
+        assert!(options.set("Contempt", "10").is_err());
+    }
+
+    #[test]
+    fn splits_setoption_commands() {
+        let words: Vec<&str> = "name Clear Hash".split_whitespace().collect();
+        assert_eq!(parse_setoption(&words), Ok(("Clear Hash".to_string(), String::new())));
+        let words: Vec<&str> = "name Skill Level value 5".split_whitespace().collect();
+        assert_eq!(parse_setoption(&words), Ok(("Skill Level".to_string(), "5".to_string())));
+        assert!(parse_setoption(&["value", "5"]).is_err());
+    }
+}
@@ -12,16 +12,23 @@ static MOD67TABLE: [usize; 67] = [
 
 pub fn bit_scan(bit: u64) -> usize {
     let remainder = (bit % 67) as usize;
-    return MOD67TABLE[remainder];
+    MOD67TABLE[remainder]
+}
+
+// removes the lowest set bit from the bitboard and returns its index
+pub fn pop_lsb(bits: &mut u64) -> usize {
+    let lowest = *bits & bits.wrapping_neg();
+    *bits ^= lowest;
+    bit_scan(lowest)
 }
 
 pub fn split_on(s: &str, sep: char) -> (&str, &str) {
-    for (i, item) in s.chars().enumerate() {
+    for (i, item) in s.char_indices() {
         if item == sep {
-            return (&s[0..i], &s[i+1..]);
+            return (&s[0..i], &s[i + item.len_utf8()..]);
         }
     }
-    (&s[..], "")
+    (s, "")
 }
 
 
@@ -52,10 +59,19 @@ mod tests {
             assert_eq!(rest, &format!("BB{}CC{}DD", ch, ch));        }
     }
 
+    #[test]
+    fn pop_lsb_works() {
+        let mut bits = (1u64 << 3) | (1u64 << 17) | (1u64 << 63);
+        assert_eq!(pop_lsb(&mut bits), 3);
+        assert_eq!(pop_lsb(&mut bits), 17);
+        assert_eq!(pop_lsb(&mut bits), 63);
+        assert_eq!(bits, 0);
+    }
+
     #[test] 
     fn bit_scan_works() {
         for i in 0..64 {
-            let bit = (1 as u64) << i;
+            let bit = 1u64 << i;
             let index = bit_scan(bit);
             assert_eq!(i, index);
         }
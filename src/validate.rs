@@ -0,0 +1,129 @@
+use std::fmt;
+use crate::attacks::{RANK_1, RANK_8};
+use crate::game::*;
+use crate::square::Square;
+use crate::utils::bit_scan;
+
+// why a syntactically fine position could not occur in a game
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PositionError {
+    MissingKing(Color),
+    TooManyKings(Color),
+    PawnOnBackRank(Square),
+    OpponentInCheck,
+    InvalidCastlingRights(CastlingRights),
+    InvalidEnPassant(Square),
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PositionError::MissingKing(color) => write!(f, "{:?} has no king", color),
+            PositionError::TooManyKings(color) => write!(f, "{:?} has more than one king", color),
+            PositionError::PawnOnBackRank(square) => write!(f, "Pawn on the back rank at {}", square),
+            PositionError::OpponentInCheck => write!(f, "The side not to move is in check"),
+            PositionError::InvalidCastlingRights(rights) => write!(f, "Castling rights {:?} do not match the king and rooks", rights),
+            PositionError::InvalidEnPassant(square) => write!(f, "No pawn can be captured en passant on {}", square),
+        }
+    }
+}
+
+impl std::error::Error for PositionError {}
+
+impl Game {
+    // checks the position could actually arise, read_FEN only checks the syntax
+    pub fn validate(&self) -> Result<(), PositionError> {
+        for color in [Color::White, Color::Black] {
+            match self.bitboard(color, PieceType::King).count_ones() {
+                0 => return Err(PositionError::MissingKing(color)),
+                1 => (),
+                _ => return Err(PositionError::TooManyKings(color)),
+            }
+        }
+
+        let pawns = self.bitboard(Color::White, PieceType::Pawn) | self.bitboard(Color::Black, PieceType::Pawn);
+        let misplaced = pawns & (RANK_1 | RANK_8);
+        if misplaced != 0 {
+            return Err(PositionError::PawnOnBackRank(Square::ALL[bit_scan(misplaced)]));
+        }
+
+        let them = self.active_color.opponent();
+        let their_king = Square::from_index(bit_scan(self.bitboard(them, PieceType::King)));
+        if self.attackers_to(their_king, self.active_color, self.occupied()) != 0 {
+            return Err(PositionError::OpponentInCheck);
+        }
+
+        // each right needs the king and that rook still on their starting squares,
+        // in chess960 the king may start anywhere on the back rank
+        let mut invalid = CastlingRights::NONE;
+        for (i, (right, color)) in CASTLING_SIDES.iter().enumerate() {
+            let color = *color;
+            let king = self.bitboard(color, PieceType::King);
+            let (back_rank, home) = match color {
+                Color::White => (RANK_1, 1u64 << 4),
+                Color::Black => (RANK_8, 1u64 << 60),
+            };
+            let king_in_place = if self.chess960 { king & back_rank != 0 } else { king == home };
+            let rook_in_place = self.piece_at(Square::from_index(self.castling_rooks[i]))
+                .is_some_and(|p| p.color == color && p.piece_type == PieceType::Rook);
+            if self.castling_rights.contains(*right) && !(king_in_place && rook_in_place) {
+                invalid |= *right;
+            }
+        }
+        if !invalid.is_empty() {
+            return Err(PositionError::InvalidCastlingRights(invalid));
+        }
+
+        // the pawn that just pushed two squares has to be in front of the en passant square,
+        // with the square it passed and the one it came from empty
+        if let Some(bit) = self.en_passant {
+            let square = Square::ALL[bit_scan(bit)];
+            let (rank, pawn, origin) = match self.active_color {
+                Color::White => (5, bit >> 8, bit << 8),
+                Color::Black => (2, bit << 8, bit >> 8),
+            };
+            let plausible = square.rank().index() == rank
+                && self.bitboard(them, PieceType::Pawn) & pawn != 0
+                && self.occupied() & (bit | origin) == 0;
+            if !plausible {
+                return Err(PositionError::InvalidEnPassant(square));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legal_positions_validate() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "rnbqkbnr/pppp1ppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3",
+            "bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w GEge - 0 1",
+        ] {
+            assert_eq!(Game::read_FEN(fen).validate(), Ok(()), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn impossible_positions_are_reported() {
+        let cases = [
+            ("8/8/8/8/8/8/8/4K3 w - - 0 1", PositionError::MissingKing(Color::Black)),
+            ("4k3/8/8/8/8/8/8/3KK3 w - - 0 1", PositionError::TooManyKings(Color::White)),
+            ("4k3/8/8/8/8/8/8/P3K3 w - - 0 1", PositionError::PawnOnBackRank(Square::A1)),
+            ("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1", PositionError::OpponentInCheck),
+            ("4k3/8/8/8/8/8/8/R3K3 w KQ - 0 1", PositionError::InvalidCastlingRights(CastlingRights::WHITEKINGSIDE)),
+            ("4k3/8/8/8/8/8/8/4K3 w - e6 0 1", PositionError::InvalidEnPassant(Square::E6)),
+            ("4k3/8/8/4p3/8/8/8/4K3 w - e3 0 1", PositionError::InvalidEnPassant(Square::E3)),
+        ];
+        for (fen, err) in cases {
+            assert_eq!(Game::read_FEN(fen).validate(), Err(err), "{}", fen);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::time::Duration;
+use wasm_bindgen::prelude::*;
+use crate::game::{Color, Game};
+use crate::movegen::Move;
+use crate::search::{SearchOptions, Searcher, MAX_DEPTH};
+use crate::square::Square;
+use crate::status::GameStatus;
+use crate::timeman::TimeLimits;
+
+// a game for javascript, moves go in and out as uci strings like e2e4 or e7e8q
+#[wasm_bindgen(js_name = Game)]
+pub struct WasmGame {
+    game: Game,
+    searcher: Searcher,
+}
+
+impl Default for WasmGame {
+    fn default() -> WasmGame {
+        WasmGame::from_game(Game::initialize())
+    }
+}
+
+#[wasm_bindgen(js_class = Game)]
+impl WasmGame {
+    // the starting position
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmGame {
+        WasmGame::default()
+    }
+
+    #[wasm_bindgen(js_name = fromFen)]
+    pub fn from_fen(fen: &str) -> Result<WasmGame, JsError> {
+        Game::try_from_fen(fen).map(WasmGame::from_game).map_err(|err| JsError::new(&err.to_string()))
+    }
+
+    pub fn fen(&self) -> String {
+        self.game.to_fen()
+    }
+
+    // "w" or "b"
+    #[wasm_bindgen(js_name = sideToMove)]
+    pub fn side_to_move(&self) -> String {
+        match self.game.active_color {
+            Color::White => "w".to_string(),
+            Color::Black => "b".to_string(),
+        }
+    }
+
+    #[wasm_bindgen(js_name = legalMoves)]
+    pub fn legal_moves(&self) -> Vec<String> {
+        self.game.legal_moves().iter().map(|mv| mv.to_uci()).collect()
+    }
+
+    // legal moves of the piece on a square like e2, for highlighting destinations
+    #[wasm_bindgen(js_name = movesFrom)]
+    pub fn moves_from(&self, square: &str) -> Result<Vec<String>, JsError> {
+        let square: Square = square.parse().map_err(|err: String| JsError::new(&err))?;
+        Ok(self.game.moves_from(square).iter().map(|mv| mv.to_uci()).collect())
+    }
+
+    // plays a legal move, returning it in SAN
+    #[wasm_bindgen(js_name = makeMove)]
+    pub fn make_move(&mut self, uci: &str) -> Result<String, JsError> {
+        let mv = Move::from_uci(uci, &self.game).map_err(|err| JsError::new(&err))?;
+        let san = self.game.to_san(mv);
+        self.game.make_move(mv);
+        Ok(san)
+    }
+
+    // takes back the last move, returning it or undefined at the start of the game
+    pub fn undo(&mut self) -> Option<String> {
+        self.game.unmake_move().map(|mv| mv.to_uci())
+    }
+
+    #[wasm_bindgen(js_name = isCheck)]
+    pub fn is_check(&self) -> bool {
+        self.game.is_in_check(self.game.active_color)
+    }
+
+    // "ongoing", "checkmate", "stalemate", "insufficient material", "seventy-five moves" or "fivefold repetition"
+    pub fn status(&self) -> String {
+        match self.game.status() {
+            GameStatus::Ongoing => "ongoing",
+            GameStatus::Checkmate(_) => "checkmate",
+            GameStatus::Stalemate => "stalemate",
+            GameStatus::InsufficientMaterial => "insufficient material",
+            GameStatus::SeventyFiveMoveRule => "seventy-five moves",
+            GameStatus::FivefoldRepetition => "fivefold repetition",
+        }.to_string()
+    }
+
+    // the best move found within `millis` milliseconds, undefined when there is no legal move
+    pub fn search(&mut self, millis: u32) -> Option<String> {
+        let options = SearchOptions {
+            depth: MAX_DEPTH,
+            time: Some(TimeLimits::fixed(Duration::from_millis(millis as u64))),
+            ..SearchOptions::default()
+        };
+        self.searcher.search_with(&mut self.game, &options).best_move.map(|mv| mv.to_uci())
+    }
+}
+
+impl WasmGame {
+    fn from_game(game: Game) -> WasmGame {
+        WasmGame { game, searcher: Searcher::new() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plays_a_game_through_the_bindings() {
+        let mut game = WasmGame::new();
+        assert_eq!(game.legal_moves().len(), 20);
+        assert_eq!(game.moves_from("g1").unwrap(), ["g1f3", "g1h3"]);
+        assert_eq!(game.make_move("e2e4").unwrap(), "e4");
+        assert_eq!(game.side_to_move(), "b");
+        assert_eq!(game.undo(), Some("e2e4".to_string()));
+        assert_eq!(game.undo(), None);
+
+        let mut mate = WasmGame::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert_eq!(mate.search(200), Some("a1a8".to_string()));
+        mate.make_move("a1a8").unwrap();
+        assert!(mate.is_check());
+        assert_eq!(mate.status(), "checkmate");
+        assert_eq!(mate.search(50), None);
+    }
+}
@@ -0,0 +1,131 @@
+use std::io;
+use std::net::TcpStream;
+use std::sync::mpsc;
+use std::time::Duration;
+use tungstenite::protocol::WebSocketConfig;
+use tungstenite::{Error, Message, WebSocket};
+
+// text messages are small json objects, anything bigger is refused
+const MAX_MESSAGE: usize = 1 << 20;
+// how long a read waits before queued messages are sent
+const POLL: Duration = Duration::from_millis(20);
+
+fn io_error(err: Error) -> io::Error {
+    match err {
+        Error::Io(err) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, err),
+    }
+}
+
+// a server side connection, messages queued on its sender from any thread are written
+// out while the owner waits in recv, so one thread owns the socket
+pub struct Connection {
+    socket: WebSocket<TcpStream>,
+    sender: mpsc::Sender<String>,
+    queue: mpsc::Receiver<String>,
+}
+
+impl Connection {
+    pub fn sender(&self) -> mpsc::Sender<String> {
+        self.sender.clone()
+    }
+
+    pub fn send(&mut self, text: &str) -> io::Result<()> {
+        self.socket.send(Message::text(text)).map_err(io_error)
+    }
+
+    // the next text message, None once the client closed the connection.
+    // pings are answered here, so a connection only stays alive while someone reads it
+    pub fn recv(&mut self) -> io::Result<Option<String>> {
+        loop {
+            while let Ok(text) = self.queue.try_recv() {
+                self.send(&text)?;
+            }
+            match self.socket.read() {
+                Ok(Message::Text(text)) => return Ok(Some(text.to_string())),
+                Ok(Message::Binary(_)) => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "only text messages are supported"));
+                }
+                // tungstenite queues pongs and the close reply, the next read sends them and
+                // then reports the closed connection
+                Ok(_) => {}
+                Err(Error::Io(err)) if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {}
+                Err(Error::ConnectionClosed | Error::AlreadyClosed) => return Ok(None),
+                Err(err) => return Err(io_error(err)),
+            }
+        }
+    }
+}
+
+// answers the opening handshake of a client that connected to a server socket,
+// frames a client sent without masking them are refused as RFC 6455 asks
+pub fn accept(stream: TcpStream) -> io::Result<Connection> {
+    let config = WebSocketConfig::default().max_message_size(Some(MAX_MESSAGE)).max_frame_size(Some(MAX_MESSAGE));
+    let socket = tungstenite::accept_with_config(stream, Some(config))
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+    socket.get_ref().set_read_timeout(Some(POLL))?;
+    let (sender, queue) = mpsc::channel();
+    Ok(Connection { socket, sender, queue })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn exchanges_messages() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let stream = TcpStream::connect(address).unwrap();
+            let (mut socket, _) = tungstenite::client(format!("ws://{}/play", address), stream).unwrap();
+            socket.send(Message::text("hello")).unwrap();
+            socket.send(Message::Ping(b"p".to_vec().into())).unwrap();
+            let mut received = vec![];
+            while received.len() < 3 {
+                received.push(socket.read().unwrap());
+            }
+            socket.close(None).unwrap();
+            while socket.read().is_ok() {}
+            received
+        });
+        let mut connection = accept(listener.accept().unwrap().0).unwrap();
+        assert_eq!(connection.recv().unwrap(), Some("hello".to_string()));
+        connection.send("world").unwrap();
+        // a queued message goes out while the reader waits
+        connection.sender().send("queued".to_string()).unwrap();
+        assert_eq!(connection.recv().unwrap(), None);
+        drop(connection);
+        let received = client.join().unwrap();
+        assert!(received.contains(&Message::Pong(b"p".to_vec().into())), "{:?}", received);
+        let texts: Vec<_> = received.iter().filter(|message| message.is_text()).collect();
+        assert_eq!(texts, [&Message::text("world"), &Message::text("queued")]);
+    }
+
+    #[test]
+    fn refuses_unmasked_client_frames() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(b"GET /play HTTP/1.1\r\nHost: x\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n").unwrap();
+            let mut head = vec![];
+            let mut byte = [0];
+            while !head.ends_with(b"\r\n\r\n") {
+                stream.read_exact(&mut byte).unwrap();
+                head.push(byte[0]);
+            }
+            let head = String::from_utf8(head).unwrap();
+            assert!(head.starts_with("HTTP/1.1 101") && head.contains("s3pPLMBiTxaQ9kYGzzhZRbK+xOo="), "{}", head);
+            // "hi" as a final text frame without the mask bit
+            stream.write_all(&[0x81, 0x02, b'h', b'i']).unwrap();
+            stream
+        });
+        let mut connection = accept(listener.accept().unwrap().0).unwrap();
+        let _stream = client.join().unwrap();
+        assert!(connection.recv().is_err());
+    }
+}
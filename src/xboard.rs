@@ -0,0 +1,302 @@
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::game::{Color, Game};
+use crate::movegen::Move;
+use crate::outcome::{GameResult, TerminationReason};
+use crate::search::{mate_in, SearchInfo, SearchOptions, Searcher, MAX_DEPTH};
+use crate::timeman::{TimeControl, TimeLimits};
+use crate::uci::ENGINE_NAME;
+
+// the features sent in reply to protover 2, moves come as usermove and positions as setboard
+const FEATURES: [&str; 9] = [
+    "ping=1", "setboard=1", "usermove=1", "playother=1", "san=0", "sigint=0", "sigterm=0", "colors=0", "reuse=1",
+];
+
+// centipawns, mates as 100000 plus the number of moves with the losing side's negative
+pub fn format_score(score: i32) -> i32 {
+    match mate_in(score) {
+        Some(moves) if moves > 0 => 100_000 + moves,
+        Some(moves) => -100_000 + moves,
+        None => score,
+    }
+}
+
+// "ply score time nodes pv" with the time in centiseconds, as post asks for
+pub fn format_thinking(info: &SearchInfo) -> String {
+    let pv: Vec<String> = info.pv.iter().map(|mv| mv.to_uci()).collect();
+    format!("{} {} {} {} {}", info.depth, format_score(info.score), info.time.as_millis() / 10, info.nodes, pv.join(" "))
+}
+
+// the engine side of a CECP session, fed one line from the gui at a time
+pub struct Xboard<W: Write + Send + 'static> {
+    game: Game,
+    searcher: Searcher,
+    // the side the engine plays, None in force mode where it only keeps track of the moves
+    engine_side: Option<Color>,
+    // our clock and increment, and the level's moves per time control with 0 for the whole game
+    time: Option<Duration>,
+    increment: Duration,
+    moves_per_session: u32,
+    // st and sd
+    move_time: Option<Duration>,
+    depth: Option<usize>,
+    post: Arc<AtomicBool>,
+    out: Arc<Mutex<W>>,
+}
+
+impl<W: Write + Send + 'static> Xboard<W> {
+    pub fn new(out: W) -> Xboard<W> {
+        let out = Arc::new(Mutex::new(out));
+        let post = Arc::new(AtomicBool::new(false));
+        let mut searcher = Searcher::new();
+        let (info_out, info_post) = (out.clone(), post.clone());
+        searcher.set_listener(move |info: &SearchInfo| {
+            if info.multipv == 1 && info_post.load(Ordering::Relaxed) {
+                let mut out = info_out.lock().unwrap();
+                let _ = writeln!(out, "{}", format_thinking(info));
+                let _ = out.flush();
+            }
+        });
+        Xboard {
+            game: Game::initialize(), searcher, engine_side: Some(Color::Black), time: None, increment: Duration::ZERO,
+            moves_per_session: 0, move_time: None, depth: None, post, out,
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn engine_side(&self) -> Option<Color> {
+        self.engine_side
+    }
+
+    // false once the gui sent quit
+    pub fn handle(&mut self, line: &str) -> bool {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((command, args)) = words.split_first() else {
+            return true;
+        };
+        match *command {
+            "xboard" | "hard" | "easy" | "random" | "computer" | "accepted" | "rejected" | "name" | "rating" | "?" => {}
+            "protover" => {
+                self.send(&format!("feature myname=\"{}\" {}", ENGINE_NAME, FEATURES.join(" ")));
+                self.send("feature done=1");
+            }
+            "new" => {
+                self.game = Game::initialize();
+                self.searcher.clear_hash();
+                self.engine_side = Some(Color::Black);
+                self.move_time = None;
+                self.depth = None;
+            }
+            "force" | "result" => self.engine_side = None,
+            "go" => {
+                self.engine_side = Some(self.game.active_color);
+                self.think();
+            }
+            "playother" => self.engine_side = Some(self.game.active_color.opponent()),
+            "usermove" => match args.first() {
+                Some(mv) => self.user_move(mv),
+                None => self.send("Error (missing move): usermove"),
+            },
+            "setboard" => match Game::try_from_fen(&args.join(" ")) {
+                Ok(game) => self.game = game,
+                Err(err) => self.send(&format!("tellusererror Illegal position: {}", err)),
+            },
+            "undo" => {
+                self.game.unmake_move();
+            }
+            "remove" => {
+                self.game.unmake_move();
+                self.game.unmake_move();
+            }
+            "ping" => self.send(&format!("pong {}", args.join(" "))),
+            "post" => self.post.store(true, Ordering::Relaxed),
+            "nopost" => self.post.store(false, Ordering::Relaxed),
+            "time" => self.time = args.first().and_then(|cs| cs.parse::<u64>().ok()).map(|cs| Duration::from_millis(cs * 10)),
+            "otim" => {}
+            "level" => {
+                if let Err(err) = self.level(args) {
+                    self.send(&format!("Error ({}): {}", err, line.trim()));
+                }
+            }
+            "st" => self.move_time = args.first().and_then(|secs| secs.parse().ok()).map(Duration::from_secs),
+            "sd" => self.depth = args.first().and_then(|depth| depth.parse().ok()),
+            "quit" => return false,
+            // protover 1 guis send the move on its own
+            _ if Move::from_uci(command, &self.game).is_ok() => self.user_move(command),
+            _ => self.send(&format!("Error (unknown command): {}", command)),
+        }
+        true
+    }
+
+    // level MPS BASE INC, BASE in minutes or minutes:seconds and INC in seconds
+    fn level(&mut self, args: &[&str]) -> Result<(), String> {
+        let [moves, base, increment] = args else {
+            return Err("expected level MPS BASE INC".to_string());
+        };
+        let (minutes, seconds) = base.split_once(':').unwrap_or((base, "0"));
+        let number = |text: &str| text.parse::<f64>().map_err(|_| format!("bad number {}", text));
+        self.moves_per_session = moves.parse().map_err(|_| format!("bad number {}", moves))?;
+        self.time = Some(Duration::from_secs_f64(number(minutes)? * 60.0 + number(seconds)?));
+        self.increment = Duration::from_secs_f64(number(increment)?);
+        Ok(())
+    }
+
+    fn user_move(&mut self, uci: &str) {
+        let mv = match Move::from_uci(uci, &self.game) {
+            Ok(mv) => mv,
+            Err(_) => {
+                self.send(&format!("Illegal move: {}", uci));
+                return;
+            }
+        };
+        self.game.make_move(mv);
+        // in force mode the gui is only setting up the game, it needs no result from us
+        if self.engine_side.is_some() && !self.report_result() && self.engine_side == Some(self.game.active_color) {
+            self.think();
+        }
+    }
+
+    fn search_options(&self) -> SearchOptions {
+        let mut options = SearchOptions::default();
+        if let Some(time) = self.move_time {
+            options.time = Some(TimeLimits::fixed(time));
+        } else if let Some(time) = self.time {
+            // the moves left until the next time control, counted from the engine's move numbers
+            let moves_to_go = (self.moves_per_session > 0)
+                .then(|| self.moves_per_session - (self.game.fullmove_number.saturating_sub(1) as u32 % self.moves_per_session));
+            options.time = Some(TimeControl { time, increment: self.increment, moves_to_go }.limits());
+        }
+        options.depth = match (self.depth, options.time) {
+            (Some(depth), _) => depth,
+            (None, Some(_)) => MAX_DEPTH,
+            (None, None) => options.depth,
+        };
+        options
+    }
+
+    // searches and plays the engine's move
+    fn think(&mut self) {
+        if self.report_result() {
+            return;
+        }
+        let options = self.search_options();
+        let result = self.searcher.search_with(&mut self.game, &options);
+        if let Some(mv) = result.best_move {
+            self.game.make_move(mv);
+            self.send(&format!("move {}", mv.to_uci()));
+            self.report_result();
+        }
+    }
+
+    // tells the gui when the game is over, claiming draws by repetition and the fifty move rule
+    fn report_result(&mut self) -> bool {
+        let outcome = self.game.update_outcome();
+        let (result, reason) = match outcome {
+            Some(outcome) => (outcome.result, outcome.reason),
+            None if self.game.repetition_count() >= 3 => (GameResult::Draw, TerminationReason::Repetition),
+            None if self.game.halfmove_clock >= 100 => (GameResult::Draw, TerminationReason::FiftyMove),
+            None => return false,
+        };
+        let reason = match (result, reason) {
+            (GameResult::WhiteWins, TerminationReason::Checkmate) => "White mates".to_string(),
+            (GameResult::BlackWins, TerminationReason::Checkmate) => "Black mates".to_string(),
+            (_, reason) => format!("Draw by {}", reason),
+        };
+        self.send(&format!("{} {{{}}}", result, reason));
+        true
+    }
+
+    fn send(&self, line: &str) {
+        let mut out = self.out.lock().unwrap();
+        let _ = writeln!(out, "{}", line);
+        let _ = out.flush();
+    }
+}
+
+// reads commands until quit or the end of the input
+pub fn run(input: impl BufRead, output: impl Write + Send + 'static) -> io::Result<()> {
+    let mut xboard = Xboard::new(output);
+    for line in input.lines() {
+        if !xboard.handle(&line?) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(xboard: &Xboard<Vec<u8>>) -> String {
+        String::from_utf8(xboard.out.lock().unwrap().clone()).unwrap()
+    }
+
+    #[test]
+    fn announces_features() {
+        let mut xboard = Xboard::new(vec![]);
+        xboard.handle("xboard");
+        xboard.handle("protover 2");
+        xboard.handle("ping 7");
+        let out = output(&xboard);
+        assert!(out.starts_with("feature myname=\"Rustic Chess"));
+        assert!(out.contains(" usermove=1 "));
+        assert!(out.ends_with("feature done=1\npong 7\n"));
+    }
+
+    #[test]
+    fn answers_user_moves() {
+        let mut xboard = Xboard::new(vec![]);
+        xboard.handle("new");
+        xboard.handle("sd 2");
+        xboard.handle("usermove e2e4");
+        let out = output(&xboard);
+        assert!(out.starts_with("move "), "{}", out);
+        assert_eq!(xboard.game().active_color, Color::White);
+        xboard.handle("usermove e2e5");
+        assert!(output(&xboard).ends_with("Illegal move: e2e5\n"));
+        // in force mode moves are only played on the board
+        xboard.handle("force");
+        xboard.handle("d2d4");
+        assert_eq!(xboard.game().active_color, Color::Black);
+        xboard.handle("remove");
+        assert_eq!(xboard.game().active_color, Color::Black);
+        assert_eq!(xboard.engine_side(), None);
+    }
+
+    #[test]
+    fn plays_from_a_set_board_and_reports_mate() {
+        let mut xboard = Xboard::new(vec![]);
+        xboard.handle("post");
+        xboard.handle("setboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        xboard.handle("level 40 0:30 0");
+        xboard.handle("time 3000");
+        xboard.handle("sd 2");
+        xboard.handle("go");
+        let out = output(&xboard);
+        assert!(out.contains("\n2 100001 "), "{}", out);
+        assert!(out.ends_with("move a1a8\n1-0 {White mates}\n"), "{}", out);
+    }
+
+    #[test]
+    fn reads_time_controls() {
+        let mut xboard = Xboard::new(vec![]);
+        xboard.handle("level 40 5 0");
+        assert_eq!(xboard.time, Some(Duration::from_secs(300)));
+        xboard.handle("level 0 2:30 12");
+        assert_eq!((xboard.time, xboard.increment), (Some(Duration::from_secs(150)), Duration::from_secs(12)));
+        xboard.handle("time 6000");
+        let options = xboard.search_options();
+        assert_eq!(options.depth, MAX_DEPTH);
+        assert!(options.time.unwrap().hard <= Duration::from_secs(20));
+        xboard.handle("st 2");
+        assert_eq!(xboard.search_options().time, Some(TimeLimits::fixed(Duration::from_secs(2))));
+        xboard.handle("level 40");
+        assert!(output(&xboard).starts_with("Error (expected level MPS BASE INC)"));
+    }
+}